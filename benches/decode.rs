@@ -0,0 +1,240 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use smali_disassembler::{
+    container::{dex_file::DexFile, standard::StandardDexFile, EmbeddedDex},
+    integrity, SmaliDecoder,
+};
+
+/// 40 000 methods with code, about a large single dex
+const CLASSES: u32 = 400;
+const METHODS_PER_CLASS: u32 = 100;
+const INSTRUCTIONS_PER_METHOD: usize = 41;
+
+const HEADER_SIZE: usize = 0x70;
+const NO_INDEX: u32 = 0xffff_ffff;
+
+/// a method body with roughly the instruction mix of app code: invokes of every width,
+/// field reads, constants, a branch and an allocation
+fn method_code(method_idx: u16, class_idx: u16) -> Vec<u8> {
+    let [method_lo, method_hi] = method_idx.to_le_bytes();
+    let [lo, hi] = class_idx.to_le_bytes();
+    let mut code = vec![];
+    for _ in 0..4 {
+        #[rustfmt::skip]
+        code.extend_from_slice(&[
+            0x6e, 0x20, method_lo, method_hi, 0x10, 0x00, // invoke-virtual {v0, v1}, method@
+            0x0c, 0x00, // move-result-object v0
+            0x54, 0x10, lo, hi, // iget-object v0, v1, field@
+            0x1a, 0x02, lo, hi, // const-string v2, string@
+            0x71, 0x10, method_lo, method_hi, 0x02, 0x00, // invoke-static {v2}, method@
+            0x38, 0x00, 0x03, 0x00, // if-eqz v0, +3
+            0x12, 0x11, // const/4 v1, 1
+            0x70, 0x30, method_lo, method_hi, 0x10, 0x02, // invoke-direct {v0, v1, v2}, method@
+            0x22, 0x00, lo, hi, // new-instance v0, type@
+            0x24, 0x54, lo, hi, 0x10, 0x32, // filled-new-array {v0, v1, v2, v3, v4}, type@
+        ]);
+    }
+    code.extend_from_slice(&[0x0e, 0x00]); // return-void
+    code
+}
+
+fn put_u16(dex: &mut [u8], offset: usize, value: u16) {
+    dex[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(dex: &mut [u8], offset: usize, value: usize) {
+    dex[offset..offset + 4].copy_from_slice(&(value as u32).to_le_bytes());
+}
+
+fn push_uleb128(dex: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        dex.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    dex.push(value as u8);
+}
+
+fn align(dex: &mut Vec<u8>) {
+    dex.resize(dex.len().next_multiple_of(4), 0);
+}
+
+/// a standard dex of `CLASSES` classes, each with a field and `METHODS_PER_CLASS` virtual
+/// methods, with sorted ids, a map list, checksum and signature so it passes verification
+fn synthetic_dex() -> Vec<u8> {
+    // strings in utf-16 order, the first CLASSES are also the class types
+    let mut strings: Vec<String> = (0..CLASSES)
+        .map(|class| format!("Lbench/C{:05};", class))
+        .collect();
+    strings.extend(["Ljava/lang/Object;", "V", "f"].map(String::from));
+    strings.extend((0..METHODS_PER_CLASS).map(|method| format!("m{:03}", method)));
+    let (object, void, field_name, first_method_name) =
+        (CLASSES, CLASSES + 1, CLASSES + 2, CLASSES + 3);
+    let types = CLASSES as usize + 2;
+    let methods = (CLASSES * METHODS_PER_CLASS) as usize;
+
+    let string_ids = HEADER_SIZE;
+    let type_ids = string_ids + strings.len() * 4;
+    let proto_ids = type_ids + types * 4;
+    let field_ids = proto_ids + 12;
+    let method_ids = field_ids + CLASSES as usize * 8;
+    let class_defs = method_ids + methods * 8;
+    let data_off = class_defs + CLASSES as usize * 32;
+    let mut dex = vec![0; data_off];
+
+    for index in 0..types {
+        put_u32(&mut dex, type_ids + index * 4, index);
+    }
+    // ()V
+    put_u32(&mut dex, proto_ids, void as usize);
+    put_u32(&mut dex, proto_ids + 4, void as usize);
+
+    let mut code_offs = vec![];
+    for class in 0..CLASSES {
+        let field_id = field_ids + class as usize * 8;
+        put_u16(&mut dex, field_id, class as u16);
+        put_u16(&mut dex, field_id + 2, object as u16);
+        put_u32(&mut dex, field_id + 4, field_name as usize);
+
+        for method in 0..METHODS_PER_CLASS {
+            let method_idx = class * METHODS_PER_CLASS + method;
+            let method_id = method_ids + method_idx as usize * 8;
+            put_u16(&mut dex, method_id, class as u16);
+            put_u32(
+                &mut dex,
+                method_id + 4,
+                (first_method_name + method) as usize,
+            );
+
+            // code_item: 5 registers, 1 in, 5 out, no tries or debug info
+            let insns = method_code(method_idx as u16, class as u16);
+            code_offs.push(dex.len());
+            for value in [5u16, 1, 5, 0] {
+                dex.extend_from_slice(&value.to_le_bytes());
+            }
+            dex.extend_from_slice(&0u32.to_le_bytes());
+            dex.extend_from_slice(&(insns.len() as u32 / 2).to_le_bytes());
+            dex.extend_from_slice(&insns);
+            align(&mut dex);
+        }
+    }
+
+    let string_data = dex.len();
+    for (index, string) in strings.iter().enumerate() {
+        let string_data_off = dex.len();
+        put_u32(&mut dex, string_ids + index * 4, string_data_off);
+        push_uleb128(&mut dex, string.len());
+        dex.extend_from_slice(string.as_bytes());
+        dex.push(0);
+    }
+
+    let class_data = dex.len();
+    for class in 0..CLASSES {
+        let class_def = class_defs + class as usize * 32;
+        put_u32(&mut dex, class_def, class as usize);
+        put_u32(&mut dex, class_def + 4, 0x1); // public
+        put_u32(&mut dex, class_def + 8, object as usize);
+        put_u32(&mut dex, class_def + 16, NO_INDEX as usize);
+        let class_data_off = dex.len();
+        put_u32(&mut dex, class_def + 24, class_data_off);
+
+        // no static fields, one instance field, no direct methods, the virtual methods
+        for size in [0, 1, 0, METHODS_PER_CLASS as usize] {
+            push_uleb128(&mut dex, size);
+        }
+        push_uleb128(&mut dex, class as usize);
+        push_uleb128(&mut dex, 0x2); // private
+        for method in 0..METHODS_PER_CLASS {
+            let method_idx = (class * METHODS_PER_CLASS + method) as usize;
+            let diff = if method == 0 { method_idx } else { 1 };
+            push_uleb128(&mut dex, diff);
+            push_uleb128(&mut dex, 0x1); // public
+            push_uleb128(&mut dex, code_offs[method_idx]);
+        }
+    }
+
+    align(&mut dex);
+    let map_off = dex.len();
+    let map = [
+        (0x0000, 1, 0),
+        (0x0001, strings.len(), string_ids),
+        (0x0002, types, type_ids),
+        (0x0003, 1, proto_ids),
+        (0x0004, CLASSES as usize, field_ids),
+        (0x0005, methods, method_ids),
+        (0x0006, CLASSES as usize, class_defs),
+        (0x2001, methods, data_off),
+        (0x2002, strings.len(), string_data),
+        (0x2000, CLASSES as usize, class_data),
+        (0x1000, 1, map_off),
+    ];
+    dex.extend_from_slice(&(map.len() as u32).to_le_bytes());
+    for (item_type, size, offset) in map {
+        dex.extend_from_slice(&(item_type as u32).to_le_bytes());
+        dex.extend_from_slice(&(size as u32).to_le_bytes());
+        dex.extend_from_slice(&(offset as u32).to_le_bytes());
+    }
+
+    dex[..8].copy_from_slice(b"dex\n035\0");
+    let file_size = dex.len();
+    put_u32(&mut dex, 0x20, file_size);
+    put_u32(&mut dex, 0x24, HEADER_SIZE);
+    put_u32(&mut dex, 0x28, 0x12345678);
+    put_u32(&mut dex, 0x34, map_off);
+    for (table, size, offset) in [
+        (0x38, strings.len(), string_ids),
+        (0x40, types, type_ids),
+        (0x48, 1, proto_ids),
+        (0x50, CLASSES as usize, field_ids),
+        (0x58, methods, method_ids),
+        (0x60, CLASSES as usize, class_defs),
+        (0x68, file_size - data_off, data_off),
+    ] {
+        put_u32(&mut dex, table, size);
+        put_u32(&mut dex, table + 4, offset);
+    }
+    let signature = integrity::computed_signature(&dex).unwrap();
+    dex[0xc..0x20].copy_from_slice(&signature);
+    let checksum = integrity::adler32(&dex[0xc..]);
+    put_u32(&mut dex, 0x8, checksum as usize);
+    dex
+}
+
+/// parse every class of the dex and decode every method, the number of instructions decoded
+fn decode_dex(data: &[u8]) -> usize {
+    let embedded = EmbeddedDex::parse(data).unwrap();
+    let dex = StandardDexFile::new(&embedded).unwrap();
+    let mut instructions = 0;
+    for class in dex.classes().unwrap() {
+        for method in &class.methods {
+            let code = dex.code_item(method.code_off).unwrap();
+            instructions += SmaliDecoder::new(code.insns, None).decode_all().len();
+        }
+    }
+    instructions
+}
+
+fn decode(c: &mut Criterion) {
+    let dex = synthetic_dex();
+    assert_eq!(integrity::verify(&dex), []);
+    // every instruction has to decode or the decoder stops early and the numbers mean nothing
+    assert_eq!(
+        decode_dex(&dex),
+        (CLASSES * METHODS_PER_CLASS) as usize * INSTRUCTIONS_PER_METHOD
+    );
+
+    let mut group = c.benchmark_group("decode");
+    group.throughput(Throughput::Bytes(dex.len() as u64));
+    group.bench_function("synthetic_dex", |b| b.iter(|| decode_dex(black_box(&dex))));
+    group.finish();
+
+    // a run of five argument invokes, the hot path of argument decoding
+    let invokes = [0x6e, 0x50, 0x01, 0x00, 0x10, 0x32].repeat(10_000);
+    let mut group = c.benchmark_group("decode");
+    group.throughput(Throughput::Bytes(invokes.len() as u64));
+    group.bench_function("invokes", |b| {
+        b.iter(|| SmaliDecoder::new(black_box(&invokes), None).decode_all())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, decode);
+criterion_main!(benches);
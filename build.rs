@@ -0,0 +1,22 @@
+fn main() {
+    // the header is written to OUT_DIR, `SMALI_DISASSEMBLER_UPDATE_HEADER=1` also refreshes
+    // the checked in include/smali_disassembler.h
+    #[cfg(feature = "capi")]
+    {
+        let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let out_dir = std::env::var("OUT_DIR").unwrap();
+        let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir)).unwrap();
+        let header = cbindgen::Builder::new()
+            .with_crate(&crate_dir)
+            .with_config(config)
+            .generate()
+            .expect("generating the c header");
+        header.write_to_file(format!("{}/smali_disassembler.h", out_dir));
+        if std::env::var_os("SMALI_DISASSEMBLER_UPDATE_HEADER").is_some() {
+            header.write_to_file(format!("{}/include/smali_disassembler.h", crate_dir));
+        }
+        println!("cargo:rerun-if-changed=src/capi.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+        println!("cargo:rerun-if-env-changed=SMALI_DISASSEMBLER_UPDATE_HEADER");
+    }
+}
@@ -0,0 +1,4 @@
+//! the `extern "C"` api of `smali_disassembler::capi` as a shared library, declared in
+//! `include/smali_disassembler.h`
+
+pub use smali_disassembler::capi::*;
@@ -0,0 +1,175 @@
+#ifndef SMALI_DISASSEMBLER_H
+#define SMALI_DISASSEMBLER_H
+
+/* generated by cbindgen from src/capi.rs, do not edit */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * what an operand is, dex indices are left unresolved
+ */
+typedef enum SmaliOperandKind {
+  SmaliOperandKind_Register,
+  SmaliOperandKind_Literal,
+  SmaliOperandKind_StringIndex,
+  SmaliOperandKind_TypeIndex,
+  SmaliOperandKind_FieldIndex,
+  SmaliOperandKind_MethodIndex,
+  SmaliOperandKind_ProtoIndex,
+  SmaliOperandKind_CallSiteIndex,
+  SmaliOperandKind_MethodHandleIndex,
+  /**
+   * field offset of a quickened `iget-quick`/`iput-quick`
+   */
+  SmaliOperandKind_FieldOffset,
+  /**
+   * vtable index of a quickened `invoke-virtual-quick`
+   */
+  SmaliOperandKind_VtableIndex,
+  /**
+   * absolute code address, in 16-bit code units, of a branch, switch or array payload
+   */
+  SmaliOperandKind_BranchTarget,
+} SmaliOperandKind;
+
+typedef struct SmaliDecoder SmaliDecoder;
+
+/**
+ * a decoded instruction, owned by its decoder
+ */
+typedef struct SmaliInstruction SmaliInstruction;
+
+/**
+ * one operand of an instruction
+ */
+typedef struct SmaliOperand {
+  enum SmaliOperandKind kind;
+  /**
+   * register pairs of wide values
+   */
+  bool wide;
+  int64_t value;
+} SmaliOperand;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * decode `len` bytes of code at `code`, the bytes are not needed after this returns.
+ * decoding stops at the first instruction that is invalid or truncated, check for that with
+ * `smali_decoder_error_offset`. returns null when `code` is null, free the decoder with
+ * `smali_decoder_free`.
+ *
+ * # Safety
+ * `code` must point to `len` readable bytes
+ */
+struct SmaliDecoder *smali_decoder_new(const uint8_t *code, size_t len);
+
+/**
+ * # Safety
+ * `decoder` must come from `smali_decoder_new` and not be used afterwards, null is ignored
+ */
+void smali_decoder_free(struct SmaliDecoder *decoder);
+
+/**
+ * write the byte offset of the instruction decoding stopped at to `offset`, false when the
+ * whole code buffer decoded
+ *
+ * # Safety
+ * `decoder` must come from `smali_decoder_new` and `offset` be writable or null
+ */
+bool smali_decoder_error_offset(const struct SmaliDecoder *decoder, size_t *offset);
+
+/**
+ * number of decoded instructions
+ *
+ * # Safety
+ * `decoder` must come from `smali_decoder_new`
+ */
+size_t smali_decoder_count(const struct SmaliDecoder *decoder);
+
+/**
+ * the instruction at `index`, null past the end. it lives as long as the decoder.
+ *
+ * # Safety
+ * `decoder` must come from `smali_decoder_new`
+ */
+const struct SmaliInstruction *smali_decoder_get(const struct SmaliDecoder *decoder, size_t index);
+
+/**
+ * the next instruction, null once every instruction was returned
+ *
+ * # Safety
+ * `decoder` must come from `smali_decoder_new`
+ */
+const struct SmaliInstruction *smali_decoder_next(struct SmaliDecoder *decoder);
+
+/**
+ * start `smali_decoder_next` over from the first instruction
+ *
+ * # Safety
+ * `decoder` must come from `smali_decoder_new`
+ */
+void smali_decoder_reset(struct SmaliDecoder *decoder);
+
+/**
+ * byte offset of the instruction in the code buffer
+ *
+ * # Safety
+ * `instruction` must come from a live decoder
+ */
+size_t smali_instruction_offset(const struct SmaliInstruction *instruction);
+
+/**
+ * size of the instruction in 16-bit code units, payloads included
+ *
+ * # Safety
+ * `instruction` must come from a live decoder
+ */
+size_t smali_instruction_code_units(const struct SmaliInstruction *instruction);
+
+/**
+ * `invoke-virtual`, `const/4`, ..., owned by the decoder
+ *
+ * # Safety
+ * `instruction` must come from a live decoder
+ */
+const char *smali_instruction_mnemonic(const struct SmaliInstruction *instruction);
+
+/**
+ * the instruction as smali with indices unresolved, `const-string v0, string@3`, owned by
+ * the decoder
+ *
+ * # Safety
+ * `instruction` must come from a live decoder
+ */
+const char *smali_instruction_text(const struct SmaliInstruction *instruction);
+
+/**
+ * number of operands, registers first in operand order then literals, indices and targets
+ *
+ * # Safety
+ * `instruction` must come from a live decoder
+ */
+size_t smali_instruction_operand_count(const struct SmaliInstruction *instruction);
+
+/**
+ * write operand `index` to `out`, false when there is no such operand
+ *
+ * # Safety
+ * `instruction` must come from a live decoder and `out` be writable
+ */
+bool smali_instruction_operand(const struct SmaliInstruction *instruction,
+                               size_t index,
+                               struct SmaliOperand *out);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* SMALI_DISASSEMBLER_H */
@@ -0,0 +1,506 @@
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyList};
+use smali_disassembler::{
+    analysis::{
+        cfg::{Cfg as MethodCfg, EdgeKind, TryBlock},
+        dot::{cfg_to_dot, Overlay},
+        xrefs::{self, XrefTarget},
+    },
+    container::{self, dex_file, EmbeddedDex},
+    dalvik::{
+        smali::{instruction_text, method_text},
+        version::DexVersion,
+        DalvikInstruction,
+    },
+    errors::Error,
+    resolver::{Resolver, Unresolved},
+    SmaliDecoder,
+};
+use std::{
+    fs,
+    io::{BufReader, BufWriter},
+};
+
+fn value_error(error: impl std::fmt::Display) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
+
+/// a decoded instruction, `text` is its smali with the operands resolved
+#[pyclass(frozen, module = "smali_disassembler")]
+struct Instruction {
+    /// byte offset in the method's code
+    #[pyo3(get)]
+    offset: usize,
+    #[pyo3(get)]
+    opcode: String,
+    #[pyo3(get)]
+    text: String,
+    /// `(kind, name)` of the string, type, field or method the instruction refers to
+    #[pyo3(get)]
+    reference: Option<(&'static str, String)>,
+}
+
+impl Instruction {
+    fn new(instruction: &DalvikInstruction, resolver: &dyn Resolver) -> Self {
+        let text = instruction_text(instruction, resolver);
+        Self {
+            offset: instruction.offset,
+            opcode: text.split(' ').next().unwrap_or_default().to_string(),
+            reference: xrefs::reference(&instruction.inst, resolver)
+                .map(|(target, _)| (target.kind(), target.name().to_string())),
+            text,
+        }
+    }
+}
+
+#[pymethods]
+impl Instruction {
+    fn __str__(&self) -> String {
+        self.text.clone()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<Instruction {:04x}: {}>", self.offset / 2, self.text)
+    }
+}
+
+/// a cfg edge, `kind` is `fallthrough`, `branch`, `case` or `exception`
+#[pyclass(frozen, module = "smali_disassembler")]
+struct Edge {
+    /// index of the target block
+    #[pyo3(get)]
+    target: usize,
+    #[pyo3(get)]
+    kind: &'static str,
+    /// switch key of a `case` edge
+    #[pyo3(get)]
+    key: Option<i32>,
+    /// caught type of an `exception` edge, `None` for a catch-all
+    #[pyo3(get)]
+    exception: Option<String>,
+}
+
+#[pymethods]
+impl Edge {
+    fn __repr__(&self) -> String {
+        format!("<Edge -> B{} {}>", self.target, self.kind)
+    }
+}
+
+/// a basic block, `start` and `end` index the method's instructions
+#[pyclass(frozen, module = "smali_disassembler")]
+struct Block {
+    #[pyo3(get)]
+    index: usize,
+    #[pyo3(get)]
+    start: usize,
+    #[pyo3(get)]
+    end: usize,
+    #[pyo3(get)]
+    successors: Vec<Py<Edge>>,
+    #[pyo3(get)]
+    predecessors: Vec<usize>,
+}
+
+#[pymethods]
+impl Block {
+    fn __repr__(&self) -> String {
+        format!("<Block B{} [{}, {})>", self.index, self.start, self.end)
+    }
+}
+
+/// control flow graph of a method, block 0 is the entry
+#[pyclass(frozen, module = "smali_disassembler")]
+struct Cfg {
+    #[pyo3(get)]
+    blocks: Vec<Py<Block>>,
+}
+
+#[pymethods]
+impl Cfg {
+    fn __len__(&self) -> usize {
+        self.blocks.len()
+    }
+
+    fn __iter__<'py>(slf: &Bound<'py, Self>) -> PyResult<Bound<'py, PyAny>> {
+        let blocks = PyList::new(slf.py(), &slf.get().blocks)?;
+        Ok(blocks.as_any().try_iter()?.into_any())
+    }
+}
+
+impl Cfg {
+    fn new(py: Python<'_>, cfg: &MethodCfg, resolver: &dyn Resolver) -> PyResult<Self> {
+        let mut blocks = vec![];
+        for (index, block) in cfg.blocks.iter().enumerate() {
+            let mut successors = vec![];
+            for edge in &block.successors {
+                let (kind, key, exception) = match edge.kind {
+                    EdgeKind::Fallthrough => ("fallthrough", None, None),
+                    EdgeKind::Branch => ("branch", None, None),
+                    EdgeKind::SwitchCase(key) => ("case", Some(key), None),
+                    EdgeKind::Exception(type_idx) => (
+                        "exception",
+                        None,
+                        type_idx.and_then(|type_idx| resolver.type_descriptor(type_idx)),
+                    ),
+                };
+                let edge = Edge {
+                    target: edge.target,
+                    kind,
+                    key,
+                    exception,
+                };
+                successors.push(Py::new(py, edge)?);
+            }
+            let block = Block {
+                index,
+                start: block.start,
+                end: block.end,
+                successors,
+                predecessors: block.predecessors.clone(),
+            };
+            blocks.push(Py::new(py, block)?);
+        }
+        Ok(Self { blocks })
+    }
+}
+
+/// a method with code, decoded when its instructions are first asked for
+#[pyclass(module = "smali_disassembler")]
+struct Method {
+    dex: Py<DexFile>,
+    /// type descriptor of the declaring class
+    #[pyo3(get)]
+    class_name: String,
+    #[pyo3(get)]
+    name: String,
+    /// method descriptor, `(ILjava/lang/String;)V`
+    #[pyo3(get)]
+    signature: String,
+    #[pyo3(get)]
+    registers: u16,
+    version: Option<DexVersion>,
+    code: Vec<u8>,
+    tries: Vec<TryBlock>,
+    decoded: Option<Vec<DalvikInstruction>>,
+}
+
+impl Method {
+    fn decoded(&mut self) -> &[DalvikInstruction] {
+        self.decoded
+            .get_or_insert_with(|| SmaliDecoder::new(&self.code, self.version).decode_all())
+    }
+
+    fn key(&self) -> String {
+        format!("{}->{}{}", self.class_name, self.name, self.signature)
+    }
+}
+
+#[pymethods]
+impl Method {
+    fn instructions(&mut self, py: Python<'_>) -> PyResult<Vec<Instruction>> {
+        let dex = self.dex.clone_ref(py);
+        let dex = dex.borrow(py);
+        let reader = dex.reader()?;
+        Ok(self
+            .decoded()
+            .iter()
+            .map(|instruction| Instruction::new(instruction, reader.as_ref()))
+            .collect())
+    }
+
+    fn __len__(&mut self) -> usize {
+        self.decoded().len()
+    }
+
+    fn __iter__(mut slf: PyRefMut<'_, Self>) -> PyResult<Bound<'_, PyAny>> {
+        let py = slf.py();
+        let instructions = PyList::new(py, slf.instructions(py)?)?;
+        Ok(instructions.as_any().try_iter()?.into_any())
+    }
+
+    /// the method as smali
+    fn smali(&mut self, py: Python<'_>) -> PyResult<String> {
+        let dex = self.dex.clone_ref(py);
+        let dex = dex.borrow(py);
+        let reader = dex.reader()?;
+        let name = format!("{}{}", self.name, self.signature);
+        let (registers, tries) = (self.registers, self.tries.clone());
+        Ok(method_text(
+            &name,
+            registers,
+            self.decoded(),
+            &tries,
+            reader.as_ref(),
+        ))
+    }
+
+    fn cfg(&mut self, py: Python<'_>) -> PyResult<Cfg> {
+        let dex = self.dex.clone_ref(py);
+        let dex = dex.borrow(py);
+        let tries = self.tries.clone();
+        let reader = dex.reader()?;
+        let cfg = MethodCfg::new(self.decoded(), &tries);
+        Cfg::new(py, &cfg, reader.as_ref())
+    }
+
+    /// the cfg as a graphviz digraph
+    #[pyo3(signature = (dominators = false, post_dominators = false))]
+    fn dot(&mut self, py: Python<'_>, dominators: bool, post_dominators: bool) -> PyResult<String> {
+        let dex = self.dex.clone_ref(py);
+        let dex = dex.borrow(py);
+        let reader = dex.reader()?;
+        let mut overlays = vec![];
+        if dominators {
+            overlays.push(Overlay::Dominators);
+        }
+        if post_dominators {
+            overlays.push(Overlay::PostDominators);
+        }
+        let key = self.key();
+        let tries = self.tries.clone();
+        let instructions = self.decoded();
+        let cfg = MethodCfg::new(instructions, &tries);
+        Ok(cfg_to_dot(
+            &key,
+            instructions,
+            &cfg,
+            reader.as_ref(),
+            &overlays,
+        ))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<Method {}>", self.key())
+    }
+}
+
+/// an instruction referring to an xref target
+#[pyclass(frozen, module = "smali_disassembler")]
+struct XrefSite {
+    #[pyo3(get)]
+    class_name: String,
+    /// method name followed by its descriptor
+    #[pyo3(get)]
+    method: String,
+    /// byte offset of the instruction in the method's code
+    #[pyo3(get)]
+    offset: usize,
+    /// `read`, `write`, `invoke-virtual`, `load`, `new`, ...
+    #[pyo3(get)]
+    access: String,
+}
+
+#[pymethods]
+impl XrefSite {
+    fn __repr__(&self) -> String {
+        format!(
+            "<XrefSite {}->{} +{:x} {}>",
+            self.class_name, self.method, self.offset, self.access
+        )
+    }
+}
+
+impl From<&xrefs::XrefSite> for XrefSite {
+    fn from(site: &xrefs::XrefSite) -> Self {
+        Self {
+            class_name: site.class.clone(),
+            method: site.method.clone(),
+            offset: site.offset,
+            access: site.access.to_string(),
+        }
+    }
+}
+
+fn target_kind(kind: &str) -> PyResult<fn(String) -> XrefTarget> {
+    Ok(match kind {
+        "string" => XrefTarget::String,
+        "type" => XrefTarget::Type,
+        "field" => XrefTarget::Field,
+        "method" => XrefTarget::Method,
+        "proto" => XrefTarget::Prototype,
+        _ => return Err(value_error(format!("unknown xref kind {:?}", kind))),
+    })
+}
+
+/// every reference to strings, types, fields, methods and prototypes, `kind` is one of
+/// `string`, `type`, `field`, `method` or `proto`
+#[pyclass(module = "smali_disassembler")]
+struct XrefIndex {
+    index: xrefs::XrefIndex,
+}
+
+#[pymethods]
+impl XrefIndex {
+    #[new]
+    fn new() -> Self {
+        Self {
+            index: xrefs::XrefIndex::new(),
+        }
+    }
+
+    fn add(&mut self, dex: PyRef<'_, DexFile>) -> PyResult<()> {
+        self.index.add_dex(dex.reader()?.as_ref());
+        Ok(())
+    }
+
+    fn get(&self, kind: &str, name: &str) -> PyResult<Vec<XrefSite>> {
+        let target = target_kind(kind)?(name.to_string());
+        Ok(self.index.get(&target).iter().map(XrefSite::from).collect())
+    }
+
+    /// targets whose name contains `needle`, with their references
+    fn search(&self, kind: &str, needle: &str) -> PyResult<Vec<(String, Vec<XrefSite>)>> {
+        Ok(self
+            .index
+            .search(target_kind(kind)?, needle)
+            .map(|(target, sites)| {
+                (
+                    target.name().to_string(),
+                    sites.iter().map(XrefSite::from).collect(),
+                )
+            })
+            .collect())
+    }
+
+    fn __len__(&self) -> usize {
+        self.index.targets().count()
+    }
+
+    fn save(&self, path: &str) -> PyResult<()> {
+        let file = fs::File::create(path)?;
+        Ok(self.index.write_to(BufWriter::new(file))?)
+    }
+
+    #[staticmethod]
+    fn load(path: &str) -> PyResult<Self> {
+        let file = fs::File::open(path)?;
+        Ok(Self {
+            index: xrefs::XrefIndex::read_from(BufReader::new(file))?,
+        })
+    }
+}
+
+/// a parsed dex file, iterating it yields its methods with code
+#[pyclass(frozen, module = "smali_disassembler")]
+struct DexFile {
+    data: Vec<u8>,
+}
+
+impl DexFile {
+    fn parse(data: Vec<u8>) -> PyResult<Self> {
+        let dex = Self { data };
+        dex.reader()?;
+        Ok(dex)
+    }
+
+    /// the reader over `data`, only its header is parsed so it's opened whenever needed
+    fn reader(&self) -> PyResult<Box<dyn dex_file::DexFile + '_>> {
+        let embedded = EmbeddedDex::parse(&self.data).ok_or(Error::InvalidContainer);
+        dex_file::open(&embedded.map_err(value_error)?).map_err(value_error)
+    }
+}
+
+#[pymethods]
+impl DexFile {
+    #[new]
+    fn new(data: &[u8]) -> PyResult<Self> {
+        Self::parse(data.to_vec())
+    }
+
+    /// type descriptors of the classes defined in the file
+    fn classes(&self) -> PyResult<Vec<String>> {
+        Ok(self
+            .reader()?
+            .readable_classes()
+            .into_iter()
+            .map(|class| class.descriptor)
+            .collect())
+    }
+
+    /// methods with code, of one class when `class_name` is given
+    #[pyo3(signature = (class_name = None))]
+    fn methods(slf: &Bound<'_, Self>, class_name: Option<&str>) -> PyResult<Vec<Method>> {
+        let reader = slf.get().reader()?;
+        let classes = match class_name {
+            Some(class_name) => reader
+                .find_class(class_name)
+                .map_err(value_error)?
+                .into_iter()
+                .collect(),
+            None => reader.readable_classes(),
+        };
+        let mut methods = vec![];
+        for class in classes {
+            for method in class.methods.iter().filter(|method| method.code_off != 0) {
+                let code = reader.code_item(method.code_off).map_err(value_error)?;
+                methods.push(Method {
+                    dex: slf.clone().unbind(),
+                    class_name: class.descriptor.clone(),
+                    name: method.name.clone(),
+                    signature: method.prototype.descriptor(),
+                    registers: code.registers_size,
+                    version: reader.version(),
+                    code: code.insns.to_vec(),
+                    tries: code.tries,
+                    decoded: None,
+                });
+            }
+        }
+        Ok(methods)
+    }
+
+    /// a method by `Lclass;->name(descriptor)`
+    fn method(slf: &Bound<'_, Self>, key: &str) -> PyResult<Option<Method>> {
+        let class_name = key.split_once("->").map(|(class, _)| class);
+        Ok(Self::methods(slf, class_name)?
+            .into_iter()
+            .find(|method| method.key() == key))
+    }
+
+    fn xrefs(&self) -> PyResult<XrefIndex> {
+        let mut index = xrefs::XrefIndex::new();
+        index.add_dex(self.reader()?.as_ref());
+        Ok(XrefIndex { index })
+    }
+
+    fn __iter__<'py>(slf: &Bound<'py, Self>) -> PyResult<Bound<'py, PyAny>> {
+        let methods = PyList::new(slf.py(), Self::methods(slf, None)?)?;
+        Ok(methods.as_any().try_iter()?.into_any())
+    }
+}
+
+/// decode raw code bytes without a dex file, operands are left as indices
+#[pyfunction]
+fn decode(code: &[u8]) -> Vec<Instruction> {
+    SmaliDecoder::new(code, None)
+        .decode_all()
+        .iter()
+        .map(|instruction| Instruction::new(instruction, &Unresolved))
+        .collect()
+}
+
+/// the dex files of an apk, dex, vdex or oat file
+#[pyfunction]
+fn load(path: &str) -> PyResult<Vec<DexFile>> {
+    let input = fs::read(path)?;
+    container::dex_files(&input)
+        .map_err(value_error)?
+        .into_iter()
+        .map(DexFile::parse)
+        .collect()
+}
+
+#[pymodule(name = "smali_disassembler")]
+fn smali_disassembler_py(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(decode, module)?)?;
+    module.add_function(wrap_pyfunction!(load, module)?)?;
+    module.add_class::<DexFile>()?;
+    module.add_class::<Method>()?;
+    module.add_class::<Instruction>()?;
+    module.add_class::<Cfg>()?;
+    module.add_class::<Block>()?;
+    module.add_class::<Edge>()?;
+    module.add_class::<XrefIndex>()?;
+    module.add_class::<XrefSite>()?;
+    Ok(())
+}
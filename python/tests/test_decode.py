@@ -0,0 +1,20 @@
+import smali_disassembler
+
+
+def test_decode():
+    code = bytes([
+        0x12, 0xF0,  # const/4 v0, -1
+        0x38, 0x00, 0xFF, 0xFF,  # if-eqz v0, -1
+        0x0E, 0x00,  # return-void
+    ])
+    instructions = smali_disassembler.decode(code)
+    assert [instruction.offset for instruction in instructions] == [0, 2, 6]
+    assert [instruction.opcode for instruction in instructions] == ["const/4", "if-eqz", "return-void"]
+    assert str(instructions[0]) == "const/4 v0, -0x1"
+    assert instructions[2].reference is None
+
+
+def test_unresolved_reference():
+    instructions = smali_disassembler.decode(bytes([0x1A, 0x00, 0x03, 0x00]))
+    assert instructions[0].text == "const-string v0, string@3"
+    assert instructions[0].reference is None
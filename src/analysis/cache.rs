@@ -0,0 +1,428 @@
+//! analysis results saved to disk, keyed by the sha-1 signatures in the dex headers and the
+//! size and modification time of the file they come from, so an unchanged apk is never decoded
+//! twice. the file is a compact binary format: a string table followed by the class index,
+//! the xrefs and the call graph, integers as uleb128.
+
+use super::{
+    callgraph::{CallEdge, CallGraph, CallKind, CallSite, MethodKey, MethodNode},
+    hierarchy::ClassHierarchy,
+    xrefs::{Access, XrefIndex, XrefSite, XrefTarget},
+};
+use crate::{
+    container::{dex_file, DexKind, EmbeddedDex},
+    errors::Error,
+    leb128::{read_uleb128, write_uleb128},
+    Result,
+};
+use sha1::{Digest, Sha1};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::Path,
+    time::UNIX_EPOCH,
+};
+
+const MAGIC: &[u8; 12] = b"smali-cache\0";
+const VERSION: u32 = 4;
+
+/// where a class is defined
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassLocation {
+    pub descriptor: String,
+    /// index of the dex file, `classes.dex` is 0
+    pub dex_index: u32,
+    /// index of the class definition in that file, see `DexFile::class`
+    pub class_def: u32,
+}
+
+/// the class index, xrefs and call graph of a set of dex files
+#[derive(Debug, Clone)]
+pub struct AnalysisCache {
+    /// header signatures of the dex files, in order
+    pub signatures: Vec<[u8; 20]>,
+    pub classes: Vec<ClassLocation>,
+    pub xrefs: XrefIndex,
+    pub call_graph: CallGraph,
+}
+
+impl AnalysisCache {
+    /// decode every dex file and run the analyses, this is the slow path the cache avoids
+    pub fn build(dex_files: &[EmbeddedDex<'_>]) -> Result<Self> {
+        Self::build_with_hierarchy(dex_files, ClassHierarchy::new())
+    }
+
+    /// `build` on a hierarchy that already holds framework classes (see
+    /// `ClassHierarchy::add_framework`), so the calls an app class inherits from them resolve
+    pub fn build_with_hierarchy(
+        dex_files: &[EmbeddedDex<'_>],
+        mut hierarchy: ClassHierarchy,
+    ) -> Result<Self> {
+        let signatures = signatures(dex_files)?;
+        let dexes = dex_files
+            .iter()
+            .map(dex_file::open)
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut classes = vec![];
+        let mut xrefs = XrefIndex::new();
+        for (dex_index, dex) in dexes.iter().enumerate() {
+            for class_def in 0..dex.class_count().unwrap_or(0) {
+                let Ok(class) = dex.class(class_def) else {
+                    continue;
+                };
+                classes.push(ClassLocation {
+                    descriptor: class.descriptor,
+                    dex_index: dex_index as u32,
+                    class_def,
+                });
+            }
+            hierarchy.add_dex(dex.as_ref());
+            xrefs.add_dex(dex.as_ref());
+        }
+        let call_graph = CallGraph::new(&dexes, &hierarchy);
+
+        Ok(Self {
+            signatures,
+            classes,
+            xrefs,
+            call_graph,
+        })
+    }
+
+    /// the cache saved in `dir` for the dex files read from `source`, `None` when it is
+    /// missing, stale or unreadable. nothing is hashed, the key is read from the headers.
+    pub fn load(dir: &Path, source: &Path, dex_files: &[EmbeddedDex<'_>]) -> Option<Self> {
+        let signatures = signatures(dex_files).ok()?;
+        let path = dir.join(file_name(&signatures, source).ok()?);
+        Self::read_from(&fs::read(path).ok()?)
+            .ok()
+            .filter(|cache| cache.signatures == signatures)
+    }
+
+    /// the cache saved in `dir` for the dex files read from `source`, built and saved there
+    /// when it is missing, stale or unreadable. the header signatures are only checked against
+    /// the files on a miss: a file whose signature doesn't match is analysed but never saved,
+    /// it could take the place of the file it claims to be.
+    pub fn load_or_build(
+        dir: &Path,
+        source: &Path,
+        dex_files: &[EmbeddedDex<'_>],
+    ) -> io::Result<Self> {
+        let invalid = |error: Error| io::Error::new(io::ErrorKind::InvalidData, error);
+
+        if let Some(cache) = Self::load(dir, source, dex_files) {
+            return Ok(cache);
+        }
+
+        let cache = Self::build(dex_files).map_err(invalid)?;
+        if !verified(dex_files) {
+            return Ok(cache);
+        }
+        let path = dir.join(file_name(&cache.signatures, source)?);
+        fs::create_dir_all(dir)?;
+        // write then rename so a concurrent reader never sees half a file
+        let partial = path.with_extension(format!("{}.partial", std::process::id()));
+        let written = fs::File::create(&partial)
+            .and_then(|file| cache.write_to(io::BufWriter::new(file)))
+            .and_then(|()| fs::rename(&partial, &path));
+        if let Err(error) = written {
+            let _ = fs::remove_file(&partial);
+            return Err(error);
+        }
+        Ok(cache)
+    }
+
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        let mut strings = Strings::default();
+        let mut body = vec![];
+
+        write_uleb128(&mut body, self.classes.len() as u32);
+        for class in &self.classes {
+            strings.write(&mut body, &class.descriptor);
+            write_uleb128(&mut body, class.dex_index);
+            write_uleb128(&mut body, class.class_def);
+        }
+
+        let targets: Vec<&XrefTarget> = self.xrefs.targets().collect();
+        write_uleb128(&mut body, targets.len() as u32);
+        for target in targets {
+            strings.write(&mut body, target.kind());
+            strings.write(&mut body, target.name());
+            let sites = self.xrefs.get(target);
+            write_uleb128(&mut body, sites.len() as u32);
+            for site in sites {
+                strings.write(&mut body, &site.class);
+                strings.write(&mut body, &site.method);
+                write_uleb128(&mut body, site.offset as u32);
+                strings.write(&mut body, site.access.as_str());
+            }
+        }
+
+        let graph = &self.call_graph;
+        write_uleb128(&mut body, graph.nodes().len() as u32);
+        for node in graph.nodes() {
+            strings.write(&mut body, &node.key.class);
+            strings.write(&mut body, &node.key.name);
+            strings.write(&mut body, &node.key.descriptor);
+            body.push(node.has_body as u8);
+        }
+        for node in 0..graph.nodes().len() {
+            let edges = graph.callees(node);
+            write_uleb128(&mut body, edges.len() as u32);
+            for edge in edges {
+                write_uleb128(&mut body, edge.target as u32);
+                body.push(call_kind_tag(edge.kind));
+                write_uleb128(&mut body, edge.offset as u32);
+            }
+        }
+        write_uleb128(&mut body, graph.call_sites().len() as u32);
+        for site in graph.call_sites() {
+            write_uleb128(&mut body, site.caller as u32);
+            write_uleb128(&mut body, site.offset as u32);
+            write_uleb128(&mut body, site.call_site_idx);
+        }
+
+        let mut header = MAGIC.to_vec();
+        write_uleb128(&mut header, VERSION);
+        write_uleb128(&mut header, self.signatures.len() as u32);
+        for signature in &self.signatures {
+            header.extend_from_slice(signature);
+        }
+        write_uleb128(&mut header, strings.table.len() as u32);
+        for string in &strings.table {
+            write_uleb128(&mut header, string.len() as u32);
+            header.extend_from_slice(string.as_bytes());
+        }
+
+        writer.write_all(&header)?;
+        writer.write_all(&body)?;
+        writer.flush()
+    }
+
+    /// a cache written by `write_to`
+    pub fn read_from(data: &[u8]) -> Result<Self> {
+        let mut reader = Reader {
+            data: data.strip_prefix(MAGIC).ok_or(Error::InvalidCache)?,
+            position: 0,
+            strings: vec![],
+        };
+        if reader.uleb()? != VERSION {
+            return Err(Error::InvalidCache);
+        }
+
+        let signatures = (0..reader.uleb()?)
+            .map(|_| {
+                reader
+                    .bytes(20)?
+                    .try_into()
+                    .map_err(|_| Error::InvalidCache)
+            })
+            .collect::<Result<Vec<[u8; 20]>>>()?;
+        for _ in 0..reader.uleb()? {
+            let len = reader.uleb()? as usize;
+            let string =
+                std::str::from_utf8(reader.bytes(len)?).map_err(|_| Error::InvalidCache)?;
+            reader.strings.push(string.to_string());
+        }
+
+        let classes = (0..reader.uleb()?)
+            .map(|_| {
+                Ok(ClassLocation {
+                    descriptor: reader.string()?,
+                    dex_index: reader.uleb()?,
+                    class_def: reader.uleb()?,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        let mut xrefs = XrefIndex::new();
+        for _ in 0..reader.uleb()? {
+            let kind = reader.string()?;
+            let name = reader.string()?;
+            let target = match kind.as_str() {
+                "string" => XrefTarget::String(name),
+                "type" => XrefTarget::Type(name),
+                "field" => XrefTarget::Field(name),
+                "method" => XrefTarget::Method(name),
+                "proto" => XrefTarget::Prototype(name),
+                _ => return Err(Error::InvalidCache),
+            };
+            for _ in 0..reader.uleb()? {
+                let site = XrefSite {
+                    class: reader.string()?,
+                    method: reader.string()?,
+                    offset: reader.uleb()? as usize,
+                    access: Access::parse(&reader.string()?).ok_or(Error::InvalidCache)?,
+                };
+                xrefs.insert(target.clone(), site);
+            }
+        }
+
+        let nodes = (0..reader.uleb()?)
+            .map(|_| {
+                Ok(MethodNode {
+                    key: MethodKey {
+                        class: reader.string()?,
+                        name: reader.string()?,
+                        descriptor: reader.string()?,
+                    },
+                    has_body: reader.byte()? != 0,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let node = |node: u32| match (node as usize) < nodes.len() {
+            true => Ok(node as usize),
+            false => Err(Error::InvalidCache),
+        };
+        let mut callees = Vec::with_capacity(nodes.len());
+        for _ in 0..nodes.len() {
+            let edges = (0..reader.uleb()?)
+                .map(|_| {
+                    Ok(CallEdge {
+                        target: node(reader.uleb()?)?,
+                        kind: call_kind(reader.byte()?)?,
+                        offset: reader.uleb()? as usize,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            callees.push(edges);
+        }
+        let call_sites = (0..reader.uleb()?)
+            .map(|_| {
+                Ok(CallSite {
+                    caller: node(reader.uleb()?)?,
+                    offset: reader.uleb()? as usize,
+                    call_site_idx: reader.uleb()?,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            signatures,
+            classes,
+            xrefs,
+            call_graph: CallGraph::from_parts(nodes, callees, call_sites),
+        })
+    }
+
+    /// the class defined with `descriptor`, the first one when several dex files define it
+    pub fn class(&self, descriptor: &str) -> Option<&ClassLocation> {
+        self.classes
+            .iter()
+            .find(|class| class.descriptor == descriptor)
+    }
+}
+
+/// the header signatures of `dex_files`, part of the cache key
+pub fn signatures(dex_files: &[EmbeddedDex<'_>]) -> Result<Vec<[u8; 20]>> {
+    dex_files
+        .iter()
+        .map(|dex| crate::integrity::signature(dex.data).ok_or(Error::InvalidContainer))
+        .collect()
+}
+
+/// whether the header signatures of the standard dex files match their contents. compact dex
+/// keeps the signature of the dex file it was converted from, there is nothing to check it
+/// against, its container's size and modification time are in the key.
+fn verified(dex_files: &[EmbeddedDex<'_>]) -> bool {
+    dex_files
+        .iter()
+        .filter(|dex| dex.kind == DexKind::Standard)
+        .all(|dex| {
+            let signature = crate::integrity::signature(dex.data);
+            signature.is_some() && signature == crate::integrity::computed_signature(dex.data)
+        })
+}
+
+/// `<sha-1 of the signatures and the size and modification time of source in hex>.cache`
+pub fn file_name(signatures: &[[u8; 20]], source: &Path) -> io::Result<String> {
+    let metadata = fs::metadata(source)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+
+    let mut hasher = Sha1::new();
+    hasher.update(signatures.concat());
+    hasher.update(metadata.len().to_le_bytes());
+    hasher.update(modified.as_nanos().to_le_bytes());
+    let hex: String = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    Ok(format!("{}.cache", hex))
+}
+
+fn call_kind_tag(kind: CallKind) -> u8 {
+    match kind {
+        CallKind::Virtual => 0,
+        CallKind::Super => 1,
+        CallKind::Direct => 2,
+        CallKind::Static => 3,
+        CallKind::Interface => 4,
+        CallKind::Polymorphic => 5,
+    }
+}
+
+fn call_kind(tag: u8) -> Result<CallKind> {
+    Ok(match tag {
+        0 => CallKind::Virtual,
+        1 => CallKind::Super,
+        2 => CallKind::Direct,
+        3 => CallKind::Static,
+        4 => CallKind::Interface,
+        5 => CallKind::Polymorphic,
+        _ => return Err(Error::InvalidCache),
+    })
+}
+
+/// every string is written once, records refer to it by its index
+#[derive(Default)]
+struct Strings<'a> {
+    table: Vec<&'a str>,
+    index: HashMap<&'a str, u32>,
+}
+
+impl<'a> Strings<'a> {
+    fn write(&mut self, out: &mut Vec<u8>, string: &'a str) {
+        let next = self.table.len() as u32;
+        let index = *self.index.entry(string).or_insert(next);
+        if index == next {
+            self.table.push(string);
+        }
+        write_uleb128(out, index);
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+    strings: Vec<String>,
+}
+
+impl<'a> Reader<'a> {
+    fn uleb(&mut self) -> Result<u32> {
+        read_uleb128(self.data, &mut self.position).map_err(|_| Error::InvalidCache)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.position.checked_add(len).ok_or(Error::InvalidCache)?;
+        let bytes = self
+            .data
+            .get(self.position..end)
+            .ok_or(Error::InvalidCache)?;
+        self.position += len;
+        Ok(bytes)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let index = self.uleb()? as usize;
+        self.strings.get(index).cloned().ok_or(Error::InvalidCache)
+    }
+}
@@ -0,0 +1,399 @@
+use super::hierarchy::ClassHierarchy;
+use crate::{
+    container::dex_file::DexFile,
+    dalvik::{opcodes::*, DalvikInstruction},
+    resolver::{MethodRef, Resolver},
+    SmaliDecoder,
+};
+use std::{
+    collections::{hash_map::Entry, BTreeSet, HashMap, HashSet, VecDeque},
+    fmt,
+};
+
+/// a method across dex files: its class, name and `(params)ret` descriptor
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MethodKey {
+    pub class: String,
+    pub name: String,
+    pub descriptor: String,
+}
+
+impl MethodKey {
+    pub fn new(class: &str, name: &str, descriptor: &str) -> Self {
+        Self {
+            class: class.to_string(),
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+        }
+    }
+}
+
+impl From<&MethodRef> for MethodKey {
+    fn from(method: &MethodRef) -> Self {
+        Self {
+            class: method.class.clone(),
+            name: method.name.clone(),
+            descriptor: method.prototype.descriptor(),
+        }
+    }
+}
+
+/// smali notation, `Lcom/example/Foo;->bar(I)V`
+impl fmt::Display for MethodKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}->{}{}", self.class, self.name, self.descriptor)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CallKind {
+    Virtual,
+    Super,
+    Direct,
+    Static,
+    Interface,
+    Polymorphic,
+}
+
+impl From<InvokeKind> for CallKind {
+    fn from(kind: InvokeKind) -> Self {
+        match kind {
+            InvokeKind::Virtual => Self::Virtual,
+            InvokeKind::Super => Self::Super,
+            InvokeKind::Direct => Self::Direct,
+            InvokeKind::Static => Self::Static,
+            InvokeKind::Interface => Self::Interface,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodNode {
+    pub key: MethodKey,
+    /// false for framework and library methods the dex files only reference
+    pub has_body: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CallEdge {
+    /// callee node
+    pub target: usize,
+    pub kind: CallKind,
+    /// byte offset of the invoke in the caller
+    pub offset: usize,
+}
+
+/// an `invoke-custom` whose target is only known once its bootstrap method runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CallSite {
+    pub caller: usize,
+    pub offset: usize,
+    pub call_site_idx: u32,
+}
+
+/// whole program call graph, virtual and interface calls resolved by class hierarchy analysis
+#[derive(Debug, Clone, Default)]
+pub struct CallGraph {
+    nodes: Vec<MethodNode>,
+    index: HashMap<MethodKey, usize>,
+    callees: Vec<Vec<CallEdge>>,
+    callers: Vec<BTreeSet<usize>>,
+    call_sites: Vec<CallSite>,
+}
+
+/// lifecycle callbacks of these classes are entered by the framework
+pub const COMPONENT_CLASSES: &[&str] = &[
+    "Landroid/app/Activity;",
+    "Landroid/app/Service;",
+    "Landroid/content/BroadcastReceiver;",
+    "Landroid/content/ContentProvider;",
+    "Landroid/app/Application;",
+];
+
+impl CallGraph {
+    /// build the call graph of every method with code in `dex_files`, the dex files of an apk
+    /// in the order they were added to `hierarchy`
+    pub fn new(dex_files: &[Box<dyn DexFile + '_>], hierarchy: &ClassHierarchy) -> Self {
+        let mut graph = Self::with_hierarchy(hierarchy);
+
+        let mut seen = HashSet::new();
+        for dex in dex_files {
+            for class in dex.readable_classes() {
+                // a class shadowed by an earlier dex file never runs
+                if !seen.insert(class.descriptor.clone()) {
+                    continue;
+                }
+
+                for method in class.methods.iter().filter(|method| method.code_off != 0) {
+                    let Ok(code) = dex.code_item(method.code_off) else {
+                        continue;
+                    };
+                    let key = MethodKey::new(
+                        &class.descriptor,
+                        &method.name,
+                        &method.prototype.descriptor(),
+                    );
+                    let instructions = SmaliDecoder::new(code.insns, dex.version()).decode_all();
+                    graph.add_method(key, &instructions, dex.as_ref(), hierarchy);
+                }
+            }
+        }
+
+        graph
+    }
+
+    /// a graph with a node for every method with code in the app classes of the hierarchy and
+    /// no calls yet
+    pub fn with_hierarchy(hierarchy: &ClassHierarchy) -> Self {
+        let mut graph = Self::default();
+        for class in hierarchy
+            .classes()
+            .filter(|class| !hierarchy.is_framework(&class.descriptor))
+        {
+            for method in class.methods.iter().filter(|method| method.has_code) {
+                let node = graph.node(MethodKey::new(
+                    &class.descriptor,
+                    &method.name,
+                    &method.descriptor,
+                ));
+                graph.nodes[node].has_body = true;
+            }
+        }
+        graph
+    }
+
+    /// add the calls made by a method, `resolver` resolves the indices of its dex file
+    pub fn add_method(
+        &mut self,
+        key: MethodKey,
+        instructions: &[DalvikInstruction],
+        resolver: &dyn Resolver,
+        hierarchy: &ClassHierarchy,
+    ) {
+        let caller = self.node(key);
+        self.nodes[caller].has_body = true;
+        for instruction in instructions {
+            self.add_calls(
+                caller,
+                instruction.offset,
+                &instruction.inst,
+                resolver,
+                hierarchy,
+            );
+        }
+    }
+
+    /// a graph from its nodes, the calls each of them makes and the `invoke-custom` sites.
+    /// the callers and the key index are rebuilt, every edge target must be a node.
+    pub(crate) fn from_parts(
+        nodes: Vec<MethodNode>,
+        callees: Vec<Vec<CallEdge>>,
+        call_sites: Vec<CallSite>,
+    ) -> Self {
+        let mut callers = vec![BTreeSet::new(); nodes.len()];
+        for (caller, edges) in callees.iter().enumerate() {
+            for edge in edges {
+                callers[edge.target].insert(caller);
+            }
+        }
+        let index = nodes
+            .iter()
+            .enumerate()
+            .map(|(node, method)| (method.key.clone(), node))
+            .collect();
+        Self {
+            nodes,
+            index,
+            callees,
+            callers,
+            call_sites,
+        }
+    }
+
+    fn node(&mut self, key: MethodKey) -> usize {
+        if let Some(&node) = self.index.get(&key) {
+            return node;
+        }
+        self.nodes.push(MethodNode {
+            key: key.clone(),
+            has_body: false,
+        });
+        self.callees.push(vec![]);
+        self.callers.push(BTreeSet::new());
+        self.index.insert(key, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    fn add_edge(&mut self, caller: usize, key: MethodKey, kind: CallKind, offset: usize) {
+        let target = self.node(key);
+        let edge = CallEdge {
+            target,
+            kind,
+            offset,
+        };
+        if !self.callees[caller].contains(&edge) {
+            self.callees[caller].push(edge);
+        }
+        self.callers[target].insert(caller);
+    }
+
+    fn add_calls(
+        &mut self,
+        caller: usize,
+        offset: usize,
+        inst: &DalvikBytecode,
+        resolver: &dyn Resolver,
+        hierarchy: &ClassHierarchy,
+    ) {
+        let (kind, method_idx) = match inst {
+            DalvikBytecode::Invoke(kind, _, method_idx)
+            | DalvikBytecode::InvokeRange(kind, _, method_idx, _) => {
+                (CallKind::from(*kind), *method_idx)
+            }
+            DalvikBytecode::InvokePolymorphic(_, method_idx, _)
+            | DalvikBytecode::InvokePolymorphicRange(_, method_idx, _, _) => {
+                (CallKind::Polymorphic, *method_idx)
+            }
+            DalvikBytecode::InvokeCustom(_, call_site_idx)
+            | DalvikBytecode::InvokeCustomRange(_, call_site_idx, _) => {
+                self.call_sites.push(CallSite {
+                    caller,
+                    offset,
+                    call_site_idx: *call_site_idx as u32,
+                });
+                return;
+            }
+            _ => return,
+        };
+        let Some(method) = resolver.method(method_idx as u32) else {
+            return;
+        };
+        let reference = MethodKey::from(&method);
+
+        let caller_class = self.nodes[caller].key.class.clone();
+        // a receiver whose method is inherited from a class that isn't loaded runs framework or
+        // library code, only the referenced method stands for it
+        let mut unresolved = false;
+        let targets: Vec<MethodKey> = match kind {
+            CallKind::Virtual | CallKind::Interface => {
+                let mut targets = BTreeSet::new();
+                for class in hierarchy.subtypes(&reference.class) {
+                    if !hierarchy
+                        .class(&class)
+                        .is_some_and(|info| !info.is_interface && !info.is_abstract)
+                    {
+                        continue;
+                    }
+                    match hierarchy.dispatch(&class, &reference.name, &reference.descriptor) {
+                        Some((class, method)) => {
+                            targets.insert(MethodKey::new(class, &method.name, &method.descriptor));
+                        }
+                        None => unresolved = true,
+                    }
+                }
+                targets.into_iter().collect()
+            }
+            CallKind::Polymorphic => vec![],
+            CallKind::Super | CallKind::Direct | CallKind::Static => {
+                let invoke_kind = match kind {
+                    CallKind::Super => InvokeKind::Super,
+                    CallKind::Direct => InvokeKind::Direct,
+                    _ => InvokeKind::Static,
+                };
+                hierarchy
+                    .resolve_invoke(
+                        invoke_kind,
+                        &caller_class,
+                        &reference.class,
+                        &reference.name,
+                        &reference.descriptor,
+                    )
+                    .map(|(class, method)| MethodKey::new(class, &method.name, &method.descriptor))
+                    .into_iter()
+                    .collect()
+            }
+        };
+
+        // calls into the framework or a library only have the referenced method
+        if targets.is_empty() || unresolved || hierarchy.class(&reference.class).is_none() {
+            self.add_edge(caller, reference, kind, offset);
+        }
+        for target in targets {
+            self.add_edge(caller, target, kind, offset);
+        }
+    }
+
+    pub fn nodes(&self) -> &[MethodNode] {
+        &self.nodes
+    }
+
+    pub fn find(&self, key: &MethodKey) -> Option<usize> {
+        self.index.get(key).copied()
+    }
+
+    pub fn callees(&self, node: usize) -> &[CallEdge] {
+        &self.callees[node]
+    }
+
+    pub fn callers(&self, node: usize) -> impl Iterator<Item = usize> + '_ {
+        self.callers[node].iter().copied()
+    }
+
+    /// `invoke-custom` calls, left unresolved
+    pub fn call_sites(&self) -> &[CallSite] {
+        &self.call_sites
+    }
+
+    /// lifecycle callbacks of activities, services, receivers, providers and the application
+    pub fn entry_points(&self, hierarchy: &ClassHierarchy) -> Vec<usize> {
+        (0..self.nodes.len())
+            .filter(|&node| {
+                let key = &self.nodes[node].key;
+                self.nodes[node].has_body
+                    && (key.name.starts_with("on") || key.name == "<init>")
+                    && COMPONENT_CLASSES
+                        .iter()
+                        .any(|component| hierarchy.is_subtype_of(&key.class, component))
+            })
+            .collect()
+    }
+
+    /// every method reachable from the given nodes
+    pub fn reachable(&self, from: &[usize]) -> BTreeSet<usize> {
+        let mut reached: BTreeSet<usize> = from.iter().copied().collect();
+        let mut worklist: Vec<usize> = from.to_vec();
+        while let Some(node) = worklist.pop() {
+            for edge in &self.callees[node] {
+                if reached.insert(edge.target) {
+                    worklist.push(edge.target);
+                }
+            }
+        }
+        reached
+    }
+
+    /// a shortest call chain from one of `from` to `to`, both ends included
+    pub fn path(&self, from: &[usize], to: usize) -> Option<Vec<usize>> {
+        let mut parent: HashMap<usize, Option<usize>> =
+            from.iter().map(|&node| (node, None)).collect();
+        let mut worklist: VecDeque<usize> = from.iter().copied().collect();
+
+        while let Some(node) = worklist.pop_front() {
+            if node == to {
+                let mut path = vec![node];
+                while let Some(&Some(previous)) = parent.get(path.last()?) {
+                    path.push(previous);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for edge in &self.callees[node] {
+                if let Entry::Vacant(entry) = parent.entry(edge.target) {
+                    entry.insert(Some(node));
+                    worklist.push_back(edge.target);
+                }
+            }
+        }
+        None
+    }
+}
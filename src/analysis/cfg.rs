@@ -0,0 +1,241 @@
+use crate::dalvik::{opcodes::DalvikBytecode, DalvikInstruction};
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    Fallthrough,
+    /// taken `if-*` or `goto`
+    Branch,
+    /// switch case with its key, the default case is the fallthrough edge
+    SwitchCase(i32),
+    /// catch handler with the caught type index, `None` for a catch-all
+    Exception(Option<u32>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    /// index of the target block
+    pub target: usize,
+    pub kind: EdgeKind,
+}
+
+#[derive(Debug, Clone)]
+pub struct BasicBlock {
+    /// index of the first instruction of the block
+    pub start: usize,
+    /// index past the last instruction of the block
+    pub end: usize,
+    pub successors: Vec<Edge>,
+    /// indices of the predecessor blocks
+    pub predecessors: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CatchHandler {
+    /// caught type index, `None` for a catch-all
+    pub type_idx: Option<u32>,
+    /// handler address in code units
+    pub addr: u32,
+}
+
+/// a try item of a code item, addresses are in code units like in the dex file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TryBlock {
+    pub start_addr: u32,
+    pub insn_count: u16,
+    pub handlers: Vec<CatchHandler>,
+}
+
+impl TryBlock {
+    fn covers(&self, addr: u32) -> bool {
+        addr >= self.start_addr && addr < self.start_addr + self.insn_count as u32
+    }
+}
+
+/// control flow graph of a method, block 0 is the entry. payloads are not part of any block.
+#[derive(Debug, Clone)]
+pub struct Cfg {
+    pub blocks: Vec<BasicBlock>,
+    /// block of every instruction, by instruction index
+    block_of: Vec<Option<usize>>,
+}
+
+impl Cfg {
+    pub fn new(instructions: &[DalvikInstruction], tries: &[TryBlock]) -> Self {
+        let index_of: HashMap<usize, usize> = instructions
+            .iter()
+            .enumerate()
+            .map(|(index, instruction)| (instruction.offset, index))
+            .collect();
+        let addr_to_index = |addr: i64| index_of.get(&((addr * 2) as usize)).copied();
+
+        // (instruction index, edge kind, target instruction index) for every explicit jump
+        let mut jumps = vec![];
+        let mut leaders = BTreeSet::new();
+        if !instructions.is_empty() {
+            leaders.insert(0);
+        }
+
+        for (index, instruction) in instructions.iter().enumerate() {
+            let addr = (instruction.offset / 2) as i64;
+            let mut jump = |kind: EdgeKind, relative: i64| {
+                if let Some(target) = addr_to_index(addr + relative) {
+                    jumps.push((index, kind, target));
+                }
+            };
+
+            match &instruction.inst {
+                DalvikBytecode::Goto(offset) => jump(EdgeKind::Branch, *offset as i64),
+                DalvikBytecode::Goto16(offset)
+                | DalvikBytecode::IfTest(_, _, _, offset)
+                | DalvikBytecode::IfTestZ(_, _, offset) => jump(EdgeKind::Branch, *offset as i64),
+                DalvikBytecode::Goto32(offset) => jump(EdgeKind::Branch, *offset as i64),
+                DalvikBytecode::PackedSwitch(_, payload)
+                | DalvikBytecode::SparseSwitch(_, payload) => {
+                    let payload = addr_to_index(addr + *payload as i64)
+                        .map(|payload| &instructions[payload].inst);
+                    for (key, target) in switch_cases(payload) {
+                        jump(EdgeKind::SwitchCase(key), target as i64);
+                    }
+                }
+                _ => {}
+            }
+
+            let ends_block = !instruction.inst.can_fall_through()
+                || matches!(
+                    instruction.inst,
+                    DalvikBytecode::IfTest(..)
+                        | DalvikBytecode::IfTestZ(..)
+                        | DalvikBytecode::PackedSwitch(..)
+                        | DalvikBytecode::SparseSwitch(..)
+                );
+            if ends_block && index + 1 < instructions.len() {
+                leaders.insert(index + 1);
+            }
+        }
+
+        leaders.extend(jumps.iter().map(|&(_, _, target)| target));
+        for try_block in tries {
+            let end = try_block.start_addr as i64 + try_block.insn_count as i64;
+            leaders.extend(addr_to_index(try_block.start_addr as i64));
+            leaders.extend(addr_to_index(end));
+            leaders.extend(
+                try_block
+                    .handlers
+                    .iter()
+                    .filter_map(|handler| addr_to_index(handler.addr as i64)),
+            );
+        }
+
+        // split into blocks, leaving payloads out
+        let mut blocks = vec![];
+        let mut block_of = vec![None; instructions.len()];
+        let leaders: Vec<usize> = leaders.into_iter().collect();
+        for (position, &start) in leaders.iter().enumerate() {
+            let limit = leaders
+                .get(position + 1)
+                .copied()
+                .unwrap_or(instructions.len());
+            let end = (start..limit)
+                .find(|&index| instructions[index].inst.is_payload())
+                .unwrap_or(limit);
+            if start == end {
+                continue;
+            }
+
+            block_of[start..end].fill(Some(blocks.len()));
+            blocks.push(BasicBlock {
+                start,
+                end,
+                successors: vec![],
+                predecessors: vec![],
+            });
+        }
+
+        let mut cfg = Self { blocks, block_of };
+        for &(index, kind, target) in &jumps {
+            cfg.add_edge(index, target, kind);
+        }
+
+        for block in 0..cfg.blocks.len() {
+            let last = cfg.blocks[block].end - 1;
+            if instructions[last].inst.can_fall_through() && last + 1 < instructions.len() {
+                cfg.add_edge(last, last + 1, EdgeKind::Fallthrough);
+            }
+
+            let addr = (instructions[cfg.blocks[block].start].offset / 2) as u32;
+            for try_block in tries.iter().filter(|try_block| try_block.covers(addr)) {
+                for handler in &try_block.handlers {
+                    if let Some(target) = addr_to_index(handler.addr as i64) {
+                        cfg.add_edge(last, target, EdgeKind::Exception(handler.type_idx));
+                    }
+                }
+            }
+        }
+
+        cfg
+    }
+
+    fn add_edge(&mut self, from: usize, to: usize, kind: EdgeKind) {
+        let (Some(from), Some(to)) = (self.block_of[from], self.block_of[to]) else {
+            return;
+        };
+
+        let edge = Edge { target: to, kind };
+        if !self.blocks[from].successors.contains(&edge) {
+            self.blocks[from].successors.push(edge);
+        }
+        if !self.blocks[to].predecessors.contains(&from) {
+            self.blocks[to].predecessors.push(from);
+        }
+    }
+
+    /// block holding the instruction at `index`, `None` for payloads
+    pub fn block_of(&self, index: usize) -> Option<usize> {
+        self.block_of.get(index).copied().flatten()
+    }
+
+    /// blocks reachable from the entry, in reverse postorder
+    pub fn reverse_postorder(&self) -> Vec<usize> {
+        let mut visited = vec![false; self.blocks.len()];
+        let mut postorder = vec![];
+        // (block, next successor to visit)
+        let mut stack = vec![];
+
+        if !self.blocks.is_empty() {
+            visited[0] = true;
+            stack.push((0, 0));
+        }
+
+        while let Some((block, successor)) = stack.pop() {
+            match self.blocks[block].successors.get(successor) {
+                Some(edge) => {
+                    stack.push((block, successor + 1));
+                    if !visited[edge.target] {
+                        visited[edge.target] = true;
+                        stack.push((edge.target, 0));
+                    }
+                }
+                None => postorder.push(block),
+            }
+        }
+
+        postorder.reverse();
+        postorder
+    }
+}
+
+/// (key, target relative to the switch) of every case of a switch payload
+pub(crate) fn switch_cases(payload: Option<&DalvikBytecode>) -> Vec<(i32, i32)> {
+    match payload {
+        Some(DalvikBytecode::PackedSwitchPayload(first_key, targets)) => targets
+            .iter()
+            .enumerate()
+            .map(|(case, &target)| (first_key.wrapping_add(case as i32), target))
+            .collect(),
+        Some(DalvikBytecode::SparseSwitchPayload(keys, targets)) => {
+            keys.iter().copied().zip(targets.iter().copied()).collect()
+        }
+        _ => vec![],
+    }
+}
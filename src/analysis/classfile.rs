@@ -0,0 +1,183 @@
+use super::hierarchy::{ClassInfo, MethodInfo, Visibility};
+use crate::errors::Error;
+
+const MAGIC: u32 = 0xcafe_babe;
+
+const ACC_PUBLIC: u16 = 0x0001;
+const ACC_PRIVATE: u16 = 0x0002;
+const ACC_PROTECTED: u16 = 0x0004;
+const ACC_STATIC: u16 = 0x0008;
+const ACC_NATIVE: u16 = 0x0100;
+const ACC_INTERFACE: u16 = 0x0200;
+const ACC_ABSTRACT: u16 = 0x0400;
+
+/// big endian reader over a class file
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> crate::Result<&'a [u8]> {
+        let end = self
+            .position
+            .checked_add(len)
+            .ok_or(Error::InvalidClassFile)?;
+        let bytes = self
+            .data
+            .get(self.position..end)
+            .ok_or(Error::InvalidClassFile)?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> crate::Result<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> crate::Result<u32> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// skip the attributes of a field, method or class
+    fn skip_attributes(&mut self) -> crate::Result<()> {
+        for _ in 0..self.u16()? {
+            self.u16()?;
+            let len = self.u32()? as usize;
+            self.bytes(len)?;
+        }
+        Ok(())
+    }
+}
+
+/// constant pool entries the hierarchy needs, everything else is skipped
+#[derive(Clone)]
+enum Constant {
+    Utf8(String),
+    Class(u16),
+    Other,
+}
+
+fn utf8(pool: &[Constant], index: u16) -> crate::Result<&str> {
+    match pool.get(index as usize) {
+        Some(Constant::Utf8(text)) => Ok(text),
+        _ => Err(Error::InvalidClassFile),
+    }
+}
+
+/// the type descriptor of a `CONSTANT_Class` entry
+fn class_descriptor(pool: &[Constant], index: u16) -> crate::Result<String> {
+    let Some(Constant::Class(name)) = pool.get(index as usize) else {
+        return Err(Error::InvalidClassFile);
+    };
+    let name = utf8(pool, *name)?;
+    Ok(match name.starts_with('[') {
+        true => name.to_string(),
+        false => format!("L{};", name),
+    })
+}
+
+fn visibility(access_flags: u16) -> Visibility {
+    if access_flags & ACC_PUBLIC != 0 {
+        Visibility::Public
+    } else if access_flags & ACC_PROTECTED != 0 {
+        Visibility::Protected
+    } else if access_flags & ACC_PRIVATE != 0 {
+        Visibility::Private
+    } else {
+        Visibility::Package
+    }
+}
+
+/// read the declaration of a java class file, as found in `android.jar`. stub methods have
+/// no dex code, so `has_code` is false for all of them.
+pub fn read_class_file(data: &[u8]) -> crate::Result<ClassInfo> {
+    let mut reader = Reader { data, position: 0 };
+    if reader.u32()? != MAGIC {
+        return Err(Error::InvalidClassFile);
+    }
+    // minor and major version
+    reader.u32()?;
+
+    let count = reader.u16()?;
+    let mut pool = vec![Constant::Other; count as usize];
+    let mut index = 1;
+    while index < count as usize {
+        let slot = index;
+        let tag = reader.bytes(1)?[0];
+        pool[slot] = match tag {
+            1 => {
+                let len = reader.u16()? as usize;
+                Constant::Utf8(String::from_utf8_lossy(reader.bytes(len)?).into_owned())
+            }
+            7 => Constant::Class(reader.u16()?),
+            // long and double take two slots, the second has to be in the pool too
+            5 | 6 => {
+                reader.bytes(8)?;
+                index += 1;
+                if index >= count as usize {
+                    return Err(Error::InvalidClassFile);
+                }
+                Constant::Other
+            }
+            3 | 4 | 9 | 10 | 11 | 12 | 17 | 18 => {
+                reader.bytes(4)?;
+                Constant::Other
+            }
+            15 => {
+                reader.bytes(3)?;
+                Constant::Other
+            }
+            8 | 16 | 19 | 20 => {
+                reader.bytes(2)?;
+                Constant::Other
+            }
+            _ => return Err(Error::InvalidClassFile),
+        };
+        index += 1;
+    }
+
+    let access_flags = reader.u16()?;
+    let descriptor = class_descriptor(&pool, reader.u16()?)?;
+    let super_class = match reader.u16()? {
+        0 => None,
+        super_class => Some(class_descriptor(&pool, super_class)?),
+    };
+    let interfaces = (0..reader.u16()?)
+        .map(|_| class_descriptor(&pool, reader.u16()?))
+        .collect::<crate::Result<Vec<_>>>()?;
+
+    for _ in 0..reader.u16()? {
+        // access flags, name and descriptor
+        reader.bytes(6)?;
+        reader.skip_attributes()?;
+    }
+
+    let mut methods = vec![];
+    for _ in 0..reader.u16()? {
+        let access_flags = reader.u16()?;
+        let name = utf8(&pool, reader.u16()?)?.to_string();
+        let method_descriptor = utf8(&pool, reader.u16()?)?.to_string();
+        reader.skip_attributes()?;
+        methods.push(MethodInfo {
+            name,
+            descriptor: method_descriptor,
+            visibility: visibility(access_flags),
+            is_static: access_flags & ACC_STATIC != 0,
+            is_abstract: access_flags & ACC_ABSTRACT != 0,
+            is_native: access_flags & ACC_NATIVE != 0,
+            has_code: false,
+        });
+    }
+
+    Ok(ClassInfo {
+        descriptor,
+        super_class,
+        interfaces,
+        is_interface: access_flags & ACC_INTERFACE != 0,
+        is_abstract: access_flags & ACC_ABSTRACT != 0,
+        methods,
+    })
+}
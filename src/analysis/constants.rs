@@ -0,0 +1,882 @@
+use super::{
+    cfg::{Cfg, EdgeKind},
+    dominators::Dominators,
+    ssa::{Ssa, SsaInstruction, Value, ValueSource},
+};
+use crate::{
+    dalvik::{opcodes::*, DalvikInstruction},
+    resolver::Resolver,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// a statically known value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConstValue {
+    /// 32 bit value, an int or the bits of a float
+    Narrow(i32),
+    /// 64 bit value held by the low half of a pair, a long or the bits of a double
+    Wide(i64),
+    /// the high half of a wide constant
+    WideHigh,
+    /// the string with this string index
+    String(u32),
+}
+
+/// sparse conditional constant propagation lattice
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Lattice {
+    /// no definition reached yet
+    Undefined,
+    Constant(ConstValue),
+    /// may take several values
+    Overdefined,
+}
+
+impl Lattice {
+    fn meet(&self, other: &Self) -> Self {
+        match (self, other) {
+            (Self::Undefined, value) | (value, Self::Undefined) => value.clone(),
+            (Self::Constant(a), Self::Constant(b)) if a == b => self.clone(),
+            _ => Self::Overdefined,
+        }
+    }
+}
+
+/// a conditional branch that always goes the same way
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpaquePredicate {
+    /// index of the `if-*` instruction
+    pub index: usize,
+    pub taken: bool,
+}
+
+/// a `new-array` whose contents never change after its `fill-array-data`
+#[derive(Debug, Clone)]
+struct KnownArray {
+    size: Value,
+    width: usize,
+    data: Vec<u8>,
+}
+
+enum User {
+    Instruction(usize),
+    Phi(usize, usize),
+}
+
+/// result of constant propagation over the ssa form of a method
+#[derive(Debug, Clone)]
+pub struct ConstantPropagation {
+    values: Vec<Lattice>,
+    executable: Vec<bool>,
+    predicates: Vec<OpaquePredicate>,
+    strings: BTreeMap<usize, String>,
+}
+
+struct Propagator<'a> {
+    instructions: &'a [DalvikInstruction],
+    cfg: &'a Cfg,
+    ssa: &'a Ssa,
+    arrays: HashMap<Value, KnownArray>,
+    values: Vec<Lattice>,
+    executable: Vec<bool>,
+    edges: BTreeSet<(usize, usize)>,
+    flow_worklist: Vec<(Option<usize>, usize)>,
+    value_worklist: Vec<Value>,
+}
+
+impl ConstantPropagation {
+    /// fold the constants of a method. arrays filled from an array literal and turned into
+    /// a `String` are folded into the string they decode to, which resolves the usual
+    /// `new String(new char[] {...})` string hiding.
+    pub fn new(
+        instructions: &[DalvikInstruction],
+        cfg: &Cfg,
+        ssa: &Ssa,
+        resolver: &dyn Resolver,
+    ) -> Self {
+        let mut users: HashMap<Value, Vec<User>> = HashMap::new();
+        for (block, data) in ssa.blocks.iter().enumerate() {
+            for (phi, data) in data.phis.iter().enumerate() {
+                for operand in &data.operands {
+                    users
+                        .entry(operand.value)
+                        .or_default()
+                        .push(User::Phi(block, phi));
+                }
+            }
+            for instruction in &data.instructions {
+                for &(_, value) in &instruction.uses {
+                    users
+                        .entry(value)
+                        .or_default()
+                        .push(User::Instruction(instruction.index));
+                }
+            }
+        }
+
+        let mut propagator = Propagator {
+            instructions,
+            cfg,
+            ssa,
+            arrays: known_arrays(instructions, cfg, ssa, resolver),
+            values: ssa
+                .values()
+                .iter()
+                .map(|info| match info.source {
+                    ValueSource::Entry => Lattice::Overdefined,
+                    _ => Lattice::Undefined,
+                })
+                .collect(),
+            executable: vec![false; cfg.blocks.len()],
+            edges: BTreeSet::new(),
+            flow_worklist: vec![],
+            value_worklist: vec![],
+        };
+        if !cfg.blocks.is_empty() {
+            propagator.flow_worklist.push((None, 0));
+        }
+
+        loop {
+            if let Some((from, to)) = propagator.flow_worklist.pop() {
+                propagator.visit_edge(from, to);
+            } else if let Some(value) = propagator.value_worklist.pop() {
+                for user in users.get(&value).into_iter().flatten() {
+                    match *user {
+                        User::Phi(block, phi) if propagator.executable[block] => {
+                            propagator.visit_phi(block, phi)
+                        }
+                        User::Instruction(index) => propagator.visit_instruction(index),
+                        User::Phi(..) => {}
+                    }
+                }
+            } else {
+                break;
+            }
+        }
+
+        let predicates = propagator.opaque_predicates();
+        let strings = propagator.fold_strings(resolver);
+        Self {
+            values: propagator.values,
+            executable: propagator.executable,
+            predicates,
+            strings,
+        }
+    }
+
+    pub fn value(&self, value: Value) -> &Lattice {
+        &self.values[value.0 as usize]
+    }
+
+    pub fn constant(&self, value: Value) -> Option<&ConstValue> {
+        match self.value(value) {
+            Lattice::Constant(constant) => Some(constant),
+            _ => None,
+        }
+    }
+
+    /// whether some path with the known constants reaches the block
+    pub fn is_executable(&self, block: usize) -> bool {
+        self.executable[block]
+    }
+
+    pub fn unreachable_blocks(&self) -> Vec<usize> {
+        (0..self.executable.len())
+            .filter(|&block| !self.executable[block])
+            .collect()
+    }
+
+    pub fn opaque_predicates(&self) -> &[OpaquePredicate] {
+        &self.predicates
+    }
+
+    /// strings built from constant arrays, by index of the invoke building them
+    pub fn folded_strings(&self) -> &BTreeMap<usize, String> {
+        &self.strings
+    }
+}
+
+/// the array descriptor when the use at `position` passes the array `root` to a string
+/// constructor
+fn string_argument(
+    inst: &DalvikBytecode,
+    ssa_inst: &SsaInstruction,
+    position: usize,
+    ssa: &Ssa,
+    instructions: &[DalvikInstruction],
+    root: Value,
+    resolver: &dyn Resolver,
+) -> Option<&'static str> {
+    let method_idx = match inst {
+        DalvikBytecode::Invoke(_, _, method_idx)
+        | DalvikBytecode::InvokeRange(_, _, method_idx, _) => *method_idx,
+        _ => return None,
+    };
+    let method = resolver.method(method_idx as u32)?;
+    if method.class != "Ljava/lang/String;" || method.prototype.parameters.len() != 1 {
+        return None;
+    }
+
+    let argument = match method.name.as_str() {
+        "<init>" => 1,
+        "valueOf" | "copyValueOf" => 0,
+        _ => return None,
+    };
+    let &(_, value) = ssa_inst.uses.get(argument)?;
+    if position != argument || ssa.copy_source(instructions, value) != root {
+        return None;
+    }
+
+    match method.prototype.parameters[0].as_str() {
+        "[C" => Some("[C"),
+        "[B" if method.name == "<init>" => Some("[B"),
+        _ => None,
+    }
+}
+
+/// find the arrays filled once by `fill-array-data` before any read, that are never written
+/// or passed anywhere except to a string constructor
+fn known_arrays(
+    instructions: &[DalvikInstruction],
+    cfg: &Cfg,
+    ssa: &Ssa,
+    resolver: &dyn Resolver,
+) -> HashMap<Value, KnownArray> {
+    let dominators = Dominators::new(cfg);
+    let index_of: HashMap<usize, usize> = instructions
+        .iter()
+        .enumerate()
+        .map(|(index, instruction)| (instruction.offset, index))
+        .collect();
+
+    let mut arrays = HashMap::new();
+    for (block, data) in ssa.blocks.iter().enumerate() {
+        for ssa_inst in &data.instructions {
+            if let (DalvikBytecode::NewArray(..), Some(&(_, array)), Some(&(_, size))) = (
+                &instructions[ssa_inst.index].inst,
+                ssa_inst.defs.first(),
+                ssa_inst.uses.first(),
+            ) {
+                arrays.insert(array, (block, size, vec![], vec![]));
+            }
+        }
+    }
+
+    let mut escaped = BTreeSet::new();
+    for data in &ssa.blocks {
+        for phi in &data.phis {
+            for operand in &phi.operands {
+                escaped.insert(ssa.copy_source(instructions, operand.value));
+            }
+        }
+    }
+
+    for (block, data) in ssa.blocks.iter().enumerate() {
+        for ssa_inst in &data.instructions {
+            let inst = &instructions[ssa_inst.index].inst;
+            for (position, &(_, value)) in ssa_inst.uses.iter().enumerate() {
+                let root = ssa.copy_source(instructions, value);
+                let Some((_, _, fills, reads)) = arrays.get_mut(&root) else {
+                    continue;
+                };
+
+                match inst {
+                    // copies are followed through `copy_source`
+                    DalvikBytecode::Move(..)
+                    | DalvikBytecode::MoveFrom16(..)
+                    | DalvikBytecode::Move16(..) => {}
+                    DalvikBytecode::ArrayLength(..) => reads.push((block, ssa_inst.index)),
+                    DalvikBytecode::ArrayOp(kind, ..) if !kind.is_put() && position == 0 => {
+                        reads.push((block, ssa_inst.index))
+                    }
+                    DalvikBytecode::FilledArrayData(_, payload) => {
+                        let addr =
+                            (instructions[ssa_inst.index].offset / 2) as i64 + *payload as i64;
+                        let payload = index_of
+                            .get(&((addr * 2) as usize))
+                            .map(|&payload| &instructions[payload].inst);
+                        match payload {
+                            Some(DalvikBytecode::FillArrayDataPayload(width, data)) => {
+                                fills.push((block, ssa_inst.index, *width as usize, data.clone()))
+                            }
+                            _ => {
+                                escaped.insert(root);
+                            }
+                        }
+                    }
+                    _ if string_argument(
+                        inst,
+                        ssa_inst,
+                        position,
+                        ssa,
+                        instructions,
+                        root,
+                        resolver,
+                    )
+                    .is_some() =>
+                    {
+                        reads.push((block, ssa_inst.index))
+                    }
+                    _ => {
+                        escaped.insert(root);
+                    }
+                }
+            }
+        }
+    }
+
+    let precedes =
+        |(a_block, a_index): (usize, usize), (b_block, b_index): (usize, usize)| match a_block
+            == b_block
+        {
+            true => a_index < b_index,
+            false => dominators.dominates(a_block, b_block),
+        };
+
+    arrays
+        .into_iter()
+        .filter(|(array, _)| !escaped.contains(array))
+        .filter_map(|(array, (_, size, fills, reads))| {
+            let (width, data) = match fills.as_slice() {
+                [] => (0, vec![]),
+                [(block, index, width, data)] => {
+                    let filled_first = reads.iter().all(|&read| precedes((*block, *index), read));
+                    if !filled_first {
+                        return None;
+                    }
+                    (*width, data.clone())
+                }
+                _ => return None,
+            };
+            Some((array, KnownArray { size, width, data }))
+        })
+        .collect()
+}
+
+impl Propagator<'_> {
+    fn lattice(&self, value: Value) -> &Lattice {
+        &self.values[value.0 as usize]
+    }
+
+    fn update(&mut self, value: Value, new: Lattice) {
+        let old = &self.values[value.0 as usize];
+        // values only ever go down the lattice
+        let merged = match (old, &new) {
+            (Lattice::Undefined, _) => new,
+            (old, new) if old == new => return,
+            _ => Lattice::Overdefined,
+        };
+        if *old != merged {
+            self.values[value.0 as usize] = merged;
+            self.value_worklist.push(value);
+        }
+    }
+
+    fn visit_edge(&mut self, from: Option<usize>, to: usize) {
+        if let Some(from) = from {
+            if !self.edges.insert((from, to)) {
+                return;
+            }
+        } else if self.executable[to] {
+            return;
+        }
+
+        for phi in 0..self.ssa.blocks[to].phis.len() {
+            self.visit_phi(to, phi);
+        }
+        if std::mem::replace(&mut self.executable[to], true) {
+            return;
+        }
+
+        let block = &self.cfg.blocks[to];
+        for edge in &block.successors {
+            if let EdgeKind::Exception(_) = edge.kind {
+                self.flow_worklist.push((Some(to), edge.target));
+            }
+        }
+        for index in block.start..block.end {
+            self.visit_instruction(index);
+        }
+    }
+
+    fn visit_phi(&mut self, block: usize, phi: usize) {
+        let phi = &self.ssa.blocks[block].phis[phi];
+        let value = phi
+            .operands
+            .iter()
+            .filter(|operand| match operand.predecessor {
+                Some(predecessor) => self.edges.contains(&(predecessor, block)),
+                None => true,
+            })
+            .fold(Lattice::Undefined, |value, operand| {
+                value.meet(self.lattice(operand.value))
+            });
+        self.update(phi.value, value);
+    }
+
+    fn visit_instruction(&mut self, index: usize) {
+        let Some(block) = self
+            .cfg
+            .block_of(index)
+            .filter(|&block| self.executable[block])
+        else {
+            return;
+        };
+        let Some(ssa_inst) = self.ssa.instruction(index) else {
+            return;
+        };
+
+        let result = self.evaluate(index, ssa_inst);
+        match (result, ssa_inst.defs.as_slice()) {
+            (_, []) => {}
+            (Lattice::Constant(ConstValue::Wide(value)), [(_, low), (_, high)]) => {
+                self.update(*low, Lattice::Constant(ConstValue::Wide(value)));
+                self.update(*high, Lattice::Constant(ConstValue::WideHigh));
+            }
+            (result, defs) => {
+                for &(_, def) in defs {
+                    self.update(def, result.clone());
+                }
+            }
+        }
+
+        if index + 1 == self.cfg.blocks[block].end {
+            self.visit_branch(block, index, ssa_inst);
+        }
+    }
+
+    /// the use at `position`, for a pair its low half
+    fn operand(&self, ssa_inst: &SsaInstruction, position: usize) -> Lattice {
+        ssa_inst
+            .uses
+            .get(position)
+            .map_or(Lattice::Overdefined, |&(_, value)| {
+                self.lattice(value).clone()
+            })
+    }
+
+    fn fold2(
+        &self,
+        a: Lattice,
+        b: Lattice,
+        fold: impl Fn(&ConstValue, &ConstValue) -> Option<ConstValue>,
+    ) -> Lattice {
+        match (a, b) {
+            (Lattice::Constant(a), Lattice::Constant(b)) => {
+                fold(&a, &b).map_or(Lattice::Overdefined, Lattice::Constant)
+            }
+            (Lattice::Overdefined, _) | (_, Lattice::Overdefined) => Lattice::Overdefined,
+            _ => Lattice::Undefined,
+        }
+    }
+
+    fn evaluate(&self, index: usize, ssa_inst: &SsaInstruction) -> Lattice {
+        use ConstValue::*;
+        use DalvikBytecode as B;
+
+        let constant = |value| Lattice::Constant(value);
+        let inst = &self.instructions[index].inst;
+        match inst {
+            B::Const4(_, value) => constant(Narrow(*value as i32)),
+            B::Const16(_, value) => constant(Narrow(*value as i32)),
+            B::Const(_, value) => constant(Narrow(*value)),
+            B::ConstHigh16(_, value) => constant(Narrow((*value as i32) << 16)),
+            B::ConstWide16(_, value) => constant(Wide(*value as i64)),
+            B::ConstWide32(_, value) => constant(Wide(*value as i64)),
+            B::ConstWide(_, value) => constant(Wide(*value as i64)),
+            B::ConstWideHigh16(_, value) => constant(Wide((*value as i64) << 48)),
+            B::ConstString(_, string_idx) => constant(String(*string_idx as u32)),
+            B::ConstStringJumbo(_, string_idx) => constant(String(*string_idx)),
+
+            B::Move(..) | B::MoveFrom16(..) | B::Move16(..) => self.operand(ssa_inst, 0),
+            B::MoveResult(MoveKind::Exception, _) => Lattice::Overdefined,
+            B::MoveResult(..) => self.operand(ssa_inst, 0),
+
+            B::Unop(kind, ..) => match self.operand(ssa_inst, 0) {
+                Lattice::Constant(value) => {
+                    fold_unop(*kind, &value).map_or(Lattice::Overdefined, Lattice::Constant)
+                }
+                other => other,
+            },
+            B::Binop(kind, ..) | B::Binop2Addr(kind, ..) => {
+                let second = if kind.is_wide() { 2 } else { 1 };
+                self.fold2(
+                    self.operand(ssa_inst, 0),
+                    self.operand(ssa_inst, second),
+                    |a, b| fold_binop(*kind, a, b),
+                )
+            }
+            B::BinopLit16(kind, _, _, literal) => self.fold2(
+                self.operand(ssa_inst, 0),
+                constant(Narrow(*literal as i32)),
+                |a, b| fold_binop(*kind, a, b),
+            ),
+            B::BinopLit8(kind, _, _, literal) => self.fold2(
+                self.operand(ssa_inst, 0),
+                constant(Narrow(*literal as i32)),
+                |a, b| fold_binop(*kind, a, b),
+            ),
+            B::Cmp(kind, ..) => {
+                let second = if kind.is_wide() { 2 } else { 1 };
+                self.fold2(
+                    self.operand(ssa_inst, 0),
+                    self.operand(ssa_inst, second),
+                    |a, b| fold_cmp(*kind, a, b),
+                )
+            }
+
+            B::ArrayLength(..) => match self.known_array(ssa_inst, 0) {
+                Some(array) => self.lattice(array.size).clone(),
+                None => Lattice::Overdefined,
+            },
+            B::ArrayOp(kind, ..) if !kind.is_put() => {
+                let Some(array) = self.known_array(ssa_inst, 0) else {
+                    return Lattice::Overdefined;
+                };
+                self.fold2(
+                    self.lattice(array.size).clone(),
+                    self.operand(ssa_inst, 1),
+                    |size, index| array.element(*kind, size, index),
+                )
+            }
+
+            _ => Lattice::Overdefined,
+        }
+    }
+
+    fn known_array(&self, ssa_inst: &SsaInstruction, position: usize) -> Option<&KnownArray> {
+        let &(_, value) = ssa_inst.uses.get(position)?;
+        self.arrays
+            .get(&self.ssa.copy_source(self.instructions, value))
+    }
+
+    /// whether the branch ending the block is taken, `None` when it can't be decided
+    fn decide(&self, inst: &DalvikBytecode, ssa_inst: &SsaInstruction) -> Option<Lattice> {
+        let (kind, a, b) = match inst {
+            DalvikBytecode::IfTest(kind, ..) => {
+                (kind, self.operand(ssa_inst, 0), self.operand(ssa_inst, 1))
+            }
+            DalvikBytecode::IfTestZ(kind, ..) => (
+                kind,
+                self.operand(ssa_inst, 0),
+                Lattice::Constant(ConstValue::Narrow(0)),
+            ),
+            _ => return None,
+        };
+
+        Some(self.fold2(a, b, |a, b| {
+            let taken = match (a, b) {
+                (ConstValue::Narrow(a), ConstValue::Narrow(b)) => match kind {
+                    IfKind::Eq => a == b,
+                    IfKind::Ne => a != b,
+                    IfKind::It => a < b,
+                    IfKind::Ge => a >= b,
+                    IfKind::Gt => a > b,
+                    IfKind::Le => a <= b,
+                },
+                // a string constant is never null
+                (ConstValue::String(_), ConstValue::Narrow(0)) => match kind {
+                    IfKind::Eq => false,
+                    IfKind::Ne => true,
+                    _ => return None,
+                },
+                _ => return None,
+            };
+            Some(ConstValue::Narrow(taken as i32))
+        }))
+    }
+
+    fn visit_branch(&mut self, block: usize, index: usize, ssa_inst: &SsaInstruction) {
+        let inst = &self.instructions[index].inst;
+        let follow: Box<dyn Fn(EdgeKind) -> bool> = match (inst, self.decide(inst, ssa_inst)) {
+            (_, Some(Lattice::Undefined)) => return,
+            (_, Some(Lattice::Constant(ConstValue::Narrow(taken)))) => {
+                Box::new(move |kind| match taken != 0 {
+                    true => kind == EdgeKind::Branch,
+                    false => kind == EdgeKind::Fallthrough,
+                })
+            }
+            (DalvikBytecode::PackedSwitch(..) | DalvikBytecode::SparseSwitch(..), _) => {
+                match self.operand(ssa_inst, 0) {
+                    Lattice::Undefined => return,
+                    Lattice::Constant(ConstValue::Narrow(key)) => {
+                        let has_case = self.cfg.blocks[block]
+                            .successors
+                            .iter()
+                            .any(|edge| edge.kind == EdgeKind::SwitchCase(key));
+                        Box::new(move |kind| match has_case {
+                            true => kind == EdgeKind::SwitchCase(key),
+                            false => kind == EdgeKind::Fallthrough,
+                        })
+                    }
+                    _ => Box::new(|kind| !matches!(kind, EdgeKind::Exception(_))),
+                }
+            }
+            _ => Box::new(|kind| !matches!(kind, EdgeKind::Exception(_))),
+        };
+
+        for edge in &self.cfg.blocks[block].successors {
+            if follow(edge.kind) {
+                self.flow_worklist.push((Some(block), edge.target));
+            }
+        }
+    }
+
+    fn opaque_predicates(&self) -> Vec<OpaquePredicate> {
+        let mut predicates = vec![];
+        for (block, data) in self.cfg.blocks.iter().enumerate() {
+            let index = data.end - 1;
+            let (true, Some(ssa_inst)) = (self.executable[block], self.ssa.instruction(index))
+            else {
+                continue;
+            };
+            if let Some(Lattice::Constant(ConstValue::Narrow(taken))) =
+                self.decide(&self.instructions[index].inst, ssa_inst)
+            {
+                predicates.push(OpaquePredicate {
+                    index,
+                    taken: taken != 0,
+                });
+            }
+        }
+        predicates
+    }
+
+    fn fold_strings(&self, resolver: &dyn Resolver) -> BTreeMap<usize, String> {
+        let mut strings = BTreeMap::new();
+        for (block, data) in self.ssa.blocks.iter().enumerate() {
+            if !self.executable[block] {
+                continue;
+            }
+
+            for ssa_inst in &data.instructions {
+                let inst = &self.instructions[ssa_inst.index].inst;
+                for (position, &(_, value)) in ssa_inst.uses.iter().enumerate() {
+                    let root = self.ssa.copy_source(self.instructions, value);
+                    let Some(array) = self.arrays.get(&root) else {
+                        continue;
+                    };
+                    let Some(descriptor) = string_argument(
+                        inst,
+                        ssa_inst,
+                        position,
+                        self.ssa,
+                        self.instructions,
+                        root,
+                        resolver,
+                    ) else {
+                        continue;
+                    };
+                    let Some(ConstValue::Narrow(size)) = self.constant(array.size) else {
+                        continue;
+                    };
+
+                    let kind = if descriptor == "[C" {
+                        OpKind::GetChar
+                    } else {
+                        OpKind::GetByte
+                    };
+                    let elements: Option<Vec<i32>> = (0..*size)
+                        .map(|index| {
+                            match array.element(
+                                kind,
+                                &ConstValue::Narrow(*size),
+                                &ConstValue::Narrow(index),
+                            ) {
+                                Some(ConstValue::Narrow(element)) => Some(element),
+                                _ => None,
+                            }
+                        })
+                        .collect();
+                    let Some(elements) = elements else {
+                        continue;
+                    };
+
+                    let string = match kind {
+                        OpKind::GetChar => {
+                            let units: Vec<u16> =
+                                elements.iter().map(|&unit| unit as u16).collect();
+                            std::string::String::from_utf16_lossy(&units)
+                        }
+                        _ => {
+                            let bytes: Vec<u8> = elements.iter().map(|&byte| byte as u8).collect();
+                            std::string::String::from_utf8_lossy(&bytes).into_owned()
+                        }
+                    };
+                    strings.insert(ssa_inst.index, string);
+                }
+            }
+        }
+        strings
+    }
+
+    fn constant(&self, value: Value) -> Option<&ConstValue> {
+        match self.lattice(value) {
+            Lattice::Constant(constant) => Some(constant),
+            _ => None,
+        }
+    }
+}
+
+impl KnownArray {
+    /// element `index` read with an `aget` of `kind`, `None` when it would throw
+    fn element(&self, kind: OpKind, size: &ConstValue, index: &ConstValue) -> Option<ConstValue> {
+        let (ConstValue::Narrow(size), ConstValue::Narrow(index)) = (size, index) else {
+            return None;
+        };
+        if *index < 0 || index >= size {
+            return None;
+        }
+
+        let width = match kind {
+            OpKind::GetWide => 8,
+            OpKind::Get => 4,
+            OpKind::GetChar | OpKind::GetShort => 2,
+            OpKind::GetBoolean | OpKind::GetByte => 1,
+            _ => return None,
+        };
+        // the payload may be shorter than the array, the rest is still zeroed
+        let start = *index as usize * width;
+        let mut bytes = [0u8; 8];
+        if start < self.data.len() {
+            if self.width != width {
+                return None;
+            }
+            bytes[..width].copy_from_slice(self.data.get(start..start + width)?);
+        }
+
+        let value = i64::from_le_bytes(bytes);
+        Some(match kind {
+            OpKind::GetWide => ConstValue::Wide(value),
+            OpKind::Get => ConstValue::Narrow(value as i32),
+            OpKind::GetChar => ConstValue::Narrow(value as u16 as i32),
+            OpKind::GetShort => ConstValue::Narrow(value as i16 as i32),
+            OpKind::GetBoolean => ConstValue::Narrow(value as u8 as i32),
+            _ => ConstValue::Narrow(value as i8 as i32),
+        })
+    }
+}
+
+fn float(bits: i32) -> f32 {
+    f32::from_bits(bits as u32)
+}
+
+fn double(bits: i64) -> f64 {
+    f64::from_bits(bits as u64)
+}
+
+fn from_float(value: f32) -> ConstValue {
+    ConstValue::Narrow(value.to_bits() as i32)
+}
+
+fn from_double(value: f64) -> ConstValue {
+    ConstValue::Wide(value.to_bits() as i64)
+}
+
+/// fold a binary operation with java semantics, `None` when it throws
+pub fn fold_binop(kind: ArithmeticKind, a: &ConstValue, b: &ConstValue) -> Option<ConstValue> {
+    use ArithmeticKind::*;
+    use ConstValue::*;
+
+    Some(match (a, b) {
+        (Narrow(a), Narrow(b)) => match kind {
+            AddInt => Narrow(a.wrapping_add(*b)),
+            SubInt => Narrow(a.wrapping_sub(*b)),
+            RSubInt => Narrow(b.wrapping_sub(*a)),
+            MulInt => Narrow(a.wrapping_mul(*b)),
+            DivInt if *b != 0 => Narrow(a.wrapping_div(*b)),
+            RemInt if *b != 0 => Narrow(a.wrapping_rem(*b)),
+            AndInt => Narrow(a & b),
+            OrInt => Narrow(a | b),
+            XorInt => Narrow(a ^ b),
+            ShlInt => Narrow(a.wrapping_shl(*b as u32)),
+            ShrInt => Narrow(a.wrapping_shr(*b as u32)),
+            UshrInt => Narrow((*a as u32).wrapping_shr(*b as u32) as i32),
+            AddFloat => from_float(float(*a) + float(*b)),
+            SubFloat => from_float(float(*a) - float(*b)),
+            MulFloat => from_float(float(*a) * float(*b)),
+            DivFloat => from_float(float(*a) / float(*b)),
+            RemFloat => from_float(float(*a) % float(*b)),
+            _ => return None,
+        },
+        (Wide(a), Wide(b)) => match kind {
+            AddLong => Wide(a.wrapping_add(*b)),
+            SubLong => Wide(a.wrapping_sub(*b)),
+            MulLong => Wide(a.wrapping_mul(*b)),
+            DivLong if *b != 0 => Wide(a.wrapping_div(*b)),
+            RemLong if *b != 0 => Wide(a.wrapping_rem(*b)),
+            AndLong => Wide(a & b),
+            OrLong => Wide(a | b),
+            XorLong => Wide(a ^ b),
+            AddDouble => from_double(double(*a) + double(*b)),
+            SubDouble => from_double(double(*a) - double(*b)),
+            MulDouble => from_double(double(*a) * double(*b)),
+            DivDouble => from_double(double(*a) / double(*b)),
+            RemDouble => from_double(double(*a) % double(*b)),
+            _ => return None,
+        },
+        (Wide(a), Narrow(b)) => match kind {
+            ShlLong => Wide(a.wrapping_shl(*b as u32)),
+            ShrLong => Wide(a.wrapping_shr(*b as u32)),
+            UshrLong => Wide((*a as u64).wrapping_shr(*b as u32) as i64),
+            _ => return None,
+        },
+        _ => return None,
+    })
+}
+
+/// fold a unary operation or conversion with java semantics
+pub fn fold_unop(kind: UnopKind, value: &ConstValue) -> Option<ConstValue> {
+    use ConstValue::*;
+    use UnopKind::*;
+
+    // `as` saturates float to integer conversions and maps NaN to 0, like java
+    Some(match (kind, value) {
+        (NegInt, Narrow(a)) => Narrow(a.wrapping_neg()),
+        (NotInt, Narrow(a)) => Narrow(!a),
+        (NegLong, Wide(a)) => Wide(a.wrapping_neg()),
+        (NotLong, Wide(a)) => Wide(!a),
+        (NegFloat, Narrow(a)) => from_float(-float(*a)),
+        (NegDouble, Wide(a)) => from_double(-double(*a)),
+        (IntToLong, Narrow(a)) => Wide(*a as i64),
+        (IntToFloat, Narrow(a)) => from_float(*a as f32),
+        (IntToDouble, Narrow(a)) => from_double(*a as f64),
+        (LongToInt, Wide(a)) => Narrow(*a as i32),
+        (LongToFloat, Wide(a)) => from_float(*a as f32),
+        (LongToDouble, Wide(a)) => from_double(*a as f64),
+        (FloatToInt, Narrow(a)) => Narrow(float(*a) as i32),
+        (FloatToLong, Narrow(a)) => Wide(float(*a) as i64),
+        (FloatToDouble, Narrow(a)) => from_double(float(*a) as f64),
+        (DoubleToInt, Wide(a)) => Narrow(double(*a) as i32),
+        (DoubleToLong, Wide(a)) => Wide(double(*a) as i64),
+        (DoubleToFloat, Wide(a)) => from_float(double(*a) as f32),
+        (IntToByte, Narrow(a)) => Narrow(*a as i8 as i32),
+        (IntToChar, Narrow(a)) => Narrow(*a as u16 as i32),
+        (IntToShort, Narrow(a)) => Narrow(*a as i16 as i32),
+        _ => return None,
+    })
+}
+
+fn fold_cmp(kind: CmpKind, a: &ConstValue, b: &ConstValue) -> Option<ConstValue> {
+    use std::cmp::Ordering;
+
+    let ordering = match (kind, a, b) {
+        (CmpKind::CmpLong, ConstValue::Wide(a), ConstValue::Wide(b)) => Some(a.cmp(b)),
+        (CmpKind::CmplFloat | CmpKind::CmpgFloat, ConstValue::Narrow(a), ConstValue::Narrow(b)) => {
+            float(*a).partial_cmp(&float(*b))
+        }
+        (CmpKind::CmplDouble | CmpKind::CmpgDouble, ConstValue::Wide(a), ConstValue::Wide(b)) => {
+            double(*a).partial_cmp(&double(*b))
+        }
+        _ => return None,
+    };
+
+    // NaN compares as -1 for cmpl and 1 for cmpg
+    Some(ConstValue::Narrow(match ordering {
+        Some(Ordering::Less) => -1,
+        Some(Ordering::Equal) => 0,
+        Some(Ordering::Greater) => 1,
+        None if matches!(kind, CmpKind::CmplFloat | CmpKind::CmplDouble) => -1,
+        None => 1,
+    }))
+}
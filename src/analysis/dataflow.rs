@@ -0,0 +1,207 @@
+use super::cfg::{Cfg, EdgeKind};
+use crate::dalvik::DalvikInstruction;
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+/// a monotone dataflow problem over the instructions of a method
+pub trait Analysis {
+    type Fact: Clone + PartialEq;
+
+    const DIRECTION: Direction;
+
+    /// fact at the method entry for forward problems, at every exit for backward ones
+    fn boundary(&self) -> Self::Fact;
+
+    /// the fact nothing has flowed into yet
+    fn bottom(&self) -> Self::Fact;
+
+    /// merge `other` into `fact` at a join point
+    fn join(&self, fact: &mut Self::Fact, other: &Self::Fact);
+
+    /// apply the effect of the instruction at `index` in the direction of the analysis
+    fn transfer(&self, index: usize, instruction: &DalvikInstruction, fact: &mut Self::Fact);
+}
+
+/// the fixpoint of an analysis, before and after every instruction in program order.
+/// payloads and, for forward problems, unreachable instructions have no facts.
+#[derive(Debug, Clone)]
+pub struct Solution<F> {
+    before: Vec<Option<F>>,
+    after: Vec<Option<F>>,
+}
+
+impl<F> Solution<F> {
+    pub fn before(&self, index: usize) -> Option<&F> {
+        self.before.get(index)?.as_ref()
+    }
+
+    pub fn after(&self, index: usize) -> Option<&F> {
+        self.after.get(index)?.as_ref()
+    }
+}
+
+struct Solver<'a, A: Analysis> {
+    analysis: &'a A,
+    instructions: &'a [DalvikInstruction],
+    cfg: &'a Cfg,
+    /// fact where each block is entered in the direction of the analysis
+    entry: Vec<Option<A::Fact>>,
+}
+
+/// solve the analysis with a worklist over the blocks of the cfg.
+/// an exception handler sees the facts before every instruction of the blocks it covers.
+pub fn solve<A: Analysis>(
+    analysis: &A,
+    instructions: &[DalvikInstruction],
+    cfg: &Cfg,
+) -> Solution<A::Fact> {
+    let mut solver = Solver {
+        analysis,
+        instructions,
+        cfg,
+        entry: vec![None; cfg.blocks.len()],
+    };
+
+    let mut worklist: VecDeque<usize> = match A::DIRECTION {
+        Direction::Forward => {
+            if !cfg.blocks.is_empty() {
+                solver.entry[0] = Some(analysis.boundary());
+            }
+            VecDeque::from([0])
+        }
+        Direction::Backward => {
+            // later blocks first converges faster going backward
+            (0..cfg.blocks.len()).rev().collect()
+        }
+    };
+    worklist.retain(|&block| block < cfg.blocks.len());
+
+    while let Some(block) = worklist.pop_front() {
+        let changed = match A::DIRECTION {
+            Direction::Forward => solver.forward(block, None),
+            Direction::Backward => solver.backward(block, None),
+        };
+
+        for next in changed {
+            if !worklist.contains(&next) {
+                worklist.push_back(next);
+            }
+        }
+    }
+
+    let mut solution = Solution {
+        before: vec![None; instructions.len()],
+        after: vec![None; instructions.len()],
+    };
+    for block in 0..cfg.blocks.len() {
+        match A::DIRECTION {
+            Direction::Forward => solver.forward(block, Some(&mut solution)),
+            Direction::Backward => solver.backward(block, Some(&mut solution)),
+        };
+    }
+    solution
+}
+
+impl<A: Analysis> Solver<'_, A> {
+    /// merge `fact` into the entry of `block`, returning whether it changed
+    fn flow(&mut self, block: usize, fact: &A::Fact) -> bool {
+        match &mut self.entry[block] {
+            Some(existing) => {
+                let before = existing.clone();
+                self.analysis.join(existing, fact);
+                *existing != before
+            }
+            slot @ None => {
+                *slot = Some(fact.clone());
+                true
+            }
+        }
+    }
+
+    /// run a block forward, returning the successors whose entry changed
+    fn forward(&mut self, block: usize, mut record: Option<&mut Solution<A::Fact>>) -> Vec<usize> {
+        let Some(mut fact) = self.entry[block].clone() else {
+            return vec![];
+        };
+        let cfg = self.cfg;
+        let successors = &cfg.blocks[block].successors;
+        let mut changed = vec![];
+
+        for index in cfg.blocks[block].start..cfg.blocks[block].end {
+            for edge in successors {
+                if matches!(edge.kind, EdgeKind::Exception(_)) && self.flow(edge.target, &fact) {
+                    changed.push(edge.target);
+                }
+            }
+
+            let before = fact.clone();
+            self.analysis
+                .transfer(index, &self.instructions[index], &mut fact);
+            if let Some(solution) = record.as_deref_mut() {
+                solution.before[index] = Some(before);
+                solution.after[index] = Some(fact.clone());
+            }
+        }
+
+        for edge in successors {
+            if !matches!(edge.kind, EdgeKind::Exception(_)) && self.flow(edge.target, &fact) {
+                changed.push(edge.target);
+            }
+        }
+        changed
+    }
+
+    /// run a block backward, returning the predecessors to revisit when its entry changed
+    fn backward(&mut self, block: usize, mut record: Option<&mut Solution<A::Fact>>) -> Vec<usize> {
+        let cfg = self.cfg;
+        let successors = &cfg.blocks[block].successors;
+        let fact_at = |solver: &Self, target: usize| {
+            solver.entry[target]
+                .clone()
+                .unwrap_or_else(|| solver.analysis.bottom())
+        };
+
+        let mut fact = self.analysis.bottom();
+        let mut exits = true;
+        for edge in successors {
+            if !matches!(edge.kind, EdgeKind::Exception(_)) {
+                self.analysis.join(&mut fact, &fact_at(self, edge.target));
+                exits = false;
+            }
+        }
+        if exits {
+            self.analysis.join(&mut fact, &self.analysis.boundary());
+        }
+
+        let handlers: Vec<A::Fact> = successors
+            .iter()
+            .filter(|edge| matches!(edge.kind, EdgeKind::Exception(_)))
+            .map(|edge| fact_at(self, edge.target))
+            .collect();
+
+        for index in (cfg.blocks[block].start..cfg.blocks[block].end).rev() {
+            for handler in &handlers {
+                self.analysis.join(&mut fact, handler);
+            }
+
+            let after = fact.clone();
+            self.analysis
+                .transfer(index, &self.instructions[index], &mut fact);
+            if let Some(solution) = record.as_deref_mut() {
+                solution.before[index] = Some(fact.clone());
+                solution.after[index] = Some(after);
+            }
+        }
+
+        if self.entry[block].as_ref() == Some(&fact) {
+            return vec![];
+        }
+        self.entry[block] = Some(fact);
+        cfg.blocks[block].predecessors.clone()
+    }
+}
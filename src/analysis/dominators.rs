@@ -0,0 +1,290 @@
+use super::{
+    cfg::Cfg,
+    loops::{find_loops, Loop},
+};
+use std::collections::BTreeSet;
+
+/// dominator tree of the blocks reachable from the entry
+#[derive(Debug, Clone)]
+pub struct Dominators {
+    /// immediate dominator of every block, the entry is its own and unreachable blocks have none
+    idom: Vec<Option<usize>>,
+    children: Vec<Vec<usize>>,
+    /// position in reverse postorder, used to walk up the tree
+    order: Vec<usize>,
+}
+
+/// the "simple, fast dominance algorithm" of Cooper, Harvey and Kennedy.
+/// `order` is the reverse postorder of the reachable nodes starting with the root.
+fn immediate_dominators(
+    len: usize,
+    order: &[usize],
+    predecessors: impl Fn(usize) -> Vec<usize>,
+) -> (Vec<Option<usize>>, Vec<usize>) {
+    let mut position = vec![usize::MAX; len];
+    for (index, &node) in order.iter().enumerate() {
+        position[node] = index;
+    }
+
+    let mut idom = vec![None; len];
+    let Some(&root) = order.first() else {
+        return (idom, position);
+    };
+    idom[root] = Some(root);
+
+    let intersect = |idom: &[Option<usize>], mut a: usize, mut b: usize| {
+        while a != b {
+            while position[a] > position[b] {
+                a = idom[a].unwrap_or(root);
+            }
+            while position[b] > position[a] {
+                b = idom[b].unwrap_or(root);
+            }
+        }
+        a
+    };
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &node in &order[1..] {
+            let new_idom = predecessors(node)
+                .into_iter()
+                .filter(|&predecessor| idom[predecessor].is_some())
+                .reduce(|a, b| intersect(&idom, a, b));
+            if new_idom.is_some() && idom[node] != new_idom {
+                idom[node] = new_idom;
+                changed = true;
+            }
+        }
+    }
+
+    (idom, position)
+}
+
+/// reverse postorder of the nodes reachable from `root`
+fn reverse_postorder(
+    root: usize,
+    len: usize,
+    successors: impl Fn(usize) -> Vec<usize>,
+) -> Vec<usize> {
+    let mut visited = vec![false; len];
+    let mut postorder = vec![];
+    // (node, its successors, next successor to visit)
+    let mut stack = vec![(root, successors(root), 0)];
+    visited[root] = true;
+
+    while let Some((node, next, position)) = stack.last_mut() {
+        match next.get(*position).copied() {
+            Some(successor) => {
+                *position += 1;
+                if !visited[successor] {
+                    visited[successor] = true;
+                    stack.push((successor, successors(successor), 0));
+                }
+            }
+            None => {
+                postorder.push(*node);
+                stack.pop();
+            }
+        }
+    }
+
+    postorder.reverse();
+    postorder
+}
+
+impl Dominators {
+    pub fn new(cfg: &Cfg) -> Self {
+        let (idom, order) =
+            immediate_dominators(cfg.blocks.len(), &cfg.reverse_postorder(), |block| {
+                cfg.blocks[block].predecessors.clone()
+            });
+        Self::from_idom(idom, order)
+    }
+
+    fn from_idom(idom: Vec<Option<usize>>, order: Vec<usize>) -> Self {
+        let mut children = vec![vec![]; idom.len()];
+        for (block, &parent) in idom.iter().enumerate() {
+            if let Some(parent) = parent.filter(|&parent| parent != block) {
+                children[parent].push(block);
+            }
+        }
+        Self {
+            idom,
+            children,
+            order,
+        }
+    }
+
+    /// the closest strict dominator of the block, `None` for the entry and unreachable blocks
+    pub fn immediate_dominator(&self, block: usize) -> Option<usize> {
+        self.idom
+            .get(block)
+            .copied()
+            .flatten()
+            .filter(|&idom| idom != block)
+    }
+
+    /// blocks immediately dominated by `block`
+    pub fn children(&self, block: usize) -> &[usize] {
+        &self.children[block]
+    }
+
+    pub fn is_reachable(&self, block: usize) -> bool {
+        self.idom.get(block).is_some_and(Option::is_some)
+    }
+
+    /// whether every path from the entry to `b` goes through `a`
+    pub fn dominates(&self, a: usize, mut b: usize) -> bool {
+        if !self.is_reachable(a) || !self.is_reachable(b) {
+            return false;
+        }
+        while self.order[b] > self.order[a] {
+            match self.immediate_dominator(b) {
+                Some(idom) => b = idom,
+                None => return false,
+            }
+        }
+        a == b
+    }
+
+    /// dominance frontier of every block: where its dominance ends
+    pub fn frontiers(&self, cfg: &Cfg) -> Vec<BTreeSet<usize>> {
+        self.frontiers_with(|block| cfg.blocks[block].predecessors.clone())
+    }
+
+    fn frontiers_with(&self, predecessors: impl Fn(usize) -> Vec<usize>) -> Vec<BTreeSet<usize>> {
+        let mut frontiers = vec![BTreeSet::new(); self.idom.len()];
+        for block in 0..self.idom.len() {
+            if !self.is_reachable(block) {
+                continue;
+            }
+
+            // the entry is its own frontier when a loop jumps back to it
+            let stop = self.immediate_dominator(block);
+            for predecessor in predecessors(block) {
+                let mut runner = Some(predecessor);
+                while let Some(current) =
+                    runner.filter(|&current| Some(current) != stop && self.is_reachable(current))
+                {
+                    frontiers[current].insert(block);
+                    runner = self.immediate_dominator(current);
+                }
+            }
+        }
+        frontiers
+    }
+}
+
+/// post-dominator tree, rooted at a virtual exit every returning or throwing block flows to.
+/// blocks that can't reach an exit, like those of an infinite loop, have no post-dominator.
+#[derive(Debug, Clone)]
+pub struct PostDominators {
+    /// dominators of the reversed cfg, node `exit` is the virtual exit
+    tree: Dominators,
+    exit: usize,
+}
+
+impl PostDominators {
+    pub fn new(cfg: &Cfg) -> Self {
+        let exit = cfg.blocks.len();
+        let successors = move |block: usize| -> Vec<usize> {
+            let targets: Vec<usize> = cfg.blocks[block]
+                .successors
+                .iter()
+                .map(|edge| edge.target)
+                .collect();
+            match targets.is_empty() {
+                true => vec![exit],
+                false => targets,
+            }
+        };
+        let predecessors = |node: usize| -> Vec<usize> {
+            match node == exit {
+                true => (0..exit)
+                    .filter(|&block| cfg.blocks[block].successors.is_empty())
+                    .collect(),
+                false => cfg.blocks[node].predecessors.clone(),
+            }
+        };
+
+        // the reversed graph swaps predecessors and successors
+        let order = reverse_postorder(exit, exit + 1, predecessors);
+        let (idom, order) = immediate_dominators(exit + 1, &order, |node| match node == exit {
+            true => vec![],
+            false => successors(node),
+        });
+
+        Self {
+            tree: Dominators::from_idom(idom, order),
+            exit,
+        }
+    }
+
+    /// the closest block every path from `block` to an exit goes through,
+    /// `None` when the method may leave right after it
+    pub fn immediate_post_dominator(&self, block: usize) -> Option<usize> {
+        self.tree
+            .immediate_dominator(block)
+            .filter(|&idom| idom != self.exit)
+    }
+
+    /// whether every path from `b` to an exit goes through `a`
+    pub fn post_dominates(&self, a: usize, b: usize) -> bool {
+        a != self.exit && b != self.exit && self.tree.dominates(a, b)
+    }
+
+    /// the blocks whose execution is decided by the branch ending each block,
+    /// i.e. the inverse of the post-dominance frontiers
+    pub fn control_dependents(&self, cfg: &Cfg) -> Vec<BTreeSet<usize>> {
+        let exit = self.exit;
+        let frontiers = self.tree.frontiers_with(|node| match node == exit {
+            true => vec![],
+            false => {
+                let targets: Vec<usize> = cfg.blocks[node]
+                    .successors
+                    .iter()
+                    .map(|edge| edge.target)
+                    .collect();
+                match targets.is_empty() {
+                    true => vec![exit],
+                    false => targets,
+                }
+            }
+        });
+
+        let mut dependents = vec![BTreeSet::new(); cfg.blocks.len()];
+        for (block, frontier) in frontiers.iter().enumerate().take(exit) {
+            for &branch in frontier.iter().filter(|&&branch| branch != exit) {
+                dependents[branch].insert(block);
+            }
+        }
+        dependents
+    }
+}
+
+/// everything the structural analyses need to know about a method's control flow
+#[derive(Debug, Clone)]
+pub struct DominanceInfo {
+    pub dominators: Dominators,
+    pub post_dominators: PostDominators,
+    pub frontiers: Vec<BTreeSet<usize>>,
+    /// blocks controlled by the branch ending each block
+    pub control_dependents: Vec<BTreeSet<usize>>,
+    pub loops: Vec<Loop>,
+}
+
+impl DominanceInfo {
+    pub fn new(cfg: &Cfg) -> Self {
+        let dominators = Dominators::new(cfg);
+        let post_dominators = PostDominators::new(cfg);
+        Self {
+            frontiers: dominators.frontiers(cfg),
+            control_dependents: post_dominators.control_dependents(cfg),
+            loops: find_loops(cfg, &dominators),
+            dominators,
+            post_dominators,
+        }
+    }
+}
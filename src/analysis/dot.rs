@@ -0,0 +1,103 @@
+use super::{
+    cfg::{Cfg, EdgeKind},
+    dominators::{Dominators, PostDominators},
+};
+use crate::{
+    dalvik::{smali::instruction_text, DalvikInstruction},
+    resolver::Resolver,
+};
+use std::fmt::Write;
+
+/// extra edges drawn over the cfg
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overlay {
+    /// from each block's immediate dominator
+    Dominators,
+    /// to each block's immediate post-dominator
+    PostDominators,
+}
+
+/// escape text for a double quoted dot string
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// the cfg of a method as a graphviz digraph named `name`. blocks are labelled with their
+/// smali, edges coloured by kind: black fallthrough, green taken branch, blue switch case
+/// and red dashed exception edges
+pub fn cfg_to_dot(
+    name: &str,
+    instructions: &[DalvikInstruction],
+    cfg: &Cfg,
+    resolver: &dyn Resolver,
+    overlays: &[Overlay],
+) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "digraph \"{}\" {{", escape(name));
+    out.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+
+    for (index, block) in cfg.blocks.iter().enumerate() {
+        let mut label = format!("B{}\\l", index);
+        for instruction in &instructions[block.start..block.end] {
+            let _ = write!(
+                label,
+                "{:04x}: {}\\l",
+                instruction.offset / 2,
+                escape(&instruction_text(instruction, resolver))
+            );
+        }
+        let _ = writeln!(out, "    b{} [label=\"{}\"];", index, label);
+    }
+
+    for (index, block) in cfg.blocks.iter().enumerate() {
+        for edge in &block.successors {
+            let attributes = match edge.kind {
+                EdgeKind::Fallthrough => "color=black".to_string(),
+                EdgeKind::Branch => "color=green, label=\"taken\"".to_string(),
+                EdgeKind::SwitchCase(key) => format!("color=blue, label=\"case {}\"", key),
+                EdgeKind::Exception(type_idx) => {
+                    let caught = match type_idx {
+                        Some(type_idx) => resolver
+                            .type_descriptor(type_idx)
+                            .unwrap_or_else(|| format!("type@{}", type_idx)),
+                        None => "catch-all".to_string(),
+                    };
+                    format!("color=red, style=dashed, label=\"{}\"", escape(&caught))
+                }
+            };
+            let _ = writeln!(out, "    b{} -> b{} [{}];", index, edge.target, attributes);
+        }
+    }
+
+    for overlay in overlays {
+        match overlay {
+            Overlay::Dominators => {
+                let dominators = Dominators::new(cfg);
+                for block in 0..cfg.blocks.len() {
+                    if let Some(idom) = dominators.immediate_dominator(block) {
+                        let _ = writeln!(
+                            out,
+                            "    b{} -> b{} [color=gray, style=dotted, constraint=false, label=\"idom\"];",
+                            idom, block
+                        );
+                    }
+                }
+            }
+            Overlay::PostDominators => {
+                let post_dominators = PostDominators::new(cfg);
+                for block in 0..cfg.blocks.len() {
+                    if let Some(ipdom) = post_dominators.immediate_post_dominator(block) {
+                        let _ = writeln!(
+                            out,
+                            "    b{} -> b{} [color=purple, style=dotted, constraint=false, label=\"ipdom\"];",
+                            block, ipdom
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    out.push_str("}\n");
+    out
+}
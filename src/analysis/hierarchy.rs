@@ -0,0 +1,469 @@
+use crate::{
+    container::dex_file::{
+        DexFile, ACC_ABSTRACT, ACC_INTERFACE, ACC_NATIVE, ACC_PRIVATE, ACC_PROTECTED, ACC_PUBLIC,
+        ACC_STATIC,
+    },
+    dalvik::opcodes::InvokeKind,
+};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+#[cfg(feature = "zip")]
+use {
+    super::classfile::read_class_file,
+    crate::{
+        container::{self, EmbeddedDex},
+        errors::Error,
+    },
+    std::io::{Cursor, Read},
+};
+
+/// the package of a class descriptor, `com/example` for `Lcom/example/Foo;`
+fn package(descriptor: &str) -> &str {
+    descriptor
+        .trim_start_matches('L')
+        .rsplit_once('/')
+        .map_or("", |(package, _)| package)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Visibility {
+    Public,
+    Protected,
+    /// no access flag, visible to the package
+    Package,
+    Private,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodInfo {
+    pub name: String,
+    /// `(params)ret` prototype descriptor
+    pub descriptor: String,
+    pub visibility: Visibility,
+    pub is_static: bool,
+    pub is_abstract: bool,
+    pub is_native: bool,
+    /// whether a dex file holds its code
+    pub has_code: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassInfo {
+    pub descriptor: String,
+    pub super_class: Option<String>,
+    pub interfaces: Vec<String>,
+    pub is_interface: bool,
+    pub is_abstract: bool,
+    pub methods: Vec<MethodInfo>,
+}
+
+impl MethodInfo {
+    /// whether the method is dispatched on the receiver: not static, private or a constructor
+    pub fn is_virtual(&self) -> bool {
+        !self.is_static && self.visibility != Visibility::Private && !self.name.starts_with('<')
+    }
+}
+
+impl ClassInfo {
+    pub fn method(&self, name: &str, descriptor: &str) -> Option<&MethodInfo> {
+        self.methods
+            .iter()
+            .find(|method| method.name == name && method.descriptor == descriptor)
+    }
+}
+
+/// classes of one or more dex files and their subtype relations
+#[derive(Debug, Clone, Default)]
+pub struct ClassHierarchy {
+    classes: HashMap<String, ClassInfo>,
+    /// direct subclasses and implementors of every type
+    subtypes: HashMap<String, Vec<String>>,
+    /// classes loaded from framework stubs
+    framework: HashSet<String>,
+}
+
+impl ClassHierarchy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// add the classes of a dex file. for multidex, add `classes.dex` first: like the class
+    /// loader, the first definition of a class wins.
+    pub fn add_dex(&mut self, dex: &dyn DexFile) {
+        for class in dex_classes(dex) {
+            self.add_class(class);
+        }
+    }
+
+    #[cfg(feature = "zip")]
+    /// add framework classes from a local `android.jar`, a framework jar holding dex files
+    /// or a dex file. boot classes shadow the classes of an app, so add them first.
+    pub fn add_framework(&mut self, data: &[u8]) -> crate::Result<()> {
+        if !data.starts_with(b"PK") {
+            return self.add_framework_dex(data);
+        }
+
+        let mut archive =
+            zip::ZipArchive::new(Cursor::new(data)).map_err(|_| Error::InvalidContainer)?;
+        for entry in 0..archive.len() {
+            let mut file = archive
+                .by_index(entry)
+                .map_err(|_| Error::InvalidContainer)?;
+            let name = file.name().to_string();
+            let is_dex = name.starts_with("classes") && name.ends_with(".dex");
+            if !is_dex && !name.ends_with(".class") {
+                continue;
+            }
+
+            let mut file_data = vec![];
+            file.read_to_end(&mut file_data)
+                .map_err(|_| Error::InvalidContainer)?;
+            if is_dex {
+                self.add_framework_dex(&file_data)?;
+            } else {
+                self.add_framework_class(read_class_file(&file_data)?);
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "zip")]
+    fn add_framework_dex(&mut self, data: &[u8]) -> crate::Result<()> {
+        let dex = EmbeddedDex::parse(data).ok_or(Error::InvalidContainer)?;
+        for class in dex_classes(container::dex_file::open(&dex)?.as_ref()) {
+            self.add_framework_class(class);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "zip")]
+    fn add_framework_class(&mut self, class: ClassInfo) {
+        if !self.classes.contains_key(&class.descriptor) {
+            self.framework.insert(class.descriptor.clone());
+        }
+        self.add_class(class);
+    }
+
+    /// whether the class was loaded from framework stubs
+    pub fn is_framework(&self, descriptor: &str) -> bool {
+        self.framework.contains(descriptor)
+    }
+
+    pub fn add_class(&mut self, class: ClassInfo) {
+        if self.classes.contains_key(&class.descriptor) {
+            return;
+        }
+
+        for parent in class.super_class.iter().chain(&class.interfaces) {
+            self.subtypes
+                .entry(parent.clone())
+                .or_default()
+                .push(class.descriptor.clone());
+        }
+        self.classes.insert(class.descriptor.clone(), class);
+    }
+
+    pub fn class(&self, descriptor: &str) -> Option<&ClassInfo> {
+        self.classes.get(descriptor)
+    }
+
+    pub fn classes(&self) -> impl Iterator<Item = &ClassInfo> {
+        self.classes.values()
+    }
+
+    /// the class followed by its known superclasses, closest first
+    pub fn superclasses<'a>(&'a self, descriptor: &'a str) -> impl Iterator<Item = &'a str> {
+        let mut next = Some(descriptor);
+        std::iter::from_fn(move || {
+            let current = next?;
+            next = self
+                .class(current)
+                .and_then(|class| class.super_class.as_deref())
+                .filter(|&super_class| super_class != current);
+            Some(current)
+        })
+    }
+
+    /// the type and every known type deriving from it
+    pub fn subtypes(&self, descriptor: &str) -> BTreeSet<String> {
+        let mut found = BTreeSet::from([descriptor.to_string()]);
+        let mut worklist = vec![descriptor.to_string()];
+        while let Some(current) = worklist.pop() {
+            for subtype in self.subtypes.get(&current).into_iter().flatten() {
+                if found.insert(subtype.clone()) {
+                    worklist.push(subtype.clone());
+                }
+            }
+        }
+        found
+    }
+
+    /// whether `descriptor` is `ancestor` or derives from it through known classes
+    pub fn is_subtype_of(&self, descriptor: &str, ancestor: &str) -> bool {
+        let mut worklist = vec![descriptor];
+        let mut visited = BTreeSet::new();
+        while let Some(current) = worklist.pop() {
+            if current == ancestor {
+                return true;
+            }
+            if !visited.insert(current) {
+                continue;
+            }
+            if let Some(class) = self.class(current) {
+                worklist.extend(class.super_class.as_deref());
+                worklist.extend(class.interfaces.iter().map(String::as_str));
+            }
+        }
+        false
+    }
+
+    /// the method a reference to `class` resolves to: the closest declaration up the
+    /// superclasses, then a default method of the interfaces. returns the declaring class with
+    /// the method.
+    pub fn resolve_method(
+        &self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Option<(&str, &MethodInfo)> {
+        for current in self.superclasses(class) {
+            if let Some(method) = self
+                .class(current)
+                .and_then(|info| info.method(name, descriptor))
+            {
+                return Some((&self.classes[current].descriptor, method));
+            }
+        }
+        self.default_method(self.superclasses(class), name, descriptor)
+    }
+
+    /// a default method of the interfaces of `classes`, searched breadth first
+    fn default_method<'a, 'b>(
+        &'a self,
+        classes: impl IntoIterator<Item = &'b str>,
+        name: &str,
+        descriptor: &str,
+    ) -> Option<(&'a str, &'a MethodInfo)> {
+        let mut worklist: VecDeque<&str> = classes
+            .into_iter()
+            .filter_map(|current| self.class(current))
+            .flat_map(|info| info.interfaces.iter().map(String::as_str))
+            .collect();
+        let mut visited = BTreeSet::new();
+        while let Some(interface) = worklist.pop_front() {
+            if !visited.insert(interface) {
+                continue;
+            }
+            let Some(info) = self.class(interface) else {
+                continue;
+            };
+            if let Some(method) = info
+                .method(name, descriptor)
+                .filter(|method| !method.is_abstract && method.is_virtual())
+            {
+                return Some((&info.descriptor, method));
+            }
+            worklist.extend(info.interfaces.iter().map(String::as_str));
+        }
+        None
+    }
+
+    /// the method a virtual or interface call runs on a receiver of class `receiver`
+    pub fn dispatch(
+        &self,
+        receiver: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Option<(&str, &MethodInfo)> {
+        for current in self.superclasses(receiver) {
+            let Some(info) = self.class(current) else {
+                continue;
+            };
+            if let Some(method) = info
+                .method(name, descriptor)
+                .filter(|method| method.is_virtual())
+            {
+                // a class method hides the interface defaults, even when abstract
+                return (!method.is_abstract).then_some((&info.descriptor, method));
+            }
+        }
+        self.default_method(self.superclasses(receiver), name, descriptor)
+    }
+
+    /// the method an `invoke-*` of `class->name descriptor` made from a method of `caller`
+    /// statically targets. virtual and interface calls resolve to the declaration, use
+    /// `dispatch` for the receiver's implementation.
+    pub fn resolve_invoke(
+        &self,
+        kind: InvokeKind,
+        caller: &str,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Option<(&str, &MethodInfo)> {
+        match kind {
+            InvokeKind::Static => self
+                .resolve_method(class, name, descriptor)
+                .filter(|(_, method)| method.is_static),
+            // private methods and constructors are declared by the referenced class
+            InvokeKind::Direct => self.class(class).and_then(|info| {
+                info.method(name, descriptor)
+                    .map(|method| (info.descriptor.as_str(), method))
+            }),
+            InvokeKind::Super => self.resolve_super(caller, class, name, descriptor),
+            InvokeKind::Virtual | InvokeKind::Interface => {
+                self.resolve_method(class, name, descriptor)
+            }
+        }
+    }
+
+    /// the method an `invoke-super` made from `caller` runs. `Iface.super.m()` references the
+    /// interface, any other super call runs the implementation of the caller's superclass.
+    pub fn resolve_super(
+        &self,
+        caller: &str,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Option<(&str, &MethodInfo)> {
+        let referenced = self.class(class)?;
+        if referenced.is_interface {
+            return match referenced.method(name, descriptor) {
+                Some(method) if !method.is_abstract => Some((&referenced.descriptor, method)),
+                _ => self.default_method([class], name, descriptor),
+            };
+        }
+
+        let super_class = self.class(caller)?.super_class.as_deref()?;
+        self.dispatch(super_class, name, descriptor)
+    }
+
+    /// whether `method` of `class` overrides `overridden` of `ancestor`. package private
+    /// methods are only overridden from their package.
+    pub fn overrides(
+        &self,
+        class: &str,
+        method: &MethodInfo,
+        ancestor: &str,
+        overridden: &MethodInfo,
+    ) -> bool {
+        class != ancestor
+            && method.name == overridden.name
+            && method.descriptor == overridden.descriptor
+            && method.is_virtual()
+            && overridden.is_virtual()
+            && (overridden.visibility != Visibility::Package || package(class) == package(ancestor))
+            && self.is_subtype_of(class, ancestor)
+    }
+
+    /// the method `class->name descriptor` resolves to followed by every declaration it
+    /// overrides: superclasses closest first, then interfaces breadth first
+    pub fn override_chain(
+        &self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Vec<(&str, &MethodInfo)> {
+        let Some((declaring, method)) = self.resolve_method(class, name, descriptor) else {
+            return vec![];
+        };
+        let mut chain = vec![(declaring, method)];
+
+        for current in self.superclasses(declaring).skip(1) {
+            let Some(info) = self.class(current) else {
+                continue;
+            };
+            if let Some(overridden) = info
+                .method(name, descriptor)
+                .filter(|overridden| self.overrides(declaring, method, current, overridden))
+            {
+                chain.push((&info.descriptor, overridden));
+            }
+        }
+
+        let mut worklist: VecDeque<&str> = self
+            .superclasses(declaring)
+            .filter_map(|current| self.class(current))
+            .flat_map(|info| info.interfaces.iter().map(String::as_str))
+            .collect();
+        let mut visited = BTreeSet::new();
+        while let Some(interface) = worklist.pop_front() {
+            if !visited.insert(interface) {
+                continue;
+            }
+            let Some(info) = self.class(interface) else {
+                continue;
+            };
+            if let Some(overridden) = info
+                .method(name, descriptor)
+                .filter(|overridden| self.overrides(declaring, method, interface, overridden))
+            {
+                chain.push((&info.descriptor, overridden));
+            }
+            worklist.extend(info.interfaces.iter().map(String::as_str));
+        }
+        chain
+    }
+
+    /// the methods of known subtypes overriding the declaration of `class`
+    pub fn overriding_methods(
+        &self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Vec<(&str, &MethodInfo)> {
+        let Some(overridden) = self
+            .class(class)
+            .and_then(|info| info.method(name, descriptor))
+        else {
+            return vec![];
+        };
+
+        self.subtypes(class)
+            .iter()
+            .filter_map(|subtype| self.class(subtype))
+            .filter_map(|info| {
+                info.method(name, descriptor)
+                    .filter(|method| self.overrides(&info.descriptor, method, class, overridden))
+                    .map(|method| (info.descriptor.as_str(), method))
+            })
+            .collect()
+    }
+}
+
+fn visibility(access_flags: u32) -> Visibility {
+    if access_flags & ACC_PUBLIC != 0 {
+        Visibility::Public
+    } else if access_flags & ACC_PROTECTED != 0 {
+        Visibility::Protected
+    } else if access_flags & ACC_PRIVATE != 0 {
+        Visibility::Private
+    } else {
+        Visibility::Package
+    }
+}
+
+fn dex_classes(dex: &dyn DexFile) -> Vec<ClassInfo> {
+    dex.readable_classes()
+        .into_iter()
+        .map(|class| ClassInfo {
+            descriptor: class.descriptor,
+            super_class: class.super_class,
+            interfaces: class.interfaces,
+            is_interface: class.access_flags & ACC_INTERFACE != 0,
+            is_abstract: class.access_flags & ACC_ABSTRACT != 0,
+            methods: class
+                .methods
+                .into_iter()
+                .map(|method| MethodInfo {
+                    descriptor: method.prototype.descriptor(),
+                    name: method.name,
+                    visibility: visibility(method.access_flags),
+                    is_static: method.access_flags & ACC_STATIC != 0,
+                    is_abstract: method.access_flags & ACC_ABSTRACT != 0,
+                    is_native: method.access_flags & ACC_NATIVE != 0,
+                    has_code: method.code_off != 0,
+                })
+                .collect(),
+        })
+        .collect()
+}
@@ -0,0 +1,40 @@
+use super::{
+    cfg::Cfg,
+    dataflow::{solve, Analysis, Direction, Solution},
+};
+use crate::dalvik::{registers::Location, DalvikInstruction};
+use std::collections::BTreeSet;
+
+/// locations whose current value may still be read
+pub struct Liveness;
+
+impl Analysis for Liveness {
+    type Fact = BTreeSet<Location>;
+
+    const DIRECTION: Direction = Direction::Backward;
+
+    fn boundary(&self) -> Self::Fact {
+        BTreeSet::new()
+    }
+
+    fn bottom(&self) -> Self::Fact {
+        BTreeSet::new()
+    }
+
+    fn join(&self, fact: &mut Self::Fact, other: &Self::Fact) {
+        fact.extend(other.iter().copied());
+    }
+
+    fn transfer(&self, _: usize, instruction: &DalvikInstruction, fact: &mut Self::Fact) {
+        let def_use = instruction.inst.def_use();
+        for def in &def_use.defs {
+            fact.remove(def);
+        }
+        fact.extend(def_use.uses);
+    }
+}
+
+/// live locations before and after every instruction
+pub fn liveness(instructions: &[DalvikInstruction], cfg: &Cfg) -> Solution<BTreeSet<Location>> {
+    solve(&Liveness, instructions, cfg)
+}
@@ -0,0 +1,92 @@
+use super::{cfg::Cfg, dominators::Dominators};
+use std::collections::BTreeSet;
+
+/// a natural loop, the loops sharing a header are merged into one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loop {
+    /// the block dominating the whole loop, every iteration starts there
+    pub header: usize,
+    /// blocks jumping back to the header
+    pub back_edges: Vec<usize>,
+    /// blocks of the loop, including the header
+    pub blocks: BTreeSet<usize>,
+    /// index of the innermost loop containing this one
+    pub parent: Option<usize>,
+}
+
+impl Loop {
+    pub fn contains(&self, block: usize) -> bool {
+        self.blocks.contains(&block)
+    }
+
+    /// edges leaving the loop as (from block, to block)
+    pub fn exits(&self, cfg: &Cfg) -> Vec<(usize, usize)> {
+        self.blocks
+            .iter()
+            .flat_map(|&block| {
+                cfg.blocks[block]
+                    .successors
+                    .iter()
+                    .filter(|edge| !self.contains(edge.target))
+                    .map(move |edge| (block, edge.target))
+            })
+            .collect()
+    }
+}
+
+/// natural loops of the cfg ordered by header, an edge is a back edge when its target
+/// dominates its source
+pub fn find_loops(cfg: &Cfg, dominators: &Dominators) -> Vec<Loop> {
+    let mut loops: Vec<Loop> = vec![];
+
+    for (block, data) in cfg.blocks.iter().enumerate() {
+        for edge in &data.successors {
+            if !dominators.dominates(edge.target, block) {
+                continue;
+            }
+
+            let index = match loops.iter().position(|found| found.header == edge.target) {
+                Some(index) => index,
+                None => {
+                    loops.push(Loop {
+                        header: edge.target,
+                        back_edges: vec![],
+                        blocks: BTreeSet::from([edge.target]),
+                        parent: None,
+                    });
+                    loops.len() - 1
+                }
+            };
+            let natural_loop = &mut loops[index];
+            if natural_loop.back_edges.contains(&block) {
+                continue;
+            }
+            natural_loop.back_edges.push(block);
+
+            // everything reaching the back edge without going through the header
+            let mut worklist = vec![block];
+            while let Some(current) = worklist.pop() {
+                if natural_loop.blocks.insert(current) {
+                    worklist.extend(
+                        cfg.blocks[current]
+                            .predecessors
+                            .iter()
+                            .filter(|&&predecessor| dominators.is_reachable(predecessor)),
+                    );
+                }
+            }
+        }
+    }
+
+    loops.sort_by_key(|found| found.header);
+    for index in 0..loops.len() {
+        loops[index].parent = (0..loops.len())
+            .filter(|&other| {
+                other != index
+                    && loops[other].blocks.len() > loops[index].blocks.len()
+                    && loops[other].blocks.is_superset(&loops[index].blocks)
+            })
+            .min_by_key(|&other| loops[other].blocks.len());
+    }
+    loops
+}
@@ -0,0 +1,16 @@
+pub mod cache;
+pub mod callgraph;
+pub mod cfg;
+pub mod classfile;
+pub mod constants;
+pub mod dataflow;
+pub mod dominators;
+pub mod dot;
+pub mod hierarchy;
+pub mod liveness;
+pub mod loops;
+pub mod reaching;
+pub mod ssa;
+pub mod types;
+pub mod verifier;
+pub mod xrefs;
@@ -0,0 +1,110 @@
+use super::{
+    cfg::Cfg,
+    dataflow::{solve, Analysis, Direction, Solution},
+};
+use crate::dalvik::{registers::Location, DalvikInstruction};
+use std::collections::{BTreeSet, HashMap};
+
+/// a definition of a location, `index` is `None` for the value it holds on method entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Definition {
+    pub location: Location,
+    pub index: Option<usize>,
+}
+
+/// definitions that may reach each instruction without being overwritten
+pub struct ReachingDefinitions {
+    pub registers_size: u16,
+}
+
+impl Analysis for ReachingDefinitions {
+    type Fact = BTreeSet<Definition>;
+
+    const DIRECTION: Direction = Direction::Forward;
+
+    fn boundary(&self) -> Self::Fact {
+        (0..self.registers_size)
+            .map(|reg| Definition {
+                location: Location::Register(reg),
+                index: None,
+            })
+            .collect()
+    }
+
+    fn bottom(&self) -> Self::Fact {
+        BTreeSet::new()
+    }
+
+    fn join(&self, fact: &mut Self::Fact, other: &Self::Fact) {
+        fact.extend(other.iter().copied());
+    }
+
+    fn transfer(&self, index: usize, instruction: &DalvikInstruction, fact: &mut Self::Fact) {
+        let defs = instruction.inst.def_use().defs;
+        fact.retain(|definition| !defs.contains(&definition.location));
+        fact.extend(defs.into_iter().map(|location| Definition {
+            location,
+            index: Some(index),
+        }));
+    }
+}
+
+pub fn reaching_definitions(
+    instructions: &[DalvikInstruction],
+    cfg: &Cfg,
+    registers_size: u16,
+) -> Solution<BTreeSet<Definition>> {
+    solve(&ReachingDefinitions { registers_size }, instructions, cfg)
+}
+
+/// use-def and def-use chains of a method
+#[derive(Debug, Clone, Default)]
+pub struct DefUseChains {
+    /// definitions reaching every (instruction index, used location)
+    definitions: HashMap<(usize, Location), Vec<Option<usize>>>,
+    /// (instruction index, location) of every use a definition reaches, by defining index
+    uses: HashMap<Option<usize>, Vec<(usize, Location)>>,
+}
+
+impl DefUseChains {
+    pub fn new(instructions: &[DalvikInstruction], cfg: &Cfg, registers_size: u16) -> Self {
+        let reaching = reaching_definitions(instructions, cfg, registers_size);
+        let mut chains = Self::default();
+
+        for (index, instruction) in instructions.iter().enumerate() {
+            let Some(fact) = reaching.before(index) else {
+                continue;
+            };
+
+            for location in instruction.inst.def_use().uses {
+                let definitions: Vec<Option<usize>> = fact
+                    .iter()
+                    .filter(|definition| definition.location == location)
+                    .map(|definition| definition.index)
+                    .collect();
+                for &definition in &definitions {
+                    let uses = chains.uses.entry(definition).or_default();
+                    if !uses.contains(&(index, location)) {
+                        uses.push((index, location));
+                    }
+                }
+                chains.definitions.insert((index, location), definitions);
+            }
+        }
+
+        chains
+    }
+
+    /// definitions reaching the use of `location` by the instruction at `index`,
+    /// `None` stands for the value on method entry
+    pub fn definitions(&self, index: usize, location: Location) -> &[Option<usize>] {
+        self.definitions
+            .get(&(index, location))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// uses reached by the instruction at `index`, or by the entry values for `None`
+    pub fn uses(&self, index: Option<usize>) -> &[(usize, Location)] {
+        self.uses.get(&index).map_or(&[], Vec::as_slice)
+    }
+}
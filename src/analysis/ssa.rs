@@ -0,0 +1,334 @@
+use super::{
+    cfg::{Cfg, Edge, EdgeKind},
+    dominators::Dominators,
+    liveness::liveness,
+};
+use crate::dalvik::{opcodes::DalvikBytecode, registers::Location, DalvikInstruction};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// an ssa value, assigned exactly once
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Value(pub u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueSource {
+    /// what the location holds when the method is entered
+    Entry,
+    /// written by the instruction at this index
+    Instruction(usize),
+    /// merged by a phi at the start of this block
+    Phi(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValueInfo {
+    /// the register (half) or result location the value lives in
+    pub location: Location,
+    pub source: ValueSource,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhiOperand {
+    /// block the value flows in from, `None` for the method entry
+    pub predecessor: Option<usize>,
+    pub value: Value,
+}
+
+/// a merge of the values a location has on the incoming edges of a block.
+/// an exception edge contributes the value before every instruction of the try block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Phi {
+    pub location: Location,
+    pub value: Value,
+    pub operands: Vec<PhiOperand>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SsaInstruction {
+    /// index of the original instruction
+    pub index: usize,
+    /// byte offset of the original instruction
+    pub offset: usize,
+    /// values written, a register pair gets one value per half
+    pub defs: Vec<(Location, Value)>,
+    pub uses: Vec<(Location, Value)>,
+}
+
+/// the ssa form of a cfg block, unreachable blocks are left empty
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SsaBlock {
+    pub phis: Vec<Phi>,
+    pub instructions: Vec<SsaInstruction>,
+}
+
+/// pruned ssa form of a method, every register half and the invoke result are renamed separately
+#[derive(Debug, Clone)]
+pub struct Ssa {
+    pub blocks: Vec<SsaBlock>,
+    values: Vec<ValueInfo>,
+    /// (block, position) of every renamed instruction by instruction index
+    positions: HashMap<usize, (usize, usize)>,
+}
+
+enum Event {
+    Enter(usize),
+    /// leave a block, popping the values it pushed
+    Exit(Vec<Location>),
+}
+
+struct Renamer {
+    values: Vec<ValueInfo>,
+    stacks: HashMap<Location, Vec<Value>>,
+    entry_values: HashMap<Location, Value>,
+}
+
+impl Renamer {
+    fn new_value(&mut self, location: Location, source: ValueSource) -> Value {
+        self.values.push(ValueInfo { location, source });
+        Value(self.values.len() as u32 - 1)
+    }
+
+    fn entry_value(&mut self, location: Location) -> Value {
+        if let Some(&value) = self.entry_values.get(&location) {
+            return value;
+        }
+        let value = self.new_value(location, ValueSource::Entry);
+        self.entry_values.insert(location, value);
+        value
+    }
+
+    fn current(&mut self, location: Location) -> Value {
+        match self.stacks.get(&location).and_then(|stack| stack.last()) {
+            Some(&value) => value,
+            None => self.entry_value(location),
+        }
+    }
+
+    fn push(&mut self, location: Location, value: Value) {
+        self.stacks.entry(location).or_default().push(value);
+    }
+
+    /// fill in the operands `from` gives the phis of `block`
+    fn flow(&mut self, blocks: &mut [SsaBlock], block: usize, from: Option<usize>) {
+        for phi in 0..blocks[block].phis.len() {
+            let value = match from {
+                Some(_) => self.current(blocks[block].phis[phi].location),
+                None => self.entry_value(blocks[block].phis[phi].location),
+            };
+            let operand = PhiOperand {
+                predecessor: from,
+                value,
+            };
+            if !blocks[block].phis[phi].operands.contains(&operand) {
+                blocks[block].phis[phi].operands.push(operand);
+            }
+        }
+    }
+}
+
+impl Ssa {
+    pub fn new(instructions: &[DalvikInstruction], cfg: &Cfg, registers_size: u16) -> Self {
+        let dominators = Dominators::new(cfg);
+        let frontiers = dominators.frontiers(cfg);
+        let live = liveness(instructions, cfg);
+
+        // blocks with an instruction defining each location
+        let mut instruction_defs: BTreeMap<Location, BTreeSet<usize>> = BTreeMap::new();
+        for (block, data) in cfg.blocks.iter().enumerate() {
+            if !dominators.is_reachable(block) {
+                continue;
+            }
+            for instruction in &instructions[data.start..data.end] {
+                for location in instruction.inst.def_use().defs {
+                    instruction_defs.entry(location).or_default().insert(block);
+                }
+            }
+        }
+
+        // the entry defines every register as well
+        let mut def_blocks = instruction_defs.clone();
+        for reg in 0..registers_size {
+            def_blocks
+                .entry(Location::Register(reg))
+                .or_default()
+                .insert(0);
+        }
+
+        let mut renamer = Renamer {
+            values: vec![],
+            stacks: HashMap::new(),
+            entry_values: HashMap::new(),
+        };
+        let mut blocks = vec![SsaBlock::default(); cfg.blocks.len()];
+
+        // phis on the iterated dominance frontier, only where the location is live. a handler
+        // sees the value before every instruction of its try block, so a block defining the
+        // location also needs a phi at its handlers, even at one it dominates.
+        let no_defs = BTreeSet::new();
+        for (location, defined_in) in def_blocks {
+            let defining = instruction_defs.get(&location).unwrap_or(&no_defs);
+            let mut has_phi = BTreeSet::new();
+            let mut worklist: Vec<usize> = defined_in.into_iter().collect();
+            while let Some(block) = worklist.pop() {
+                let handlers = cfg.blocks[block]
+                    .successors
+                    .iter()
+                    .filter(|edge| matches!(edge.kind, EdgeKind::Exception(_)))
+                    .filter(|_| defining.contains(&block))
+                    .map(|edge| edge.target);
+                for frontier in frontiers[block].iter().copied().chain(handlers) {
+                    let is_live = live
+                        .before(cfg.blocks[frontier].start)
+                        .is_some_and(|live| live.contains(&location));
+                    if !is_live || !has_phi.insert(frontier) {
+                        continue;
+                    }
+
+                    let value = renamer.new_value(location, ValueSource::Phi(frontier));
+                    blocks[frontier].phis.push(Phi {
+                        location,
+                        value,
+                        operands: vec![],
+                    });
+                    worklist.push(frontier);
+                }
+            }
+        }
+
+        for reg in 0..registers_size {
+            renamer.entry_value(Location::Register(reg));
+        }
+
+        let mut positions = HashMap::new();
+        let mut events = vec![];
+        if dominators.is_reachable(0) {
+            renamer.flow(&mut blocks, 0, None);
+            events.push(Event::Enter(0));
+        }
+
+        while let Some(event) = events.pop() {
+            let block = match event {
+                Event::Enter(block) => block,
+                Event::Exit(pushed) => {
+                    for location in pushed {
+                        if let Some(stack) = renamer.stacks.get_mut(&location) {
+                            stack.pop();
+                        }
+                    }
+                    continue;
+                }
+            };
+
+            let mut pushed = vec![];
+            for phi in 0..blocks[block].phis.len() {
+                let Phi {
+                    location, value, ..
+                } = blocks[block].phis[phi];
+                renamer.push(location, value);
+                pushed.push(location);
+            }
+
+            let data = &cfg.blocks[block];
+            let (handlers, successors): (Vec<&Edge>, Vec<&Edge>) = data
+                .successors
+                .iter()
+                .partition(|edge| matches!(edge.kind, EdgeKind::Exception(_)));
+
+            for (index, instruction) in instructions
+                .iter()
+                .enumerate()
+                .take(data.end)
+                .skip(data.start)
+            {
+                for handler in &handlers {
+                    renamer.flow(&mut blocks, handler.target, Some(block));
+                }
+
+                let def_use = instruction.inst.def_use();
+                let uses = def_use
+                    .uses
+                    .into_iter()
+                    .map(|location| (location, renamer.current(location)))
+                    .collect();
+                let defs = def_use
+                    .defs
+                    .into_iter()
+                    .map(|location| {
+                        let value = renamer.new_value(location, ValueSource::Instruction(index));
+                        renamer.push(location, value);
+                        pushed.push(location);
+                        (location, value)
+                    })
+                    .collect();
+
+                positions.insert(index, (block, blocks[block].instructions.len()));
+                blocks[block].instructions.push(SsaInstruction {
+                    index,
+                    offset: instruction.offset,
+                    defs,
+                    uses,
+                });
+            }
+
+            for successor in successors {
+                renamer.flow(&mut blocks, successor.target, Some(block));
+            }
+
+            events.push(Event::Exit(pushed));
+            events.extend(
+                dominators
+                    .children(block)
+                    .iter()
+                    .rev()
+                    .map(|&child| Event::Enter(child)),
+            );
+        }
+
+        Self {
+            blocks,
+            values: renamer.values,
+            positions,
+        }
+    }
+
+    pub fn value(&self, value: Value) -> &ValueInfo {
+        &self.values[value.0 as usize]
+    }
+
+    pub fn values(&self) -> &[ValueInfo] {
+        &self.values
+    }
+
+    /// the renamed instruction at `index`, `None` for payloads and unreachable code
+    pub fn instruction(&self, index: usize) -> Option<&SsaInstruction> {
+        let &(block, position) = self.positions.get(&index)?;
+        self.blocks[block].instructions.get(position)
+    }
+
+    /// follow `move*` instructions back to the value they copy
+    pub fn copy_source(&self, instructions: &[DalvikInstruction], mut value: Value) -> Value {
+        while let ValueSource::Instruction(index) = self.value(value).source {
+            let is_move = matches!(
+                instructions[index].inst,
+                DalvikBytecode::Move(..)
+                    | DalvikBytecode::MoveFrom16(..)
+                    | DalvikBytecode::Move16(..)
+            );
+            let Some(instruction) = self.instruction(index).filter(|_| is_move) else {
+                break;
+            };
+
+            // a move copies each half of a pair to the same half
+            match instruction
+                .defs
+                .iter()
+                .position(|&(_, def)| def == value)
+                .and_then(|half| instruction.uses.get(half))
+            {
+                Some(&(_, source)) => value = source,
+                None => break,
+            }
+        }
+        value
+    }
+}
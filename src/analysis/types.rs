@@ -0,0 +1,758 @@
+use super::cfg::{Cfg, EdgeKind};
+use crate::{
+    dalvik::{opcodes::*, DalvikInstruction},
+    resolver::{Prototype, Resolver},
+};
+use std::collections::{HashMap, VecDeque};
+
+const OBJECT: &str = "Ljava/lang/Object;";
+const THROWABLE: &str = "Ljava/lang/Throwable;";
+
+/// the type of a register at a point in the method, ordered like the ART verifier's lattice:
+/// `Undefined` and `Conflict` are unusable, constants are refined by merging with real types
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RegType {
+    /// never assigned
+    Undefined,
+    /// assigned incompatible types on different paths
+    Conflict,
+    /// the constant 0, usable as any integral type, float or null
+    Zero,
+    /// a 32 bit value usable as either int or float
+    Constant,
+    /// halves of a 64 bit value usable as either long or double
+    ConstantWideLo,
+    ConstantWideHi,
+    Boolean,
+    Byte,
+    Short,
+    Char,
+    Integer,
+    Float,
+    LongLo,
+    LongHi,
+    DoubleLo,
+    DoubleHi,
+    /// reference with its type descriptor
+    Reference(String),
+    /// result of `new-instance` before the constructor ran
+    Uninitialized(String),
+}
+
+impl RegType {
+    /// the type of a value with the given descriptor, the low half for wide types
+    pub fn from_descriptor(descriptor: &str) -> Self {
+        match descriptor {
+            "Z" => Self::Boolean,
+            "B" => Self::Byte,
+            "S" => Self::Short,
+            "C" => Self::Char,
+            "I" => Self::Integer,
+            "F" => Self::Float,
+            "J" => Self::LongLo,
+            "D" => Self::DoubleLo,
+            "V" => Self::Undefined,
+            _ => Self::Reference(descriptor.to_string()),
+        }
+    }
+
+    /// low half of a register pair
+    pub fn is_wide(&self) -> bool {
+        matches!(self, Self::ConstantWideLo | Self::LongLo | Self::DoubleLo)
+    }
+
+    fn is_wide_high(&self) -> bool {
+        matches!(self, Self::ConstantWideHi | Self::LongHi | Self::DoubleHi)
+    }
+
+    fn high_half(&self) -> Self {
+        match self {
+            Self::ConstantWideLo => Self::ConstantWideHi,
+            Self::LongLo => Self::LongHi,
+            Self::DoubleLo => Self::DoubleHi,
+            _ => Self::Conflict,
+        }
+    }
+
+    fn is_integral(&self) -> bool {
+        matches!(
+            self,
+            Self::Boolean | Self::Byte | Self::Short | Self::Char | Self::Integer
+        )
+    }
+
+    pub fn is_reference(&self) -> bool {
+        matches!(self, Self::Reference(_))
+    }
+
+    /// least upper bound of two types at a control flow join
+    pub fn merge(&self, other: &Self) -> Self {
+        use RegType::*;
+
+        match (self, other) {
+            (a, b) if a == b => a.clone(),
+            (Zero, t) | (t, Zero) if t.is_integral() || t.is_reference() || *t == Float => {
+                t.clone()
+            }
+            (Zero, Constant) | (Constant, Zero) => Constant,
+            (Constant, Float) | (Float, Constant) => Float,
+            (Constant, t) | (t, Constant) if t.is_integral() => Integer,
+            (Boolean, t) | (t, Boolean) if t.is_integral() => t.clone(),
+            (Byte, Short) | (Short, Byte) => Short,
+            (a, b) if a.is_integral() && b.is_integral() => Integer,
+            (ConstantWideLo, t @ (LongLo | DoubleLo))
+            | (t @ (LongLo | DoubleLo), ConstantWideLo) => t.clone(),
+            (ConstantWideHi, t @ (LongHi | DoubleHi))
+            | (t @ (LongHi | DoubleHi), ConstantWideHi) => t.clone(),
+            // without the class hierarchy the only common superclass we know of is Object
+            (Reference(_), Reference(_)) => Reference(OBJECT.to_string()),
+            _ => Conflict,
+        }
+    }
+}
+
+/// the method whose registers are typed, its arguments occupy the last registers
+pub struct MethodSignature<'a> {
+    pub class: &'a str,
+    pub is_static: bool,
+    pub prototype: &'a Prototype,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct State {
+    types: Vec<RegType>,
+    /// indices of the `const*` instructions whose value the register may still hold
+    origins: Vec<Vec<usize>>,
+    /// pending result of the previous invoke or filled-new-array
+    result: RegType,
+}
+
+impl State {
+    fn merge(&mut self, other: &State) -> bool {
+        let before = self.clone();
+        for (mine, theirs) in self.types.iter_mut().zip(&other.types) {
+            *mine = mine.merge(theirs);
+        }
+        for (mine, theirs) in self.origins.iter_mut().zip(&other.origins) {
+            for origin in theirs {
+                if !mine.contains(origin) {
+                    mine.push(*origin);
+                }
+            }
+        }
+        self.result = self.result.merge(&other.result);
+        *self != before
+    }
+
+    fn get(&self, reg: u16) -> RegType {
+        self.types
+            .get(reg as usize)
+            .cloned()
+            .unwrap_or(RegType::Conflict)
+    }
+
+    fn set_one(&mut self, reg: usize, value: RegType, origins: Vec<usize>) {
+        let Some(old) = self.types.get(reg) else {
+            return;
+        };
+
+        // overwriting half of a register pair breaks the other half
+        if old.is_wide() && reg + 1 < self.types.len() {
+            self.types[reg + 1] = RegType::Conflict;
+        } else if old.is_wide_high() && reg > 0 {
+            self.types[reg - 1] = RegType::Conflict;
+        }
+
+        self.types[reg] = value;
+        self.origins[reg] = origins;
+    }
+
+    fn set(&mut self, reg: u16, value: RegType, origin: Option<usize>) {
+        self.set_from(reg, value, origin.into_iter().collect());
+    }
+
+    fn set_from(&mut self, reg: u16, value: RegType, origins: Vec<usize>) {
+        let reg = reg as usize;
+        let high = value.is_wide().then(|| value.high_half());
+
+        self.set_one(reg, value, origins.clone());
+        if let Some(high) = high {
+            self.set_one(reg + 1, high, origins);
+        }
+    }
+}
+
+/// consecutive registers of a `/range` instruction, `None` when they run past v65535
+fn range(first: u16, count: impl Into<u16>) -> Option<Vec<u16>> {
+    (0..count.into())
+        .map(|index| first.checked_add(index))
+        .collect()
+}
+
+/// the inferred type of every register before every reachable instruction
+#[derive(Debug, Clone)]
+pub struct RegisterTypes {
+    before: Vec<Option<Vec<RegType>>>,
+    constants: HashMap<usize, RegType>,
+}
+
+impl RegisterTypes {
+    /// register types before the instruction at `index`, `None` when it is unreachable
+    pub fn before(&self, index: usize) -> Option<&[RegType]> {
+        self.before.get(index)?.as_deref()
+    }
+
+    pub fn register(&self, index: usize, reg: u16) -> Option<&RegType> {
+        self.before(index)?.get(reg as usize)
+    }
+
+    /// the type the value loaded by the `const*` instruction at `index` is used as, if it is
+    /// used somewhere that requires one. a `Reference` means the constant is `null`.
+    pub fn constant_use(&self, index: usize) -> Option<&RegType> {
+        self.constants.get(&index)
+    }
+}
+
+struct Inference<'a> {
+    instructions: &'a [DalvikInstruction],
+    cfg: &'a Cfg,
+    signature: &'a MethodSignature<'a>,
+    resolver: &'a dyn Resolver,
+    /// expected type of every constant use, only gathered once the types are stable
+    constants: Option<HashMap<usize, RegType>>,
+}
+
+/// infer the type of every register at every instruction with a forward dataflow analysis
+pub fn infer_types(
+    instructions: &[DalvikInstruction],
+    cfg: &Cfg,
+    registers_size: u16,
+    signature: &MethodSignature,
+    resolver: &dyn Resolver,
+) -> RegisterTypes {
+    let mut inference = Inference {
+        instructions,
+        cfg,
+        signature,
+        resolver,
+        constants: None,
+    };
+
+    let mut in_states: Vec<Option<State>> = vec![None; cfg.blocks.len()];
+    if !cfg.blocks.is_empty() {
+        in_states[0] = Some(entry_state(registers_size, signature));
+    }
+
+    let mut worklist = VecDeque::from([0]);
+    while let Some(block) = worklist.pop_front() {
+        for (target, state) in inference.run_block(block, &in_states, &mut []) {
+            let changed = match &mut in_states[target] {
+                Some(existing) => existing.merge(&state),
+                slot @ None => {
+                    *slot = Some(state);
+                    true
+                }
+            };
+            if changed && !worklist.contains(&target) {
+                worklist.push_back(target);
+            }
+        }
+    }
+
+    // one last pass over the stable states to record the types and constant uses
+    let mut before = vec![None; instructions.len()];
+    inference.constants = Some(HashMap::new());
+    for block in (0..cfg.blocks.len()).filter(|&block| in_states[block].is_some()) {
+        inference.run_block(block, &in_states, &mut before);
+    }
+
+    RegisterTypes {
+        before,
+        constants: inference.constants.unwrap_or_default(),
+    }
+}
+
+fn entry_state(registers_size: u16, signature: &MethodSignature) -> State {
+    let mut state = State {
+        types: vec![RegType::Undefined; registers_size as usize],
+        origins: vec![vec![]; registers_size as usize],
+        result: RegType::Undefined,
+    };
+
+    let parameters: Vec<RegType> = signature
+        .prototype
+        .parameters
+        .iter()
+        .map(|parameter| RegType::from_descriptor(parameter))
+        .collect();
+    let ins_size = parameters
+        .iter()
+        .map(|parameter| if parameter.is_wide() { 2 } else { 1 })
+        .sum::<u16>()
+        + if signature.is_static { 0 } else { 1 };
+
+    let mut reg = registers_size.saturating_sub(ins_size);
+    if !signature.is_static {
+        state.set(reg, RegType::Reference(signature.class.to_string()), None);
+        reg += 1;
+    }
+    for parameter in parameters {
+        let width = if parameter.is_wide() { 2 } else { 1 };
+        state.set(reg, parameter, None);
+        reg += width;
+    }
+
+    state
+}
+
+fn arithmetic_type(kind: ArithmeticKind) -> RegType {
+    use ArithmeticKind::*;
+
+    match kind {
+        AddLong | SubLong | MulLong | DivLong | RemLong | AndLong | OrLong | XorLong | ShlLong
+        | ShrLong | UshrLong => RegType::LongLo,
+        AddFloat | SubFloat | MulFloat | DivFloat | RemFloat => RegType::Float,
+        AddDouble | SubDouble | MulDouble | DivDouble | RemDouble => RegType::DoubleLo,
+        _ => RegType::Integer,
+    }
+}
+
+/// (source type, destination type) of a unary operation
+fn unop_types(kind: UnopKind) -> (RegType, RegType) {
+    use RegType::*;
+    use UnopKind::*;
+
+    match kind {
+        NegInt | NotInt => (Integer, Integer),
+        NegLong | NotLong => (LongLo, LongLo),
+        NegFloat => (Float, Float),
+        NegDouble => (DoubleLo, DoubleLo),
+        IntToLong => (Integer, LongLo),
+        IntToFloat => (Integer, Float),
+        IntToDouble => (Integer, DoubleLo),
+        LongToInt => (LongLo, Integer),
+        LongToFloat => (LongLo, Float),
+        LongToDouble => (LongLo, DoubleLo),
+        FloatToInt => (Float, Integer),
+        FloatToLong => (Float, LongLo),
+        FloatToDouble => (Float, DoubleLo),
+        DoubleToInt => (DoubleLo, Integer),
+        DoubleToLong => (DoubleLo, LongLo),
+        DoubleToFloat => (DoubleLo, Float),
+        IntToByte => (Integer, Byte),
+        IntToChar => (Integer, Char),
+        IntToShort => (Integer, Short),
+    }
+}
+
+/// the value type of an array/field access when nothing better is known
+fn access_type(kind: OpKind) -> RegType {
+    match kind {
+        OpKind::Get | OpKind::Put => RegType::Constant,
+        OpKind::GetWide | OpKind::PutWide => RegType::ConstantWideLo,
+        OpKind::GetObject | OpKind::PutObject => RegType::Reference(OBJECT.to_string()),
+        OpKind::GetBoolean | OpKind::PutBoolean => RegType::Boolean,
+        OpKind::GetByte | OpKind::PutByte => RegType::Byte,
+        OpKind::GetChar | OpKind::PutChar => RegType::Char,
+        OpKind::GetShort | OpKind::PutShort => RegType::Short,
+    }
+}
+
+fn component_type(array: &RegType, kind: OpKind) -> RegType {
+    match array {
+        RegType::Reference(descriptor) if descriptor.starts_with('[') => {
+            RegType::from_descriptor(&descriptor[1..])
+        }
+        _ => access_type(kind),
+    }
+}
+
+fn object() -> RegType {
+    RegType::Reference(OBJECT.to_string())
+}
+
+impl Inference<'_> {
+    /// run the block from its in state, returning the states flowing to its successors.
+    /// the state before every instruction is stored in `before` when it is large enough.
+    fn run_block(
+        &mut self,
+        block: usize,
+        in_states: &[Option<State>],
+        before: &mut [Option<Vec<RegType>>],
+    ) -> Vec<(usize, State)> {
+        let Some(mut state) = in_states[block].clone() else {
+            return vec![];
+        };
+        let block = &self.cfg.blocks[block];
+        let mut outgoing = vec![];
+
+        for index in block.start..block.end {
+            if let Some(slot) = before.get_mut(index) {
+                *slot = Some(state.types.clone());
+            }
+
+            // any instruction of a try block may throw, handlers see the state before it
+            for edge in &block.successors {
+                if let EdgeKind::Exception(_) = edge.kind {
+                    let mut handler_state = state.clone();
+                    handler_state.result = RegType::Undefined;
+                    outgoing.push((edge.target, handler_state));
+                }
+            }
+
+            self.step(index, &mut state);
+        }
+
+        for edge in &block.successors {
+            if !matches!(edge.kind, EdgeKind::Exception(_)) {
+                outgoing.push((edge.target, state.clone()));
+            }
+        }
+        outgoing
+    }
+
+    /// record that `reg` is used as `expected`, refining the constant it may hold
+    fn expect(&mut self, state: &State, reg: u16, expected: RegType) {
+        let (Some(constants), Some(origins)) =
+            (self.constants.as_mut(), state.origins.get(reg as usize))
+        else {
+            return;
+        };
+
+        for origin in origins {
+            constants
+                .entry(*origin)
+                .and_modify(|existing| {
+                    if *existing != expected {
+                        *existing = RegType::Conflict
+                    }
+                })
+                .or_insert(expected.clone());
+        }
+    }
+
+    fn expect_arguments(
+        &mut self,
+        state: &State,
+        regs: &[u16],
+        receiver: Option<&str>,
+        parameters: &[String],
+    ) {
+        let mut regs = regs.iter();
+        if let (Some(receiver), Some(&reg)) = (receiver, regs.next()) {
+            self.expect(state, reg, RegType::Reference(receiver.to_string()));
+        }
+
+        for parameter in parameters {
+            let expected = RegType::from_descriptor(parameter);
+            let wide = expected.is_wide();
+            if let Some(&reg) = regs.next() {
+                self.expect(state, reg, expected);
+            }
+            if wide {
+                regs.next();
+            }
+        }
+    }
+
+    fn invoke(
+        &mut self,
+        state: &mut State,
+        kind: Option<InvokeKind>,
+        regs: &[u16],
+        method_idx: u16,
+    ) {
+        let Some(method) = self.resolver.method(method_idx as u32) else {
+            state.result = RegType::Conflict;
+            return;
+        };
+
+        let receiver = (kind != Some(InvokeKind::Static)).then_some(method.class.as_str());
+        self.expect_arguments(state, regs, receiver, &method.prototype.parameters);
+
+        // running a constructor initializes every copy of the new instance
+        if method.name == "<init>" {
+            if let Some(RegType::Uninitialized(class)) = regs.first().map(|&reg| state.get(reg)) {
+                for value in state.types.iter_mut() {
+                    if *value == RegType::Uninitialized(class.clone()) {
+                        *value = RegType::Reference(class.clone());
+                    }
+                }
+            }
+        }
+
+        state.result = RegType::from_descriptor(&method.prototype.return_type);
+    }
+
+    fn exception_type(&self, index: usize) -> RegType {
+        let Some(block) = self.cfg.block_of(index) else {
+            return RegType::Reference(THROWABLE.to_string());
+        };
+
+        let mut caught = self.cfg.blocks[block]
+            .predecessors
+            .iter()
+            .flat_map(|&predecessor| &self.cfg.blocks[predecessor].successors)
+            .filter(|edge| edge.target == block)
+            .filter_map(|edge| match edge.kind {
+                EdgeKind::Exception(type_idx) => Some(type_idx),
+                _ => None,
+            });
+
+        match caught.next() {
+            Some(Some(type_idx)) if caught.all(|other| other == Some(type_idx)) => {
+                self.resolver.type_descriptor(type_idx).map_or(
+                    RegType::Reference(THROWABLE.to_string()),
+                    RegType::Reference,
+                )
+            }
+            _ => RegType::Reference(THROWABLE.to_string()),
+        }
+    }
+
+    fn step(&mut self, index: usize, state: &mut State) {
+        use DalvikBytecode as B;
+
+        let instructions = self.instructions;
+        let inst = &instructions[index].inst;
+        let result = std::mem::replace(&mut state.result, RegType::Undefined);
+        let const_type = |value: i64| match value {
+            0 => RegType::Zero,
+            _ => RegType::Constant,
+        };
+
+        match inst {
+            B::Move(_, dst, src) => self.copy(state, *dst as u16, *src as u16),
+            B::MoveFrom16(_, dst, src) => self.copy(state, *dst as u16, *src),
+            B::Move16(_, dst, src) => self.copy(state, *dst, *src),
+            B::MoveResult(MoveKind::Exception, dst) => {
+                let exception = self.exception_type(index);
+                state.set(*dst as u16, exception, None);
+            }
+            B::MoveResult(_, dst) => state.set(*dst as u16, result, None),
+
+            B::Return(ReturnKind::ReturnVoid, _) | B::ReturnVoidNoBarrier => {}
+            B::Return(_, reg) => {
+                let expected = RegType::from_descriptor(&self.signature.prototype.return_type);
+                self.expect(state, *reg as u16, expected);
+            }
+
+            B::Const4(reg, value) => state.set(*reg as u16, const_type(*value as i64), Some(index)),
+            B::Const16(reg, value) | B::ConstHigh16(reg, value) => {
+                state.set(*reg as u16, const_type(*value as i64), Some(index))
+            }
+            B::Const(reg, value) => state.set(*reg as u16, const_type(*value as i64), Some(index)),
+            B::ConstWide16(reg, _)
+            | B::ConstWide32(reg, _)
+            | B::ConstWide(reg, _)
+            | B::ConstWideHigh16(reg, _) => {
+                state.set(*reg as u16, RegType::ConstantWideLo, Some(index))
+            }
+            B::ConstString(reg, _) | B::ConstStringJumbo(reg, _) => state.set(
+                *reg as u16,
+                RegType::Reference("Ljava/lang/String;".to_string()),
+                None,
+            ),
+            B::ConstClass(reg, _) => state.set(
+                *reg as u16,
+                RegType::Reference("Ljava/lang/Class;".to_string()),
+                None,
+            ),
+            B::ConstMethodHandle(reg, _) => state.set(
+                *reg as u16,
+                RegType::Reference("Ljava/lang/invoke/MethodHandle;".to_string()),
+                None,
+            ),
+            B::ConstMethodType(reg, _) => state.set(
+                *reg as u16,
+                RegType::Reference("Ljava/lang/invoke/MethodType;".to_string()),
+                None,
+            ),
+
+            B::MonitorEnter(reg)
+            | B::MonitorExit(reg)
+            | B::Throw(reg)
+            | B::FilledArrayData(reg, _) => self.expect(state, *reg as u16, object()),
+            B::CheckCast(reg, type_idx) => {
+                self.expect(state, *reg as u16, object());
+                let cast = self.type_of(*type_idx as u32);
+                state.set(*reg as u16, cast, None);
+            }
+            B::InstanceOf(dst, src, _) => {
+                self.expect(state, *src as u16, object());
+                state.set(*dst as u16, RegType::Boolean, None);
+            }
+            B::ArrayLength(dst, src) => {
+                self.expect(state, *src as u16, object());
+                state.set(*dst as u16, RegType::Integer, None);
+            }
+            B::NewInstance(dst, type_idx) => {
+                let class = self
+                    .resolver
+                    .type_descriptor(*type_idx as u32)
+                    .unwrap_or_else(|| OBJECT.to_string());
+                state.set(*dst as u16, RegType::Uninitialized(class), None);
+            }
+            B::NewArray(dst, size, type_idx) => {
+                self.expect(state, *size as u16, RegType::Integer);
+                let array = self.type_of(*type_idx as u32);
+                state.set(*dst as u16, array, None);
+            }
+            B::FilledNewArray(type_idx, regs) => {
+                let regs: Vec<u16> = regs.iter().map(|&reg| reg as u16).collect();
+                self.filled_new_array(state, *type_idx, &regs);
+            }
+            B::FilledNewArrayRange(count, type_idx, first) => match range(*first, *count) {
+                Some(regs) => self.filled_new_array(state, *type_idx, &regs),
+                None => state.result = RegType::Conflict,
+            },
+
+            B::PackedSwitch(reg, _) | B::SparseSwitch(reg, _) => {
+                self.expect(state, *reg as u16, RegType::Integer)
+            }
+            B::IfTest(_, reg1, reg2, _) => {
+                // comparing against a reference makes the other side a reference too
+                for (reg, other) in [(*reg1, *reg2), (*reg2, *reg1)] {
+                    if state.get(other as u16).is_reference() {
+                        self.expect(state, reg as u16, object());
+                    }
+                }
+            }
+            B::Cmp(kind, dst, src1, src2) => {
+                let operand = match kind {
+                    CmpKind::CmplFloat | CmpKind::CmpgFloat => RegType::Float,
+                    CmpKind::CmplDouble | CmpKind::CmpgDouble => RegType::DoubleLo,
+                    CmpKind::CmpLong => RegType::LongLo,
+                };
+                self.expect(state, *src1 as u16, operand.clone());
+                self.expect(state, *src2 as u16, operand);
+                state.set(*dst as u16, RegType::Integer, None);
+            }
+
+            B::ArrayOp(kind, value, array, array_index) => {
+                let array_type = state.get(*array as u16);
+                let component = component_type(&array_type, *kind);
+                self.expect(state, *array as u16, object());
+                self.expect(state, *array_index as u16, RegType::Integer);
+                self.access(state, *kind, *value as u16, component);
+            }
+            B::InstanceOp(kind, value, object_reg, field_idx) => {
+                self.expect(state, *object_reg as u16, object());
+                let field_type = self.field_type(*field_idx, *kind);
+                self.access(state, *kind, *value as u16, field_type);
+            }
+            B::InstanceOpQuick(kind, value, object_reg, _) => {
+                self.expect(state, *object_reg as u16, object());
+                self.access(state, *kind, *value as u16, access_type(*kind));
+            }
+            B::StaticOp(kind, value, field_idx) => {
+                let field_type = self.field_type(*field_idx, *kind);
+                self.access(state, *kind, *value as u16, field_type);
+            }
+
+            B::Invoke(kind, regs, method_idx) => {
+                let regs: Vec<u16> = regs.iter().map(|&reg| reg as u16).collect();
+                self.invoke(state, Some(*kind), &regs, *method_idx);
+            }
+            B::InvokeRange(kind, count, method_idx, first) => match range(*first, *count) {
+                Some(regs) => self.invoke(state, Some(*kind), &regs, *method_idx),
+                None => state.result = RegType::Conflict,
+            },
+            B::InvokePolymorphic(_, _, proto_idx)
+            | B::InvokePolymorphicRange(_, _, _, proto_idx) => {
+                state.result = self
+                    .resolver
+                    .prototype(*proto_idx as u32)
+                    .map_or(RegType::Conflict, |prototype| {
+                        RegType::from_descriptor(&prototype.return_type)
+                    });
+            }
+            B::InvokeCustom(..)
+            | B::InvokeCustomRange(..)
+            | B::InvokeVirtualQuick(..)
+            | B::InvokeVirtualRangeQuick(..) => {
+                state.result = RegType::Conflict;
+            }
+
+            B::Unop(kind, dst, src) => {
+                let (src_type, dst_type) = unop_types(*kind);
+                self.expect(state, *src as u16, src_type);
+                state.set(*dst as u16, dst_type, None);
+            }
+            B::Binop(kind, dst, src1, src2) => {
+                let operand = arithmetic_type(*kind);
+                let shift = if kind.is_shift() {
+                    RegType::Integer
+                } else {
+                    operand.clone()
+                };
+                self.expect(state, *src1 as u16, operand.clone());
+                self.expect(state, *src2 as u16, shift);
+                state.set(*dst as u16, operand, None);
+            }
+            B::Binop2Addr(kind, dst, src) => {
+                let operand = arithmetic_type(*kind);
+                let shift = if kind.is_shift() {
+                    RegType::Integer
+                } else {
+                    operand.clone()
+                };
+                self.expect(state, *dst as u16, operand.clone());
+                self.expect(state, *src as u16, shift);
+                state.set(*dst as u16, operand, None);
+            }
+            B::BinopLit16(_, dst, src, _) | B::BinopLit8(_, dst, src, _) => {
+                self.expect(state, *src as u16, RegType::Integer);
+                state.set(*dst as u16, RegType::Integer, None);
+            }
+
+            B::IfTestZ(..)
+            | B::Nop
+            | B::Goto(_)
+            | B::Goto16(_)
+            | B::Goto32(_)
+            | B::PackedSwitchPayload(..)
+            | B::SparseSwitchPayload(..)
+            | B::FillArrayDataPayload(..) => {}
+        }
+    }
+
+    fn copy(&mut self, state: &mut State, dst: u16, src: u16) {
+        let value = state.get(src);
+        let origins = state.origins.get(src as usize).cloned().unwrap_or_default();
+        state.set_from(dst, value, origins);
+    }
+
+    fn type_of(&self, type_idx: u32) -> RegType {
+        self.resolver
+            .type_descriptor(type_idx)
+            .map_or(object(), RegType::Reference)
+    }
+
+    fn field_type(&self, field_idx: u16, kind: OpKind) -> RegType {
+        self.resolver
+            .field(field_idx as u32)
+            .map_or(access_type(kind), |field| {
+                RegType::from_descriptor(&field.field_type)
+            })
+    }
+
+    /// a get defines `value`, a put uses it
+    fn access(&mut self, state: &mut State, kind: OpKind, value: u16, value_type: RegType) {
+        match kind.is_put() {
+            true => self.expect(state, value, value_type),
+            false => state.set(value, value_type, None),
+        }
+    }
+
+    fn filled_new_array(&mut self, state: &mut State, type_idx: u16, regs: &[u16]) {
+        let array = self.type_of(type_idx as u32);
+        if let RegType::Reference(descriptor) = &array {
+            let component = RegType::from_descriptor(descriptor.get(1..).unwrap_or(OBJECT));
+            for &reg in regs {
+                self.expect(state, reg, component.clone());
+            }
+        }
+        state.result = array;
+    }
+}
@@ -0,0 +1,236 @@
+use crate::dalvik::{
+    opcodes::{DalvikBytecode, MoveKind},
+    DalvikInstruction,
+};
+use std::collections::HashMap;
+
+/// what is wrong with an instruction, modeled on the checks done by the ART method verifier
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// the register is not below `registers_size`
+    RegisterOutOfBounds(u16),
+    /// the second half of the wide operand's register pair is not below `registers_size`
+    RegisterPairOutOfBounds(u16),
+    /// byte offset of a branch or payload target outside of the method
+    TargetOutOfBounds(i64),
+    /// byte offset of a branch target in the middle of an instruction
+    TargetNotInstruction(usize),
+    /// byte offset of a branch target that is a payload rather than code
+    BranchToPayload(usize),
+    /// `goto`, `goto/16` and `if-*` can't branch to themselves
+    ZeroBranchOffset,
+    /// byte offset a switch or fill-array-data points at that isn't a payload of the right kind
+    BadPayloadReference(usize),
+    /// payloads must start on a 4 byte boundary
+    MisalignedPayload,
+    /// `move-result*` not directly after an invoke or filled-new-array
+    MisplacedMoveResult,
+    /// `filled-new-array` result picked up by something other than `move-result-object`
+    MoveResultKindMismatch,
+    /// execution continues from code straight into a payload
+    FallsIntoPayload,
+    /// execution continues past the last instruction
+    FallsOffEnd,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// byte offset of the offending instruction
+    pub offset: usize,
+    pub kind: DiagnosticKind,
+}
+
+struct Verifier<'a> {
+    instructions: &'a [DalvikInstruction],
+    registers_size: u16,
+    /// instruction index by byte offset
+    boundaries: HashMap<usize, usize>,
+    /// byte offset right after the last instruction
+    end: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// check a method's instructions for register, branch, `move-result` and payload problems that
+/// would make ART reject it. every problem is reported, not only the first one.
+pub fn verify_method(instructions: &[DalvikInstruction], registers_size: u16) -> Vec<Diagnostic> {
+    let mut verifier = Verifier {
+        instructions,
+        registers_size,
+        boundaries: instructions
+            .iter()
+            .enumerate()
+            .map(|(index, instruction)| (instruction.offset, index))
+            .collect(),
+        end: instructions
+            .last()
+            .map_or(0, |last| last.offset + last.inst.code_units() * 2),
+        diagnostics: vec![],
+    };
+
+    for (index, instruction) in instructions.iter().enumerate() {
+        verifier.check_registers(instruction);
+        verifier.check_targets(instruction);
+        verifier.check_move_result(index, instruction);
+        verifier.check_fallthrough(index, instruction);
+
+        if instruction.inst.is_payload() && instruction.offset % 4 != 0 {
+            verifier.report(instruction, DiagnosticKind::MisalignedPayload);
+        }
+    }
+
+    verifier.diagnostics
+}
+
+impl<'a> Verifier<'a> {
+    fn report(&mut self, instruction: &DalvikInstruction, kind: DiagnosticKind) {
+        self.diagnostics.push(Diagnostic {
+            offset: instruction.offset,
+            kind,
+        });
+    }
+
+    fn check_registers(&mut self, instruction: &DalvikInstruction) {
+        for operand in instruction.inst.registers() {
+            if operand.reg >= self.registers_size {
+                self.report(
+                    instruction,
+                    DiagnosticKind::RegisterOutOfBounds(operand.reg),
+                );
+            } else if operand.wide && operand.reg + 1 >= self.registers_size {
+                self.report(
+                    instruction,
+                    DiagnosticKind::RegisterPairOutOfBounds(operand.reg),
+                );
+            }
+        }
+    }
+
+    fn check_targets(&mut self, instruction: &DalvikInstruction) {
+        match &instruction.inst {
+            DalvikBytecode::Goto(offset) => self.check_branch(instruction, *offset as i32, false),
+            DalvikBytecode::Goto16(offset)
+            | DalvikBytecode::IfTest(_, _, _, offset)
+            | DalvikBytecode::IfTestZ(_, _, offset) => {
+                self.check_branch(instruction, *offset as i32, false)
+            }
+            DalvikBytecode::Goto32(offset) => self.check_branch(instruction, *offset, true),
+
+            DalvikBytecode::PackedSwitch(_, offset) | DalvikBytecode::SparseSwitch(_, offset) => {
+                let targets = match self.payload(instruction, *offset) {
+                    Some(DalvikBytecode::PackedSwitchPayload(_, targets))
+                        if matches!(instruction.inst, DalvikBytecode::PackedSwitch(..)) =>
+                    {
+                        targets
+                    }
+                    Some(DalvikBytecode::SparseSwitchPayload(_, targets))
+                        if matches!(instruction.inst, DalvikBytecode::SparseSwitch(..)) =>
+                    {
+                        targets
+                    }
+                    _ => return,
+                };
+
+                // switch targets are relative to the switch instruction, not the payload
+                for &target in targets {
+                    self.check_branch(instruction, target, true);
+                }
+            }
+
+            DalvikBytecode::FilledArrayData(_, offset) => {
+                if let Some(payload) = self.payload(instruction, *offset) {
+                    if !matches!(payload, DalvikBytecode::FillArrayDataPayload(..)) {
+                        let target = (instruction.offset as i64 + *offset as i64 * 2) as usize;
+                        self.report(instruction, DiagnosticKind::BadPayloadReference(target));
+                    }
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    /// byte offset of a target given in code units relative to the instruction,
+    /// reports and returns `None` when it is outside of the method
+    fn target(&mut self, instruction: &DalvikInstruction, relative: i32) -> Option<usize> {
+        let target = instruction.offset as i64 + relative as i64 * 2;
+        if target < 0 || target >= self.end as i64 {
+            self.report(instruction, DiagnosticKind::TargetOutOfBounds(target));
+            return None;
+        }
+        Some(target as usize)
+    }
+
+    fn check_branch(&mut self, instruction: &DalvikInstruction, relative: i32, allow_zero: bool) {
+        if relative == 0 && !allow_zero {
+            self.report(instruction, DiagnosticKind::ZeroBranchOffset);
+        }
+
+        let Some(target) = self.target(instruction, relative) else {
+            return;
+        };
+
+        match self.boundaries.get(&target) {
+            None => self.report(instruction, DiagnosticKind::TargetNotInstruction(target)),
+            Some(&index) if self.instructions[index].inst.is_payload() => {
+                self.report(instruction, DiagnosticKind::BranchToPayload(target))
+            }
+            Some(_) => {}
+        }
+    }
+
+    fn payload(
+        &mut self,
+        instruction: &DalvikInstruction,
+        relative: i32,
+    ) -> Option<&'a DalvikBytecode> {
+        let target = self.target(instruction, relative)?;
+        let instructions = self.instructions;
+
+        match self.boundaries.get(&target) {
+            Some(&index) if instructions[index].inst.is_payload() => {
+                Some(&instructions[index].inst)
+            }
+            _ => {
+                self.report(instruction, DiagnosticKind::BadPayloadReference(target));
+                None
+            }
+        }
+    }
+
+    fn check_move_result(&mut self, index: usize, instruction: &DalvikInstruction) {
+        let kind = match instruction.inst {
+            DalvikBytecode::MoveResult(MoveKind::Exception, _) => return,
+            DalvikBytecode::MoveResult(kind, _) => kind,
+            _ => return,
+        };
+
+        let previous = index
+            .checked_sub(1)
+            .map(|previous| &self.instructions[previous].inst);
+
+        match previous {
+            Some(DalvikBytecode::FilledNewArray(..) | DalvikBytecode::FilledNewArrayRange(..))
+                if kind != MoveKind::MoveObject =>
+            {
+                self.report(instruction, DiagnosticKind::MoveResultKindMismatch)
+            }
+            Some(previous) if previous.has_result() => {}
+            _ => self.report(instruction, DiagnosticKind::MisplacedMoveResult),
+        }
+    }
+
+    fn check_fallthrough(&mut self, index: usize, instruction: &DalvikInstruction) {
+        // compilers pad payloads to 4 bytes with a nop that is never executed
+        if !instruction.inst.can_fall_through() || instruction.inst == DalvikBytecode::Nop {
+            return;
+        }
+
+        match self.instructions.get(index + 1) {
+            None => self.report(instruction, DiagnosticKind::FallsOffEnd),
+            Some(next) if next.inst.is_payload() => {
+                self.report(instruction, DiagnosticKind::FallsIntoPayload)
+            }
+            Some(_) => {}
+        }
+    }
+}
@@ -0,0 +1,380 @@
+use super::callgraph::CallKind;
+use crate::{
+    container::dex_file::DexFile,
+    dalvik::opcodes::*,
+    dalvik::DalvikInstruction,
+    resolver::{FieldRef, MethodHandle, MethodRef, Resolver},
+    SmaliDecoder,
+};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    io::{self, BufRead, Write},
+};
+
+const HEADER: &str = "smali-xrefs 1";
+
+/// something instructions refer to, by name so references from every dex file meet
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum XrefTarget {
+    String(String),
+    /// type descriptor
+    Type(String),
+    /// `Lclass;->name:type`
+    Field(String),
+    /// `Lclass;->name(params)ret`
+    Method(String),
+    /// `(params)ret`, of `const-method-type`
+    Prototype(String),
+}
+
+/// how an instruction uses the target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Access {
+    Read,
+    Write,
+    Invoke(CallKind),
+    /// `invoke-custom`, the target is the bootstrap method of its call site
+    Bootstrap,
+    /// `const-string`, `const-class`, `const-method-handle` or `const-method-type`
+    Load,
+    /// `new-instance`, `new-array` or `filled-new-array`
+    New,
+    CheckCast,
+    InstanceOf,
+}
+
+impl Access {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::Invoke(CallKind::Virtual) => "invoke-virtual",
+            Self::Invoke(CallKind::Super) => "invoke-super",
+            Self::Invoke(CallKind::Direct) => "invoke-direct",
+            Self::Invoke(CallKind::Static) => "invoke-static",
+            Self::Invoke(CallKind::Interface) => "invoke-interface",
+            Self::Invoke(CallKind::Polymorphic) => "invoke-polymorphic",
+            Self::Bootstrap => "bootstrap",
+            Self::Load => "load",
+            Self::New => "new",
+            Self::CheckCast => "check-cast",
+            Self::InstanceOf => "instance-of",
+        }
+    }
+
+    pub(crate) fn parse(access: &str) -> Option<Self> {
+        Some(match access {
+            "read" => Self::Read,
+            "write" => Self::Write,
+            "invoke-virtual" => Self::Invoke(CallKind::Virtual),
+            "invoke-super" => Self::Invoke(CallKind::Super),
+            "invoke-direct" => Self::Invoke(CallKind::Direct),
+            "invoke-static" => Self::Invoke(CallKind::Static),
+            "invoke-interface" => Self::Invoke(CallKind::Interface),
+            "invoke-polymorphic" => Self::Invoke(CallKind::Polymorphic),
+            "bootstrap" => Self::Bootstrap,
+            "load" => Self::Load,
+            "new" => Self::New,
+            "check-cast" => Self::CheckCast,
+            "instance-of" => Self::InstanceOf,
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// an instruction referring to a target
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct XrefSite {
+    pub class: String,
+    /// method name followed by its descriptor, `onCreate(Landroid/os/Bundle;)V`
+    pub method: String,
+    /// byte offset of the instruction in the method's code
+    pub offset: usize,
+    pub access: Access,
+}
+
+/// every reference to strings, types, fields, methods and prototypes of a program
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct XrefIndex {
+    xrefs: BTreeMap<XrefTarget, Vec<XrefSite>>,
+}
+
+/// what an instruction refers to and how
+pub fn reference(inst: &DalvikBytecode, resolver: &dyn Resolver) -> Option<(XrefTarget, Access)> {
+    use DalvikBytecode as B;
+
+    let string = |string_idx: u32| resolver.string(string_idx).map(XrefTarget::String);
+    let jtype = |type_idx: u16| {
+        resolver
+            .type_descriptor(type_idx as u32)
+            .map(XrefTarget::Type)
+    };
+    let field_target = |field: FieldRef| {
+        XrefTarget::Field(format!(
+            "{}->{}:{}",
+            field.class, field.name, field.field_type
+        ))
+    };
+    let method_target = |method: MethodRef| {
+        XrefTarget::Method(format!(
+            "{}->{}{}",
+            method.class,
+            method.name,
+            method.prototype.descriptor()
+        ))
+    };
+    let handle_target = |handle: MethodHandle| match handle {
+        MethodHandle::Field(field) => field_target(field),
+        MethodHandle::Method(method) => method_target(method),
+    };
+    let field = |field_idx: u16| resolver.field(field_idx as u32).map(field_target);
+    let method = |method_idx: u16| resolver.method(method_idx as u32).map(method_target);
+    let field_access = |kind: &OpKind| {
+        if kind.is_put() {
+            Access::Write
+        } else {
+            Access::Read
+        }
+    };
+
+    match inst {
+        B::ConstString(_, string_idx) => {
+            string(*string_idx as u32).map(|target| (target, Access::Load))
+        }
+        B::ConstStringJumbo(_, string_idx) => {
+            string(*string_idx).map(|target| (target, Access::Load))
+        }
+        B::ConstClass(_, type_idx) => jtype(*type_idx).map(|target| (target, Access::Load)),
+        B::CheckCast(_, type_idx) => jtype(*type_idx).map(|target| (target, Access::CheckCast)),
+        B::InstanceOf(_, _, type_idx) => {
+            jtype(*type_idx).map(|target| (target, Access::InstanceOf))
+        }
+        B::NewInstance(_, type_idx) | B::NewArray(_, _, type_idx) => {
+            jtype(*type_idx).map(|target| (target, Access::New))
+        }
+        B::FilledNewArray(type_idx, _) | B::FilledNewArrayRange(_, type_idx, _) => {
+            jtype(*type_idx).map(|target| (target, Access::New))
+        }
+        B::InstanceOp(kind, _, _, field_idx) | B::StaticOp(kind, _, field_idx) => {
+            field(*field_idx).map(|target| (target, field_access(kind)))
+        }
+        B::Invoke(kind, _, method_idx) | B::InvokeRange(kind, _, method_idx, _) => {
+            method(*method_idx).map(|target| (target, Access::Invoke(CallKind::from(*kind))))
+        }
+        B::InvokePolymorphic(_, method_idx, _) | B::InvokePolymorphicRange(_, method_idx, _, _) => {
+            method(*method_idx).map(|target| (target, Access::Invoke(CallKind::Polymorphic)))
+        }
+        B::InvokeCustom(_, call_site_idx) | B::InvokeCustomRange(_, call_site_idx, _) => resolver
+            .call_site_bootstrap(*call_site_idx as u32)
+            .map(|handle| (handle_target(handle), Access::Bootstrap)),
+        B::ConstMethodHandle(_, method_handle_idx) => resolver
+            .method_handle(*method_handle_idx as u32)
+            .map(|handle| (handle_target(handle), Access::Load)),
+        B::ConstMethodType(_, proto_idx) => resolver
+            .prototype(*proto_idx as u32)
+            .map(|prototype| (XrefTarget::Prototype(prototype.descriptor()), Access::Load)),
+        _ => None,
+    }
+}
+
+/// escape the separators of the text format
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(other) => unescaped.push(other),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+fn invalid_data(line: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("malformed xref index line {}", line),
+    )
+}
+
+impl XrefIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// index every method with code of a dex file, classes and code items that don't parse
+    /// are skipped
+    pub fn add_dex(&mut self, dex: &dyn DexFile) {
+        for class in dex.readable_classes() {
+            for method in class.methods.iter().filter(|method| method.code_off != 0) {
+                let Ok(code) = dex.code_item(method.code_off) else {
+                    continue;
+                };
+                let instructions = SmaliDecoder::new(code.insns, dex.version()).decode_all();
+                let name = format!("{}{}", method.name, method.prototype.descriptor());
+                self.add_method(&class.descriptor, &name, &instructions, dex);
+            }
+        }
+    }
+
+    /// index the instructions of one method, `method` is its name followed by its descriptor
+    pub fn add_method(
+        &mut self,
+        class: &str,
+        method: &str,
+        instructions: &[DalvikInstruction],
+        resolver: &dyn Resolver,
+    ) {
+        for instruction in instructions {
+            if let Some((target, access)) = reference(&instruction.inst, resolver) {
+                self.insert(
+                    target,
+                    XrefSite {
+                        class: class.to_string(),
+                        method: method.to_string(),
+                        offset: instruction.offset,
+                        access,
+                    },
+                );
+            }
+        }
+    }
+
+    pub(crate) fn insert(&mut self, target: XrefTarget, site: XrefSite) {
+        self.xrefs.entry(target).or_default().push(site);
+    }
+
+    pub fn get(&self, target: &XrefTarget) -> &[XrefSite] {
+        self.xrefs.get(target).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn targets(&self) -> impl Iterator<Item = &XrefTarget> {
+        self.xrefs.keys()
+    }
+
+    /// targets of the same kind as `kind` whose name contains `needle`, with their references
+    pub fn search<'a>(
+        &'a self,
+        kind: fn(String) -> XrefTarget,
+        needle: &'a str,
+    ) -> impl Iterator<Item = (&'a XrefTarget, &'a [XrefSite])> {
+        let probe = kind(String::new());
+        self.xrefs
+            .iter()
+            .filter(move |(target, _)| {
+                std::mem::discriminant(*target) == std::mem::discriminant(&probe)
+                    && target.name().contains(needle)
+            })
+            .map(|(target, sites)| (target, sites.as_slice()))
+    }
+
+    /// save the index as tab separated text, one reference per line
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "{}", HEADER)?;
+        for (target, sites) in &self.xrefs {
+            for site in sites {
+                writeln!(
+                    writer,
+                    "{}\t{}\t{}\t{}\t{}\t{}",
+                    target.kind(),
+                    escape(target.name()),
+                    escape(&site.class),
+                    escape(&site.method),
+                    site.offset,
+                    site.access
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// load an index saved by `write_to`
+    pub fn read_from(reader: impl BufRead) -> io::Result<Self> {
+        let mut lines = reader.lines();
+        if lines.next().transpose()?.as_deref() != Some(HEADER) {
+            return Err(invalid_data(1));
+        }
+
+        let mut index = Self::new();
+        for (number, line) in lines.enumerate() {
+            let line = line?;
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [kind, name, class, method, offset, access] = fields[..] else {
+                return Err(invalid_data(number + 2));
+            };
+
+            let name = unescape(name);
+            let target = match kind {
+                "string" => XrefTarget::String(name),
+                "type" => XrefTarget::Type(name),
+                "field" => XrefTarget::Field(name),
+                "method" => XrefTarget::Method(name),
+                "proto" => XrefTarget::Prototype(name),
+                _ => return Err(invalid_data(number + 2)),
+            };
+            let site = XrefSite {
+                class: unescape(class),
+                method: unescape(method),
+                offset: offset.parse().map_err(|_| invalid_data(number + 2))?,
+                access: Access::parse(access).ok_or_else(|| invalid_data(number + 2))?,
+            };
+            index.insert(target, site);
+        }
+        Ok(index)
+    }
+
+    pub fn is_index(data: &[u8]) -> bool {
+        data.starts_with(HEADER.as_bytes())
+    }
+}
+
+impl XrefTarget {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::String(name)
+            | Self::Type(name)
+            | Self::Field(name)
+            | Self::Method(name)
+            | Self::Prototype(name) => name,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::String(_) => "string",
+            Self::Type(_) => "type",
+            Self::Field(_) => "field",
+            Self::Method(_) => "method",
+            Self::Prototype(_) => "proto",
+        }
+    }
+}
@@ -0,0 +1,337 @@
+//! `extern "C"` api over the decoder, `include/smali_disassembler.h` is generated from this
+//! module by the build script. build the shared library with
+//! `cargo build --release -p smali_disassembler_capi`.
+
+use crate::{
+    dalvik::{opcodes::DalvikBytecode, smali::instruction_text, DalvikInstruction},
+    resolver::Unresolved,
+};
+use std::{ffi::CString, os::raw::c_char, ptr, slice};
+
+/// what an operand is, dex indices are left unresolved
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmaliOperandKind {
+    Register,
+    Literal,
+    StringIndex,
+    TypeIndex,
+    FieldIndex,
+    MethodIndex,
+    ProtoIndex,
+    CallSiteIndex,
+    MethodHandleIndex,
+    /// field offset of a quickened `iget-quick`/`iput-quick`
+    FieldOffset,
+    /// vtable index of a quickened `invoke-virtual-quick`
+    VtableIndex,
+    /// absolute code address, in 16-bit code units, of a branch, switch or array payload
+    BranchTarget,
+}
+
+/// one operand of an instruction
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SmaliOperand {
+    pub kind: SmaliOperandKind,
+    /// register pairs of wide values
+    pub wide: bool,
+    pub value: i64,
+}
+
+/// a decoded instruction, owned by its decoder
+pub struct SmaliInstruction {
+    instruction: DalvikInstruction,
+    mnemonic: CString,
+    text: CString,
+    operands: Vec<SmaliOperand>,
+}
+
+/// the instructions decoded from one code buffer
+pub struct SmaliDecoder {
+    instructions: Vec<SmaliInstruction>,
+    /// index of the instruction `smali_decoder_next` returns
+    cursor: usize,
+    /// byte offset of the instruction decoding stopped at, `None` when the whole buffer decoded
+    error_offset: Option<usize>,
+}
+
+fn operand(kind: SmaliOperandKind, value: impl Into<i64>) -> SmaliOperand {
+    SmaliOperand {
+        kind,
+        wide: false,
+        value: value.into(),
+    }
+}
+
+/// the literal, index and branch target operands following the registers
+fn other_operands(instruction: &DalvikInstruction) -> Vec<SmaliOperand> {
+    use DalvikBytecode as B;
+    use SmaliOperandKind as K;
+
+    let addr = (instruction.offset / 2) as i64;
+    let target = |relative: i64| operand(K::BranchTarget, addr + relative);
+
+    match &instruction.inst {
+        B::Const4(_, value) => vec![operand(K::Literal, *value)],
+        B::Const16(_, value) | B::ConstWide16(_, value) => vec![operand(K::Literal, *value)],
+        B::Const(_, value) | B::ConstWide32(_, value) => vec![operand(K::Literal, *value)],
+        B::ConstHigh16(_, value) => vec![operand(K::Literal, (*value as i32) << 16)],
+        B::ConstWide(_, value) => vec![operand(K::Literal, *value as i64)],
+        B::ConstWideHigh16(_, value) => vec![operand(K::Literal, (*value as i64) << 48)],
+        B::BinopLit16(.., value) => vec![operand(K::Literal, *value)],
+        B::BinopLit8(.., value) => vec![operand(K::Literal, *value)],
+
+        B::ConstString(_, string_idx) => vec![operand(K::StringIndex, *string_idx)],
+        B::ConstStringJumbo(_, string_idx) => vec![operand(K::StringIndex, *string_idx)],
+        B::ConstClass(_, type_idx)
+        | B::CheckCast(_, type_idx)
+        | B::InstanceOf(_, _, type_idx)
+        | B::NewInstance(_, type_idx)
+        | B::NewArray(_, _, type_idx)
+        | B::FilledNewArray(type_idx, _)
+        | B::FilledNewArrayRange(_, type_idx, _) => vec![operand(K::TypeIndex, *type_idx)],
+        B::InstanceOp(.., field_idx) | B::StaticOp(_, _, field_idx) => {
+            vec![operand(K::FieldIndex, *field_idx)]
+        }
+        B::Invoke(_, _, method_idx) | B::InvokeRange(_, _, method_idx, _) => {
+            vec![operand(K::MethodIndex, *method_idx)]
+        }
+        B::InvokePolymorphic(_, method_idx, proto_idx)
+        | B::InvokePolymorphicRange(_, method_idx, _, proto_idx) => vec![
+            operand(K::MethodIndex, *method_idx),
+            operand(K::ProtoIndex, *proto_idx),
+        ],
+        B::InvokeCustom(_, call_site_idx) | B::InvokeCustomRange(_, call_site_idx, _) => {
+            vec![operand(K::CallSiteIndex, *call_site_idx)]
+        }
+        B::ConstMethodHandle(_, handle_idx) => vec![operand(K::MethodHandleIndex, *handle_idx)],
+        B::ConstMethodType(_, proto_idx) => vec![operand(K::ProtoIndex, *proto_idx)],
+        B::InstanceOpQuick(.., offset) => vec![operand(K::FieldOffset, *offset)],
+        B::InvokeVirtualQuick(_, vtable_idx) | B::InvokeVirtualRangeQuick(_, vtable_idx, _) => {
+            vec![operand(K::VtableIndex, *vtable_idx)]
+        }
+
+        B::Goto(relative) => vec![target(*relative as i64)],
+        B::Goto16(relative) | B::IfTest(.., relative) | B::IfTestZ(.., relative) => {
+            vec![target(*relative as i64)]
+        }
+        B::Goto32(relative)
+        | B::FilledArrayData(_, relative)
+        | B::PackedSwitch(_, relative)
+        | B::SparseSwitch(_, relative) => vec![target(*relative as i64)],
+
+        _ => vec![],
+    }
+}
+
+impl SmaliInstruction {
+    fn new(instruction: DalvikInstruction) -> Self {
+        let text = instruction_text(&instruction, &Unresolved);
+        let mnemonic = text.split(' ').next().unwrap_or_default().to_string();
+        let mut operands: Vec<SmaliOperand> = instruction
+            .inst
+            .registers()
+            .iter()
+            .map(|register| SmaliOperand {
+                kind: SmaliOperandKind::Register,
+                wide: register.wide,
+                value: register.reg as i64,
+            })
+            .collect();
+        operands.extend(other_operands(&instruction));
+        Self {
+            // smali text never holds a nul, string literals stay unresolved
+            mnemonic: CString::new(mnemonic).unwrap_or_default(),
+            text: CString::new(text).unwrap_or_default(),
+            instruction,
+            operands,
+        }
+    }
+}
+
+/// decode `len` bytes of code at `code`, the bytes are not needed after this returns.
+/// decoding stops at the first instruction that is invalid or truncated, check for that with
+/// `smali_decoder_error_offset`. returns null when `code` is null, free the decoder with
+/// `smali_decoder_free`.
+///
+/// # Safety
+/// `code` must point to `len` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn smali_decoder_new(code: *const u8, len: usize) -> *mut SmaliDecoder {
+    if code.is_null() {
+        return ptr::null_mut();
+    }
+    let decoder = crate::SmaliDecoder::new(slice::from_raw_parts(code, len), None);
+    // keep the instructions before a decode error, like `decode_all`
+    let (instructions, error_offset) = match decoder.try_decode_all() {
+        Ok(instructions) => (instructions, None),
+        Err(error) => (decoder.decode_all(), Some(error.offset)),
+    };
+    let decoder = SmaliDecoder {
+        instructions: instructions
+            .into_iter()
+            .map(SmaliInstruction::new)
+            .collect(),
+        cursor: 0,
+        error_offset,
+    };
+    Box::into_raw(Box::new(decoder))
+}
+
+/// # Safety
+/// `decoder` must come from `smali_decoder_new` and not be used afterwards, null is ignored
+#[no_mangle]
+pub unsafe extern "C" fn smali_decoder_free(decoder: *mut SmaliDecoder) {
+    if !decoder.is_null() {
+        drop(Box::from_raw(decoder));
+    }
+}
+
+/// write the byte offset of the instruction decoding stopped at to `offset`, false when the
+/// whole code buffer decoded
+///
+/// # Safety
+/// `decoder` must come from `smali_decoder_new` and `offset` be writable or null
+#[no_mangle]
+pub unsafe extern "C" fn smali_decoder_error_offset(
+    decoder: *const SmaliDecoder,
+    offset: *mut usize,
+) -> bool {
+    match decoder.as_ref().and_then(|decoder| decoder.error_offset) {
+        Some(error_offset) => {
+            if let Some(offset) = offset.as_mut() {
+                *offset = error_offset;
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// number of decoded instructions
+///
+/// # Safety
+/// `decoder` must come from `smali_decoder_new`
+#[no_mangle]
+pub unsafe extern "C" fn smali_decoder_count(decoder: *const SmaliDecoder) -> usize {
+    decoder
+        .as_ref()
+        .map_or(0, |decoder| decoder.instructions.len())
+}
+
+/// the instruction at `index`, null past the end. it lives as long as the decoder.
+///
+/// # Safety
+/// `decoder` must come from `smali_decoder_new`
+#[no_mangle]
+pub unsafe extern "C" fn smali_decoder_get(
+    decoder: *const SmaliDecoder,
+    index: usize,
+) -> *const SmaliInstruction {
+    decoder
+        .as_ref()
+        .and_then(|decoder| decoder.instructions.get(index))
+        .map_or(ptr::null(), |instruction| instruction as *const _)
+}
+
+/// the next instruction, null once every instruction was returned
+///
+/// # Safety
+/// `decoder` must come from `smali_decoder_new`
+#[no_mangle]
+pub unsafe extern "C" fn smali_decoder_next(decoder: *mut SmaliDecoder) -> *const SmaliInstruction {
+    let Some(decoder) = decoder.as_mut() else {
+        return ptr::null();
+    };
+    let Some(instruction) = decoder.instructions.get(decoder.cursor) else {
+        return ptr::null();
+    };
+    decoder.cursor += 1;
+    instruction
+}
+
+/// start `smali_decoder_next` over from the first instruction
+///
+/// # Safety
+/// `decoder` must come from `smali_decoder_new`
+#[no_mangle]
+pub unsafe extern "C" fn smali_decoder_reset(decoder: *mut SmaliDecoder) {
+    if let Some(decoder) = decoder.as_mut() {
+        decoder.cursor = 0;
+    }
+}
+
+/// byte offset of the instruction in the code buffer
+///
+/// # Safety
+/// `instruction` must come from a live decoder
+#[no_mangle]
+pub unsafe extern "C" fn smali_instruction_offset(instruction: *const SmaliInstruction) -> usize {
+    (*instruction).instruction.offset
+}
+
+/// size of the instruction in 16-bit code units, payloads included
+///
+/// # Safety
+/// `instruction` must come from a live decoder
+#[no_mangle]
+pub unsafe extern "C" fn smali_instruction_code_units(
+    instruction: *const SmaliInstruction,
+) -> usize {
+    (*instruction).instruction.inst.code_units()
+}
+
+/// `invoke-virtual`, `const/4`, ..., owned by the decoder
+///
+/// # Safety
+/// `instruction` must come from a live decoder
+#[no_mangle]
+pub unsafe extern "C" fn smali_instruction_mnemonic(
+    instruction: *const SmaliInstruction,
+) -> *const c_char {
+    (*instruction).mnemonic.as_ptr()
+}
+
+/// the instruction as smali with indices unresolved, `const-string v0, string@3`, owned by
+/// the decoder
+///
+/// # Safety
+/// `instruction` must come from a live decoder
+#[no_mangle]
+pub unsafe extern "C" fn smali_instruction_text(
+    instruction: *const SmaliInstruction,
+) -> *const c_char {
+    (*instruction).text.as_ptr()
+}
+
+/// number of operands, registers first in operand order then literals, indices and targets
+///
+/// # Safety
+/// `instruction` must come from a live decoder
+#[no_mangle]
+pub unsafe extern "C" fn smali_instruction_operand_count(
+    instruction: *const SmaliInstruction,
+) -> usize {
+    (*instruction).operands.len()
+}
+
+/// write operand `index` to `out`, false when there is no such operand
+///
+/// # Safety
+/// `instruction` must come from a live decoder and `out` be writable
+#[no_mangle]
+pub unsafe extern "C" fn smali_instruction_operand(
+    instruction: *const SmaliInstruction,
+    index: usize,
+    out: *mut SmaliOperand,
+) -> bool {
+    let instruction = &*instruction;
+    match (instruction.operands.get(index), out.is_null()) {
+        (Some(operand), false) => {
+            *out = *operand;
+            true
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,198 @@
+use super::{
+    dex_file::{CodeItem, DexClass, DexFile},
+    range, read_u16, read_u32,
+    standard::StandardDexFile,
+    EmbeddedDex,
+};
+use crate::{
+    dalvik::version::DexVersion,
+    errors::Error,
+    resolver::{FieldRef, MethodHandle, MethodRef, Prototype, Resolver},
+    Result,
+};
+
+const CDEX_MAGIC: &[u8; 4] = b"cdex";
+const HEADER_SIZE: usize = 0x88;
+
+const DATA_SIZE_OFFSET: usize = 0x68;
+const DATA_OFF_OFFSET: usize = 0x6c;
+
+// code item packing, see art/libdexfile/dex/compact_dex_file.h
+const REGISTERS_SIZE_SHIFT: u16 = 12;
+const INS_SIZE_SHIFT: u16 = 8;
+const OUTS_SIZE_SHIFT: u16 = 4;
+const TRIES_SIZE_SHIFT: u16 = 0;
+const INSNS_SIZE_SHIFT: u16 = 5;
+const FLAG_PREHEADER_REGISTERS_SIZE: u16 = 1 << 0;
+const FLAG_PREHEADER_INS_SIZE: u16 = 1 << 1;
+const FLAG_PREHEADER_OUTS_SIZE: u16 = 1 << 2;
+const FLAG_PREHEADER_TRIES_SIZE: u16 = 1 << 3;
+const FLAG_PREHEADER_INSNS_SIZE: u16 = 1 << 4;
+
+/// the fields compact dex adds after the standard dex header
+#[derive(Debug, Clone, Copy)]
+pub struct CompactDexHeader {
+    pub feature_flags: u32,
+    pub debug_info_offsets_pos: u32,
+    pub debug_info_offsets_table_offset: u32,
+    pub debug_info_base: u32,
+    pub owned_data_begin: u32,
+    pub owned_data_end: u32,
+}
+
+/// a reader over a compact dex (cdex) file, as found in android 9+ vdex files. its ids, class
+/// definitions and class data are laid out like a standard dex file's, only code items differ.
+pub struct CompactDexFile<'a> {
+    dex: StandardDexFile<'a>,
+    /// every offset that points into the data section (strings, class data, code items)
+    /// is relative to this, which is the vdex shared data section or, for a standalone cdex,
+    /// the `data_size` bytes at its `data_off`
+    data_section: &'a [u8],
+    header: CompactDexHeader,
+}
+
+impl<'a> CompactDexFile<'a> {
+    pub fn new(dex: &EmbeddedDex<'a>) -> Result<Self> {
+        let data = dex.data;
+        if !data.starts_with(CDEX_MAGIC) || data.len() < HEADER_SIZE {
+            return Err(Error::InvalidContainer);
+        }
+
+        let header = CompactDexHeader {
+            feature_flags: read_u32(data, 0x70)?,
+            debug_info_offsets_pos: read_u32(data, 0x74)?,
+            debug_info_offsets_table_offset: read_u32(data, 0x78)?,
+            debug_info_base: read_u32(data, 0x7c)?,
+            owned_data_begin: read_u32(data, 0x80)?,
+            owned_data_end: read_u32(data, 0x84)?,
+        };
+
+        let data_section = match dex.shared_data.is_empty() {
+            true => {
+                let data_size = read_u32(data, DATA_SIZE_OFFSET)? as usize;
+                let data_off = read_u32(data, DATA_OFF_OFFSET)? as usize;
+                range(data, data_off, data_size).ok_or(Error::InvalidContainer)?
+            }
+            false => dex.shared_data,
+        };
+
+        Ok(Self {
+            dex: StandardDexFile::with_data_section(data, data_section),
+            data_section,
+            header,
+        })
+    }
+
+    pub fn header(&self) -> &CompactDexHeader {
+        &self.header
+    }
+}
+
+impl DexFile for CompactDexFile<'_> {
+    /// compact dex has its own version, code is not checked against a dex version
+    fn version(&self) -> Option<DexVersion> {
+        None
+    }
+
+    fn class_count(&self) -> Result<u32> {
+        self.dex.class_count()
+    }
+
+    fn class(&self, index: u32) -> Result<DexClass> {
+        self.dex.class(index)
+    }
+
+    fn find_class(&self, descriptor: &str) -> Result<Option<DexClass>> {
+        self.dex.find_class(descriptor)
+    }
+
+    /// decode the compact code item at `code_off`, relative to the data section
+    fn code_item(&self, code_off: u32) -> Result<CodeItem<'_>> {
+        let code_off = code_off as usize;
+        let header = range(self.data_section, code_off, 4).ok_or(Error::InvalidContainer)?;
+        let fields = read_u16(header, 0)?;
+        let insns_count_and_flags = read_u16(header, 2)?;
+
+        let mut insns_count = (insns_count_and_flags >> INSNS_SIZE_SHIFT) as u32;
+        let mut registers_size = (fields >> REGISTERS_SIZE_SHIFT) & 0xf;
+        let mut ins_size = (fields >> INS_SIZE_SHIFT) & 0xf;
+        let mut outs_size = (fields >> OUTS_SIZE_SHIFT) & 0xf;
+        let mut tries_size = (fields >> TRIES_SIZE_SHIFT) & 0xf;
+
+        // fields that didn't fit are extended by u16s stored right before the code item
+        let mut preheader = code_off;
+        let mut previous_u16 = || -> Result<u16> {
+            preheader = preheader.checked_sub(2).ok_or(Error::InvalidContainer)?;
+            read_u16(self.data_section, preheader)
+        };
+
+        // the extensions are added to the inline values, a crafted preheader may overflow them
+        let extend = |value: u16, extension: u16| {
+            value.checked_add(extension).ok_or(Error::InvalidContainer)
+        };
+        if insns_count_and_flags & FLAG_PREHEADER_INSNS_SIZE != 0 {
+            let extension = previous_u16()? as u32 | (previous_u16()? as u32) << 16;
+            insns_count = insns_count
+                .checked_add(extension)
+                .ok_or(Error::InvalidContainer)?;
+        }
+        if insns_count_and_flags & FLAG_PREHEADER_REGISTERS_SIZE != 0 {
+            registers_size = extend(registers_size, previous_u16()?)?;
+        }
+        if insns_count_and_flags & FLAG_PREHEADER_INS_SIZE != 0 {
+            ins_size = extend(ins_size, previous_u16()?)?;
+        }
+        if insns_count_and_flags & FLAG_PREHEADER_OUTS_SIZE != 0 {
+            outs_size = extend(outs_size, previous_u16()?)?;
+        }
+        if insns_count_and_flags & FLAG_PREHEADER_TRIES_SIZE != 0 {
+            tries_size = extend(tries_size, previous_u16()?)?;
+        }
+
+        let insns_begin = code_off + 4;
+        let insns = (insns_count as usize)
+            .checked_mul(2)
+            .and_then(|size| range(self.data_section, insns_begin, size))
+            .ok_or(Error::InvalidContainer)?;
+
+        Ok(CodeItem {
+            registers_size: extend(registers_size, ins_size)?,
+            ins_size,
+            outs_size,
+            insns,
+            tries: self
+                .dex
+                .tries(insns_begin + insns.len(), tries_size as usize)?,
+        })
+    }
+}
+
+impl Resolver for CompactDexFile<'_> {
+    fn string(&self, string_idx: u32) -> Option<String> {
+        self.dex.string(string_idx).ok()
+    }
+
+    fn type_descriptor(&self, type_idx: u32) -> Option<String> {
+        self.dex.type_descriptor(type_idx).ok()
+    }
+
+    fn field(&self, field_idx: u32) -> Option<FieldRef> {
+        self.dex.field_ref(field_idx).ok()
+    }
+
+    fn method(&self, method_idx: u32) -> Option<MethodRef> {
+        self.dex.method_ref(method_idx).ok()
+    }
+
+    fn prototype(&self, proto_idx: u32) -> Option<Prototype> {
+        self.dex.prototype(proto_idx).ok()
+    }
+
+    fn method_handle(&self, method_handle_idx: u32) -> Option<MethodHandle> {
+        self.dex.method_handle(method_handle_idx).ok()
+    }
+
+    fn call_site_bootstrap(&self, call_site_idx: u32) -> Option<MethodHandle> {
+        self.dex.call_site_bootstrap(call_site_idx).ok()
+    }
+}
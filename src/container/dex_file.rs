@@ -0,0 +1,98 @@
+use super::{cdex::CompactDexFile, standard::StandardDexFile, DexKind, EmbeddedDex};
+use crate::{
+    analysis::cfg::TryBlock,
+    dalvik::version::DexVersion,
+    resolver::{Prototype, Resolver},
+    Result,
+};
+
+// access flags of classes, fields and methods
+pub const ACC_PUBLIC: u32 = 0x0001;
+pub const ACC_PRIVATE: u32 = 0x0002;
+pub const ACC_PROTECTED: u32 = 0x0004;
+pub const ACC_STATIC: u32 = 0x0008;
+pub const ACC_NATIVE: u32 = 0x0100;
+pub const ACC_INTERFACE: u32 = 0x0200;
+pub const ACC_ABSTRACT: u32 = 0x0400;
+
+#[derive(Debug, Clone)]
+pub struct CodeItem<'a> {
+    /// includes the `ins_size` incoming argument registers
+    pub registers_size: u16,
+    pub ins_size: u16,
+    pub outs_size: u16,
+    /// the raw little endian instruction stream, ready to be handed to `SmaliDecoder`
+    pub insns: &'a [u8],
+    pub tries: Vec<TryBlock>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DexMethod {
+    pub method_idx: u32,
+    pub name: String,
+    pub prototype: Prototype,
+    pub access_flags: u32,
+    /// 0 for abstract and native methods
+    pub code_off: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct DexField {
+    pub field_idx: u32,
+    pub name: String,
+    pub field_type: String,
+    pub access_flags: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct DexClass {
+    pub class_idx: u32,
+    pub descriptor: String,
+    pub access_flags: u32,
+    pub super_class: Option<String>,
+    pub interfaces: Vec<String>,
+    /// static fields first, then instance fields
+    pub fields: Vec<DexField>,
+    /// direct methods first, then virtual methods
+    pub methods: Vec<DexMethod>,
+}
+
+/// the classes and code of a standard or compact dex file, its indices resolve through
+/// `Resolver`. everything reading dex files goes through this, see `open`.
+pub trait DexFile: Resolver + Send + Sync {
+    /// the version code is checked against, `None` when the format doesn't say
+    fn version(&self) -> Option<DexVersion>;
+
+    fn class_count(&self) -> Result<u32>;
+
+    /// the `index`th class definition, nothing else of the file is parsed
+    fn class(&self, index: u32) -> Result<DexClass>;
+
+    /// the class defined with `descriptor`, `Lcom/example/Main;`, without parsing the others
+    fn find_class(&self, descriptor: &str) -> Result<Option<DexClass>>;
+
+    /// the code item of a method with code, at its `code_off`
+    fn code_item(&self, code_off: u32) -> Result<CodeItem<'_>>;
+
+    /// every class definition along with its fields and methods
+    fn classes(&self) -> Result<Vec<DexClass>> {
+        (0..self.class_count()?)
+            .map(|index| self.class(index))
+            .collect()
+    }
+
+    /// the class definitions that parse, for analyses that read what they can of a damaged file
+    fn readable_classes(&self) -> Vec<DexClass> {
+        (0..self.class_count().unwrap_or(0))
+            .filter_map(|index| self.class(index).ok())
+            .collect()
+    }
+}
+
+/// the reader for a dex file found by `extract` or `EmbeddedDex::parse`, whichever its format
+pub fn open<'a>(dex: &EmbeddedDex<'a>) -> Result<Box<dyn DexFile + 'a>> {
+    Ok(match dex.kind {
+        DexKind::Standard => Box::new(StandardDexFile::new(dex)?),
+        DexKind::Compact => Box::new(CompactDexFile::new(dex)?),
+    })
+}
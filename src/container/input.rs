@@ -0,0 +1,50 @@
+use std::{fs::File, io, ops::Deref, path::Path};
+
+/// the bytes of an apk, dex, vdex or oat file. with the `mmap` feature the file is mapped
+/// and its pages are only read when a class or method in them is looked at, otherwise it is
+/// read into memory.
+pub struct InputFile {
+    #[cfg(feature = "mmap")]
+    data: memmap2::Mmap,
+    #[cfg(not(feature = "mmap"))]
+    data: Vec<u8>,
+}
+
+impl InputFile {
+    /// the file must not be truncated or written to while it is open, mapped pages would
+    /// change or go away under the borrowed slices
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        Self::from_file(&file)
+    }
+
+    #[cfg(feature = "mmap")]
+    fn from_file(file: &File) -> io::Result<Self> {
+        // SAFETY: see `open`, the map is read only
+        let data = unsafe { memmap2::Mmap::map(file)? };
+        Ok(Self { data })
+    }
+
+    #[cfg(not(feature = "mmap"))]
+    fn from_file(mut file: &File) -> io::Result<Self> {
+        use std::io::Read;
+
+        let mut data = vec![];
+        file.read_to_end(&mut data)?;
+        Ok(Self { data })
+    }
+}
+
+impl Deref for InputFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl AsRef<[u8]> for InputFile {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
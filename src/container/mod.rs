@@ -0,0 +1,170 @@
+pub mod cdex;
+pub mod dex_file;
+pub mod input;
+pub mod oat;
+pub mod standard;
+pub mod vdex;
+
+use crate::{
+    dalvik::version::{DEX_MAGIC, ENDIAN_CONSTANT},
+    errors::Error,
+    Result,
+};
+use std::borrow::Cow;
+#[cfg(feature = "zip")]
+use {
+    std::io::{Cursor, Read},
+    zip::{CompressionMethod, ZipArchive},
+};
+
+const CDEX_MAGIC: &[u8; 4] = b"cdex";
+const VDEX_MAGIC: &[u8; 4] = b"vdex";
+const ELF_MAGIC: &[u8; 4] = b"\x7fELF";
+
+const FILE_SIZE_OFFSET: usize = 0x20;
+const ENDIAN_TAG_OFFSET: usize = 0x28;
+const MIN_DEX_HEADER_SIZE: usize = 0x70;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DexKind {
+    Standard,
+    Compact,
+}
+
+/// a dex file found inside a container, borrowing the container's bytes
+#[derive(Debug, Clone, Copy)]
+pub struct EmbeddedDex<'a> {
+    pub kind: DexKind,
+    /// the dex file itself, from its magic up to its `file_size`
+    pub data: &'a [u8],
+    /// compact dex files in a vdex share one data section, code item offsets are relative to it
+    pub shared_data: &'a [u8],
+}
+
+impl<'a> EmbeddedDex<'a> {
+    /// check the magic, endian tag and size of a dex/cdex header at the start of `data`
+    /// and return the dex file it describes
+    pub fn parse(data: &'a [u8]) -> Option<Self> {
+        if data.len() < MIN_DEX_HEADER_SIZE {
+            return None;
+        }
+
+        let kind = match &data[..4] {
+            magic if magic == DEX_MAGIC => DexKind::Standard,
+            magic if magic == CDEX_MAGIC => DexKind::Compact,
+            _ => return None,
+        };
+
+        let version = &data[4..8];
+        if !version[..3].iter().all(u8::is_ascii_digit) || version[3] != 0 {
+            return None;
+        }
+
+        if read_u32(data, ENDIAN_TAG_OFFSET).ok()? != ENDIAN_CONSTANT {
+            return None;
+        }
+
+        let file_size = read_u32(data, FILE_SIZE_OFFSET).ok()? as usize;
+        if file_size < MIN_DEX_HEADER_SIZE || file_size > data.len() {
+            return None;
+        }
+
+        Some(Self {
+            kind,
+            data: &data[..file_size],
+            shared_data: &[],
+        })
+    }
+}
+
+/// find every dex file in a dex, vdex or oat (elf) file, a plain dex file yields itself
+pub fn extract(data: &[u8]) -> Result<Vec<EmbeddedDex<'_>>> {
+    match data.get(..4) {
+        Some(magic) if magic == VDEX_MAGIC => vdex::extract(data),
+        Some(magic) if magic == ELF_MAGIC => oat::extract(data),
+        Some(magic) if magic == DEX_MAGIC || magic == CDEX_MAGIC => {
+            Ok(EmbeddedDex::parse(data).into_iter().collect())
+        }
+        _ => Err(Error::InvalidContainer),
+    }
+}
+
+/// the standard dex files of an apk, `classes.dex`, `classes2.dex`, ..., or of any container
+/// `extract` accepts
+pub fn dex_files(data: &[u8]) -> Result<Vec<Vec<u8>>> {
+    Ok(dex_slices(data)?.into_iter().map(Cow::into_owned).collect())
+}
+
+/// the same files as `dex_files`, borrowing from `data` instead of copying where possible:
+/// dex files in a vdex or oat and apk entries that are stored without compression
+pub fn dex_slices(data: &[u8]) -> Result<Vec<Cow<'_, [u8]>>> {
+    if data.starts_with(b"PK") {
+        return apk_dex_files(data);
+    }
+    Ok(extract(data)?
+        .into_iter()
+        .filter(|dex| dex.kind == DexKind::Standard)
+        .map(|dex| Cow::Borrowed(dex.data))
+        .collect())
+}
+
+#[cfg(feature = "zip")]
+fn apk_dex_files(data: &[u8]) -> Result<Vec<Cow<'_, [u8]>>> {
+    let mut archive = ZipArchive::new(Cursor::new(data)).map_err(|_| Error::InvalidArchive)?;
+    let mut files = vec![];
+    for number in 1.. {
+        let name = match number {
+            1 => "classes.dex".to_string(),
+            _ => format!("classes{}.dex", number),
+        };
+        let Ok(mut dex_file) = archive.by_name(&name) else {
+            break;
+        };
+
+        // stored entries are the dex file as is
+        if dex_file.compression() == CompressionMethod::Stored && !dex_file.encrypted() {
+            let start = dex_file.data_start() as usize;
+            let end = start + dex_file.size() as usize;
+            files.push(Cow::Borrowed(
+                data.get(start..end).ok_or(Error::InvalidArchive)?,
+            ));
+            continue;
+        }
+
+        let mut file_data = vec![];
+        dex_file
+            .read_to_end(&mut file_data)
+            .map_err(|_| Error::InvalidArchive)?;
+        files.push(Cow::Owned(file_data));
+    }
+    Ok(files)
+}
+
+/// reading apks needs the `zip` feature
+#[cfg(not(feature = "zip"))]
+fn apk_dex_files(_: &[u8]) -> Result<Vec<Cow<'_, [u8]>>> {
+    Err(Error::InvalidArchive)
+}
+
+pub(crate) fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    let bytes = range(data, offset, 2).ok_or(Error::InvalidContainer)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+pub(crate) fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    let bytes = range(data, offset, 4).ok_or(Error::InvalidContainer)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+pub(crate) fn read_u64(data: &[u8], offset: usize) -> Result<u64> {
+    Ok(read_u32(data, offset)? as u64 | (read_u32(data, offset + 4)? as u64) << 32)
+}
+
+/// `size` bytes at `offset`, `None` when they are out of bounds or the end overflows
+pub(crate) fn range(data: &[u8], offset: usize, size: usize) -> Option<&[u8]> {
+    data.get(offset..offset.checked_add(size)?)
+}
+
+pub(crate) fn align4(value: usize) -> usize {
+    (value + 3) & !3
+}
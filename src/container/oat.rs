@@ -0,0 +1,116 @@
+use super::{align4, range, read_u16, read_u32, read_u64, EmbeddedDex};
+use crate::{errors::Error, Result};
+
+const OAT_MAGIC: &[u8; 4] = b"oat\n";
+const RODATA_SECTION: &[u8] = b".rodata";
+
+const ELF_CLASS_32: u8 = 1;
+const ELF_CLASS_64: u8 = 2;
+const ELF_DATA_LITTLE_ENDIAN: u8 = 1;
+
+struct SectionHeader {
+    name: usize,
+    offset: u64,
+    size: u64,
+}
+
+impl SectionHeader {
+    /// the bytes of the section, `None` when it does not fit in `data`
+    fn data<'a>(&self, data: &'a [u8]) -> Option<&'a [u8]> {
+        range(
+            data,
+            usize::try_from(self.offset).ok()?,
+            usize::try_from(self.size).ok()?,
+        )
+    }
+}
+
+/// extract the dex files embedded in the `.rodata` section of an oat (elf) file.
+/// the oat dex file table layout changes with nearly every android release, so instead of
+/// parsing it the section is scanned for valid dex and compact dex headers.
+pub fn extract(data: &[u8]) -> Result<Vec<EmbeddedDex<'_>>> {
+    let rodata = find_section(data, RODATA_SECTION)?;
+    if !rodata.starts_with(OAT_MAGIC) {
+        return Err(Error::InvalidContainer);
+    }
+
+    let mut dex_files = vec![];
+    let mut position = 0;
+    while position < rodata.len() {
+        match EmbeddedDex::parse(&rodata[position..]) {
+            Some(dex) => {
+                position += align4(dex.data.len());
+                dex_files.push(dex);
+            }
+            // dex files are always 4 byte aligned inside the oat data
+            None => position += 4,
+        }
+    }
+
+    Ok(dex_files)
+}
+
+fn find_section<'a>(data: &'a [u8], name: &[u8]) -> Result<&'a [u8]> {
+    let headers = section_headers(data)?;
+    let string_table_index = match data.get(4) {
+        Some(&ELF_CLASS_64) => read_u16(data, 0x3e)?,
+        _ => read_u16(data, 0x32)?,
+    } as usize;
+
+    let string_table = headers
+        .get(string_table_index)
+        .and_then(|header| header.data(data))
+        .ok_or(Error::InvalidContainer)?;
+
+    headers
+        .iter()
+        .find(|header| {
+            string_table.get(header.name..).is_some_and(|section_name| {
+                section_name.starts_with(name) && section_name.get(name.len()) == Some(&0)
+            })
+        })
+        .and_then(|header| header.data(data))
+        .ok_or(Error::InvalidContainer)
+}
+
+fn section_headers(data: &[u8]) -> Result<Vec<SectionHeader>> {
+    if data.get(5) != Some(&ELF_DATA_LITTLE_ENDIAN) {
+        return Err(Error::InvalidContainer);
+    }
+
+    let (table_offset, entry_size, entries) = match data.get(4) {
+        Some(&ELF_CLASS_64) => (
+            usize::try_from(read_u64(data, 0x28)?).map_err(|_| Error::InvalidContainer)?,
+            read_u16(data, 0x3a)? as usize,
+            read_u16(data, 0x3c)? as usize,
+        ),
+        Some(&ELF_CLASS_32) => (
+            read_u32(data, 0x20)? as usize,
+            read_u16(data, 0x2e)? as usize,
+            read_u16(data, 0x30)? as usize,
+        ),
+        _ => return Err(Error::InvalidContainer),
+    };
+
+    (0..entries)
+        .map(|index| {
+            // the header fields come from the file, a crafted table may wrap around
+            let header = index
+                .checked_mul(entry_size)
+                .and_then(|position| position.checked_add(table_offset))
+                .ok_or(Error::InvalidContainer)?;
+            let field = |offset: usize| header.checked_add(offset).ok_or(Error::InvalidContainer);
+            let name = read_u32(data, header)? as usize;
+
+            let (offset, size) = match data[4] {
+                ELF_CLASS_64 => (read_u64(data, field(24)?)?, read_u64(data, field(32)?)?),
+                _ => (
+                    read_u32(data, field(16)?)? as u64,
+                    read_u32(data, field(20)?)? as u64,
+                ),
+            };
+
+            Ok(SectionHeader { name, offset, size })
+        })
+        .collect()
+}
@@ -0,0 +1,439 @@
+use super::{
+    dex_file::{CodeItem, DexClass, DexField, DexFile, DexMethod},
+    range, read_u16, read_u32, EmbeddedDex,
+};
+use crate::{
+    analysis::cfg::{CatchHandler, TryBlock},
+    dalvik::version::{DexVersion, DEX_MAGIC},
+    errors::Error,
+    integrity::{TYPE_CALL_SITE_ID_ITEM, TYPE_METHOD_HANDLE_ITEM},
+    leb128::{read_sleb128, read_uleb128},
+    resolver::{FieldRef, MethodHandle, MethodRef, Prototype, Resolver},
+    Result,
+};
+use std::cmp::Ordering;
+
+const HEADER_SIZE: usize = 0x70;
+
+const STRING_IDS_OFFSET: usize = 0x38;
+const TYPE_IDS_OFFSET: usize = 0x40;
+const PROTO_IDS_OFFSET: usize = 0x48;
+const FIELD_IDS_OFFSET: usize = 0x50;
+const METHOD_IDS_OFFSET: usize = 0x58;
+const CLASS_DEFS_OFFSET: usize = 0x60;
+const MAP_OFF_OFFSET: usize = 0x34;
+
+const PROTO_ID_SIZE: usize = 12;
+const FIELD_ID_SIZE: usize = 8;
+const METHOD_ID_SIZE: usize = 8;
+const CLASS_DEF_SIZE: usize = 32;
+const CALL_SITE_ID_SIZE: usize = 4;
+const METHOD_HANDLE_SIZE: usize = 8;
+const MAP_ITEM_SIZE: usize = 12;
+const CLASS_DATA_OFFSET: usize = 24;
+const CODE_ITEM_HEADER_SIZE: usize = 16;
+const TRY_ITEM_SIZE: usize = 8;
+const NO_INDEX: u32 = 0xffff_ffff;
+
+// method handle types up to instance-get access a field, the others invoke a method
+const METHOD_HANDLE_TYPE_INSTANCE_GET: u16 = 0x03;
+const VALUE_METHOD_HANDLE: u8 = 0x16;
+
+/// a reader over a standard dex file that only needs its bytes. compact dex files share its
+/// id tables, class definitions and class data, `CompactDexFile` reads them through this.
+pub struct StandardDexFile<'a> {
+    data: &'a [u8],
+    /// what string data, type list, class data and code item offsets are relative to, the
+    /// file itself for a standard dex
+    data_section: &'a [u8],
+}
+
+impl<'a> StandardDexFile<'a> {
+    pub fn new(dex: &EmbeddedDex<'a>) -> Result<Self> {
+        let data = dex.data;
+        if !data.starts_with(DEX_MAGIC) || data.len() < HEADER_SIZE {
+            return Err(Error::InvalidContainer);
+        }
+        Ok(Self::with_data_section(data, data))
+    }
+
+    /// the ids of `data` with their data items in `data_section`, the header isn't checked
+    pub(super) fn with_data_section(data: &'a [u8], data_section: &'a [u8]) -> Self {
+        Self { data, data_section }
+    }
+
+    pub fn string(&self, string_idx: u32) -> Result<String> {
+        let string_id = self.id_item(STRING_IDS_OFFSET, 4, string_idx)?;
+        let mut position = read_u32(self.data, string_id)? as usize;
+
+        // skip the utf16 length, the string itself is null terminated mutf-8
+        read_uleb128(self.data_section, &mut position)?;
+        let string = self
+            .data_section
+            .get(position..)
+            .and_then(|rest| rest.split(|&byte| byte == 0).next())
+            .ok_or(Error::InvalidContainer)?;
+
+        Ok(String::from_utf8_lossy(string).into_owned())
+    }
+
+    pub fn type_descriptor(&self, type_idx: u32) -> Result<String> {
+        let type_id = self.id_item(TYPE_IDS_OFFSET, 4, type_idx)?;
+        self.string(read_u32(self.data, type_id)?)
+    }
+
+    pub fn prototype(&self, proto_idx: u32) -> Result<Prototype> {
+        let proto_id = self.id_item(PROTO_IDS_OFFSET, PROTO_ID_SIZE, proto_idx)?;
+        let return_type = self.type_descriptor(read_u32(self.data, proto_id + 4)?)?;
+        let parameters = self.type_list(read_u32(self.data, proto_id + 8)?)?;
+
+        Ok(Prototype {
+            return_type,
+            parameters,
+        })
+    }
+
+    /// the descriptors of the type_list at `offset`, empty for 0
+    fn type_list(&self, offset: u32) -> Result<Vec<String>> {
+        let offset = offset as usize;
+        if offset == 0 {
+            return Ok(vec![]);
+        }
+        let size = read_u32(self.data_section, offset)? as usize;
+        (0..size)
+            .map(|index| {
+                let position = index
+                    .checked_mul(2)
+                    .and_then(|position| position.checked_add(offset)?.checked_add(4))
+                    .ok_or(Error::InvalidContainer)?;
+                self.type_descriptor(read_u16(self.data_section, position)? as u32)
+            })
+            .collect()
+    }
+
+    pub fn field_ref(&self, field_idx: u32) -> Result<FieldRef> {
+        let field_id = self.id_item(FIELD_IDS_OFFSET, FIELD_ID_SIZE, field_idx)?;
+        Ok(FieldRef {
+            class: self.type_descriptor(read_u16(self.data, field_id)? as u32)?,
+            field_type: self.type_descriptor(read_u16(self.data, field_id + 2)? as u32)?,
+            name: self.string(read_u32(self.data, field_id + 4)?)?,
+        })
+    }
+
+    pub fn method_ref(&self, method_idx: u32) -> Result<MethodRef> {
+        let method_id = self.id_item(METHOD_IDS_OFFSET, METHOD_ID_SIZE, method_idx)?;
+        Ok(MethodRef {
+            class: self.type_descriptor(read_u16(self.data, method_id)? as u32)?,
+            prototype: self.prototype(read_u16(self.data, method_id + 2)? as u32)?,
+            name: self.string(read_u32(self.data, method_id + 4)?)?,
+        })
+    }
+
+    pub fn method_handle(&self, method_handle_idx: u32) -> Result<MethodHandle> {
+        let (size, offset) = self.map_section(TYPE_METHOD_HANDLE_ITEM)?;
+        let method_handle = item(
+            self.data,
+            size,
+            offset,
+            METHOD_HANDLE_SIZE,
+            method_handle_idx,
+        )?;
+        let id = read_u16(self.data, method_handle + 4)? as u32;
+
+        Ok(match read_u16(self.data, method_handle)? {
+            handle_type if handle_type <= METHOD_HANDLE_TYPE_INSTANCE_GET => {
+                MethodHandle::Field(self.field_ref(id)?)
+            }
+            _ => MethodHandle::Method(self.method_ref(id)?),
+        })
+    }
+
+    /// the method handle the encoded array of a call site starts with, its bootstrap method
+    pub fn call_site_bootstrap(&self, call_site_idx: u32) -> Result<MethodHandle> {
+        let (size, offset) = self.map_section(TYPE_CALL_SITE_ID_ITEM)?;
+        let call_site_id = item(self.data, size, offset, CALL_SITE_ID_SIZE, call_site_idx)?;
+        let mut position = read_u32(self.data, call_site_id)? as usize;
+
+        if read_uleb128(self.data_section, &mut position)? == 0 {
+            return Err(Error::InvalidContainer);
+        }
+        // the value's type is in the low 5 bits, its byte count minus one in the high 3
+        let value = *self
+            .data_section
+            .get(position)
+            .ok_or(Error::InvalidContainer)?;
+        let length = (value >> 5) as usize + 1;
+        if value & 0x1f != VALUE_METHOD_HANDLE || length > 4 {
+            return Err(Error::InvalidContainer);
+        }
+        let bytes =
+            range(self.data_section, position + 1, length).ok_or(Error::InvalidContainer)?;
+        let method_handle_idx = bytes
+            .iter()
+            .rev()
+            .fold(0, |index, &byte| index << 8 | byte as u32);
+
+        self.method_handle(method_handle_idx)
+    }
+
+    /// binary search of the type ids, they are sorted by string id and the string ids by
+    /// their utf-16 code units
+    pub fn type_index(&self, descriptor: &str) -> Result<Option<u32>> {
+        let (mut low, mut high) = (0, read_u32(self.data, TYPE_IDS_OFFSET)?);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            let candidate = self.type_descriptor(middle)?;
+            match candidate.encode_utf16().cmp(descriptor.encode_utf16()) {
+                Ordering::Less => low = middle + 1,
+                Ordering::Greater => high = middle,
+                Ordering::Equal => return Ok(Some(middle)),
+            }
+        }
+        Ok(None)
+    }
+
+    /// the try items following instructions that end at `insns_end` in the data section,
+    /// each with its encoded_catch_handler
+    pub(super) fn tries(&self, insns_end: usize, tries_size: usize) -> Result<Vec<TryBlock>> {
+        // try items are 4 byte aligned, after a padding code unit when insns_size is odd
+        let tries_begin = insns_end
+            .checked_next_multiple_of(4)
+            .ok_or(Error::InvalidContainer)?;
+        let handlers_begin = tries_size
+            .checked_mul(TRY_ITEM_SIZE)
+            .and_then(|tries| tries_begin.checked_add(tries))
+            .ok_or(Error::InvalidContainer)?;
+        (0..tries_size)
+            .map(|index| {
+                let try_item = tries_begin + index * TRY_ITEM_SIZE;
+                let handler_off = read_u16(self.data_section, try_item + 6)? as usize;
+                let handlers = handlers_begin
+                    .checked_add(handler_off)
+                    .ok_or(Error::InvalidContainer)?;
+                Ok(TryBlock {
+                    start_addr: read_u32(self.data_section, try_item)?,
+                    insn_count: read_u16(self.data_section, try_item + 4)?,
+                    handlers: self.catch_handlers(handlers)?,
+                })
+            })
+            .collect()
+    }
+
+    /// an encoded_catch_handler, the catch-all comes last
+    fn catch_handlers(&self, mut position: usize) -> Result<Vec<CatchHandler>> {
+        let data = self.data_section;
+        let size = read_sleb128(data, &mut position)?;
+
+        let mut handlers = vec![];
+        for _ in 0..size.unsigned_abs() {
+            let type_idx = read_uleb128(data, &mut position)?;
+            let addr = read_uleb128(data, &mut position)?;
+            handlers.push(CatchHandler {
+                type_idx: Some(type_idx),
+                addr,
+            });
+        }
+        // a size of zero or less is followed by a catch-all address
+        if size <= 0 {
+            let addr = read_uleb128(data, &mut position)?;
+            handlers.push(CatchHandler {
+                type_idx: None,
+                addr,
+            });
+        }
+        Ok(handlers)
+    }
+
+    fn class_data(&self, class_data_off: u32) -> Result<(Vec<DexField>, Vec<DexMethod>)> {
+        if class_data_off == 0 {
+            return Ok((vec![], vec![]));
+        }
+
+        let data = self.data_section;
+        let mut position = class_data_off as usize;
+        let static_fields_size = read_uleb128(data, &mut position)?;
+        let instance_fields_size = read_uleb128(data, &mut position)?;
+        let direct_methods_size = read_uleb128(data, &mut position)?;
+        let virtual_methods_size = read_uleb128(data, &mut position)?;
+
+        let mut fields = vec![];
+        for fields_size in [static_fields_size, instance_fields_size] {
+            // field indices are delta encoded, restarting for the instance fields list
+            let mut field_idx = 0u32;
+            for _ in 0..fields_size {
+                field_idx = field_idx
+                    .checked_add(read_uleb128(data, &mut position)?)
+                    .ok_or(Error::InvalidContainer)?;
+                let access_flags = read_uleb128(data, &mut position)?;
+                let field = self.field_ref(field_idx)?;
+
+                fields.push(DexField {
+                    field_idx,
+                    name: field.name,
+                    field_type: field.field_type,
+                    access_flags,
+                });
+            }
+        }
+
+        let mut methods = vec![];
+        for methods_size in [direct_methods_size, virtual_methods_size] {
+            // method indices are delta encoded, restarting for the virtual methods list
+            let mut method_idx = 0u32;
+            for _ in 0..methods_size {
+                method_idx = method_idx
+                    .checked_add(read_uleb128(data, &mut position)?)
+                    .ok_or(Error::InvalidContainer)?;
+                let access_flags = read_uleb128(data, &mut position)?;
+                let code_off = read_uleb128(data, &mut position)?;
+                let method = self.method_ref(method_idx)?;
+
+                methods.push(DexMethod {
+                    method_idx,
+                    name: method.name,
+                    prototype: method.prototype,
+                    access_flags,
+                    code_off,
+                });
+            }
+        }
+
+        Ok((fields, methods))
+    }
+
+    /// offset of the `index`th item of the id table whose (size, offset) pair is at `table`,
+    /// the whole item is in bounds
+    fn id_item(&self, table: usize, item_size: usize, index: u32) -> Result<usize> {
+        let size = read_u32(self.data, table)?;
+        let offset = read_u32(self.data, table + 4)? as usize;
+        item(self.data, size, offset, item_size, index)
+    }
+
+    /// the (size, offset) of the section of `item_type` in the map list, for the sections the
+    /// header doesn't point to. empty when the file has none.
+    fn map_section(&self, item_type: u16) -> Result<(u32, usize)> {
+        let map_off = read_u32(self.data, MAP_OFF_OFFSET)? as usize;
+        let size = read_u32(self.data_section, map_off)?;
+        let items = map_off.checked_add(4).ok_or(Error::InvalidContainer)?;
+        for index in 0..size {
+            let map_item = item(self.data_section, size, items, MAP_ITEM_SIZE, index)?;
+            if read_u16(self.data_section, map_item)? == item_type {
+                let size = read_u32(self.data_section, map_item + 4)?;
+                let offset = read_u32(self.data_section, map_item + 8)? as usize;
+                return Ok((size, offset));
+            }
+        }
+        Ok((0, 0))
+    }
+}
+
+/// offset of the `index`th of the `size` items at `offset` in `data`, the whole item is in
+/// bounds
+fn item(data: &[u8], size: u32, offset: usize, item_size: usize, index: u32) -> Result<usize> {
+    if index >= size {
+        return Err(Error::InvalidContainer);
+    }
+    let position = (index as usize)
+        .checked_mul(item_size)
+        .and_then(|position| position.checked_add(offset))
+        .ok_or(Error::InvalidContainer)?;
+    range(data, position, item_size).ok_or(Error::InvalidContainer)?;
+    Ok(position)
+}
+
+impl DexFile for StandardDexFile<'_> {
+    fn version(&self) -> Option<DexVersion> {
+        DexVersion::from_magic(self.data)
+    }
+
+    fn class_count(&self) -> Result<u32> {
+        read_u32(self.data, CLASS_DEFS_OFFSET)
+    }
+
+    fn class(&self, index: u32) -> Result<DexClass> {
+        let class_def = self.id_item(CLASS_DEFS_OFFSET, CLASS_DEF_SIZE, index)?;
+        let class_idx = read_u32(self.data, class_def)?;
+        let super_class = match read_u32(self.data, class_def + 8)? {
+            NO_INDEX => None,
+            super_idx => Some(self.type_descriptor(super_idx)?),
+        };
+        let interfaces = self.type_list(read_u32(self.data, class_def + 12)?)?;
+        let class_data_off = read_u32(self.data, class_def + CLASS_DATA_OFFSET)?;
+        let (fields, methods) = self.class_data(class_data_off)?;
+
+        Ok(DexClass {
+            class_idx,
+            descriptor: self.type_descriptor(class_idx)?,
+            access_flags: read_u32(self.data, class_def + 4)?,
+            super_class,
+            interfaces,
+            fields,
+            methods,
+        })
+    }
+
+    fn find_class(&self, descriptor: &str) -> Result<Option<DexClass>> {
+        let Some(type_idx) = self.type_index(descriptor)? else {
+            return Ok(None);
+        };
+        for index in 0..self.class_count()? {
+            let class_def = self.id_item(CLASS_DEFS_OFFSET, CLASS_DEF_SIZE, index)?;
+            if read_u32(self.data, class_def)? == type_idx {
+                return self.class(index).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    fn code_item(&self, code_off: u32) -> Result<CodeItem<'_>> {
+        let data = self.data_section;
+        let header =
+            range(data, code_off as usize, CODE_ITEM_HEADER_SIZE).ok_or(Error::InvalidContainer)?;
+        let tries_size = read_u16(header, 6)? as usize;
+        let insns_size = read_u32(header, 12)? as usize;
+
+        let insns_begin = code_off as usize + CODE_ITEM_HEADER_SIZE;
+        let insns = insns_size
+            .checked_mul(2)
+            .and_then(|size| range(data, insns_begin, size))
+            .ok_or(Error::InvalidContainer)?;
+
+        Ok(CodeItem {
+            registers_size: read_u16(header, 0)?,
+            ins_size: read_u16(header, 2)?,
+            outs_size: read_u16(header, 4)?,
+            insns,
+            tries: self.tries(insns_begin + insns.len(), tries_size)?,
+        })
+    }
+}
+
+impl Resolver for StandardDexFile<'_> {
+    fn string(&self, string_idx: u32) -> Option<String> {
+        StandardDexFile::string(self, string_idx).ok()
+    }
+
+    fn type_descriptor(&self, type_idx: u32) -> Option<String> {
+        StandardDexFile::type_descriptor(self, type_idx).ok()
+    }
+
+    fn field(&self, field_idx: u32) -> Option<FieldRef> {
+        self.field_ref(field_idx).ok()
+    }
+
+    fn method(&self, method_idx: u32) -> Option<MethodRef> {
+        self.method_ref(method_idx).ok()
+    }
+
+    fn prototype(&self, proto_idx: u32) -> Option<Prototype> {
+        StandardDexFile::prototype(self, proto_idx).ok()
+    }
+
+    fn method_handle(&self, method_handle_idx: u32) -> Option<MethodHandle> {
+        StandardDexFile::method_handle(self, method_handle_idx).ok()
+    }
+
+    fn call_site_bootstrap(&self, call_site_idx: u32) -> Option<MethodHandle> {
+        StandardDexFile::call_site_bootstrap(self, call_site_idx).ok()
+    }
+}
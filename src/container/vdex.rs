@@ -0,0 +1,126 @@
+use super::{align4, range, read_u32, EmbeddedDex};
+use crate::{errors::Error, Result};
+
+const MIN_VERSION: u32 = 19;
+/// 021 added the boot classpath checksums and class loader context sizes to the header
+const EXTENDED_HEADER_VERSION: u32 = 21;
+/// 027 replaced the fixed header with a table of sections
+const SECTIONED_VERSION: u32 = 27;
+
+const HEADER_SIZE: usize = 20;
+const EXTENDED_HEADER_SIZE: usize = 28;
+const DEX_SECTION_HEADER_SIZE: usize = 12;
+const EMPTY_DEX_SECTION_VERSION: &[u8; 4] = b"000\0";
+
+const SECTION_HEADER_SIZE: usize = 12;
+const CHECKSUM_SECTION: u32 = 0;
+const DEX_FILE_SECTION: u32 = 1;
+
+/// the `vdex\nXXX\0` version number of a vdex file
+pub fn version(data: &[u8]) -> Result<u32> {
+    let version = data.get(4..8).ok_or(Error::InvalidContainer)?;
+    if version[3] != 0 || !version[..3].iter().all(u8::is_ascii_digit) {
+        return Err(Error::InvalidContainer);
+    }
+
+    Ok(version[..3]
+        .iter()
+        .fold(0, |acc, digit| acc * 10 + (digit - b'0') as u32))
+}
+
+/// extract the dex files of a vdex file (versions 019 to 027).
+/// vdex files generated next to an apk that still holds its dex files have an empty dex section.
+pub fn extract(data: &[u8]) -> Result<Vec<EmbeddedDex<'_>>> {
+    match version(data)? {
+        version @ MIN_VERSION..=26 => extract_legacy(data, version),
+        SECTIONED_VERSION => extract_sectioned(data),
+        _ => Err(Error::UnsupportedContainerVersion),
+    }
+}
+
+fn extract_legacy(data: &[u8], version: u32) -> Result<Vec<EmbeddedDex<'_>>> {
+    if data.get(8..12) == Some(EMPTY_DEX_SECTION_VERSION) {
+        return Ok(vec![]);
+    }
+
+    let number_of_dex_files = read_u32(data, 12)? as usize;
+    let header_size = match version {
+        EXTENDED_HEADER_VERSION.. => EXTENDED_HEADER_SIZE,
+        _ => HEADER_SIZE,
+    };
+
+    // the dex section header follows the dex checksums
+    let dex_section_header = number_of_dex_files
+        .checked_mul(4)
+        .and_then(|checksums| checksums.checked_add(header_size))
+        .ok_or(Error::InvalidContainer)?;
+    let dex_size = read_u32(data, dex_section_header)? as usize;
+    let shared_data_size = read_u32(data, dex_section_header + 4)? as usize;
+
+    let dex_begin = dex_section_header + DEX_SECTION_HEADER_SIZE;
+    let dex_end = dex_begin
+        .checked_add(dex_size)
+        .ok_or(Error::InvalidContainer)?;
+    let shared_data = range(data, dex_end, shared_data_size).ok_or(Error::InvalidContainer)?;
+
+    // every dex file is 4 byte aligned and prefixed by the offset of its quickening table
+    let mut position = dex_begin;
+    let mut dex_files = vec![];
+    for _ in 0..number_of_dex_files {
+        position = align4(position) + 4;
+        let mut dex = data
+            .get(position..dex_end)
+            .and_then(EmbeddedDex::parse)
+            .ok_or(Error::InvalidContainer)?;
+
+        dex.shared_data = shared_data;
+        position += dex.data.len();
+        dex_files.push(dex);
+    }
+
+    Ok(dex_files)
+}
+
+fn extract_sectioned(data: &[u8]) -> Result<Vec<EmbeddedDex<'_>>> {
+    let number_of_sections = read_u32(data, 8)? as usize;
+
+    let mut number_of_dex_files = 0;
+    let mut dex_section = None;
+    for index in 0..number_of_sections {
+        let header = index
+            .checked_mul(SECTION_HEADER_SIZE)
+            .and_then(|position| position.checked_add(12))
+            .ok_or(Error::InvalidContainer)?;
+        let offset = read_u32(data, header + 4)? as usize;
+        let size = read_u32(data, header + 8)? as usize;
+
+        match read_u32(data, header)? {
+            CHECKSUM_SECTION => number_of_dex_files = size / 4,
+            DEX_FILE_SECTION => dex_section = Some((offset, size)),
+            _ => {}
+        }
+    }
+
+    let (dex_begin, dex_size) = match dex_section {
+        Some((_, 0)) | None => return Ok(vec![]),
+        Some(section) => section,
+    };
+    let dex_end = dex_begin
+        .checked_add(dex_size)
+        .ok_or(Error::InvalidContainer)?;
+
+    let mut position = dex_begin;
+    let mut dex_files = vec![];
+    for _ in 0..number_of_dex_files {
+        position = align4(position);
+        let dex = data
+            .get(position..dex_end)
+            .and_then(EmbeddedDex::parse)
+            .ok_or(Error::InvalidContainer)?;
+
+        position += dex.data.len();
+        dex_files.push(dex);
+    }
+
+    Ok(dex_files)
+}
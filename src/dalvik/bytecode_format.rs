@@ -1,27 +1,55 @@
+use super::{opcodes::ArgumentRegisters, version::DexVersion};
 use crate::{errors::Error, Result};
-use std::{
-    io::{Cursor, Read},
-    mem,
-};
+use alloc::vec::Vec;
+use core::mem;
 
 const LOW_NIBBLE: u8 = 0x0f;
 const HIGH_NIBBLE: u8 = 0xf0;
 
 pub struct DexInstructionFormatReader<'a> {
-    cursor: Cursor<&'a [u8]>,
+    stream: &'a [u8],
+    position: usize,
+    version: Option<DexVersion>,
+    quickened: bool,
 }
 
 impl<'a> DexInstructionFormatReader<'a> {
-    pub fn new(stream: &'a [u8]) -> Self {
+    /// when a version is given, opcodes that are illegal for it are rejected while decoding
+    pub fn new(stream: &'a [u8], version: Option<DexVersion>) -> Self {
         Self {
-            cursor: Cursor::new(&stream),
+            stream,
+            position: 0,
+            version,
+            quickened: false,
         }
     }
 
+    /// also accept the odex/vdex quickened opcodes, they are rejected as invalid by default
+    pub fn with_quickened(mut self, quickened: bool) -> Self {
+        self.quickened = quickened;
+        self
+    }
+
+    pub fn version(&self) -> Option<DexVersion> {
+        self.version
+    }
+
+    pub fn quickened(&self) -> bool {
+        self.quickened
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        self.position >= self.stream.len()
+    }
+
     pub fn read_byte(&mut self) -> Result<(u8, usize)> {
-        let position = self.cursor.position();
+        let position = self.position;
         let value = self.read_u8()?;
-        Ok((value, position as usize))
+        Ok((value, position))
     }
 
     pub fn r_10x(&mut self) -> Result<u8> {
@@ -32,9 +60,10 @@ impl<'a> DexInstructionFormatReader<'a> {
         self.get_single_byte_regs()
     }
 
+    /// the literal is a signed nibble, sign extended here so callers see -8..=7
     pub fn r_11n(&mut self) -> Result<(u8, i8)> {
         let (reg, value) = self.get_single_byte_regs()?;
-        Ok((reg, value as i8))
+        Ok((reg, ((value << 4) as i8) >> 4))
     }
 
     pub fn r_11x(&mut self) -> Result<u8> {
@@ -94,7 +123,7 @@ impl<'a> DexInstructionFormatReader<'a> {
 
     pub fn r_30t(&mut self) -> Result<i32> {
         self.read_u8()?;
-        Ok(self.read_i32()?)
+        self.read_i32()
     }
 
     pub fn r_32x(&mut self) -> Result<(u16, u16)> {
@@ -114,24 +143,10 @@ impl<'a> DexInstructionFormatReader<'a> {
         Ok((self.read_u8()?, self.read_u32()?))
     }
 
-    pub fn r_35c(&mut self) -> Result<(Vec<u8>, u16)> {
-        let mut registers = vec![];
-
+    pub fn r_35c(&mut self) -> Result<(ArgumentRegisters, u16)> {
         let (g, number_of_registers) = self.get_single_byte_regs()?;
         let value = self.read_u16()?;
-
-        let (c, d) = self.get_single_byte_regs()?;
-        let (e, f) = self.get_single_byte_regs()?;
-
-        registers.push(c);
-        registers.push(d);
-        registers.push(e);
-        registers.push(f);
-        registers.push(g);
-
-        while number_of_registers as usize != registers.len() {
-            registers.pop();
-        }
+        let registers = self.argument_registers(g, number_of_registers)?;
 
         Ok((registers, value))
     }
@@ -140,25 +155,12 @@ impl<'a> DexInstructionFormatReader<'a> {
         Ok((self.read_u8()?, self.read_u16()?, self.read_u16()?))
     }
 
-    pub fn r_45cc(&mut self) -> Result<(Vec<u8>, u16, u16)> {
-        let mut registers = vec![];
-
+    pub fn r_45cc(&mut self) -> Result<(ArgumentRegisters, u16, u16)> {
         let (g, number_of_registers) = self.get_single_byte_regs()?;
         let field1 = self.read_u16()?;
-        let (c, d) = self.get_single_byte_regs()?;
-        let (e, f) = self.get_single_byte_regs()?;
+        let registers = self.argument_registers(g, number_of_registers)?;
         let field2 = self.read_u16()?;
 
-        registers.push(c);
-        registers.push(d);
-        registers.push(e);
-        registers.push(f);
-        registers.push(g);
-
-        while number_of_registers as usize != registers.len() {
-            registers.pop();
-        }
-
         Ok((registers, field1, field2))
     }
 
@@ -175,22 +177,53 @@ impl<'a> DexInstructionFormatReader<'a> {
         Ok((self.read_u8()?, self.read_u64()?))
     }
 
-    fn read_u8(&mut self) -> Result<u8> {
-        let mut out = [0u8; 1];
-        let please = match self.cursor.read_exact(&mut out) {
-            Ok(_) => Ok(u8::from_le_bytes(out)),
-            Err(_) => Err(Error::ReadByteFailed),
-        };
+    /// `packed-switch-payload` after its ident: (first key, relative targets)
+    pub fn r_packed_switch_payload(&mut self) -> Result<(i32, Vec<i32>)> {
+        let size = self.read_u16()?;
+        let first_key = self.read_i32()?;
+        let targets = (0..size).map(|_| self.read_i32()).collect::<Result<_>>()?;
+        Ok((first_key, targets))
+    }
+
+    /// `sparse-switch-payload` after its ident: (sorted keys, relative targets)
+    pub fn r_sparse_switch_payload(&mut self) -> Result<(Vec<i32>, Vec<i32>)> {
+        let size = self.read_u16()?;
+        let keys = (0..size).map(|_| self.read_i32()).collect::<Result<_>>()?;
+        let targets = (0..size).map(|_| self.read_i32()).collect::<Result<_>>()?;
+        Ok((keys, targets))
+    }
+
+    /// `fill-array-data-payload` after its ident: (element width, raw element bytes)
+    pub fn r_fill_array_data_payload(&mut self) -> Result<(u16, Vec<u8>)> {
+        let element_width = self.read_u16()?;
+        let size = self.read_u32()?;
+        let data = (0..size as usize * element_width as usize)
+            .map(|_| self.read_u8())
+            .collect::<Result<Vec<_>>>()?;
 
-        please
+        // the payload is made of whole code units
+        if data.len() % 2 == 1 {
+            self.read_u8()?;
+        }
+        Ok((element_width, data))
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        let value = *self
+            .stream
+            .get(self.position)
+            .ok_or(Error::ReadByteFailed)?;
+        self.position += 1;
+        Ok(value)
     }
 
     fn read_i8(&mut self) -> Result<i8> {
         Ok(self.read_u8()? as i8)
     }
 
+    // dex code is little endian, and wider values are stored low code unit first
     fn read_u16(&mut self) -> Result<u16> {
-        Ok(((self.read_u8()? as u16) << (mem::size_of::<u16>() * 4)) + self.read_u8()? as u16)
+        Ok(self.read_u8()? as u16 | (self.read_u8()? as u16) << (mem::size_of::<u16>() * 4))
     }
 
     fn read_i16(&mut self) -> Result<i16> {
@@ -198,7 +231,7 @@ impl<'a> DexInstructionFormatReader<'a> {
     }
 
     fn read_u32(&mut self) -> Result<u32> {
-        Ok(((self.read_u16()? as u32) << (mem::size_of::<u32>() * 4)) + self.read_u16()? as u32)
+        Ok(self.read_u16()? as u32 | (self.read_u16()? as u32) << (mem::size_of::<u32>() * 4))
     }
 
     fn read_i32(&mut self) -> Result<i32> {
@@ -206,14 +239,27 @@ impl<'a> DexInstructionFormatReader<'a> {
     }
 
     fn read_u64(&mut self) -> Result<u64> {
-        Ok(((self.read_u32()? as u64) << (mem::size_of::<u64>() * 4)) + self.read_u32()? as u64)
+        Ok(self.read_u32()? as u64 | (self.read_u32()? as u64) << (mem::size_of::<u64>() * 4))
+    }
+
+    /// the `C|D|E|F` code unit of 35c/45cc, `G` comes from the first code unit and is the
+    /// fifth register
+    fn argument_registers(&mut self, g: u8, number_of_registers: u8) -> Result<ArgumentRegisters> {
+        let (c, d) = self.get_single_byte_regs()?;
+        let (e, f) = self.get_single_byte_regs()?;
+
+        let registers = [c, d, e, f, g];
+        registers
+            .get(..number_of_registers as usize)
+            .and_then(ArgumentRegisters::new)
+            .ok_or(Error::InvalidOpcode)
     }
 
     fn get_single_byte_regs(&mut self) -> Result<(u8, u8)> {
         let value = self.read_u8()?;
         Ok((
             value & LOW_NIBBLE,
-            (value & HIGH_NIBBLE) >> mem::size_of::<u8>() * 4,
+            (value & HIGH_NIBBLE) >> (mem::size_of::<u8>() * 4),
         ))
     }
 }
@@ -1,12 +1,17 @@
 pub mod bytecode_format;
 pub mod opcodes;
+pub mod quickening;
+pub mod registers;
+pub mod smali;
+pub mod version;
 
 use bytecode_format::DexInstructionFormatReader;
 use opcodes::*;
 
 use crate::{errors, Result};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DalvikInstruction {
     pub inst: DalvikBytecode,
     pub offset: usize,
@@ -15,27 +20,34 @@ pub struct DalvikInstruction {
 impl DalvikInstruction {
     /// return the next instruction and its offset from the beginning of the function
     /// if there aren't any other instructions None is returned.
-    pub fn decode_instruction<'a>(reader: &'a mut DexInstructionFormatReader) -> Result<Self> {
+    /// opcodes newer than the reader's dex version are rejected with `Error::UnsupportedOpcode`.
+    pub fn decode_instruction(reader: &mut DexInstructionFormatReader) -> Result<Self> {
         let (opcode, offset) = reader.read_byte()?;
 
+        if let Some(version) = reader.version() {
+            if !version.supports_opcode(opcode) {
+                return Err(errors::Error::UnsupportedOpcode);
+            }
+        }
+
         let dalvik_bytecode = match opcode {
             NOP_OP => {
                 let pseudo_opcode = reader.r_10x()?;
 
                 match pseudo_opcode {
                     PSEUDO_PACKED_SWITCH_OP => {
-                        let (reg, value) = reader.r_31t()?;
-                        Ok(DalvikBytecode::PackedSwitch(reg, value))
+                        let (first_key, targets) = reader.r_packed_switch_payload()?;
+                        Ok(DalvikBytecode::PackedSwitchPayload(first_key, targets))
                     }
 
                     PSEUDO_SPARSE_SWITCH_OP => {
-                        let (reg, value) = reader.r_31t()?;
-                        Ok(DalvikBytecode::SparseSwitch(reg, value))
+                        let (keys, targets) = reader.r_sparse_switch_payload()?;
+                        Ok(DalvikBytecode::SparseSwitchPayload(keys, targets))
                     }
 
                     PSEUDO_FILL_ARRAY_DATA_OP => {
-                        let (reg, value) = reader.r_31t()?;
-                        Ok(DalvikBytecode::FilledArrayData(reg, value))
+                        let (element_width, data) = reader.r_fill_array_data_payload()?;
+                        Ok(DalvikBytecode::FillArrayDataPayload(element_width, data))
                     }
 
                     NOP_OP => Ok(DalvikBytecode::Nop),
@@ -144,8 +156,8 @@ impl DalvikInstruction {
             }
 
             CHECK_CAST_OP => {
-                let (reg, type_index): (u8, u16) = reader.r_21c()?;
-                Ok(DalvikBytecode::ConstClass(reg, type_index))
+                let (reg, type_index) = reader.r_21c()?;
+                Ok(DalvikBytecode::CheckCast(reg, type_index))
             }
 
             INSTANCE_OF_OP => {
@@ -182,6 +194,11 @@ impl DalvikInstruction {
                 ))
             }
 
+            FILL_ARRAY_DATA_OP => {
+                let (reg, offset) = reader.r_31t()?;
+                Ok(DalvikBytecode::FilledArrayData(reg, offset))
+            }
+
             THROW_OP => {
                 let reg = reader.r_11x()?;
                 Ok(DalvikBytecode::Throw(reg))
@@ -202,6 +219,16 @@ impl DalvikInstruction {
                 Ok(DalvikBytecode::Goto32(reg))
             }
 
+            PACKED_SWITCH_OP => {
+                let (reg, offset) = reader.r_31t()?;
+                Ok(DalvikBytecode::PackedSwitch(reg, offset))
+            }
+
+            SPARSE_SWITCH_OP => {
+                let (reg, offset) = reader.r_31t()?;
+                Ok(DalvikBytecode::SparseSwitch(reg, offset))
+            }
+
             op @ CMPL_FLOAT_OP..=CMP_LONG_OP => {
                 let (dst, src, regs) = reader.r_23x()?;
                 Ok(DalvikBytecode::Cmp(
@@ -359,6 +386,38 @@ impl DalvikInstruction {
                 Ok(DalvikBytecode::ConstMethodType(dst, type_index))
             }
 
+            RETURN_VOID_NO_BARRIER_OP if reader.quickened() => {
+                let _ = reader.r_10x()?;
+                Ok(DalvikBytecode::ReturnVoidNoBarrier)
+            }
+
+            op @ (INSTANCE_GET_QUICK_OP..=INSTANCE_PUT_OBJECT_QUICK_OP
+            | INSTANCE_PUT_BOOLEAN_QUICK_OP..=INSTANCE_GET_SHORT_QUICK_OP)
+                if reader.quickened() =>
+            {
+                let (reg1, reg2, field_offset) = reader.r_22c()?;
+                Ok(DalvikBytecode::InstanceOpQuick(
+                    OpKind::from_opcode(op),
+                    reg1,
+                    reg2,
+                    field_offset,
+                ))
+            }
+
+            INVOKE_VIRTUAL_QUICK_OP if reader.quickened() => {
+                let (regs, vtable_index) = reader.r_35c()?;
+                Ok(DalvikBytecode::InvokeVirtualQuick(regs, vtable_index))
+            }
+
+            INVOKE_VIRTUAL_RANGE_QUICK_OP if reader.quickened() => {
+                let (regs_count, vtable_index, first_argument_reg) = reader.r_3rc()?;
+                Ok(DalvikBytecode::InvokeVirtualRangeQuick(
+                    regs_count,
+                    vtable_index,
+                    first_argument_reg,
+                ))
+            }
+
             _ => Err(errors::Error::InvalidOpcode),
         };
         let inst = dalvik_bytecode?;
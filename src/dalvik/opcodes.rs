@@ -1,3 +1,7 @@
+use crate::errors::Error;
+use alloc::vec::Vec;
+use core::{fmt, ops::Deref};
+
 /// all dalvik opcodes
 pub const NOP_OP: u8 = 0x0;
 pub const MOV_OP: u8 = 0x1;
@@ -37,10 +41,13 @@ pub const NEW_INSTANCE_OP: u8 = 0x22;
 pub const NEW_ARRAY_OP: u8 = 0x23;
 pub const FILLED_NEW_ARRAY_OP: u8 = 0x24;
 pub const FILLED_NEW_ARRAY_RANGE_OP: u8 = 0x25;
+pub const FILL_ARRAY_DATA_OP: u8 = 0x26;
 pub const THROW_OP: u8 = 0x27;
 pub const GOTO_OP: u8 = 0x28;
 pub const GOTO16_OP: u8 = 0x29;
 pub const GOTO32_OP: u8 = 0x2a;
+pub const PACKED_SWITCH_OP: u8 = 0x2b;
+pub const SPARSE_SWITCH_OP: u8 = 0x2c;
 pub const CMPL_FLOAT_OP: u8 = 0x2d;
 pub const CMPG_FLOAT_OP: u8 = 0x2e;
 pub const CMPL_DOUBLE_OP: u8 = 0x2f;
@@ -221,11 +228,101 @@ pub const INVOKE_CUSTOM_RANGE_OP: u8 = 0xfd;
 pub const CONST_METHOD_HANDLE_OP: u8 = 0xfe;
 pub const CONST_METHOD_TYPE_OP: u8 = 0xff;
 
+/// quickened opcodes, only emitted by dex2oat into odex/vdex files
+pub const RETURN_VOID_NO_BARRIER_OP: u8 = 0x73;
+pub const INSTANCE_GET_QUICK_OP: u8 = 0xe3;
+pub const INSTANCE_GET_WIDE_QUICK_OP: u8 = 0xe4;
+pub const INSTANCE_GET_OBJECT_QUICK_OP: u8 = 0xe5;
+pub const INSTANCE_PUT_QUICK_OP: u8 = 0xe6;
+pub const INSTANCE_PUT_WIDE_QUICK_OP: u8 = 0xe7;
+pub const INSTANCE_PUT_OBJECT_QUICK_OP: u8 = 0xe8;
+pub const INVOKE_VIRTUAL_QUICK_OP: u8 = 0xe9;
+pub const INVOKE_VIRTUAL_RANGE_QUICK_OP: u8 = 0xea;
+pub const INSTANCE_PUT_BOOLEAN_QUICK_OP: u8 = 0xeb;
+pub const INSTANCE_PUT_BYTE_QUICK_OP: u8 = 0xec;
+pub const INSTANCE_PUT_CHAR_QUICK_OP: u8 = 0xed;
+pub const INSTANCE_PUT_SHORT_QUICK_OP: u8 = 0xee;
+pub const INSTANCE_GET_BOOLEAN_QUICK_OP: u8 = 0xef;
+pub const INSTANCE_GET_BYTE_QUICK_OP: u8 = 0xf0;
+pub const INSTANCE_GET_CHAR_QUICK_OP: u8 = 0xf1;
+pub const INSTANCE_GET_SHORT_QUICK_OP: u8 = 0xf2;
+
 pub const PSEUDO_PACKED_SWITCH_OP: u8 = 0x1;
 pub const PSEUDO_SPARSE_SWITCH_OP: u8 = 0x2;
 pub const PSEUDO_FILL_ARRAY_DATA_OP: u8 = 0x3;
 
-#[derive(Debug)]
+/// the argument registers of a 35c/45cc instruction (invoke, filled-new-array), held inline so
+/// decoding them does not allocate. derefs to the registers in operand order.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "Vec<u8>", try_from = "Vec<u8>")
+)]
+pub struct ArgumentRegisters {
+    // registers past `len` are always 0 so the derived comparisons only see the used ones
+    registers: [u8; ArgumentRegisters::MAX],
+    len: u8,
+}
+
+impl ArgumentRegisters {
+    /// a 35c instruction names at most five registers
+    pub const MAX: usize = 5;
+
+    /// `None` when there are more than `MAX` registers
+    pub fn new(registers: &[u8]) -> Option<Self> {
+        let mut out = Self::default();
+        out.registers
+            .get_mut(..registers.len())?
+            .copy_from_slice(registers);
+        out.len = registers.len() as u8;
+        Some(out)
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.registers[..self.len as usize]
+    }
+}
+
+impl Deref for ArgumentRegisters {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl<'a> IntoIterator for &'a ArgumentRegisters {
+    type Item = &'a u8;
+    type IntoIter = core::slice::Iter<'a, u8>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.as_slice().iter()
+    }
+}
+
+impl fmt::Debug for ArgumentRegisters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.as_slice()).finish()
+    }
+}
+
+impl From<ArgumentRegisters> for Vec<u8> {
+    fn from(registers: ArgumentRegisters) -> Self {
+        registers.as_slice().to_vec()
+    }
+}
+
+impl TryFrom<Vec<u8>> for ArgumentRegisters {
+    type Error = Error;
+
+    fn try_from(registers: Vec<u8>) -> Result<Self, Error> {
+        Self::new(&registers).ok_or(Error::InvalidOpcode)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DalvikBytecode {
     Nop,
 
@@ -251,13 +348,15 @@ pub enum DalvikBytecode {
     MonitorEnter(u8),
     MonitorExit(u8),
 
+    CheckCast(u8, u16),
+
     InstanceOf(u8, u8, u16),
     ArrayLength(u8, u8),
 
     NewInstance(u8, u16),
 
     NewArray(u8, u8, u16),
-    FilledNewArray(u16, Vec<u8>),
+    FilledNewArray(u16, ArgumentRegisters),
     FilledNewArrayRange(u8, u16, u16),
     FilledArrayData(u8, i32),
 
@@ -270,6 +369,11 @@ pub enum DalvikBytecode {
     PackedSwitch(u8, i32),
     SparseSwitch(u8, i32),
 
+    // data payloads embedded in the instruction stream, referenced by the instructions above
+    PackedSwitchPayload(i32, Vec<i32>),
+    SparseSwitchPayload(Vec<i32>, Vec<i32>),
+    FillArrayDataPayload(u16, Vec<u8>),
+
     Cmp(CmpKind, u8, u8, u8),
     IfTest(IfKind, u8, u8, i16),
     IfTestZ(IfKind, u8, i16),
@@ -278,7 +382,7 @@ pub enum DalvikBytecode {
     InstanceOp(OpKind, u8, u8, u16),
     StaticOp(OpKind, u8, u16),
 
-    Invoke(InvokeKind, Vec<u8>, u16),
+    Invoke(InvokeKind, ArgumentRegisters, u16),
     InvokeRange(InvokeKind, u8, u16, u16),
 
     Unop(UnopKind, u8, u8),
@@ -287,15 +391,132 @@ pub enum DalvikBytecode {
     BinopLit16(ArithmeticKind, u8, u8, i16),
     BinopLit8(ArithmeticKind, u8, u8, i8),
 
-    InvokePolymorphic(Vec<u8>, u16, u16),
+    InvokePolymorphic(ArgumentRegisters, u16, u16),
     InvokePolymorphicRange(u8, u16, u16, u16),
-    InvokeCustom(Vec<u8>, u16),
+    InvokeCustom(ArgumentRegisters, u16),
     InvokeCustomRange(u8, u16, u16),
     ConstMethodHandle(u8, u16),
     ConstMethodType(u8, u16),
+
+    // quickened instructions, the u16 is a field offset / vtable index instead of a dex index
+    ReturnVoidNoBarrier,
+    InstanceOpQuick(OpKind, u8, u8, u16),
+    InvokeVirtualQuick(ArgumentRegisters, u16),
+    InvokeVirtualRangeQuick(u8, u16, u16),
 }
 
-#[derive(Debug)]
+impl DalvikBytecode {
+    /// size of the instruction in 16-bit code units, payloads included
+    pub fn code_units(&self) -> usize {
+        match self {
+            Self::Nop
+            | Self::Move(..)
+            | Self::MoveResult(..)
+            | Self::Return(..)
+            | Self::Const4(..)
+            | Self::MonitorEnter(..)
+            | Self::MonitorExit(..)
+            | Self::ArrayLength(..)
+            | Self::Throw(..)
+            | Self::Goto(..)
+            | Self::Unop(..)
+            | Self::Binop2Addr(..)
+            | Self::ReturnVoidNoBarrier => 1,
+
+            Self::MoveFrom16(..)
+            | Self::Const16(..)
+            | Self::ConstHigh16(..)
+            | Self::ConstWide16(..)
+            | Self::ConstWideHigh16(..)
+            | Self::ConstString(..)
+            | Self::ConstClass(..)
+            | Self::CheckCast(..)
+            | Self::InstanceOf(..)
+            | Self::NewInstance(..)
+            | Self::NewArray(..)
+            | Self::Goto16(..)
+            | Self::Cmp(..)
+            | Self::IfTest(..)
+            | Self::IfTestZ(..)
+            | Self::ArrayOp(..)
+            | Self::InstanceOp(..)
+            | Self::StaticOp(..)
+            | Self::Binop(..)
+            | Self::BinopLit16(..)
+            | Self::BinopLit8(..)
+            | Self::ConstMethodHandle(..)
+            | Self::ConstMethodType(..)
+            | Self::InstanceOpQuick(..) => 2,
+
+            Self::Move16(..)
+            | Self::Const(..)
+            | Self::ConstWide32(..)
+            | Self::ConstStringJumbo(..)
+            | Self::FilledNewArray(..)
+            | Self::FilledNewArrayRange(..)
+            | Self::FilledArrayData(..)
+            | Self::Goto32(..)
+            | Self::PackedSwitch(..)
+            | Self::SparseSwitch(..)
+            | Self::Invoke(..)
+            | Self::InvokeRange(..)
+            | Self::InvokeCustom(..)
+            | Self::InvokeCustomRange(..)
+            | Self::InvokeVirtualQuick(..)
+            | Self::InvokeVirtualRangeQuick(..) => 3,
+
+            Self::InvokePolymorphic(..) | Self::InvokePolymorphicRange(..) => 4,
+
+            Self::ConstWide(..) => 5,
+
+            Self::PackedSwitchPayload(_, targets) => 4 + targets.len() * 2,
+            Self::SparseSwitchPayload(keys, targets) => 2 + (keys.len() + targets.len()) * 2,
+            Self::FillArrayDataPayload(_, data) => 4 + data.len().div_ceil(2),
+        }
+    }
+
+    pub fn is_payload(&self) -> bool {
+        matches!(
+            self,
+            Self::PackedSwitchPayload(..)
+                | Self::SparseSwitchPayload(..)
+                | Self::FillArrayDataPayload(..)
+        )
+    }
+
+    /// whether execution can continue with the next instruction
+    pub fn can_fall_through(&self) -> bool {
+        !matches!(
+            self,
+            Self::Goto(_)
+                | Self::Goto16(_)
+                | Self::Goto32(_)
+                | Self::Return(..)
+                | Self::ReturnVoidNoBarrier
+                | Self::Throw(_)
+        ) && !self.is_payload()
+    }
+
+    /// instructions whose result is picked up by a following `move-result*`
+    pub fn has_result(&self) -> bool {
+        matches!(
+            self,
+            Self::Invoke(..)
+                | Self::InvokeRange(..)
+                | Self::InvokePolymorphic(..)
+                | Self::InvokePolymorphicRange(..)
+                | Self::InvokeCustom(..)
+                | Self::InvokeCustomRange(..)
+                | Self::InvokeVirtualQuick(..)
+                | Self::InvokeVirtualRangeQuick(..)
+                | Self::FilledNewArray(..)
+                | Self::FilledNewArrayRange(..)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MoveKind {
     Move,
     MovWide,
@@ -315,9 +536,14 @@ impl MoveKind {
             _ => unreachable!(),
         }
     }
+
+    pub fn is_wide(&self) -> bool {
+        matches!(self, Self::MovWide)
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ReturnKind {
     Return,
     ReturnWide,
@@ -335,9 +561,14 @@ impl ReturnKind {
             _ => unreachable!(),
         }
     }
+
+    pub fn is_wide(&self) -> bool {
+        matches!(self, Self::ReturnWide)
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CmpKind {
     CmplFloat,
     CmpgFloat,
@@ -357,9 +588,15 @@ impl CmpKind {
             _ => unreachable!(),
         }
     }
+
+    /// whether the compared registers are register pairs, the result is always an int
+    pub fn is_wide(&self) -> bool {
+        matches!(self, Self::CmplDouble | Self::CmpgDouble | Self::CmpLong)
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum IfKind {
     Eq,
     Ne,
@@ -383,7 +620,8 @@ impl IfKind {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum OpKind {
     Get,
     GetWide,
@@ -404,30 +642,80 @@ pub enum OpKind {
 impl OpKind {
     pub fn from_opcode(op: u8) -> Self {
         match op {
-            ARRAY_GET_OP | INSTANCE_GET_OP | STATIC_GET_OP => Self::Get,
-            ARRAY_GET_WIDE_OP | INSTANCE_GET_WIDE_OP | STATIC_GET_WIDE_OP => Self::GetWide,
-            ARRAY_GET_OBJECT_OP | INSTANCE_GET_OBJECT_OP | STATIC_GET_OBJECT_OP => Self::GetObject,
-            ARRAY_GET_BOOLEAN_OP | INSTANCE_GET_BOOLEAN_OP | STATIC_GET_BOOLEAN_OP => {
-                Self::GetBoolean
-            }
-            ARRAY_GET_BYTE_OP | INSTANCE_GET_BYTE_OP | STATIC_GET_BYTE_OP => Self::GetByte,
-            ARRAY_GET_CHAR_OP | INSTANCE_GET_CHAR_OP | STATIC_GET_CHAR_OP => Self::GetChar,
-            ARRAY_GET_SHORT_OP | INSTANCE_GET_SHORT_OP | STATIC_GET_SHORT_OP => Self::GetShort,
-            ARRAY_PUT_OP | INSTANCE_PUT_OP | STATIC_PUT_OP => Self::Put,
-            ARRAY_PUT_WIDE_OP | INSTANCE_PUT_WIDE_OP | STATIC_PUT_WIDE_OP => Self::PutWide,
-            ARRAY_PUT_OBJECT_OP | INSTANCE_PUT_OBJECT_OP | STATIC_PUT_OBJECT_OP => Self::PutObject,
-            ARRAY_PUT_BOOLEAN_OP | INSTANCE_PUT_BOOLEAN_OP | STATIC_PUT_BOOLEAN_OP => {
-                Self::PutBoolean
-            }
-            ARRAY_PUT_BYTE_OP | INSTANCE_PUT_BYTE_OP | STATIC_PUT_BYTE_OP => Self::PutByte,
-            ARRAY_PUT_CHAR_OP | INSTANCE_PUT_CHAR_OP | STATIC_PUT_CHAR_OP => Self::PutChar,
-            ARRAY_PUT_SHORT_OP | INSTANCE_PUT_SHORT_OP | STATIC_PUT_SHORT_OP => Self::PutShort,
+            ARRAY_GET_OP | INSTANCE_GET_OP | STATIC_GET_OP | INSTANCE_GET_QUICK_OP => Self::Get,
+            ARRAY_GET_WIDE_OP
+            | INSTANCE_GET_WIDE_OP
+            | STATIC_GET_WIDE_OP
+            | INSTANCE_GET_WIDE_QUICK_OP => Self::GetWide,
+            ARRAY_GET_OBJECT_OP
+            | INSTANCE_GET_OBJECT_OP
+            | STATIC_GET_OBJECT_OP
+            | INSTANCE_GET_OBJECT_QUICK_OP => Self::GetObject,
+            ARRAY_GET_BOOLEAN_OP
+            | INSTANCE_GET_BOOLEAN_OP
+            | STATIC_GET_BOOLEAN_OP
+            | INSTANCE_GET_BOOLEAN_QUICK_OP => Self::GetBoolean,
+            ARRAY_GET_BYTE_OP
+            | INSTANCE_GET_BYTE_OP
+            | STATIC_GET_BYTE_OP
+            | INSTANCE_GET_BYTE_QUICK_OP => Self::GetByte,
+            ARRAY_GET_CHAR_OP
+            | INSTANCE_GET_CHAR_OP
+            | STATIC_GET_CHAR_OP
+            | INSTANCE_GET_CHAR_QUICK_OP => Self::GetChar,
+            ARRAY_GET_SHORT_OP
+            | INSTANCE_GET_SHORT_OP
+            | STATIC_GET_SHORT_OP
+            | INSTANCE_GET_SHORT_QUICK_OP => Self::GetShort,
+            ARRAY_PUT_OP | INSTANCE_PUT_OP | STATIC_PUT_OP | INSTANCE_PUT_QUICK_OP => Self::Put,
+            ARRAY_PUT_WIDE_OP
+            | INSTANCE_PUT_WIDE_OP
+            | STATIC_PUT_WIDE_OP
+            | INSTANCE_PUT_WIDE_QUICK_OP => Self::PutWide,
+            ARRAY_PUT_OBJECT_OP
+            | INSTANCE_PUT_OBJECT_OP
+            | STATIC_PUT_OBJECT_OP
+            | INSTANCE_PUT_OBJECT_QUICK_OP => Self::PutObject,
+            ARRAY_PUT_BOOLEAN_OP
+            | INSTANCE_PUT_BOOLEAN_OP
+            | STATIC_PUT_BOOLEAN_OP
+            | INSTANCE_PUT_BOOLEAN_QUICK_OP => Self::PutBoolean,
+            ARRAY_PUT_BYTE_OP
+            | INSTANCE_PUT_BYTE_OP
+            | STATIC_PUT_BYTE_OP
+            | INSTANCE_PUT_BYTE_QUICK_OP => Self::PutByte,
+            ARRAY_PUT_CHAR_OP
+            | INSTANCE_PUT_CHAR_OP
+            | STATIC_PUT_CHAR_OP
+            | INSTANCE_PUT_CHAR_QUICK_OP => Self::PutChar,
+            ARRAY_PUT_SHORT_OP
+            | INSTANCE_PUT_SHORT_OP
+            | STATIC_PUT_SHORT_OP
+            | INSTANCE_PUT_SHORT_QUICK_OP => Self::PutShort,
             _ => unreachable!(),
         }
     }
+
+    pub fn is_wide(&self) -> bool {
+        matches!(self, Self::GetWide | Self::PutWide)
+    }
+
+    pub fn is_put(&self) -> bool {
+        matches!(
+            self,
+            Self::Put
+                | Self::PutWide
+                | Self::PutObject
+                | Self::PutBoolean
+                | Self::PutByte
+                | Self::PutChar
+                | Self::PutShort
+        )
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum InvokeKind {
     Virtual,
     Super,
@@ -449,7 +737,8 @@ impl InvokeKind {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum UnopKind {
     NegInt,
     NotInt,
@@ -501,9 +790,42 @@ impl UnopKind {
             _ => unreachable!(),
         }
     }
+
+    /// whether the source operand is a register pair
+    pub fn is_src_wide(&self) -> bool {
+        matches!(
+            self,
+            Self::NegLong
+                | Self::NotLong
+                | Self::NegDouble
+                | Self::LongToInt
+                | Self::LongToFloat
+                | Self::LongToDouble
+                | Self::DoubleToInt
+                | Self::DoubleToLong
+                | Self::DoubleToFloat
+        )
+    }
+
+    /// whether the destination operand is a register pair
+    pub fn is_dst_wide(&self) -> bool {
+        matches!(
+            self,
+            Self::NegLong
+                | Self::NotLong
+                | Self::NegDouble
+                | Self::IntToLong
+                | Self::IntToDouble
+                | Self::LongToDouble
+                | Self::FloatToLong
+                | Self::FloatToDouble
+                | Self::DoubleToLong
+        )
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ArithmeticKind {
     AddInt,
     SubInt,
@@ -579,4 +901,40 @@ impl ArithmeticKind {
             _ => unreachable!(),
         }
     }
+
+    /// whether the operands are register pairs (long and double arithmetic)
+    pub fn is_wide(&self) -> bool {
+        matches!(
+            self,
+            Self::AddLong
+                | Self::SubLong
+                | Self::MulLong
+                | Self::DivLong
+                | Self::RemLong
+                | Self::AndLong
+                | Self::OrLong
+                | Self::XorLong
+                | Self::ShlLong
+                | Self::ShrLong
+                | Self::UshrLong
+                | Self::AddDouble
+                | Self::SubDouble
+                | Self::MulDouble
+                | Self::DivDouble
+                | Self::RemDouble
+        )
+    }
+
+    /// long shifts take their shift amount from a single int register
+    pub fn is_shift(&self) -> bool {
+        matches!(
+            self,
+            Self::ShlInt
+                | Self::ShrInt
+                | Self::UshrInt
+                | Self::ShlLong
+                | Self::ShrLong
+                | Self::UshrLong
+        )
+    }
 }
@@ -0,0 +1,174 @@
+use super::{opcodes::*, DalvikInstruction};
+use crate::{errors::Error, leb128::read_uleb128, Result};
+use alloc::{vec, vec::Vec};
+
+/// one dex index recorded by dex2oat when it quickened an instruction
+#[derive(Debug, Clone, Copy)]
+pub struct QuickeningEntry {
+    /// dex pc (in 16-bit code units) of the quickened instruction, old vdex files only
+    pub dex_pc: Option<u32>,
+    pub index: u16,
+}
+
+/// the quickening info of a single method, as stored in a vdex file
+#[derive(Debug, Default)]
+pub struct QuickeningInfo {
+    entries: Vec<QuickeningEntry>,
+}
+
+impl QuickeningInfo {
+    /// vdex 006-010 store `(dex_pc, index)` uleb128 pairs
+    pub fn from_leb128_pairs(data: &[u8]) -> Result<Self> {
+        let mut entries = vec![];
+        let mut pos = 0;
+
+        while pos < data.len() {
+            let dex_pc = read_uleb128(data, &mut pos)?;
+            let index = u16::try_from(read_uleb128(data, &mut pos)?)
+                .map_err(|_| Error::InvalidQuickeningInfo)?;
+            entries.push(QuickeningEntry {
+                dex_pc: Some(dex_pc),
+                index,
+            });
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// vdex 011 and newer store one little endian u16 index per quickened instruction, in code order
+    pub fn from_u16_table(data: &[u8]) -> Self {
+        let entries = data
+            .chunks_exact(2)
+            .map(|index| QuickeningEntry {
+                dex_pc: None,
+                index: u16::from_le_bytes([index[0], index[1]]),
+            })
+            .collect();
+
+        Self { entries }
+    }
+
+    pub fn entries(&self) -> &[QuickeningEntry] {
+        &self.entries
+    }
+
+    /// old vdex files key entries by dex pc and only record the nops that were check-casts
+    fn has_dex_pcs(&self) -> bool {
+        self.entries
+            .first()
+            .is_some_and(|entry| entry.dex_pc.is_some())
+    }
+}
+
+/// index recorded by dex2oat for a nop that was already a nop
+const NO_INDEX: u16 = 0xffff;
+
+/// the indices of a method, in the order dequickening consumes them
+struct Indices<'a> {
+    info: &'a QuickeningInfo,
+    /// entries consumed so far, for the u16 table
+    position: usize,
+    /// dex pc of the current instruction and entries consumed at it, for dex pc keyed entries
+    dex_pc: u32,
+    nth: usize,
+}
+
+impl Indices<'_> {
+    fn at(&mut self, offset: usize) {
+        self.dex_pc = (offset / 2) as u32;
+        self.nth = 0;
+    }
+
+    fn next(&mut self) -> Option<u16> {
+        let entries = &self.info.entries;
+        let entry = match self.info.has_dex_pcs() {
+            true => entries
+                .iter()
+                .filter(|entry| entry.dex_pc == Some(self.dex_pc))
+                .nth(self.nth),
+            false => entries.get(self.position),
+        };
+        self.position += 1;
+        self.nth += 1;
+        entry.map(|entry| entry.index)
+    }
+}
+
+/// rewrite quickened instructions back to their original form using the method's quickening info.
+/// `return-void-no-barrier` needs no info and always becomes `return-void`. dex2oat records an
+/// entry for every nop and payload too, and turns an elided `check-cast` into two nops whose
+/// entries are its register and type; those are restored as a single `check-cast`.
+pub fn dequicken(instructions: &mut Vec<DalvikInstruction>, info: &QuickeningInfo) -> Result<()> {
+    let mut indices = Indices {
+        info,
+        position: 0,
+        dex_pc: 0,
+        nth: 0,
+    };
+    let mut elided = vec![];
+
+    for (position, instruction) in instructions.iter_mut().enumerate() {
+        if elided.last() == Some(&position) {
+            continue;
+        }
+        indices.at(instruction.offset);
+        let mut next_index = || indices.next().ok_or(Error::MissingQuickeningInfo);
+
+        let dequickened = match &instruction.inst {
+            DalvikBytecode::ReturnVoidNoBarrier => {
+                DalvikBytecode::Return(ReturnKind::ReturnVoid, 0)
+            }
+            DalvikBytecode::InstanceOpQuick(kind, reg1, reg2, _) => {
+                DalvikBytecode::InstanceOp(*kind, *reg1, *reg2, next_index()?)
+            }
+            DalvikBytecode::InvokeVirtualQuick(regs, _) => {
+                DalvikBytecode::Invoke(InvokeKind::Virtual, *regs, next_index()?)
+            }
+            DalvikBytecode::InvokeVirtualRangeQuick(regs_count, _, first_argument_reg) => {
+                DalvikBytecode::InvokeRange(
+                    InvokeKind::Virtual,
+                    *regs_count,
+                    next_index()?,
+                    *first_argument_reg,
+                )
+            }
+            DalvikBytecode::Nop => {
+                let register = match next_index() {
+                    Ok(NO_INDEX) => continue,
+                    Ok(register) => register,
+                    Err(_) if info.has_dex_pcs() => continue,
+                    Err(error) => return Err(error),
+                };
+                let register = u8::try_from(register).map_err(|_| Error::InvalidQuickeningInfo)?;
+                let type_idx = next_index()?;
+                // the second nop of the pair is part of the restored check-cast
+                elided.push(position + 1);
+                DalvikBytecode::CheckCast(register, type_idx)
+            }
+            DalvikBytecode::PackedSwitchPayload(..)
+            | DalvikBytecode::SparseSwitchPayload(..)
+            | DalvikBytecode::FillArrayDataPayload(..) => {
+                if !info.has_dex_pcs() {
+                    next_index()?;
+                }
+                continue;
+            }
+            _ => continue,
+        };
+
+        instruction.inst = dequickened;
+    }
+
+    for position in elided.into_iter().rev() {
+        if instructions
+            .get(position)
+            .map(|instruction| &instruction.inst)
+            != Some(&DalvikBytecode::Nop)
+        {
+            return Err(Error::InvalidQuickeningInfo);
+        }
+        instructions.remove(position);
+    }
+
+    Ok(())
+}
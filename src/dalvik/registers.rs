@@ -0,0 +1,229 @@
+use super::opcodes::{DalvikBytecode, MoveKind};
+use alloc::{vec, vec::Vec};
+
+/// a register named by an instruction, wide operands are the pair `reg`, `reg + 1`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RegisterOperand {
+    pub reg: u16,
+    pub wide: bool,
+}
+
+impl RegisterOperand {
+    fn narrow(reg: impl Into<u16>) -> Self {
+        Self {
+            reg: reg.into(),
+            wide: false,
+        }
+    }
+
+    fn new(reg: impl Into<u16>, wide: bool) -> Self {
+        Self {
+            reg: reg.into(),
+            wide,
+        }
+    }
+}
+
+/// a storage location read or written by an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Location {
+    Register(u16),
+    /// the implicit result of an invoke or filled-new-array, read by `move-result*`
+    Result,
+}
+
+/// locations an instruction writes and reads, register pairs are split into both halves
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DefUse {
+    pub defs: Vec<Location>,
+    pub uses: Vec<Location>,
+}
+
+fn locations(operands: &[RegisterOperand]) -> Vec<Location> {
+    operands
+        .iter()
+        .flat_map(|operand| {
+            let high = operand.wide.then(|| operand.reg.wrapping_add(1));
+            core::iter::once(operand.reg)
+                .chain(high)
+                .map(Location::Register)
+        })
+        .collect()
+}
+
+/// consecutive registers of a `/range` instruction
+fn range(first: u16, count: impl Into<u16>) -> Vec<RegisterOperand> {
+    (0..count.into())
+        .map(|index| RegisterOperand::narrow(first.wrapping_add(index)))
+        .collect()
+}
+
+impl DalvikBytecode {
+    /// every register the instruction names, in operand order.
+    /// invoke arguments are listed one register at a time since their widths come from the
+    /// method prototype.
+    pub fn registers(&self) -> Vec<RegisterOperand> {
+        use RegisterOperand as R;
+
+        match self {
+            Self::Move(kind, dst, src) => {
+                vec![R::new(*dst, kind.is_wide()), R::new(*src, kind.is_wide())]
+            }
+            Self::MoveFrom16(kind, dst, src) => {
+                vec![R::new(*dst, kind.is_wide()), R::new(*src, kind.is_wide())]
+            }
+            Self::Move16(kind, dst, src) => {
+                vec![R::new(*dst, kind.is_wide()), R::new(*src, kind.is_wide())]
+            }
+            Self::MoveResult(kind, dst) => vec![R::new(*dst, kind.is_wide())],
+            Self::Return(kind, reg) => match kind {
+                super::opcodes::ReturnKind::ReturnVoid => vec![],
+                _ => vec![R::new(*reg, kind.is_wide())],
+            },
+
+            Self::Const4(reg, _)
+            | Self::ConstHigh16(reg, _)
+            | Self::Const16(reg, _)
+            | Self::Const(reg, _)
+            | Self::ConstString(reg, _)
+            | Self::ConstStringJumbo(reg, _)
+            | Self::ConstClass(reg, _)
+            | Self::CheckCast(reg, _)
+            | Self::MonitorEnter(reg)
+            | Self::MonitorExit(reg)
+            | Self::NewInstance(reg, _)
+            | Self::FilledArrayData(reg, _)
+            | Self::Throw(reg)
+            | Self::PackedSwitch(reg, _)
+            | Self::SparseSwitch(reg, _)
+            | Self::IfTestZ(_, reg, _)
+            | Self::ConstMethodHandle(reg, _)
+            | Self::ConstMethodType(reg, _) => vec![R::narrow(*reg)],
+
+            Self::ConstWide16(reg, _)
+            | Self::ConstWide32(reg, _)
+            | Self::ConstWide(reg, _)
+            | Self::ConstWideHigh16(reg, _) => vec![R::new(*reg, true)],
+
+            Self::InstanceOf(dst, src, _)
+            | Self::ArrayLength(dst, src)
+            | Self::NewArray(dst, src, _)
+            | Self::IfTest(_, dst, src, _)
+            | Self::BinopLit16(_, dst, src, _)
+            | Self::BinopLit8(_, dst, src, _) => vec![R::narrow(*dst), R::narrow(*src)],
+
+            Self::FilledNewArray(_, regs)
+            | Self::Invoke(_, regs, _)
+            | Self::InvokePolymorphic(regs, ..)
+            | Self::InvokeCustom(regs, _)
+            | Self::InvokeVirtualQuick(regs, _) => regs.iter().map(|&reg| R::narrow(reg)).collect(),
+
+            Self::FilledNewArrayRange(count, _, first)
+            | Self::InvokeRange(_, count, _, first)
+            | Self::InvokePolymorphicRange(count, _, first, _)
+            | Self::InvokeCustomRange(count, _, first)
+            | Self::InvokeVirtualRangeQuick(count, _, first) => range(*first, *count),
+
+            Self::Cmp(kind, dst, src1, src2) => vec![
+                R::narrow(*dst),
+                R::new(*src1, kind.is_wide()),
+                R::new(*src2, kind.is_wide()),
+            ],
+
+            Self::ArrayOp(kind, value, array, index) => vec![
+                R::new(*value, kind.is_wide()),
+                R::narrow(*array),
+                R::narrow(*index),
+            ],
+            Self::InstanceOp(kind, value, object, _)
+            | Self::InstanceOpQuick(kind, value, object, _) => {
+                vec![R::new(*value, kind.is_wide()), R::narrow(*object)]
+            }
+            Self::StaticOp(kind, value, _) => vec![R::new(*value, kind.is_wide())],
+
+            Self::Unop(kind, dst, src) => {
+                vec![
+                    R::new(*dst, kind.is_dst_wide()),
+                    R::new(*src, kind.is_src_wide()),
+                ]
+            }
+            Self::Binop(kind, dst, src1, src2) => vec![
+                R::new(*dst, kind.is_wide()),
+                R::new(*src1, kind.is_wide()),
+                R::new(*src2, kind.is_wide() && !kind.is_shift()),
+            ],
+            Self::Binop2Addr(kind, dst, src) => vec![
+                R::new(*dst, kind.is_wide()),
+                R::new(*src, kind.is_wide() && !kind.is_shift()),
+            ],
+
+            Self::Nop
+            | Self::Goto(_)
+            | Self::Goto16(_)
+            | Self::Goto32(_)
+            | Self::PackedSwitchPayload(..)
+            | Self::SparseSwitchPayload(..)
+            | Self::FillArrayDataPayload(..)
+            | Self::ReturnVoidNoBarrier => vec![],
+        }
+    }
+
+    /// the locations the instruction defines and uses. every invoke defines the result
+    /// location whatever its return type, since that comes from the method prototype.
+    pub fn def_use(&self) -> DefUse {
+        let operands = self.registers();
+        let (defs, uses) = match self {
+            // first operand written, the others read
+            Self::Move(..)
+            | Self::MoveFrom16(..)
+            | Self::Move16(..)
+            | Self::MoveResult(..)
+            | Self::Const4(..)
+            | Self::Const16(..)
+            | Self::Const(..)
+            | Self::ConstHigh16(..)
+            | Self::ConstWide16(..)
+            | Self::ConstWide32(..)
+            | Self::ConstWide(..)
+            | Self::ConstWideHigh16(..)
+            | Self::ConstString(..)
+            | Self::ConstStringJumbo(..)
+            | Self::ConstClass(..)
+            | Self::ConstMethodHandle(..)
+            | Self::ConstMethodType(..)
+            | Self::NewInstance(..)
+            | Self::InstanceOf(..)
+            | Self::ArrayLength(..)
+            | Self::NewArray(..)
+            | Self::Cmp(..)
+            | Self::Unop(..)
+            | Self::Binop(..)
+            | Self::BinopLit16(..)
+            | Self::BinopLit8(..) => (&operands[..1], &operands[1..]),
+            // the destination is also the first source
+            Self::Binop2Addr(..) => (&operands[..1], &operands[..]),
+            Self::ArrayOp(kind, ..)
+            | Self::InstanceOp(kind, ..)
+            | Self::InstanceOpQuick(kind, ..)
+            | Self::StaticOp(kind, ..)
+                if !kind.is_put() =>
+            {
+                (&operands[..1], &operands[1..])
+            }
+            _ => (&operands[..0], &operands[..]),
+        };
+
+        let mut def_use = DefUse {
+            defs: locations(defs),
+            uses: locations(uses),
+        };
+        match self {
+            Self::MoveResult(kind, _) if *kind != MoveKind::Exception => {
+                def_use.uses.push(Location::Result)
+            }
+            _ if self.has_result() => def_use.defs.push(Location::Result),
+            _ => {}
+        }
+        def_use
+    }
+}
@@ -0,0 +1,546 @@
+//! smali text of decoded instructions, in the syntax baksmali prints
+
+use super::{opcodes::*, DalvikInstruction};
+use crate::resolver::Resolver;
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+#[cfg(feature = "std")]
+use {
+    crate::analysis::cfg::{Cfg, TryBlock},
+    alloc::collections::BTreeSet,
+    core::fmt::Write,
+};
+
+fn move_suffix(kind: MoveKind) -> &'static str {
+    match kind {
+        MoveKind::Move | MoveKind::Exception => "",
+        MoveKind::MovWide => "-wide",
+        MoveKind::MoveObject => "-object",
+    }
+}
+
+fn op_suffix(kind: OpKind) -> &'static str {
+    match kind {
+        OpKind::Get | OpKind::Put => "",
+        OpKind::GetWide | OpKind::PutWide => "-wide",
+        OpKind::GetObject | OpKind::PutObject => "-object",
+        OpKind::GetBoolean | OpKind::PutBoolean => "-boolean",
+        OpKind::GetByte | OpKind::PutByte => "-byte",
+        OpKind::GetChar | OpKind::PutChar => "-char",
+        OpKind::GetShort | OpKind::PutShort => "-short",
+    }
+}
+
+/// `aget-wide`, `iput-object`, ... for prefix `a`, `i` or `s`
+fn op_mnemonic(prefix: &str, kind: OpKind) -> String {
+    let access = match kind.is_put() {
+        true => "put",
+        false => "get",
+    };
+    format!("{}{}{}", prefix, access, op_suffix(kind))
+}
+
+fn if_mnemonic(kind: IfKind) -> &'static str {
+    match kind {
+        IfKind::Eq => "eq",
+        IfKind::Ne => "ne",
+        IfKind::It => "lt",
+        IfKind::Ge => "ge",
+        IfKind::Gt => "gt",
+        IfKind::Le => "le",
+    }
+}
+
+fn invoke_mnemonic(kind: InvokeKind) -> &'static str {
+    match kind {
+        InvokeKind::Virtual => "invoke-virtual",
+        InvokeKind::Super => "invoke-super",
+        InvokeKind::Direct => "invoke-direct",
+        InvokeKind::Static => "invoke-static",
+        InvokeKind::Interface => "invoke-interface",
+    }
+}
+
+fn cmp_mnemonic(kind: CmpKind) -> &'static str {
+    match kind {
+        CmpKind::CmplFloat => "cmpl-float",
+        CmpKind::CmpgFloat => "cmpg-float",
+        CmpKind::CmplDouble => "cmpl-double",
+        CmpKind::CmpgDouble => "cmpg-double",
+        CmpKind::CmpLong => "cmp-long",
+    }
+}
+
+fn unop_mnemonic(kind: UnopKind) -> &'static str {
+    use UnopKind as U;
+
+    match kind {
+        U::NegInt => "neg-int",
+        U::NotInt => "not-int",
+        U::NegLong => "neg-long",
+        U::NotLong => "not-long",
+        U::NegFloat => "neg-float",
+        U::NegDouble => "neg-double",
+        U::IntToLong => "int-to-long",
+        U::IntToFloat => "int-to-float",
+        U::IntToDouble => "int-to-double",
+        U::LongToInt => "long-to-int",
+        U::LongToFloat => "long-to-float",
+        U::LongToDouble => "long-to-double",
+        U::FloatToInt => "float-to-int",
+        U::FloatToLong => "float-to-long",
+        U::FloatToDouble => "float-to-double",
+        U::DoubleToInt => "double-to-int",
+        U::DoubleToLong => "double-to-long",
+        U::DoubleToFloat => "double-to-float",
+        U::IntToByte => "int-to-byte",
+        U::IntToChar => "int-to-char",
+        U::IntToShort => "int-to-short",
+    }
+}
+
+fn binop_mnemonic(kind: ArithmeticKind) -> &'static str {
+    use ArithmeticKind as A;
+
+    match kind {
+        A::AddInt => "add-int",
+        A::SubInt => "sub-int",
+        A::MulInt => "mul-int",
+        A::DivInt => "div-int",
+        A::RemInt => "rem-int",
+        A::AndInt => "and-int",
+        A::OrInt => "or-int",
+        A::XorInt => "xor-int",
+        A::ShlInt => "shl-int",
+        A::ShrInt => "shr-int",
+        A::UshrInt => "ushr-int",
+        A::AddLong => "add-long",
+        A::SubLong => "sub-long",
+        A::MulLong => "mul-long",
+        A::DivLong => "div-long",
+        A::RemLong => "rem-long",
+        A::AndLong => "and-long",
+        A::OrLong => "or-long",
+        A::XorLong => "xor-long",
+        A::ShlLong => "shl-long",
+        A::ShrLong => "shr-long",
+        A::UshrLong => "ushr-long",
+        A::AddFloat => "add-float",
+        A::SubFloat => "sub-float",
+        A::MulFloat => "mul-float",
+        A::DivFloat => "div-float",
+        A::RemFloat => "rem-float",
+        A::AddDouble => "add-double",
+        A::SubDouble => "sub-double",
+        A::MulDouble => "mul-double",
+        A::DivDouble => "div-double",
+        A::RemDouble => "rem-double",
+        A::RSubInt => "rsub-int",
+    }
+}
+
+/// a literal the way smali writes it, `0x10`, `-0x1`
+fn hex(value: i64) -> String {
+    match value < 0 {
+        true => format!("-{:#x}", value.unsigned_abs()),
+        false => format!("{:#x}", value),
+    }
+}
+
+/// a quoted string with java escapes, the same in smali and java source
+pub fn string_literal(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// `{v0, v1}`
+fn register_list(regs: &[u8]) -> String {
+    let regs: Vec<String> = regs.iter().map(|reg| format!("v{}", reg)).collect();
+    format!("{{{}}}", regs.join(", "))
+}
+
+/// `{v3 .. v5}`
+fn register_range(count: u8, first: u16) -> String {
+    match count {
+        0 => "{}".to_string(),
+        _ => format!("{{v{} .. v{}}}", first, first as u32 + count as u32 - 1),
+    }
+}
+
+/// the label of a code address, branch targets and payloads are written as `:addr_1a`
+pub fn label(addr: usize) -> String {
+    format!(":addr_{:x}", addr)
+}
+
+struct Names<'a>(&'a dyn Resolver);
+
+impl Names<'_> {
+    fn string(&self, string_idx: u32) -> String {
+        self.0.string(string_idx).map_or_else(
+            || format!("string@{}", string_idx),
+            |text| string_literal(&text),
+        )
+    }
+
+    fn jtype(&self, type_idx: u16) -> String {
+        self.0
+            .type_descriptor(type_idx as u32)
+            .unwrap_or_else(|| format!("type@{}", type_idx))
+    }
+
+    fn field(&self, field_idx: u16) -> String {
+        self.0.field(field_idx as u32).map_or_else(
+            || format!("field@{}", field_idx),
+            |field| format!("{}->{}:{}", field.class, field.name, field.field_type),
+        )
+    }
+
+    fn method(&self, method_idx: u16) -> String {
+        self.0.method(method_idx as u32).map_or_else(
+            || format!("method@{}", method_idx),
+            |method| {
+                format!(
+                    "{}->{}{}",
+                    method.class,
+                    method.name,
+                    method.prototype.descriptor()
+                )
+            },
+        )
+    }
+
+    fn prototype(&self, proto_idx: u16) -> String {
+        self.0.prototype(proto_idx as u32).map_or_else(
+            || format!("proto@{}", proto_idx),
+            |proto| proto.descriptor(),
+        )
+    }
+}
+
+/// one line of smali for `instruction`, indices are named through `resolver` and branch
+/// targets written as labels (see `label`)
+pub fn instruction_text(instruction: &DalvikInstruction, resolver: &dyn Resolver) -> String {
+    use DalvikBytecode as B;
+
+    let names = Names(resolver);
+    let addr = instruction.offset / 2;
+    let target = |relative: i64| label((addr as i64 + relative) as usize);
+
+    match &instruction.inst {
+        B::Nop => "nop".to_string(),
+
+        B::Move(kind, dst, src) => format!("move{} v{}, v{}", move_suffix(*kind), dst, src),
+        B::MoveFrom16(kind, dst, src) => {
+            format!("move{}/from16 v{}, v{}", move_suffix(*kind), dst, src)
+        }
+        B::Move16(kind, dst, src) => format!("move{}/16 v{}, v{}", move_suffix(*kind), dst, src),
+        B::MoveResult(MoveKind::Exception, reg) => format!("move-exception v{}", reg),
+        B::MoveResult(kind, reg) => format!("move-result{} v{}", move_suffix(*kind), reg),
+
+        B::Return(ReturnKind::ReturnVoid, _) => "return-void".to_string(),
+        B::Return(kind, reg) => {
+            let suffix = match kind {
+                ReturnKind::ReturnWide => "-wide",
+                ReturnKind::ReturnObject => "-object",
+                _ => "",
+            };
+            format!("return{} v{}", suffix, reg)
+        }
+
+        B::Const4(reg, value) => format!("const/4 v{}, {}", reg, hex(*value as i64)),
+        B::Const16(reg, value) => format!("const/16 v{}, {}", reg, hex(*value as i64)),
+        B::Const(reg, value) => format!("const v{}, {}", reg, hex(*value as i64)),
+        B::ConstHigh16(reg, value) => {
+            format!(
+                "const/high16 v{}, {}",
+                reg,
+                hex(((*value as i32) << 16) as i64)
+            )
+        }
+        B::ConstWide16(reg, value) => format!("const-wide/16 v{}, {}L", reg, hex(*value as i64)),
+        B::ConstWide32(reg, value) => format!("const-wide/32 v{}, {}L", reg, hex(*value as i64)),
+        B::ConstWide(reg, value) => format!("const-wide v{}, {}L", reg, hex(*value as i64)),
+        B::ConstWideHigh16(reg, value) => format!(
+            "const-wide/high16 v{}, {}L",
+            reg,
+            hex((*value as i64) << 48)
+        ),
+        B::ConstString(reg, string_idx) => {
+            format!(
+                "const-string v{}, {}",
+                reg,
+                names.string(*string_idx as u32)
+            )
+        }
+        B::ConstStringJumbo(reg, string_idx) => {
+            format!("const-string/jumbo v{}, {}", reg, names.string(*string_idx))
+        }
+        B::ConstClass(reg, type_idx) => format!("const-class v{}, {}", reg, names.jtype(*type_idx)),
+        B::ConstMethodHandle(reg, handle_idx) => {
+            format!("const-method-handle v{}, method_handle@{}", reg, handle_idx)
+        }
+        B::ConstMethodType(reg, proto_idx) => {
+            format!(
+                "const-method-type v{}, {}",
+                reg,
+                names.prototype(*proto_idx)
+            )
+        }
+
+        B::MonitorEnter(reg) => format!("monitor-enter v{}", reg),
+        B::MonitorExit(reg) => format!("monitor-exit v{}", reg),
+        B::CheckCast(reg, type_idx) => format!("check-cast v{}, {}", reg, names.jtype(*type_idx)),
+        B::InstanceOf(dst, src, type_idx) => {
+            format!("instance-of v{}, v{}, {}", dst, src, names.jtype(*type_idx))
+        }
+        B::ArrayLength(dst, src) => format!("array-length v{}, v{}", dst, src),
+        B::NewInstance(reg, type_idx) => {
+            format!("new-instance v{}, {}", reg, names.jtype(*type_idx))
+        }
+        B::NewArray(dst, size, type_idx) => {
+            format!("new-array v{}, v{}, {}", dst, size, names.jtype(*type_idx))
+        }
+        B::FilledNewArray(type_idx, regs) => format!(
+            "filled-new-array {}, {}",
+            register_list(regs),
+            names.jtype(*type_idx)
+        ),
+        B::FilledNewArrayRange(count, type_idx, first) => format!(
+            "filled-new-array/range {}, {}",
+            register_range(*count, *first),
+            names.jtype(*type_idx)
+        ),
+        B::FilledArrayData(reg, relative) => {
+            format!("fill-array-data v{}, {}", reg, target(*relative as i64))
+        }
+        B::Throw(reg) => format!("throw v{}", reg),
+
+        B::Goto(relative) => format!("goto {}", target(*relative as i64)),
+        B::Goto16(relative) => format!("goto/16 {}", target(*relative as i64)),
+        B::Goto32(relative) => format!("goto/32 {}", target(*relative as i64)),
+        B::PackedSwitch(reg, relative) => {
+            format!("packed-switch v{}, {}", reg, target(*relative as i64))
+        }
+        B::SparseSwitch(reg, relative) => {
+            format!("sparse-switch v{}, {}", reg, target(*relative as i64))
+        }
+
+        B::PackedSwitchPayload(first_key, targets) => format!(
+            ".packed-switch {} ({} targets)",
+            hex(*first_key as i64),
+            targets.len()
+        ),
+        B::SparseSwitchPayload(keys, _) => format!(".sparse-switch ({} keys)", keys.len()),
+        B::FillArrayDataPayload(width, data) => format!(
+            ".array-data {} ({} elements)",
+            width,
+            data.len() / (*width).max(1) as usize
+        ),
+
+        B::Cmp(kind, dst, left, right) => {
+            format!("{} v{}, v{}, v{}", cmp_mnemonic(*kind), dst, left, right)
+        }
+        B::IfTest(kind, left, right, relative) => format!(
+            "if-{} v{}, v{}, {}",
+            if_mnemonic(*kind),
+            left,
+            right,
+            target(*relative as i64)
+        ),
+        B::IfTestZ(kind, reg, relative) => format!(
+            "if-{}z v{}, {}",
+            if_mnemonic(*kind),
+            reg,
+            target(*relative as i64)
+        ),
+
+        B::ArrayOp(kind, value, array, index) => format!(
+            "{} v{}, v{}, v{}",
+            op_mnemonic("a", *kind),
+            value,
+            array,
+            index
+        ),
+        B::InstanceOp(kind, value, object, field_idx) => format!(
+            "{} v{}, v{}, {}",
+            op_mnemonic("i", *kind),
+            value,
+            object,
+            names.field(*field_idx)
+        ),
+        B::StaticOp(kind, value, field_idx) => format!(
+            "{} v{}, {}",
+            op_mnemonic("s", *kind),
+            value,
+            names.field(*field_idx)
+        ),
+
+        B::Invoke(kind, regs, method_idx) => format!(
+            "{} {}, {}",
+            invoke_mnemonic(*kind),
+            register_list(regs),
+            names.method(*method_idx)
+        ),
+        B::InvokeRange(kind, count, method_idx, first) => format!(
+            "{}/range {}, {}",
+            invoke_mnemonic(*kind),
+            register_range(*count, *first),
+            names.method(*method_idx)
+        ),
+        B::InvokePolymorphic(regs, method_idx, proto_idx) => format!(
+            "invoke-polymorphic {}, {}, {}",
+            register_list(regs),
+            names.method(*method_idx),
+            names.prototype(*proto_idx)
+        ),
+        B::InvokePolymorphicRange(count, method_idx, first, proto_idx) => format!(
+            "invoke-polymorphic/range {}, {}, {}",
+            register_range(*count, *first),
+            names.method(*method_idx),
+            names.prototype(*proto_idx)
+        ),
+        B::InvokeCustom(regs, call_site_idx) => format!(
+            "invoke-custom {}, call_site@{}",
+            register_list(regs),
+            call_site_idx
+        ),
+        B::InvokeCustomRange(count, call_site_idx, first) => format!(
+            "invoke-custom/range {}, call_site@{}",
+            register_range(*count, *first),
+            call_site_idx
+        ),
+
+        B::Unop(kind, dst, src) => format!("{} v{}, v{}", unop_mnemonic(*kind), dst, src),
+        B::Binop(kind, dst, left, right) => {
+            format!("{} v{}, v{}, v{}", binop_mnemonic(*kind), dst, left, right)
+        }
+        B::Binop2Addr(kind, dst, src) => {
+            format!("{}/2addr v{}, v{}", binop_mnemonic(*kind), dst, src)
+        }
+        B::BinopLit16(kind, dst, src, literal) => {
+            // rsub-int has no /lit16 suffix
+            let suffix = match kind {
+                ArithmeticKind::RSubInt => "",
+                _ => "/lit16",
+            };
+            format!(
+                "{}{} v{}, v{}, {}",
+                binop_mnemonic(*kind),
+                suffix,
+                dst,
+                src,
+                hex(*literal as i64)
+            )
+        }
+        B::BinopLit8(kind, dst, src, literal) => format!(
+            "{}/lit8 v{}, v{}, {}",
+            binop_mnemonic(*kind),
+            dst,
+            src,
+            hex(*literal as i64)
+        ),
+
+        B::ReturnVoidNoBarrier => "return-void-no-barrier".to_string(),
+        B::InstanceOpQuick(kind, value, object, offset) => format!(
+            "{}-quick v{}, v{}, field@{:#x}",
+            op_mnemonic("i", *kind),
+            value,
+            object,
+            offset
+        ),
+        B::InvokeVirtualQuick(regs, vtable_idx) => format!(
+            "invoke-virtual-quick {}, vtable@{:#x}",
+            register_list(regs),
+            vtable_idx
+        ),
+        B::InvokeVirtualRangeQuick(count, vtable_idx, first) => format!(
+            "invoke-virtual-quick/range {}, vtable@{:#x}",
+            register_range(*count, *first),
+            vtable_idx
+        ),
+    }
+}
+
+/// a whole method as smali, `name` followed by its descriptor. branch targets, try ranges
+/// and handlers get a label and the try blocks are listed as `.catch` directives.
+#[cfg(feature = "std")]
+pub fn method_text(
+    name: &str,
+    registers: u16,
+    instructions: &[DalvikInstruction],
+    tries: &[TryBlock],
+    resolver: &dyn Resolver,
+) -> String {
+    let cfg = Cfg::new(instructions, tries);
+    let mut labels: BTreeSet<usize> = cfg
+        .blocks
+        .iter()
+        .skip(1)
+        .map(|block| instructions[block.start].offset / 2)
+        .collect();
+    for try_block in tries {
+        labels.insert(try_block.start_addr as usize);
+        labels.insert((try_block.start_addr + try_block.insn_count as u32) as usize);
+    }
+
+    let mut out = format!(".method {}\n    .registers {}\n", name, registers);
+    for instruction in instructions {
+        if labels.contains(&(instruction.offset / 2)) {
+            let _ = writeln!(out, "\n    {}", label(instruction.offset / 2));
+        }
+        let _ = writeln!(out, "    {}", instruction_text(instruction, resolver));
+    }
+    // a try block may run up to the end of the code
+    let end = instructions
+        .last()
+        .map_or(0, |last| last.offset / 2 + last.inst.code_units());
+    if labels.contains(&end) {
+        let _ = writeln!(out, "\n    {}", label(end));
+    }
+
+    if !tries.is_empty() {
+        out.push('\n');
+    }
+    for try_block in tries {
+        let start = label(try_block.start_addr as usize);
+        let end = label((try_block.start_addr + try_block.insn_count as u32) as usize);
+        for handler in &try_block.handlers {
+            let _ = match handler.type_idx {
+                Some(type_idx) => writeln!(
+                    out,
+                    "    .catch {} {{{} .. {}}} {}",
+                    resolver
+                        .type_descriptor(type_idx)
+                        .unwrap_or_else(|| format!("type@{}", type_idx)),
+                    start,
+                    end,
+                    label(handler.addr as usize)
+                ),
+                None => writeln!(
+                    out,
+                    "    .catchall {{{} .. {}}} {}",
+                    start,
+                    end,
+                    label(handler.addr as usize)
+                ),
+            };
+        }
+    }
+    out.push_str(".end method\n");
+    out
+}
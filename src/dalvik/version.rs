@@ -0,0 +1,72 @@
+use super::opcodes::*;
+
+pub(crate) const DEX_MAGIC: &[u8; 4] = b"dex\n";
+/// the endian tag of a little endian dex or cdex header
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+pub(crate) const ENDIAN_CONSTANT: u32 = 0x12345678;
+
+/// dex file format versions, each one is a superset of the previous one opcode wise
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DexVersion {
+    V035,
+    V037,
+    V038,
+    V039,
+    V040,
+    V041,
+}
+
+impl DexVersion {
+    /// parse the version out of the `dex\nXXX\0` magic at the start of a dex file
+    pub fn from_magic(magic: &[u8]) -> Option<Self> {
+        if magic.len() < 8 || &magic[..4] != DEX_MAGIC || magic[7] != 0 {
+            return None;
+        }
+
+        match &magic[4..7] {
+            b"035" => Some(Self::V035),
+            b"037" => Some(Self::V037),
+            b"038" => Some(Self::V038),
+            b"039" => Some(Self::V039),
+            b"040" => Some(Self::V040),
+            b"041" => Some(Self::V041),
+            _ => None,
+        }
+    }
+
+    /// the newest dex version a runtime with the given api level can load
+    pub fn from_api_level(api_level: u32) -> Self {
+        match api_level {
+            0..=23 => Self::V035,
+            24..=25 => Self::V037,
+            26..=27 => Self::V038,
+            28..=34 => Self::V039,
+            _ => Self::V041,
+        }
+    }
+
+    /// the three digit version string as it appears in the magic
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::V035 => "035",
+            Self::V037 => "037",
+            Self::V038 => "038",
+            Self::V039 => "039",
+            Self::V040 => "040",
+            Self::V041 => "041",
+        }
+    }
+
+    /// the first dex version in which the opcode is legal
+    pub fn introduced_opcode(op: u8) -> Self {
+        match op {
+            INVOKE_POLYMORPHIC_OP..=INVOKE_CUSTOM_RANGE_OP => Self::V038,
+            CONST_METHOD_HANDLE_OP | CONST_METHOD_TYPE_OP => Self::V039,
+            _ => Self::V035,
+        }
+    }
+
+    pub fn supports_opcode(&self, op: u8) -> bool {
+        *self >= Self::introduced_opcode(op)
+    }
+}
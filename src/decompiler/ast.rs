@@ -0,0 +1,291 @@
+use std::fmt::{self, Write};
+
+/// an expression of the decompiled source
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// a register, a parameter or `this`
+    Local(String),
+    Literal(String),
+    /// a class name qualifying a static access, or `super`
+    Type(String),
+    Unary(&'static str, Box<Expr>),
+    Binary(Box<Expr>, &'static str, Box<Expr>),
+    Cast(String, Box<Expr>),
+    InstanceOf(Box<Expr>, String),
+    /// `cmp`, `cmpl` or `cmpg` of two values, folded into the condition testing it
+    Compare(&'static str, Box<Expr>, Box<Expr>),
+    Field(Box<Expr>, String),
+    Index(Box<Expr>, Box<Expr>),
+    Length(Box<Expr>),
+    /// a call with its receiver, `None` for a bare function like `super(...)`
+    Call(Option<Box<Expr>>, String, Vec<Expr>),
+    New(String, Vec<Expr>),
+    /// `new T[size]` with the element type
+    NewArray(String, Box<Expr>),
+    /// `new T[]{...}` with the element type
+    ArrayLiteral(String, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Catch {
+    /// caught class, `Throwable` for a catch-all
+    pub exception: String,
+    pub variable: String,
+    pub body: Vec<Statement>,
+}
+
+/// a statement of the decompiled source
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Assign(Expr, Expr),
+    Expr(Expr),
+    Return(Option<Expr>),
+    Throw(Expr),
+    If {
+        condition: Expr,
+        then: Vec<Statement>,
+        otherwise: Vec<Statement>,
+    },
+    While {
+        label: Option<String>,
+        condition: Expr,
+        body: Vec<Statement>,
+    },
+    Switch {
+        value: Expr,
+        /// keys of every case with the statements they run
+        cases: Vec<(Vec<i32>, Vec<Statement>)>,
+        default: Vec<Statement>,
+    },
+    Try {
+        body: Vec<Statement>,
+        catches: Vec<Catch>,
+    },
+    Break(Option<String>),
+    Continue(Option<String>),
+    Label(String),
+    Goto(String),
+    Comment(String),
+}
+
+impl Statement {
+    /// whether control never reaches the statement after this one
+    pub fn is_jump(&self) -> bool {
+        matches!(
+            self,
+            Self::Return(_) | Self::Throw(_) | Self::Break(_) | Self::Continue(_) | Self::Goto(_)
+        )
+    }
+}
+
+impl Expr {
+    /// binding strength, higher binds tighter
+    fn precedence(&self) -> u8 {
+        match self {
+            Self::Unary(..) | Self::Cast(..) => 13,
+            Self::Binary(_, op, _) => binary_precedence(op),
+            Self::InstanceOf(..) => 9,
+            _ => 14,
+        }
+    }
+
+    /// the expression, parenthesized if it binds looser than `precedence`
+    fn operand(&self, precedence: u8) -> String {
+        match self.precedence() < precedence {
+            true => format!("({})", self),
+            false => self.to_string(),
+        }
+    }
+
+    /// the logical negation of a condition
+    pub fn negate(self) -> Self {
+        match self {
+            Self::Binary(left, "&&", right) => {
+                Self::Binary(Box::new(left.negate()), "||", Box::new(right.negate()))
+            }
+            Self::Binary(left, "||", right) => {
+                Self::Binary(Box::new(left.negate()), "&&", Box::new(right.negate()))
+            }
+            Self::Binary(left, op, right) => match negated_comparison(op) {
+                Some(negated) => Self::Binary(left, negated, right),
+                None => Self::Unary("!", Box::new(Self::Binary(left, op, right))),
+            },
+            Self::Unary("!", inner) => *inner,
+            other => Self::Unary("!", Box::new(other)),
+        }
+    }
+}
+
+fn binary_precedence(op: &str) -> u8 {
+    match op {
+        "*" | "/" | "%" => 12,
+        "+" | "-" => 11,
+        "<<" | ">>" | ">>>" => 10,
+        "<" | "<=" | ">" | ">=" => 9,
+        "==" | "!=" => 8,
+        "&" => 7,
+        "^" => 6,
+        "|" => 5,
+        "&&" => 4,
+        _ => 3,
+    }
+}
+
+fn negated_comparison(op: &str) -> Option<&'static str> {
+    Some(match op {
+        "==" => "!=",
+        "!=" => "==",
+        "<" => ">=",
+        ">=" => "<",
+        ">" => "<=",
+        "<=" => ">",
+        _ => return None,
+    })
+}
+
+fn list(exprs: &[Expr]) -> String {
+    exprs
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Local(name) | Self::Literal(name) | Self::Type(name) => f.write_str(name),
+            Self::Unary(op, inner) => write!(f, "{}{}", op, inner.operand(13)),
+            Self::Binary(left, op, right) => {
+                let precedence = binary_precedence(op);
+                write!(
+                    f,
+                    "{} {} {}",
+                    left.operand(precedence),
+                    op,
+                    right.operand(precedence + 1)
+                )
+            }
+            Self::Cast(jtype, inner) => write!(f, "({}) {}", jtype, inner.operand(13)),
+            Self::InstanceOf(inner, jtype) => {
+                write!(f, "{} instanceof {}", inner.operand(10), jtype)
+            }
+            Self::Compare(name, left, right) => write!(f, "{}({}, {})", name, left, right),
+            Self::Field(object, name) => write!(f, "{}.{}", object.operand(14), name),
+            Self::Index(array, index) => write!(f, "{}[{}]", array.operand(14), index),
+            Self::Length(array) => write!(f, "{}.length", array.operand(14)),
+            Self::Call(Some(receiver), name, args) => {
+                write!(f, "{}.{}({})", receiver.operand(14), name, list(args))
+            }
+            Self::Call(None, name, args) => write!(f, "{}({})", name, list(args)),
+            Self::New(jtype, args) => write!(f, "new {}({})", jtype, list(args)),
+            Self::NewArray(element, size) => match element.split_once('[') {
+                // the size goes before the dimensions of a nested array type
+                Some((base, dimensions)) => write!(f, "new {}[{}][{}", base, size, dimensions),
+                None => write!(f, "new {}[{}]", element, size),
+            },
+            Self::ArrayLiteral(element, elements) => {
+                write!(f, "new {}[]{{{}}}", element, list(elements))
+            }
+        }
+    }
+}
+
+/// write statements as indented java-like source
+pub fn write_statements(out: &mut String, statements: &[Statement], indent: usize) {
+    for statement in statements {
+        write_statement(out, statement, indent);
+    }
+}
+
+fn write_block(out: &mut String, statements: &[Statement], indent: usize) {
+    out.push_str(" {\n");
+    write_statements(out, statements, indent + 1);
+    let _ = write!(out, "{}}}", "    ".repeat(indent));
+}
+
+fn write_statement(out: &mut String, statement: &Statement, indent: usize) {
+    let pad = "    ".repeat(indent);
+    let _ = match statement {
+        Statement::Assign(target, value) => writeln!(out, "{}{} = {};", pad, target, value),
+        Statement::Expr(expr) => writeln!(out, "{}{};", pad, expr),
+        Statement::Return(None) => writeln!(out, "{}return;", pad),
+        Statement::Return(Some(value)) => writeln!(out, "{}return {};", pad, value),
+        Statement::Throw(value) => writeln!(out, "{}throw {};", pad, value),
+        Statement::Break(None) => writeln!(out, "{}break;", pad),
+        Statement::Break(Some(label)) => writeln!(out, "{}break {};", pad, label),
+        Statement::Continue(None) => writeln!(out, "{}continue;", pad),
+        Statement::Continue(Some(label)) => writeln!(out, "{}continue {};", pad, label),
+        // labels sit one level out, like in hand written code
+        Statement::Label(label) => {
+            writeln!(out, "{}{}:", "    ".repeat(indent.saturating_sub(1)), label)
+        }
+        Statement::Goto(label) => writeln!(out, "{}goto {};", pad, label),
+        Statement::Comment(text) => writeln!(out, "{}// {}", pad, text),
+        Statement::If {
+            condition,
+            then,
+            otherwise,
+        } => {
+            let _ = write!(out, "{}if ({})", pad, condition);
+            write_block(out, then, indent);
+            // chain `else if` instead of nesting
+            let mut otherwise = otherwise;
+            while let [Statement::If {
+                condition,
+                then,
+                otherwise: next,
+            }] = otherwise.as_slice()
+            {
+                let _ = write!(out, " else if ({})", condition);
+                write_block(out, then, indent);
+                otherwise = next;
+            }
+            if !otherwise.is_empty() {
+                out.push_str(" else");
+                write_block(out, otherwise, indent);
+            }
+            writeln!(out)
+        }
+        Statement::While {
+            label,
+            condition,
+            body,
+        } => {
+            if let Some(label) = label {
+                let _ = writeln!(out, "{}{}:", pad, label);
+            }
+            let _ = write!(out, "{}while ({})", pad, condition);
+            write_block(out, body, indent);
+            writeln!(out)
+        }
+        Statement::Switch {
+            value,
+            cases,
+            default,
+        } => {
+            let _ = writeln!(out, "{}switch ({}) {{", pad, value);
+            let case_pad = "    ".repeat(indent + 1);
+            for (keys, body) in cases {
+                for key in keys {
+                    let _ = writeln!(out, "{}case {}:", case_pad, key);
+                }
+                write_statements(out, body, indent + 2);
+            }
+            if !default.is_empty() {
+                let _ = writeln!(out, "{}default:", case_pad);
+                write_statements(out, default, indent + 2);
+            }
+            writeln!(out, "{}}}", pad)
+        }
+        Statement::Try { body, catches } => {
+            let _ = write!(out, "{}try", pad);
+            write_block(out, body, indent);
+            for catch in catches {
+                let _ = write!(out, " catch ({} {})", catch.exception, catch.variable);
+                write_block(out, &catch.body, indent);
+            }
+            writeln!(out)
+        }
+    };
+}
@@ -0,0 +1,998 @@
+use super::{
+    ast::{Expr, Statement},
+    first_argument, java_type, register_name, MethodBody,
+};
+use crate::{
+    analysis::{
+        cfg::{Cfg, EdgeKind},
+        reaching::DefUseChains,
+        types::{infer_types, MethodSignature, RegType, RegisterTypes},
+    },
+    dalvik::{opcodes::*, registers::Location, smali::string_literal},
+    resolver::Resolver,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// how control leaves a lifted block
+pub(crate) enum Exit {
+    /// to the normal successor, `None` after a return or throw
+    Jump(Option<usize>),
+    Branch {
+        condition: Expr,
+        taken: usize,
+        fallthrough: usize,
+    },
+    Switch {
+        value: Expr,
+        cases: Vec<(i32, usize)>,
+        default: Option<usize>,
+    },
+}
+
+/// the statements of a basic block and how it ends
+pub(crate) struct LiftedBlock {
+    pub statements: Vec<Statement>,
+    pub exit: Exit,
+    /// variable of the caught exception when the block starts with `move-exception`
+    pub exception: Option<String>,
+}
+
+/// an assignment held back to be inlined into its only use
+struct Pending {
+    expr: Expr,
+    /// registers the expression reads
+    reads: BTreeSet<u16>,
+    pure: bool,
+    /// index of the instruction using the value
+    use_index: usize,
+    def_index: usize,
+}
+
+/// a register assignment, `def_index` is the instruction def-use chains know it by
+struct Def {
+    reg: u16,
+    pure: bool,
+    def_index: usize,
+    /// a use that consumes the value some other way, the constructor call of a `new-instance`
+    exclude: Option<usize>,
+}
+
+impl Def {
+    fn pure(index: usize, reg: impl Into<u16>) -> Self {
+        Self {
+            reg: reg.into(),
+            pure: true,
+            def_index: index,
+            exclude: None,
+        }
+    }
+
+    fn impure(index: usize, reg: impl Into<u16>) -> Self {
+        Self {
+            pure: false,
+            ..Self::pure(index, reg)
+        }
+    }
+}
+
+struct BlockState {
+    block: usize,
+    statements: Vec<Statement>,
+    pending: BTreeMap<u16, Pending>,
+    /// registers read by the instruction being lifted
+    reads: BTreeSet<u16>,
+}
+
+/// turns the instructions of each block into statements, inlining single use values
+pub(crate) struct Lifter<'a> {
+    method: &'a MethodBody<'a>,
+    cfg: &'a Cfg,
+    resolver: &'a dyn Resolver,
+    chains: DefUseChains,
+    types: RegisterTypes,
+    first_argument: u16,
+    index_of: HashMap<usize, usize>,
+    /// the `new-instance` and class built by each `<init>` call, by invoke index
+    constructed: HashMap<usize, (usize, String)>,
+}
+
+fn comparison(kind: IfKind) -> &'static str {
+    match kind {
+        IfKind::Eq => "==",
+        IfKind::Ne => "!=",
+        IfKind::It => "<",
+        IfKind::Ge => ">=",
+        IfKind::Gt => ">",
+        IfKind::Le => "<=",
+    }
+}
+
+fn arithmetic(kind: ArithmeticKind) -> &'static str {
+    use ArithmeticKind as A;
+
+    match kind {
+        A::AddInt | A::AddLong | A::AddFloat | A::AddDouble => "+",
+        A::SubInt | A::SubLong | A::SubFloat | A::SubDouble | A::RSubInt => "-",
+        A::MulInt | A::MulLong | A::MulFloat | A::MulDouble => "*",
+        A::DivInt | A::DivLong | A::DivFloat | A::DivDouble => "/",
+        A::RemInt | A::RemLong | A::RemFloat | A::RemDouble => "%",
+        A::AndInt | A::AndLong => "&",
+        A::OrInt | A::OrLong => "|",
+        A::XorInt | A::XorLong => "^",
+        A::ShlInt | A::ShlLong => "<<",
+        A::ShrInt | A::ShrLong => ">>",
+        A::UshrInt | A::UshrLong => ">>>",
+    }
+}
+
+fn unary(kind: UnopKind, operand: Expr) -> Expr {
+    use UnopKind as U;
+
+    let cast = |jtype: &str| Expr::Cast(jtype.to_string(), Box::new(operand.clone()));
+    match kind {
+        U::NegInt | U::NegLong | U::NegFloat | U::NegDouble => Expr::Unary("-", Box::new(operand)),
+        U::NotInt | U::NotLong => Expr::Unary("~", Box::new(operand)),
+        U::IntToLong | U::FloatToLong | U::DoubleToLong => cast("long"),
+        U::IntToFloat | U::LongToFloat | U::DoubleToFloat => cast("float"),
+        U::IntToDouble | U::LongToDouble | U::FloatToDouble => cast("double"),
+        U::LongToInt | U::FloatToInt | U::DoubleToInt => cast("int"),
+        U::IntToByte => cast("byte"),
+        U::IntToChar => cast("char"),
+        U::IntToShort => cast("short"),
+    }
+}
+
+/// `literal` is the shortest round-tripping form of the value, like `1.5`
+fn float_literal(value: f64, literal: String, suffix: &str) -> String {
+    match value {
+        value if value.is_nan() => "NaN".to_string(),
+        value if value.is_infinite() && value > 0.0 => "Infinity".to_string(),
+        value if value.is_infinite() => "-Infinity".to_string(),
+        _ => literal + suffix,
+    }
+}
+
+impl<'a> Lifter<'a> {
+    pub fn new(method: &'a MethodBody<'a>, cfg: &'a Cfg, resolver: &'a dyn Resolver) -> Self {
+        let signature = MethodSignature {
+            class: method.class,
+            is_static: method.is_static,
+            prototype: method.prototype,
+        };
+        let chains = DefUseChains::new(method.instructions, cfg, method.registers_size);
+        let types = infer_types(
+            method.instructions,
+            cfg,
+            method.registers_size,
+            &signature,
+            resolver,
+        );
+
+        let mut constructed = HashMap::new();
+        for (index, instruction) in method.instructions.iter().enumerate() {
+            let DalvikBytecode::NewInstance(reg, type_idx) = instruction.inst else {
+                continue;
+            };
+            let location = Location::Register(reg as u16);
+            let init = chains
+                .uses(Some(index))
+                .iter()
+                .filter(|&&(_, used)| used == location)
+                .map(|&(use_index, _)| use_index)
+                .find(|&use_index| {
+                    let receiver = match &method.instructions[use_index].inst {
+                        DalvikBytecode::Invoke(InvokeKind::Direct, regs, method_idx) => {
+                            regs.first().map(|&first| (first as u16, *method_idx))
+                        }
+                        DalvikBytecode::InvokeRange(InvokeKind::Direct, _, method_idx, first) => {
+                            Some((*first, *method_idx))
+                        }
+                        _ => None,
+                    };
+                    receiver.is_some_and(|(first, method_idx)| {
+                        first == reg as u16
+                            && resolver
+                                .method(method_idx as u32)
+                                .is_some_and(|method| method.name == "<init>")
+                    })
+                });
+            if let Some(init) = init {
+                constructed.insert(init, (index, Self::type_name(resolver, type_idx)));
+            }
+        }
+
+        Self {
+            method,
+            cfg,
+            resolver,
+            chains,
+            types,
+            first_argument: first_argument(method),
+            index_of: method
+                .instructions
+                .iter()
+                .enumerate()
+                .map(|(index, instruction)| (instruction.offset, index))
+                .collect(),
+            constructed,
+        }
+    }
+
+    fn type_name(resolver: &dyn Resolver, type_idx: u16) -> String {
+        resolver
+            .type_descriptor(type_idx as u32)
+            .map_or_else(|| format!("type@{}", type_idx), |jtype| java_type(&jtype))
+    }
+
+    fn name(&self, reg: u16) -> String {
+        register_name(self.method, self.first_argument, reg)
+    }
+
+    fn operand(&self, state: &mut BlockState, index: usize, reg: impl Into<u16>) -> Expr {
+        let reg = reg.into();
+        if state
+            .pending
+            .get(&reg)
+            .is_some_and(|pending| pending.use_index == index)
+        {
+            let pending = state.pending.remove(&reg).unwrap();
+            // side effects defined earlier must still happen first
+            if !pending.pure {
+                let def_index = pending.def_index;
+                self.flush(state, |_, earlier| {
+                    !earlier.pure && earlier.def_index < def_index
+                });
+            }
+            state.reads.extend(pending.reads);
+            return pending.expr;
+        }
+        state.reads.insert(reg);
+        Expr::Local(self.name(reg))
+    }
+
+    /// write out the held back assignments matching `filter`, in definition order
+    fn flush(&self, state: &mut BlockState, filter: impl Fn(u16, &Pending) -> bool) {
+        let regs: Vec<u16> = state
+            .pending
+            .iter()
+            .filter(|&(&reg, pending)| filter(reg, pending))
+            .map(|(&reg, _)| reg)
+            .collect();
+        let mut flushed: Vec<(u16, Pending)> = regs
+            .into_iter()
+            .map(|reg| (reg, state.pending.remove(&reg).unwrap()))
+            .collect();
+        flushed.sort_by_key(|(_, pending)| pending.def_index);
+        for (reg, pending) in flushed {
+            state
+                .statements
+                .push(Statement::Assign(Expr::Local(self.name(reg)), pending.expr));
+        }
+    }
+
+    /// emit a statement with side effects, after the values computed before it
+    fn emit(&self, state: &mut BlockState, statement: Statement) {
+        match statement {
+            Statement::Return(_) | Statement::Throw(_) => self.flush(state, |_, _| true),
+            _ => self.flush(state, |_, pending| !pending.pure),
+        }
+        state.statements.push(statement);
+    }
+
+    /// the use a definition can be inlined into: its only use, later in the same block
+    fn single_use(&self, state: &BlockState, index: usize, def: &Def) -> Option<usize> {
+        let location = Location::Register(def.reg);
+        let uses: Vec<usize> = self
+            .chains
+            .uses(Some(def.def_index))
+            .iter()
+            .filter(|&&(use_index, used)| used == location && Some(use_index) != def.exclude)
+            .map(|&(use_index, _)| use_index)
+            .collect();
+        // a cast of the value right after it is folded into it, the cast passes it on
+        let first = uses
+            .iter()
+            .copied()
+            .filter(|&use_index| use_index > index)
+            .min();
+        if let Some(cast) = first {
+            let is_cast = matches!(
+                self.method.instructions[cast].inst,
+                DalvikBytecode::CheckCast(reg, _) if reg as u16 == def.reg
+            );
+            if is_cast
+                && self.cfg.block_of(cast) == Some(state.block)
+                && self.chains.definitions(cast, location) == [Some(def.def_index)]
+            {
+                return Some(cast);
+            }
+        }
+
+        let [use_index] = uses[..] else {
+            return None;
+        };
+
+        let reads = self.method.instructions[use_index]
+            .inst
+            .def_use()
+            .uses
+            .iter()
+            .filter(|&&used| used == location)
+            .count();
+        (use_index > index
+            && self.cfg.block_of(use_index) == Some(state.block)
+            && self.chains.definitions(use_index, location) == [Some(def.def_index)]
+            && reads == 1)
+            .then_some(use_index)
+    }
+
+    fn define(&self, state: &mut BlockState, index: usize, def: Def, expr: Expr) {
+        let (low, high) = (def.reg, def.reg.wrapping_add(1));
+        self.flush(state, |reg, pending| {
+            reg == low
+                || reg == high
+                || [low, high, low.wrapping_sub(1)]
+                    .iter()
+                    .any(|clobbered| pending.reads.contains(clobbered))
+        });
+
+        match self.single_use(state, index, &def) {
+            Some(use_index) => {
+                let reads = std::mem::take(&mut state.reads);
+                state.pending.insert(
+                    def.reg,
+                    Pending {
+                        expr,
+                        reads,
+                        pure: def.pure,
+                        use_index,
+                        def_index: def.def_index,
+                    },
+                );
+            }
+            None => state
+                .statements
+                .push(Statement::Assign(Expr::Local(self.name(def.reg)), expr)),
+        }
+    }
+
+    fn narrow_literal(&self, index: usize, value: i32) -> Expr {
+        Expr::Literal(match self.types.constant_use(index) {
+            Some(RegType::Reference(_)) if value == 0 => "null".to_string(),
+            Some(RegType::Boolean) if value == 0 || value == 1 => (value == 1).to_string(),
+            Some(RegType::Float) => {
+                let float = f32::from_bits(value as u32);
+                float_literal(float as f64, format!("{:?}", float), "f")
+            }
+            Some(RegType::Char) => match char::from_u32(value as u32) {
+                Some(c) if c.is_ascii_graphic() || c == ' ' => match c {
+                    '\'' | '\\' => format!("'\\{}'", c),
+                    c => format!("'{}'", c),
+                },
+                _ => value.to_string(),
+            },
+            _ => value.to_string(),
+        })
+    }
+
+    fn wide_literal(&self, index: usize, value: i64) -> Expr {
+        Expr::Literal(match self.types.constant_use(index) {
+            Some(RegType::DoubleLo) => {
+                let double = f64::from_bits(value as u64);
+                float_literal(double, format!("{:?}", double), "")
+            }
+            _ => format!("{}L", value),
+        })
+    }
+
+    fn field(&self, field_idx: u16) -> (String, String) {
+        match self.resolver.field(field_idx as u32) {
+            Some(field) => (java_type(&field.class), field.name),
+            None => ("?".to_string(), format!("field@{}", field_idx)),
+        }
+    }
+
+    /// arguments of a call, wide values take two registers
+    fn arguments(
+        &self,
+        state: &mut BlockState,
+        index: usize,
+        regs: &mut impl Iterator<Item = u16>,
+        parameters: &[String],
+    ) -> Vec<Expr> {
+        let mut args = vec![];
+        for parameter in parameters {
+            let Some(reg) = regs.next() else {
+                break;
+            };
+            if parameter == "J" || parameter == "D" {
+                regs.next();
+            }
+            args.push(self.operand(state, index, reg));
+        }
+        args.extend(regs.map(|reg| self.operand(state, index, reg)));
+        args
+    }
+
+    /// a call or filled-new-array, assigned by the `move-result` following it
+    fn result(&self, state: &mut BlockState, index: usize, expr: Expr) {
+        match self
+            .method
+            .instructions
+            .get(index + 1)
+            .map(|next| &next.inst)
+        {
+            Some(DalvikBytecode::MoveResult(kind, dst)) if *kind != MoveKind::Exception => {
+                let def = Def {
+                    def_index: index + 1,
+                    ..Def::impure(index, *dst)
+                };
+                self.define(state, index, def, expr)
+            }
+            _ => self.emit(state, Statement::Expr(expr)),
+        }
+    }
+
+    fn invoke(
+        &self,
+        state: &mut BlockState,
+        index: usize,
+        kind: InvokeKind,
+        regs: Vec<u16>,
+        method_idx: u16,
+    ) {
+        let mut regs = regs.into_iter();
+        let Some(method) = self.resolver.method(method_idx as u32) else {
+            let args = self.arguments(state, index, &mut regs, &[]);
+            return self.result(
+                state,
+                index,
+                Expr::Call(None, format!("method@{}", method_idx), args),
+            );
+        };
+
+        let receiver = match kind {
+            InvokeKind::Static => None,
+            _ => regs
+                .next()
+                .map(|reg| (reg, self.operand(state, index, reg))),
+        };
+        let args = self.arguments(state, index, &mut regs, &method.prototype.parameters);
+
+        let expr = match (kind, receiver) {
+            (InvokeKind::Static, _) | (_, None) => Expr::Call(
+                Some(Box::new(Expr::Type(java_type(&method.class)))),
+                method.name,
+                args,
+            ),
+            (InvokeKind::Direct, Some((reg, receiver))) if method.name == "<init>" => {
+                if let Some((new_index, jtype)) = self.constructed.get(&index) {
+                    state.reads.remove(&reg);
+                    let def = Def {
+                        def_index: *new_index,
+                        exclude: Some(index),
+                        ..Def::impure(index, reg)
+                    };
+                    return self.define(state, index, def, Expr::New(jtype.clone(), args));
+                }
+
+                let is_this = !self.method.is_static && reg == self.first_argument;
+                if is_this && self.method.name == "<init>" {
+                    let name = match method.class == self.method.class {
+                        true => "this",
+                        false => "super",
+                    };
+                    return self.emit(
+                        state,
+                        Statement::Expr(Expr::Call(None, name.to_string(), args)),
+                    );
+                }
+                Expr::Call(Some(Box::new(receiver)), method.name, args)
+            }
+            (InvokeKind::Super, Some(_)) => Expr::Call(
+                Some(Box::new(Expr::Type("super".to_string()))),
+                method.name,
+                args,
+            ),
+            (_, Some((_, receiver))) => Expr::Call(Some(Box::new(receiver)), method.name, args),
+        };
+        self.result(state, index, expr)
+    }
+
+    /// the block's successor along the given kind of edge
+    fn successor(&self, block: usize, kind: EdgeKind) -> Option<usize> {
+        self.cfg.blocks[block]
+            .successors
+            .iter()
+            .find(|edge| edge.kind == kind)
+            .map(|edge| edge.target)
+    }
+
+    fn zero_condition(&self, index: usize, kind: IfKind, reg: u8, operand: Expr) -> Expr {
+        let op = comparison(kind);
+        match (operand, self.types.register(index, reg as u16)) {
+            (Expr::Compare(_, left, right), _) => Expr::Binary(left, op, right),
+            (operand, Some(RegType::Boolean)) if kind == IfKind::Ne => operand,
+            (operand, Some(RegType::Boolean)) if kind == IfKind::Eq => operand.negate(),
+            (operand, Some(jtype)) if jtype.is_reference() => Expr::Binary(
+                Box::new(operand),
+                op,
+                Box::new(Expr::Literal("null".into())),
+            ),
+            (operand, _) => {
+                Expr::Binary(Box::new(operand), op, Box::new(Expr::Literal("0".into())))
+            }
+        }
+    }
+
+    /// elements of the fill-array-data payload at `relative` code units from `index`
+    fn array_data(&self, index: usize, relative: i32) -> Vec<Expr> {
+        let addr = (self.method.instructions[index].offset / 2) as i64 + relative as i64;
+        let payload = self
+            .index_of
+            .get(&((addr * 2) as usize))
+            .map(|&payload| &self.method.instructions[payload].inst);
+        let Some(DalvikBytecode::FillArrayDataPayload(width, data)) = payload else {
+            return vec![];
+        };
+
+        data.chunks_exact((*width).max(1) as usize)
+            .map(|element| {
+                let mut bytes = [0; 8];
+                bytes[..element.len().min(8)].copy_from_slice(&element[..element.len().min(8)]);
+                // sign extend from the element width
+                let shift = 64 - 8 * element.len().min(8) as u32;
+                let value = (i64::from_le_bytes(bytes) << shift) >> shift;
+                Expr::Literal(match *width {
+                    8 => format!("{}L", value),
+                    _ => value.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    pub fn lift(&self, block: usize) -> LiftedBlock {
+        let range = self.cfg.blocks[block].start..self.cfg.blocks[block].end;
+        let mut state = BlockState {
+            block,
+            statements: vec![],
+            pending: BTreeMap::new(),
+            reads: BTreeSet::new(),
+        };
+        let mut exception = None;
+        let mut exit = None;
+
+        for index in range.clone() {
+            state.reads.clear();
+            if let DalvikBytecode::MoveResult(MoveKind::Exception, reg) =
+                self.method.instructions[index].inst
+            {
+                match index == range.start {
+                    true => exception = Some(self.name(reg as u16)),
+                    false => self.define(
+                        &mut state,
+                        index,
+                        Def::impure(index, reg),
+                        Expr::Local("exception".to_string()),
+                    ),
+                }
+                continue;
+            }
+            exit = self.lift_instruction(&mut state, index);
+        }
+
+        self.flush(&mut state, |_, _| true);
+        let exit = exit.unwrap_or_else(|| {
+            let last = &self.method.instructions[range.end - 1].inst;
+            match last.can_fall_through() {
+                true => Exit::Jump(self.successor(block, EdgeKind::Fallthrough)),
+                false => Exit::Jump(self.successor(block, EdgeKind::Branch)),
+            }
+        });
+
+        LiftedBlock {
+            statements: state.statements,
+            exit,
+            exception,
+        }
+    }
+
+    fn lift_instruction(&self, state: &mut BlockState, index: usize) -> Option<Exit> {
+        use DalvikBytecode as B;
+
+        let block = state.block;
+        match &self.method.instructions[index].inst {
+            B::Move(_, dst, src) => {
+                let value = self.operand(state, index, *src);
+                self.define(state, index, Def::pure(index, *dst), value);
+            }
+            B::MoveFrom16(_, dst, src) => {
+                let value = self.operand(state, index, *src);
+                self.define(state, index, Def::pure(index, *dst), value);
+            }
+            B::Move16(_, dst, src) => {
+                let value = self.operand(state, index, *src);
+                self.define(state, index, Def::pure(index, *dst), value);
+            }
+            // assigned along with the call before it
+            B::MoveResult(..) => {}
+            B::Return(ReturnKind::ReturnVoid, _) | B::ReturnVoidNoBarrier => {
+                self.emit(state, Statement::Return(None))
+            }
+            B::Return(_, reg) => {
+                let value = self.operand(state, index, *reg);
+                self.emit(state, Statement::Return(Some(value)));
+            }
+
+            B::Const4(reg, value) => {
+                let literal = self.narrow_literal(index, *value as i32);
+                self.define(state, index, Def::pure(index, *reg), literal);
+            }
+            B::Const16(reg, value) => {
+                let literal = self.narrow_literal(index, *value as i32);
+                self.define(state, index, Def::pure(index, *reg), literal);
+            }
+            B::Const(reg, value) => {
+                let literal = self.narrow_literal(index, *value);
+                self.define(state, index, Def::pure(index, *reg), literal);
+            }
+            B::ConstHigh16(reg, value) => {
+                let literal = self.narrow_literal(index, (*value as i32) << 16);
+                self.define(state, index, Def::pure(index, *reg), literal);
+            }
+            B::ConstWide16(reg, value) => {
+                let literal = self.wide_literal(index, *value as i64);
+                self.define(state, index, Def::pure(index, *reg), literal);
+            }
+            B::ConstWide32(reg, value) => {
+                let literal = self.wide_literal(index, *value as i64);
+                self.define(state, index, Def::pure(index, *reg), literal);
+            }
+            B::ConstWide(reg, value) => {
+                let literal = self.wide_literal(index, *value as i64);
+                self.define(state, index, Def::pure(index, *reg), literal);
+            }
+            B::ConstWideHigh16(reg, value) => {
+                let literal = self.wide_literal(index, (*value as i64) << 48);
+                self.define(state, index, Def::pure(index, *reg), literal);
+            }
+            B::ConstString(reg, string_idx) => {
+                let literal = self.string(*string_idx as u32);
+                self.define(state, index, Def::pure(index, *reg), literal);
+            }
+            B::ConstStringJumbo(reg, string_idx) => {
+                let literal = self.string(*string_idx);
+                self.define(state, index, Def::pure(index, *reg), literal);
+            }
+            B::ConstClass(reg, type_idx) => {
+                let literal = Expr::Literal(format!(
+                    "{}.class",
+                    Self::type_name(self.resolver, *type_idx)
+                ));
+                self.define(state, index, Def::pure(index, *reg), literal);
+            }
+            B::ConstMethodHandle(reg, handle_idx) => {
+                let literal = Expr::Literal(format!("method_handle@{}", handle_idx));
+                self.define(state, index, Def::pure(index, *reg), literal);
+            }
+            B::ConstMethodType(reg, proto_idx) => {
+                let literal = Expr::Literal(format!("method_type@{}", proto_idx));
+                self.define(state, index, Def::pure(index, *reg), literal);
+            }
+
+            B::MonitorEnter(reg) | B::MonitorExit(reg) => {
+                let name = match self.method.instructions[index].inst {
+                    B::MonitorEnter(_) => "monitorEnter",
+                    _ => "monitorExit",
+                };
+                let object = self.operand(state, index, *reg);
+                self.emit(
+                    state,
+                    Statement::Expr(Expr::Call(None, name.to_string(), vec![object])),
+                );
+            }
+            B::CheckCast(reg, type_idx) => {
+                let object = self.operand(state, index, *reg);
+                let cast = Expr::Cast(Self::type_name(self.resolver, *type_idx), Box::new(object));
+                // the cast doesn't define the register, the value keeps its definition
+                let location = Location::Register(*reg as u16);
+                let def = match self.chains.definitions(index, location) {
+                    &[Some(def_index)] => Def {
+                        def_index,
+                        exclude: Some(index),
+                        ..Def::pure(index, *reg)
+                    },
+                    _ => Def::pure(index, *reg),
+                };
+                self.define(state, index, def, cast);
+            }
+            B::InstanceOf(dst, src, type_idx) => {
+                let object = self.operand(state, index, *src);
+                let test =
+                    Expr::InstanceOf(Box::new(object), Self::type_name(self.resolver, *type_idx));
+                self.define(state, index, Def::pure(index, *dst), test);
+            }
+            B::ArrayLength(dst, src) => {
+                let array = self.operand(state, index, *src);
+                self.define(
+                    state,
+                    index,
+                    Def::pure(index, *dst),
+                    Expr::Length(Box::new(array)),
+                );
+            }
+            B::NewInstance(reg, type_idx) => {
+                // built by its constructor call
+                if !self.constructed.values().any(|&(new, _)| new == index) {
+                    let object =
+                        Expr::Literal(format!("new {}", Self::type_name(self.resolver, *type_idx)));
+                    self.define(state, index, Def::impure(index, *reg), object);
+                }
+            }
+            B::NewArray(dst, size, type_idx) => {
+                let size = self.operand(state, index, *size);
+                let jtype = Self::type_name(self.resolver, *type_idx);
+                let element = jtype.strip_suffix("[]").unwrap_or(&jtype).to_string();
+                self.define(
+                    state,
+                    index,
+                    Def::pure(index, *dst),
+                    Expr::NewArray(element, Box::new(size)),
+                );
+            }
+            B::FilledNewArray(type_idx, regs) => {
+                let mut regs = regs.iter().map(|&reg| reg as u16);
+                let elements = self.arguments(state, index, &mut regs, &[]);
+                let jtype = Self::type_name(self.resolver, *type_idx);
+                let element = jtype.strip_suffix("[]").unwrap_or(&jtype).to_string();
+                self.result(state, index, Expr::ArrayLiteral(element, elements));
+            }
+            B::FilledNewArrayRange(count, type_idx, first) => {
+                let mut regs = (0..*count as u16).map(|reg| first.wrapping_add(reg));
+                let elements = self.arguments(state, index, &mut regs, &[]);
+                let jtype = Self::type_name(self.resolver, *type_idx);
+                let element = jtype.strip_suffix("[]").unwrap_or(&jtype).to_string();
+                self.result(state, index, Expr::ArrayLiteral(element, elements));
+            }
+            B::FilledArrayData(reg, relative) => {
+                self.operand(state, index, *reg);
+                let target = Expr::Local(self.name(*reg as u16));
+                let mut element = "?".to_string();
+                // `new int[3]` right before the fill becomes `new int[]{...}`
+                if let Some(Statement::Assign(assigned, Expr::NewArray(jtype, _))) =
+                    state.statements.last()
+                {
+                    if *assigned == target {
+                        element = jtype.clone();
+                        state.statements.pop();
+                    }
+                }
+                let elements = self.array_data(index, *relative);
+                self.emit(
+                    state,
+                    Statement::Assign(target, Expr::ArrayLiteral(element, elements)),
+                );
+            }
+            B::Throw(reg) => {
+                let exception = self.operand(state, index, *reg);
+                self.emit(state, Statement::Throw(exception));
+            }
+            B::PackedSwitch(reg, _) | B::SparseSwitch(reg, _) => {
+                let value = self.operand(state, index, *reg);
+                let cases = self.cfg.blocks[block]
+                    .successors
+                    .iter()
+                    .filter_map(|edge| match edge.kind {
+                        EdgeKind::SwitchCase(key) => Some((key, edge.target)),
+                        _ => None,
+                    })
+                    .collect();
+                return Some(Exit::Switch {
+                    value,
+                    cases,
+                    default: self.successor(block, EdgeKind::Fallthrough),
+                });
+            }
+
+            B::Cmp(kind, dst, left, right) => {
+                let left = self.operand(state, index, *left);
+                let right = self.operand(state, index, *right);
+                let name = match kind {
+                    CmpKind::CmplFloat | CmpKind::CmplDouble => "cmpl",
+                    CmpKind::CmpgFloat | CmpKind::CmpgDouble => "cmpg",
+                    CmpKind::CmpLong => "cmp",
+                };
+                self.define(
+                    state,
+                    index,
+                    Def::pure(index, *dst),
+                    Expr::Compare(name, Box::new(left), Box::new(right)),
+                );
+            }
+            B::IfTest(kind, left, right, _) => {
+                let left = self.operand(state, index, *left);
+                let right = self.operand(state, index, *right);
+                let condition = Expr::Binary(Box::new(left), comparison(*kind), Box::new(right));
+                return self.branch(block, condition);
+            }
+            B::IfTestZ(kind, reg, _) => {
+                let operand = self.operand(state, index, *reg);
+                let condition = self.zero_condition(index, *kind, *reg, operand);
+                return self.branch(block, condition);
+            }
+
+            B::ArrayOp(kind, value, array, array_index) => {
+                let array = self.operand(state, index, *array);
+                let array_index = self.operand(state, index, *array_index);
+                let element = Expr::Index(Box::new(array), Box::new(array_index));
+                match kind.is_put() {
+                    true => {
+                        let value = self.operand(state, index, *value);
+                        self.emit(state, Statement::Assign(element, value));
+                    }
+                    false => self.define(state, index, Def::impure(index, *value), element),
+                }
+            }
+            B::InstanceOp(kind, value, object, field_idx) => {
+                let object = self.operand(state, index, *object);
+                let (_, name) = self.field(*field_idx);
+                self.field_access(
+                    state,
+                    index,
+                    kind,
+                    *value,
+                    Expr::Field(Box::new(object), name),
+                );
+            }
+            B::InstanceOpQuick(kind, value, object, offset) => {
+                let object = self.operand(state, index, *object);
+                let name = format!("field@{:#x}", offset);
+                self.field_access(
+                    state,
+                    index,
+                    kind,
+                    *value,
+                    Expr::Field(Box::new(object), name),
+                );
+            }
+            B::StaticOp(kind, value, field_idx) => {
+                let (class, name) = self.field(*field_idx);
+                let field = Expr::Field(Box::new(Expr::Type(class)), name);
+                self.field_access(state, index, kind, *value, field);
+            }
+
+            B::Invoke(kind, regs, method_idx) => {
+                let regs = regs.iter().map(|&reg| reg as u16).collect();
+                self.invoke(state, index, *kind, regs, *method_idx);
+            }
+            B::InvokeRange(kind, count, method_idx, first) => {
+                let regs = (0..*count as u16)
+                    .map(|reg| first.wrapping_add(reg))
+                    .collect();
+                self.invoke(state, index, *kind, regs, *method_idx);
+            }
+            B::InvokePolymorphic(regs, method_idx, _) => {
+                let regs = regs.iter().map(|&reg| reg as u16).collect();
+                self.invoke(state, index, InvokeKind::Virtual, regs, *method_idx);
+            }
+            B::InvokePolymorphicRange(count, method_idx, first, _) => {
+                let regs = (0..*count as u16)
+                    .map(|reg| first.wrapping_add(reg))
+                    .collect();
+                self.invoke(state, index, InvokeKind::Virtual, regs, *method_idx);
+            }
+            B::InvokeCustom(regs, call_site_idx) => {
+                let mut regs = regs.iter().map(|&reg| reg as u16);
+                let args = self.arguments(state, index, &mut regs, &[]);
+                let call = Expr::Call(None, format!("invoke_custom@{}", call_site_idx), args);
+                self.result(state, index, call);
+            }
+            B::InvokeCustomRange(count, call_site_idx, first) => {
+                let mut regs = (0..*count as u16).map(|reg| first.wrapping_add(reg));
+                let args = self.arguments(state, index, &mut regs, &[]);
+                let call = Expr::Call(None, format!("invoke_custom@{}", call_site_idx), args);
+                self.result(state, index, call);
+            }
+            B::InvokeVirtualQuick(regs, vtable_idx) => {
+                let regs: Vec<u16> = regs.iter().map(|&reg| reg as u16).collect();
+                self.invoke_quick(state, index, regs, *vtable_idx);
+            }
+            B::InvokeVirtualRangeQuick(count, vtable_idx, first) => {
+                let regs = (0..*count as u16)
+                    .map(|reg| first.wrapping_add(reg))
+                    .collect();
+                self.invoke_quick(state, index, regs, *vtable_idx);
+            }
+
+            B::Unop(kind, dst, src) => {
+                let operand = self.operand(state, index, *src);
+                self.define(state, index, Def::pure(index, *dst), unary(*kind, operand));
+            }
+            B::Binop(kind, dst, left, right) => {
+                let left = self.operand(state, index, *left);
+                let right = self.operand(state, index, *right);
+                let value = Expr::Binary(Box::new(left), arithmetic(*kind), Box::new(right));
+                self.define(state, index, Def::pure(index, *dst), value);
+            }
+            B::Binop2Addr(kind, dst, src) => {
+                let left = self.operand(state, index, *dst);
+                let right = self.operand(state, index, *src);
+                let value = Expr::Binary(Box::new(left), arithmetic(*kind), Box::new(right));
+                self.define(state, index, Def::pure(index, *dst), value);
+            }
+            B::BinopLit16(kind, dst, src, literal) => {
+                let operand = self.operand(state, index, *src);
+                let value = Self::literal_binop(*kind, operand, *literal as i32);
+                self.define(state, index, Def::pure(index, *dst), value);
+            }
+            B::BinopLit8(kind, dst, src, literal) => {
+                let operand = self.operand(state, index, *src);
+                let value = Self::literal_binop(*kind, operand, *literal as i32);
+                self.define(state, index, Def::pure(index, *dst), value);
+            }
+
+            B::Nop
+            | B::Goto(_)
+            | B::Goto16(_)
+            | B::Goto32(_)
+            | B::PackedSwitchPayload(..)
+            | B::SparseSwitchPayload(..)
+            | B::FillArrayDataPayload(..) => {}
+        }
+        None
+    }
+
+    fn string(&self, string_idx: u32) -> Expr {
+        Expr::Literal(match self.resolver.string(string_idx) {
+            Some(string) => string_literal(&string),
+            None => format!("string@{}", string_idx),
+        })
+    }
+
+    fn literal_binop(kind: ArithmeticKind, operand: Expr, literal: i32) -> Expr {
+        let literal = Box::new(Expr::Literal(literal.to_string()));
+        match kind {
+            ArithmeticKind::RSubInt => Expr::Binary(literal, "-", Box::new(operand)),
+            kind => Expr::Binary(Box::new(operand), arithmetic(kind), literal),
+        }
+    }
+
+    fn field_access(
+        &self,
+        state: &mut BlockState,
+        index: usize,
+        kind: &OpKind,
+        value: u8,
+        field: Expr,
+    ) {
+        match kind.is_put() {
+            true => {
+                let value = self.operand(state, index, value);
+                self.emit(state, Statement::Assign(field, value));
+            }
+            false => self.define(state, index, Def::impure(index, value), field),
+        }
+    }
+
+    fn invoke_quick(&self, state: &mut BlockState, index: usize, regs: Vec<u16>, vtable_idx: u16) {
+        let mut regs = regs.into_iter();
+        let receiver = regs.next().map(|reg| self.operand(state, index, reg));
+        let args = self.arguments(state, index, &mut regs, &[]);
+        let call = Expr::Call(
+            receiver.map(Box::new),
+            format!("vtable@{}", vtable_idx),
+            args,
+        );
+        self.result(state, index, call);
+    }
+
+    fn branch(&self, block: usize, condition: Expr) -> Option<Exit> {
+        let taken = self.successor(block, EdgeKind::Branch)?;
+        let fallthrough = self.successor(block, EdgeKind::Fallthrough)?;
+        Some(Exit::Branch {
+            condition,
+            taken,
+            fallthrough,
+        })
+    }
+}
@@ -0,0 +1,262 @@
+//! best-effort java-like source from dalvik bytecode, flow that doesn't map to
+//! if/else, loops, switch or try/catch is kept as labelled gotos
+
+pub mod ast;
+mod lift;
+mod structure;
+
+use crate::{
+    analysis::cfg::{Cfg, TryBlock},
+    container::dex_file::{DexClass, DexFile, ACC_ABSTRACT, ACC_INTERFACE, ACC_NATIVE, ACC_STATIC},
+    dalvik::DalvikInstruction,
+    resolver::{Prototype, Resolver},
+    Result, SmaliDecoder,
+};
+use ast::{write_statements, Statement};
+use lift::Lifter;
+use std::fmt::Write;
+use structure::Structurer;
+
+/// a method with code, as the decompiler needs it
+pub struct MethodBody<'a> {
+    /// descriptor of the declaring class
+    pub class: &'a str,
+    pub name: &'a str,
+    pub is_static: bool,
+    pub prototype: &'a Prototype,
+    pub registers_size: u16,
+    pub instructions: &'a [DalvikInstruction],
+    pub tries: &'a [TryBlock],
+}
+
+/// the java name of a type descriptor, `java.lang` classes go unqualified
+pub fn java_type(descriptor: &str) -> String {
+    if let Some(element) = descriptor.strip_prefix('[') {
+        return format!("{}[]", java_type(element));
+    }
+    match descriptor {
+        "V" => "void",
+        "Z" => "boolean",
+        "B" => "byte",
+        "S" => "short",
+        "C" => "char",
+        "I" => "int",
+        "J" => "long",
+        "F" => "float",
+        "D" => "double",
+        _ => {
+            let name = descriptor
+                .strip_prefix('L')
+                .and_then(|name| name.strip_suffix(';'))
+                .unwrap_or(descriptor)
+                .replace('/', ".");
+            return match name.strip_prefix("java.lang.") {
+                Some(simple) if !simple.contains('.') => simple.to_string(),
+                _ => name,
+            };
+        }
+    }
+    .to_string()
+}
+
+/// registers taken by the arguments, `this` included
+fn ins_size(prototype: &Prototype, is_static: bool) -> u16 {
+    prototype
+        .parameters
+        .iter()
+        .map(|parameter| match parameter.as_str() {
+            "J" | "D" => 2,
+            _ => 1,
+        })
+        .sum::<u16>()
+        + !is_static as u16
+}
+
+/// the register holding the first argument, arguments sit at the top of the frame
+fn first_argument(method: &MethodBody) -> u16 {
+    method
+        .registers_size
+        .saturating_sub(ins_size(method.prototype, method.is_static))
+}
+
+/// `this`, `p1`.. for arguments and `v0`.. for locals
+fn register_name(method: &MethodBody, first_argument: u16, reg: u16) -> String {
+    match reg.checked_sub(first_argument) {
+        Some(0) if !method.is_static => "this".to_string(),
+        Some(argument) if reg < method.registers_size => format!("p{}", argument),
+        _ => format!("v{}", reg),
+    }
+}
+
+/// the statements of a method body
+pub fn decompile_method(method: &MethodBody, resolver: &dyn Resolver) -> Vec<Statement> {
+    if method.instructions.is_empty() {
+        return vec![];
+    }
+    let cfg = Cfg::new(method.instructions, method.tries);
+    let lifter = Lifter::new(method, &cfg, resolver);
+    let blocks = (0..cfg.blocks.len())
+        .map(|block| lifter.lift(block))
+        .collect();
+    let addrs = cfg
+        .blocks
+        .iter()
+        .map(|block| (method.instructions[block.start].offset / 2) as u32)
+        .collect();
+    let mut statements = Structurer::new(&cfg, method.tries, resolver, blocks, addrs).structure();
+    // a void method returns at its end anyway
+    if statements.last() == Some(&Statement::Return(None)) {
+        statements.pop();
+    }
+    statements
+}
+
+/// the declaration of a method, `static int add(int p0, int p1)`
+fn method_header(method: &MethodBody) -> String {
+    let first = first_argument(method);
+    let mut reg = first + !method.is_static as u16;
+    let mut parameters = vec![];
+    for parameter in &method.prototype.parameters {
+        parameters.push(format!(
+            "{} {}",
+            java_type(parameter),
+            register_name(method, first, reg)
+        ));
+        reg += match parameter.as_str() {
+            "J" | "D" => 2,
+            _ => 1,
+        };
+    }
+
+    let simple_name = java_type(method.class);
+    let simple_name = simple_name.rsplit('.').next().unwrap_or_default();
+    match method.name {
+        "<clinit>" => "static".to_string(),
+        "<init>" => format!("{}({})", simple_name, parameters.join(", ")),
+        name => format!(
+            "{}{} {}({})",
+            if method.is_static { "static " } else { "" },
+            java_type(&method.prototype.return_type),
+            name,
+            parameters.join(", ")
+        ),
+    }
+}
+
+/// a method as java-like source, indented by `indent` levels
+pub fn method_source(method: &MethodBody, resolver: &dyn Resolver, indent: usize) -> String {
+    let pad = "    ".repeat(indent);
+    let mut out = format!("{}{} {{\n", pad, method_header(method));
+    write_statements(&mut out, &decompile_method(method, resolver), indent + 1);
+    let _ = writeln!(out, "{}}}", pad);
+    out
+}
+
+/// `class Main extends Base implements Runnable {`
+fn write_class_declaration(
+    out: &mut String,
+    access_flags: u32,
+    descriptor: &str,
+    super_class: Option<&str>,
+    interfaces: &[String],
+) {
+    let kind = match access_flags {
+        flags if flags & ACC_INTERFACE != 0 => "interface",
+        flags if flags & ACC_ABSTRACT != 0 => "abstract class",
+        _ => "class",
+    };
+    let _ = write!(out, "{} {}", kind, java_type(descriptor));
+    if let Some(super_class) = super_class.filter(|&name| name != "Ljava/lang/Object;") {
+        let _ = write!(out, " extends {}", java_type(super_class));
+    }
+    if !interfaces.is_empty() {
+        let interfaces: Vec<String> = interfaces.iter().map(|name| java_type(name)).collect();
+        let _ = write!(out, " implements {}", interfaces.join(", "));
+    }
+    out.push_str(" {\n");
+}
+
+fn write_field(out: &mut String, access_flags: u32, field_type: &str, name: &str) {
+    let _ = writeln!(
+        out,
+        "    {}{} {};",
+        if access_flags & ACC_STATIC != 0 {
+            "static "
+        } else {
+            ""
+        },
+        java_type(field_type),
+        name
+    );
+}
+
+/// abstract and native methods only have a declaration
+fn write_method_declaration(
+    out: &mut String,
+    class: &str,
+    name: &str,
+    access_flags: u32,
+    prototype: &Prototype,
+) {
+    let is_static = access_flags & ACC_STATIC != 0;
+    let body = MethodBody {
+        class,
+        name,
+        is_static,
+        prototype,
+        registers_size: ins_size(prototype, is_static),
+        instructions: &[],
+        tries: &[],
+    };
+    let modifier = match access_flags & ACC_NATIVE != 0 {
+        true => "native ",
+        false => "abstract ",
+    };
+    let _ = writeln!(out, "    {}{};", modifier, method_header(&body));
+}
+
+/// a whole class as java-like source
+pub fn class_source(dex: &dyn DexFile, class: &DexClass) -> Result<String> {
+    let mut out = String::new();
+    write_class_declaration(
+        &mut out,
+        class.access_flags,
+        &class.descriptor,
+        class.super_class.as_deref(),
+        &class.interfaces,
+    );
+
+    for field in &class.fields {
+        write_field(&mut out, field.access_flags, &field.field_type, &field.name);
+    }
+
+    for method in &class.methods {
+        out.push('\n');
+        if method.code_off == 0 {
+            write_method_declaration(
+                &mut out,
+                &class.descriptor,
+                &method.name,
+                method.access_flags,
+                &method.prototype,
+            );
+            continue;
+        }
+
+        let code = dex.code_item(method.code_off)?;
+        let instructions = SmaliDecoder::new(code.insns, dex.version()).decode_all();
+        let body = MethodBody {
+            class: &class.descriptor,
+            name: &method.name,
+            is_static: method.access_flags & ACC_STATIC != 0,
+            prototype: &method.prototype,
+            registers_size: code.registers_size,
+            instructions: &instructions,
+            tries: &code.tries,
+        };
+        out.push_str(&method_source(&body, dex, 1));
+    }
+
+    out.push_str("}\n");
+    Ok(out)
+}
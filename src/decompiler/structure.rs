@@ -0,0 +1,580 @@
+use super::{
+    ast::{Catch, Expr, Statement},
+    java_type,
+    lift::{Exit, LiftedBlock},
+};
+use crate::{
+    analysis::{
+        cfg::{Cfg, EdgeKind, TryBlock},
+        dominators::{Dominators, PostDominators},
+        loops::{find_loops, Loop},
+    },
+    resolver::Resolver,
+};
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Clone)]
+struct LoopContext {
+    /// index into the loops of the method
+    index: usize,
+    header: usize,
+    /// block after the loop, where `break` goes
+    follow: Option<usize>,
+    label: String,
+}
+
+#[derive(Clone, Default)]
+struct Context {
+    /// block the enclosing construct continues at, left for the caller to emit
+    stop: Option<usize>,
+    /// enclosing loops, innermost last
+    loops: Vec<LoopContext>,
+    /// whether a plain `break` would leave a switch rather than the innermost loop
+    in_switch: bool,
+    /// try blocks being structured
+    tries: Vec<usize>,
+}
+
+/// rebuilds if/else, loops, switches and try/catch from lifted blocks,
+/// falling back to labelled gotos where the flow isn't structured
+pub(crate) struct Structurer<'a> {
+    cfg: &'a Cfg,
+    tries: &'a [TryBlock],
+    resolver: &'a dyn Resolver,
+    blocks: Vec<Option<LiftedBlock>>,
+    /// start address of each block in code units
+    addrs: Vec<u32>,
+    dominators: Dominators,
+    post_dominators: PostDominators,
+    loops: Vec<Loop>,
+    loop_at: HashMap<usize, usize>,
+    /// labels some `goto`, `break` or `continue` refers to
+    used_labels: BTreeSet<String>,
+}
+
+fn label(block: usize) -> String {
+    format!("L{}", block)
+}
+
+impl<'a> Structurer<'a> {
+    pub fn new(
+        cfg: &'a Cfg,
+        tries: &'a [TryBlock],
+        resolver: &'a dyn Resolver,
+        blocks: Vec<LiftedBlock>,
+        addrs: Vec<u32>,
+    ) -> Self {
+        let dominators = Dominators::new(cfg);
+        let loops = find_loops(cfg, &dominators);
+
+        // branches meet again where the normal flow does, paths ending in a throw don't count
+        let throws: Vec<bool> = blocks
+            .iter()
+            .map(|lifted| {
+                matches!(lifted.exit, Exit::Jump(None))
+                    && matches!(lifted.statements.last(), Some(Statement::Throw(_)))
+            })
+            .collect();
+        let mut normal = cfg.clone();
+        for block in &mut normal.blocks {
+            block.successors.retain(|edge| {
+                !matches!(edge.kind, EdgeKind::Exception(_)) && !throws[edge.target]
+            });
+            block.predecessors.clear();
+        }
+        for block in 0..normal.blocks.len() {
+            for target in normal.blocks[block]
+                .successors
+                .iter()
+                .map(|edge| edge.target)
+                .collect::<Vec<_>>()
+            {
+                normal.blocks[target].predecessors.push(block);
+            }
+        }
+
+        Self {
+            cfg,
+            tries,
+            resolver,
+            blocks: blocks.into_iter().map(Some).collect(),
+            addrs,
+            post_dominators: PostDominators::new(&normal),
+            loop_at: loops
+                .iter()
+                .enumerate()
+                .map(|(index, natural_loop)| (natural_loop.header, index))
+                .collect(),
+            loops,
+            dominators,
+            used_labels: BTreeSet::new(),
+        }
+    }
+
+    pub fn structure(mut self) -> Vec<Statement> {
+        let mut out = vec![];
+        if !self.blocks.is_empty() {
+            self.region(Some(0), &Context::default(), &mut out);
+        }
+
+        // whatever the regions didn't reach, only through gotos or exception edges
+        for block in 0..self.blocks.len() {
+            if self.blocks[block].is_some() && self.dominators.is_reachable(block) {
+                self.used_labels.insert(label(block));
+                self.region(Some(block), &Context::default(), &mut out);
+            }
+        }
+        simplify(out, &self.used_labels)
+    }
+
+    fn region(&mut self, mut next: Option<usize>, ctx: &Context, out: &mut Vec<Statement>) {
+        while let Some(block) = next {
+            if Some(block) == ctx.stop {
+                return;
+            }
+            if let Some(jump) = self.loop_jump(block, ctx) {
+                out.push(jump);
+                return;
+            }
+            if self.blocks[block].is_none() {
+                self.used_labels.insert(label(block));
+                out.push(Statement::Goto(label(block)));
+                return;
+            }
+
+            next = match self.loop_at.get(&block) {
+                Some(&index) if !ctx.loops.iter().any(|lc| lc.header == block) => {
+                    self.structure_loop(index, ctx, out)
+                }
+                _ => match self.try_at(block, ctx) {
+                    Some(try_index) => self.structure_try(try_index, block, ctx, out),
+                    None => self.structure_block(block, ctx, out),
+                },
+            };
+        }
+    }
+
+    /// `continue` or `break` when `block` is the header or follow of an enclosing loop
+    fn loop_jump(&mut self, block: usize, ctx: &Context) -> Option<Statement> {
+        let innermost = ctx.loops.len().checked_sub(1)?;
+        for (depth, lc) in ctx.loops.iter().enumerate().rev() {
+            if block == lc.header {
+                let label = (depth != innermost).then(|| lc.label.clone());
+                self.used_labels.extend(label.clone());
+                return Some(Statement::Continue(label));
+            }
+            if Some(block) == lc.follow {
+                let label = (depth != innermost || ctx.in_switch).then(|| lc.label.clone());
+                self.used_labels.extend(label.clone());
+                return Some(Statement::Break(label));
+            }
+        }
+        None
+    }
+
+    /// the block after a loop: the post-dominator of the header outside it, else the
+    /// last exit target in code order, earlier ones are usually returns and throws
+    fn loop_follow(&self, index: usize) -> Option<usize> {
+        let natural_loop = &self.loops[index];
+        let mut block = natural_loop.header;
+        while let Some(ipdom) = self.post_dominators.immediate_post_dominator(block) {
+            if !natural_loop.contains(ipdom) {
+                return Some(ipdom);
+            }
+            block = ipdom;
+        }
+
+        natural_loop
+            .exits(self.cfg)
+            .into_iter()
+            .filter(|&(from, to)| {
+                self.cfg.blocks[from]
+                    .successors
+                    .iter()
+                    .any(|edge| edge.target == to && !matches!(edge.kind, EdgeKind::Exception(_)))
+            })
+            .map(|(_, to)| to)
+            .max()
+    }
+
+    fn structure_loop(
+        &mut self,
+        index: usize,
+        ctx: &Context,
+        out: &mut Vec<Statement>,
+    ) -> Option<usize> {
+        let header = self.loops[index].header;
+        let follow = self.loop_follow(index);
+        let loop_label = format!("loop_{}", header);
+
+        let mut inner = ctx.clone();
+        inner.stop = None;
+        inner.in_switch = false;
+        inner.loops.push(LoopContext {
+            index,
+            header,
+            follow,
+            label: loop_label.clone(),
+        });
+
+        let mut body = vec![];
+        let next = self.structure_block(header, &inner, &mut body);
+        self.region(next, &inner, &mut body);
+
+        out.push(Statement::While {
+            label: Some(loop_label),
+            condition: Expr::Literal("true".to_string()),
+            body,
+        });
+        follow
+    }
+
+    /// the outermost try block covering `block` that isn't being structured yet
+    fn try_at(&self, block: usize, ctx: &Context) -> Option<usize> {
+        let addr = self.addrs[block];
+        (0..self.tries.len())
+            .filter(|index| !ctx.tries.contains(index))
+            .filter(|&index| {
+                let try_block = &self.tries[index];
+                addr >= try_block.start_addr
+                    && addr < try_block.start_addr + try_block.insn_count as u32
+            })
+            .max_by_key(|&index| self.tries[index].insn_count)
+    }
+
+    fn block_at(&self, addr: u32) -> Option<usize> {
+        self.addrs.iter().position(|&start| start == addr)
+    }
+
+    /// the first block outside the try block its body flows to
+    fn try_follow(&self, try_index: usize) -> Option<usize> {
+        let try_block = &self.tries[try_index];
+        let covered = |block: usize| {
+            let addr = self.addrs[block];
+            addr >= try_block.start_addr
+                && addr < try_block.start_addr + try_block.insn_count as u32
+        };
+        (0..self.cfg.blocks.len())
+            .filter(|&block| covered(block))
+            .flat_map(|block| &self.cfg.blocks[block].successors)
+            .filter(|edge| !matches!(edge.kind, EdgeKind::Exception(_)) && !covered(edge.target))
+            .map(|edge| edge.target)
+            .min()
+    }
+
+    fn structure_try(
+        &mut self,
+        try_index: usize,
+        block: usize,
+        ctx: &Context,
+        out: &mut Vec<Statement>,
+    ) -> Option<usize> {
+        let follow = self.try_follow(try_index).or(ctx.stop);
+        let mut inner = ctx.clone();
+        inner.stop = follow;
+        inner.tries.push(try_index);
+
+        let mut body = vec![];
+        self.region(Some(block), &inner, &mut body);
+
+        let mut catches = vec![];
+        for handler in self.tries[try_index].handlers.clone() {
+            let Some(handler_block) = self.block_at(handler.addr) else {
+                continue;
+            };
+            let variable = self.blocks[handler_block]
+                .as_ref()
+                .and_then(|lifted| lifted.exception.clone())
+                .unwrap_or_else(|| "e".to_string());
+            let exception = match handler.type_idx {
+                Some(type_idx) => self
+                    .resolver
+                    .type_descriptor(type_idx)
+                    .map_or_else(|| format!("type@{}", type_idx), |jtype| java_type(&jtype)),
+                None => "Throwable".to_string(),
+            };
+
+            let handler_ctx = Context {
+                stop: follow,
+                ..ctx.clone()
+            };
+            let mut handler_body = vec![];
+            self.region(Some(handler_block), &handler_ctx, &mut handler_body);
+            catches.push(Catch {
+                exception,
+                variable,
+                body: handler_body,
+            });
+        }
+
+        out.push(Statement::Try { body, catches });
+        follow
+    }
+
+    /// where the branches of a conditional meet again
+    fn merge(&self, block: usize, ctx: &Context) -> Option<usize> {
+        let mut merge = self.post_dominators.immediate_post_dominator(block);
+        if let Some(stop) = ctx.stop {
+            if merge.is_none_or(|merge| {
+                merge != stop && self.post_dominators.post_dominates(merge, stop)
+            }) {
+                merge = Some(stop);
+            }
+        }
+        // leaving the loop goes through `break` instead
+        if let Some(lc) = ctx.loops.last() {
+            if merge.is_some_and(|merge| !self.loops[lc.index].contains(merge)) {
+                merge = None;
+            }
+        }
+        merge
+    }
+
+    /// the condition of a block that only tests and branches, when `block` is its only way in
+    fn condition_block(&self, block: usize) -> Option<(&Expr, usize, usize)> {
+        let lifted = self.blocks[block].as_ref()?;
+        match &lifted.exit {
+            Exit::Branch {
+                condition,
+                taken,
+                fallthrough,
+            } if lifted.statements.is_empty() && self.cfg.blocks[block].predecessors.len() == 1 => {
+                Some((condition, *taken, *fallthrough))
+            }
+            _ => None,
+        }
+    }
+
+    /// folds the blocks testing the next operand of `||` and `&&` into the condition
+    fn short_circuit(
+        &mut self,
+        mut condition: Expr,
+        mut taken: usize,
+        mut fallthrough: usize,
+    ) -> (Expr, usize, usize) {
+        let or = |left: Expr, right: Expr| Expr::Binary(Box::new(left), "||", Box::new(right));
+        let and = |left: Expr, right: Expr| Expr::Binary(Box::new(left), "&&", Box::new(right));
+        loop {
+            if let Some((next, next_taken, next_fallthrough)) = self.condition_block(fallthrough) {
+                let next = next.clone();
+                let block = fallthrough;
+                if next_taken == taken {
+                    condition = or(condition, next);
+                    fallthrough = next_fallthrough;
+                } else if next_fallthrough == taken {
+                    condition = or(condition, next.negate());
+                    fallthrough = next_taken;
+                } else {
+                    return (condition, taken, fallthrough);
+                }
+                self.blocks[block] = None;
+                continue;
+            }
+            if let Some((next, next_taken, next_fallthrough)) = self.condition_block(taken) {
+                let next = next.clone();
+                let block = taken;
+                if next_fallthrough == fallthrough {
+                    condition = and(condition, next);
+                    taken = next_taken;
+                } else if next_taken == fallthrough {
+                    condition = and(condition, next.negate());
+                    taken = next_fallthrough;
+                } else {
+                    return (condition, taken, fallthrough);
+                }
+                self.blocks[block] = None;
+                continue;
+            }
+            return (condition, taken, fallthrough);
+        }
+    }
+
+    fn structure_block(
+        &mut self,
+        block: usize,
+        ctx: &Context,
+        out: &mut Vec<Statement>,
+    ) -> Option<usize> {
+        let lifted = self.blocks[block].take().unwrap();
+        out.push(Statement::Label(label(block)));
+        out.extend(lifted.statements);
+
+        match lifted.exit {
+            Exit::Jump(next) => next,
+            Exit::Branch {
+                condition,
+                taken,
+                fallthrough,
+            } => {
+                let (condition, taken, fallthrough) =
+                    self.short_circuit(condition, taken, fallthrough);
+                let merge = self.merge(block, ctx);
+                let inner = Context {
+                    stop: merge,
+                    ..ctx.clone()
+                };
+                let mut then = vec![];
+                self.region(Some(fallthrough), &inner, &mut then);
+                let mut otherwise = vec![];
+                self.region(Some(taken), &inner, &mut otherwise);
+                out.push(Statement::If {
+                    condition: condition.negate(),
+                    then,
+                    otherwise,
+                });
+                merge
+            }
+            Exit::Switch {
+                value,
+                cases,
+                default,
+            } => {
+                let merge = self.merge(block, ctx);
+                let inner = Context {
+                    stop: merge,
+                    in_switch: true,
+                    ..ctx.clone()
+                };
+
+                let mut targets: Vec<(Vec<i32>, usize)> = vec![];
+                for (key, target) in cases {
+                    match targets.iter_mut().find(|(_, other)| *other == target) {
+                        Some((keys, _)) => keys.push(key),
+                        None => targets.push((vec![key], target)),
+                    }
+                }
+                targets.sort_by_key(|&(_, target)| target);
+
+                let mut switch_cases = vec![];
+                for (keys, target) in targets {
+                    let mut body = vec![];
+                    // a case running the default code shares its statements
+                    if Some(target) != default {
+                        self.region(Some(target), &inner, &mut body);
+                    }
+                    if body.last().is_none_or(|last| !last.is_jump()) && Some(target) != default {
+                        body.push(Statement::Break(None));
+                    }
+                    switch_cases.push((keys, body));
+                }
+                let mut default_body = vec![];
+                self.region(default, &inner, &mut default_body);
+
+                out.push(Statement::Switch {
+                    value,
+                    cases: switch_cases,
+                    default: default_body,
+                });
+                merge
+            }
+        }
+    }
+}
+
+/// removes unused labels and empty branches and turns `while (true) { if (c) break; ... }`
+/// into `while (!c) { ... }`
+fn simplify(statements: Vec<Statement>, used: &BTreeSet<String>) -> Vec<Statement> {
+    let mut out = vec![];
+    for statement in statements {
+        match statement {
+            Statement::Label(name) if !used.contains(&name) => {}
+            Statement::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let (mut condition, mut then, mut otherwise) =
+                    (condition, simplify(then, used), simplify(otherwise, used));
+                // the shorter branch leaving early goes first, the other one follows the if
+                let jumps = |branch: &[Statement]| branch.last().is_some_and(Statement::is_jump);
+                if then.is_empty() && !otherwise.is_empty()
+                    || jumps(&otherwise) && (!jumps(&then) || otherwise.len() < then.len())
+                {
+                    condition = condition.negate();
+                    std::mem::swap(&mut then, &mut otherwise);
+                }
+                // `if (c) { return; } else { ... }` reads better without the else
+                let flattened = match jumps(&then) {
+                    true => std::mem::take(&mut otherwise),
+                    false => vec![],
+                };
+                out.push(Statement::If {
+                    condition,
+                    then,
+                    otherwise,
+                });
+                out.extend(flattened);
+            }
+            Statement::While {
+                label,
+                condition,
+                body,
+            } => {
+                let mut body = simplify(body, used);
+                strip_continue(&mut body);
+                let mut condition = condition;
+                if condition == Expr::Literal("true".to_string()) {
+                    if let Some(Statement::If {
+                        then, otherwise, ..
+                    }) = body.first()
+                    {
+                        if then == &[Statement::Break(None)] && otherwise.is_empty() {
+                            let Statement::If {
+                                condition: exit, ..
+                            } = body.remove(0)
+                            else {
+                                unreachable!()
+                            };
+                            condition = exit.negate();
+                        }
+                    }
+                }
+                out.push(Statement::While {
+                    label: label.filter(|label| used.contains(label)),
+                    condition,
+                    body,
+                });
+            }
+            Statement::Switch {
+                value,
+                cases,
+                default,
+            } => out.push(Statement::Switch {
+                value,
+                cases: cases
+                    .into_iter()
+                    .map(|(keys, body)| (keys, simplify(body, used)))
+                    .collect(),
+                default: simplify(default, used),
+            }),
+            Statement::Try { body, catches } => out.push(Statement::Try {
+                body: simplify(body, used),
+                catches: catches
+                    .into_iter()
+                    .map(|catch| Catch {
+                        body: simplify(catch.body, used),
+                        ..catch
+                    })
+                    .collect(),
+            }),
+            statement => out.push(statement),
+        }
+    }
+    out
+}
+
+/// drops the `continue` a loop body ends with, the loop goes round anyway
+fn strip_continue(body: &mut Vec<Statement>) {
+    match body.last_mut() {
+        Some(Statement::Continue(None)) => {
+            body.pop();
+        }
+        Some(Statement::If {
+            then, otherwise, ..
+        }) => {
+            strip_continue(then);
+            strip_continue(otherwise);
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,171 @@
+//! whole dex files as smali text, one class at a time. with the `parallel` feature the
+//! classes of every dex file are disassembled on a rayon thread pool.
+
+use crate::{
+    container::dex_file::{DexClass, DexFile},
+    dalvik::smali::method_text,
+    Result, SmaliDecoder,
+};
+use std::{collections::HashSet, fmt::Write, path::PathBuf};
+#[cfg(feature = "parallel")]
+use {
+    rayon::prelude::*,
+    std::{fs, io, path::Path},
+};
+
+/// a class of the `dex_index`th dex file as smali
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmaliClass {
+    pub dex_index: usize,
+    pub descriptor: String,
+    pub text: String,
+}
+
+impl SmaliClass {
+    /// where baksmali/apktool put the class: `smali/com/example/Main.smali` for the first dex
+    /// file, `smali_classes2/...` for the second and so on. `.` and `..` components of the
+    /// descriptor are dropped so a crafted class name can not escape the output directory,
+    /// which means different descriptors may share a path, see `unique_paths`.
+    pub fn path(&self) -> PathBuf {
+        class_path(self.dex_index, &self.descriptor, None)
+    }
+}
+
+fn class_path(dex_index: usize, descriptor: &str, suffix: Option<usize>) -> PathBuf {
+    let mut path = PathBuf::from(match dex_index {
+        0 => "smali".to_string(),
+        index => format!("smali_classes{}", index + 1),
+    });
+    let name = descriptor.trim_start_matches('L').trim_end_matches(';');
+    let mut components: Vec<&str> = name
+        .split('/')
+        .filter(|component| !matches!(*component, "" | "." | ".."))
+        .collect();
+    let name = components.pop().unwrap_or("_");
+    // not `set_extension`, inner class names may hold a `.`
+    let file = match suffix {
+        Some(suffix) => format!("{}.{}.smali", name, suffix),
+        None => format!("{}.smali", name),
+    };
+    path.extend(components);
+    path.push(file);
+    path
+}
+
+/// `SmaliClass::path` of every class, in order. a class whose path was already given to an
+/// earlier class gets the first free `Name.2.smali`, `Name.3.smali`, ... instead, paths
+/// differing only in case count as taken too for the sake of case insensitive file systems.
+pub fn unique_paths(classes: &[SmaliClass]) -> Vec<PathBuf> {
+    dedup_paths(
+        classes
+            .iter()
+            .map(|class| (class.dex_index, class.descriptor.as_str())),
+    )
+}
+
+fn dedup_paths<'a>(classes: impl IntoIterator<Item = (usize, &'a str)>) -> Vec<PathBuf> {
+    let mut taken = HashSet::new();
+    classes
+        .into_iter()
+        .map(|(dex_index, descriptor)| {
+            let mut path = class_path(dex_index, descriptor, None);
+            let mut suffix = 1;
+            while !taken.insert(path.to_string_lossy().to_lowercase()) {
+                suffix += 1;
+                path = class_path(dex_index, descriptor, Some(suffix));
+            }
+            path
+        })
+        .collect()
+}
+
+/// a class with its methods, abstract and native methods have no body
+pub fn class_text(dex: &dyn DexFile, class: &DexClass) -> Result<String> {
+    let mut out = String::new();
+    let _ = writeln!(out, ".class {}", class.descriptor);
+    if let Some(super_class) = &class.super_class {
+        let _ = writeln!(out, ".super {}", super_class);
+    }
+    for method in &class.methods {
+        let name = format!("{}{}", method.name, method.prototype.descriptor());
+        out.push('\n');
+        if method.code_off == 0 {
+            let _ = writeln!(out, ".method {}\n.end method", name);
+            continue;
+        }
+        let code = dex.code_item(method.code_off)?;
+        let instructions = SmaliDecoder::new(code.insns, dex.version()).decode_all();
+        out.push_str(&method_text(
+            &name,
+            code.registers_size,
+            &instructions,
+            &code.tries,
+            dex,
+        ));
+    }
+    Ok(out)
+}
+
+/// every class of every dex file, in dex file then class definition order
+#[cfg(feature = "parallel")]
+fn classes<'a>(
+    dex_files: &'a [Box<dyn DexFile + '_>],
+) -> Result<Vec<(usize, &'a dyn DexFile, DexClass)>> {
+    let classes = dex_files
+        .par_iter()
+        .map(|dex| dex.classes())
+        .collect::<Result<Vec<_>>>()?;
+    Ok(classes
+        .into_iter()
+        .enumerate()
+        .flat_map(|(dex_index, classes)| {
+            let dex = dex_files[dex_index].as_ref();
+            classes
+                .into_iter()
+                .map(move |class| (dex_index, dex, class))
+        })
+        .collect())
+}
+
+/// disassemble every class on the current rayon thread pool, the classes come back in the
+/// same order a serial loop would produce them
+#[cfg(feature = "parallel")]
+pub fn disassemble_all(dex_files: &[Box<dyn DexFile + '_>]) -> Result<Vec<SmaliClass>> {
+    classes(dex_files)?
+        .into_par_iter()
+        .map(|(dex_index, dex, class)| {
+            Ok(SmaliClass {
+                dex_index,
+                text: class_text(dex, &class)?,
+                descriptor: class.descriptor,
+            })
+        })
+        .collect()
+}
+
+/// disassemble every class and write it to its `unique_paths` path under `out`, classes are
+/// written as soon as they are done instead of being kept in memory
+#[cfg(feature = "parallel")]
+pub fn write_all(dex_files: &[Box<dyn DexFile + '_>], out: &Path) -> io::Result<()> {
+    let invalid = |error: crate::errors::Error| io::Error::new(io::ErrorKind::InvalidData, error);
+
+    // paths are picked up front so colliding classes are named the same way on every run
+    let classes = classes(dex_files).map_err(invalid)?;
+    let paths = dedup_paths(
+        classes
+            .iter()
+            .map(|(dex_index, _, class)| (*dex_index, class.descriptor.as_str())),
+    );
+
+    classes
+        .into_par_iter()
+        .zip(paths)
+        .try_for_each(|((_, dex, class), path)| {
+            let text = class_text(dex, &class).map_err(invalid)?;
+            let path = out.join(path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, text)
+        })
+}
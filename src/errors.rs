@@ -1,6 +1,41 @@
+use core::fmt;
+
 #[derive(Debug)]
 pub enum Error {
     InvalidOpcode,
     InvalidPseudoOpcode,
     ReadByteFailed,
+    UnsupportedOpcode,
+    MissingQuickeningInfo,
+    InvalidQuickeningInfo,
+    InvalidContainer,
+    UnsupportedContainerVersion,
+    InvalidClassFile,
+    InvalidArchive,
+    InvalidCache,
 }
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// an instruction that could not be decoded, `offset` is in bytes from the start of the code
+#[derive(Debug)]
+pub struct DecodeError {
+    pub offset: usize,
+    pub error: Error,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} at {:#x}", self.error, self.offset)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
@@ -0,0 +1,85 @@
+use crate::{
+    analysis::xrefs::{reference, XrefTarget},
+    container::dex_file::DexFile,
+    dalvik::{opcodes::DalvikBytecode, smali::instruction_text, DalvikInstruction},
+    resolver::Resolver,
+    Result, SmaliDecoder,
+};
+use serde::{Deserialize, Serialize};
+
+/// a decoded instruction with the operands it refers to resolved
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstructionRecord {
+    /// byte offset in the method's code
+    pub offset: usize,
+    pub inst: DalvikBytecode,
+    /// smali text, `invoke-virtual {v0}, Lcom/example/Foo;->run()V`
+    pub text: String,
+    /// the string, type, field or method the instruction refers to
+    pub reference: Option<XrefTarget>,
+}
+
+impl InstructionRecord {
+    pub fn new(instruction: &DalvikInstruction, resolver: &dyn Resolver) -> Self {
+        Self {
+            offset: instruction.offset,
+            inst: instruction.inst.clone(),
+            text: instruction_text(instruction, resolver),
+            reference: reference(&instruction.inst, resolver).map(|(target, _)| target),
+        }
+    }
+}
+
+/// one method with code, a json object per method in the cli output
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MethodRecord {
+    /// type descriptor of the declaring class
+    pub class: String,
+    pub name: String,
+    /// method descriptor, `(ILjava/lang/String;)V`
+    pub signature: String,
+    pub registers: u16,
+    pub instructions: Vec<InstructionRecord>,
+}
+
+impl MethodRecord {
+    pub fn new(
+        class: &str,
+        name: &str,
+        signature: &str,
+        registers: u16,
+        instructions: &[DalvikInstruction],
+        resolver: &dyn Resolver,
+    ) -> Self {
+        Self {
+            class: class.to_string(),
+            name: name.to_string(),
+            signature: signature.to_string(),
+            registers,
+            instructions: instructions
+                .iter()
+                .map(|instruction| InstructionRecord::new(instruction, resolver))
+                .collect(),
+        }
+    }
+}
+
+/// a record for every method with code of a dex file
+pub fn dex_methods(dex: &dyn DexFile) -> Result<Vec<MethodRecord>> {
+    let mut records = vec![];
+    for class in dex.classes()? {
+        for method in class.methods.iter().filter(|method| method.code_off != 0) {
+            let code = dex.code_item(method.code_off)?;
+            let instructions = SmaliDecoder::new(code.insns, dex.version()).decode_all();
+            records.push(MethodRecord::new(
+                &class.descriptor,
+                &method.name,
+                &method.prototype.descriptor(),
+                code.registers_size,
+                &instructions,
+                dex,
+            ));
+        }
+    }
+    Ok(records)
+}
@@ -0,0 +1,371 @@
+use crate::{
+    container::{read_u16, read_u32},
+    dalvik::version::{DEX_MAGIC, ENDIAN_CONSTANT},
+};
+use sha1::{Digest, Sha1};
+use std::cmp::Ordering;
+
+const HEADER_SIZE: u32 = 0x70;
+const REVERSE_ENDIAN_CONSTANT: u32 = 0x78563412;
+
+const CHECKSUM_OFFSET: usize = 0x8;
+const SIGNATURE_OFFSET: usize = 0xc;
+const SIGNED_DATA_OFFSET: usize = 0x20;
+const MAP_OFFSET: usize = 0x34;
+
+// map item types
+pub(crate) const TYPE_HEADER_ITEM: u16 = 0x0000;
+pub(crate) const TYPE_STRING_ID_ITEM: u16 = 0x0001;
+pub(crate) const TYPE_TYPE_ID_ITEM: u16 = 0x0002;
+pub(crate) const TYPE_PROTO_ID_ITEM: u16 = 0x0003;
+pub(crate) const TYPE_FIELD_ID_ITEM: u16 = 0x0004;
+pub(crate) const TYPE_METHOD_ID_ITEM: u16 = 0x0005;
+pub(crate) const TYPE_CLASS_DEF_ITEM: u16 = 0x0006;
+pub(crate) const TYPE_CALL_SITE_ID_ITEM: u16 = 0x0007;
+pub(crate) const TYPE_METHOD_HANDLE_ITEM: u16 = 0x0008;
+pub(crate) const TYPE_MAP_LIST: u16 = 0x1000;
+
+/// the id tables whose ordering the dex format mandates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdTable {
+    StringIds,
+    TypeIds,
+    ProtoIds,
+    FieldIds,
+    MethodIds,
+}
+
+impl IdTable {
+    /// offset of the (size, offset) pair describing the table in the header
+    fn header_offset(&self) -> usize {
+        match self {
+            Self::StringIds => 0x38,
+            Self::TypeIds => 0x40,
+            Self::ProtoIds => 0x48,
+            Self::FieldIds => 0x50,
+            Self::MethodIds => 0x58,
+        }
+    }
+
+    fn map_type(&self) -> u16 {
+        match self {
+            Self::StringIds => TYPE_STRING_ID_ITEM,
+            Self::TypeIds => TYPE_TYPE_ID_ITEM,
+            Self::ProtoIds => TYPE_PROTO_ID_ITEM,
+            Self::FieldIds => TYPE_FIELD_ID_ITEM,
+            Self::MethodIds => TYPE_METHOD_ID_ITEM,
+        }
+    }
+}
+
+/// a single problem found while verifying a dex file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Finding {
+    BadMagic,
+    /// (header file_size, actual buffer size)
+    FileSizeMismatch(u32, usize),
+    HeaderSizeMismatch(u32),
+    /// the endian tag is swapped, the file is big endian
+    ReverseEndianTag,
+    BadEndianTag(u32),
+    /// (header checksum, computed adler-32)
+    ChecksumMismatch(u32, u32),
+    /// (header signature, computed sha-1)
+    SignatureMismatch([u8; 20], [u8; 20]),
+    MapListOutOfBounds(u32),
+    /// (item type, offset, size in items)
+    MapItemOutOfBounds(u16, u32, u32),
+    /// (first item type, second item type), the first section runs into the second
+    MapItemsOverlap(u16, u16),
+    /// the item type appearing before a lower offset item
+    MapItemsUnsorted(u16),
+    DuplicateMapItem(u16),
+    /// a section described by the header is missing from the map or disagrees with it
+    MapHeaderMismatch(u16),
+    /// (table, index) where the item at index is not strictly greater than the one before it
+    UnsortedIds(IdTable, u32),
+}
+
+/// verify the header, checksum, signature, map list and id table ordering of a dex file.
+/// every problem found is reported rather than stopping at the first one.
+pub fn verify(data: &[u8]) -> Vec<Finding> {
+    let mut findings = vec![];
+
+    if data.len() < HEADER_SIZE as usize {
+        findings.push(Finding::FileSizeMismatch(0, data.len()));
+        return findings;
+    }
+
+    verify_header(data, &mut findings);
+    verify_map_list(data, &mut findings);
+
+    // the id tables can only be walked when they are in bounds
+    for table in [
+        IdTable::StringIds,
+        IdTable::TypeIds,
+        IdTable::ProtoIds,
+        IdTable::FieldIds,
+        IdTable::MethodIds,
+    ] {
+        verify_id_ordering(data, table, &mut findings);
+    }
+
+    findings
+}
+
+/// the sha-1 signature stored in the header, as is: it is not checked against the file
+pub fn signature(data: &[u8]) -> Option<[u8; 20]> {
+    data.get(SIGNATURE_OFFSET..SIGNED_DATA_OFFSET)?
+        .try_into()
+        .ok()
+}
+
+/// the sha-1 of everything after the signature, what the header signature should hold
+pub fn computed_signature(data: &[u8]) -> Option<[u8; 20]> {
+    Some(Sha1::digest(data.get(SIGNED_DATA_OFFSET..)?).into())
+}
+
+/// adler-32 as used by the dex header checksum
+pub fn adler32(data: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+    // largest chunk for which the sums can't overflow a u32
+    const CHUNK: usize = 5552;
+
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(CHUNK) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD_ADLER;
+        b %= MOD_ADLER;
+    }
+
+    (b << 16) | a
+}
+
+fn verify_header(data: &[u8], findings: &mut Vec<Finding>) {
+    let version = &data[4..8];
+    if &data[..4] != DEX_MAGIC || !version[..3].iter().all(u8::is_ascii_digit) || version[3] != 0 {
+        findings.push(Finding::BadMagic);
+    }
+
+    let file_size = read_u32(data, 0x20).unwrap_or(0);
+    if file_size as usize != data.len() {
+        findings.push(Finding::FileSizeMismatch(file_size, data.len()));
+    }
+
+    let header_size = read_u32(data, 0x24).unwrap_or(0);
+    if header_size != HEADER_SIZE {
+        findings.push(Finding::HeaderSizeMismatch(header_size));
+    }
+
+    match read_u32(data, 0x28).unwrap_or(0) {
+        ENDIAN_CONSTANT => {}
+        REVERSE_ENDIAN_CONSTANT => findings.push(Finding::ReverseEndianTag),
+        tag => findings.push(Finding::BadEndianTag(tag)),
+    }
+
+    let checksum = read_u32(data, CHECKSUM_OFFSET).unwrap_or(0);
+    let computed = adler32(&data[SIGNATURE_OFFSET..]);
+    if checksum != computed {
+        findings.push(Finding::ChecksumMismatch(checksum, computed));
+    }
+
+    let mut signature = [0u8; 20];
+    signature.copy_from_slice(&data[SIGNATURE_OFFSET..SIGNED_DATA_OFFSET]);
+    let computed = computed_signature(data).unwrap_or_default();
+    if signature != computed {
+        findings.push(Finding::SignatureMismatch(signature, computed));
+    }
+}
+
+/// size in bytes of one item of a section, `None` for variable sized data items
+fn item_size(item_type: u16) -> Option<u32> {
+    match item_type {
+        TYPE_HEADER_ITEM => Some(HEADER_SIZE),
+        TYPE_STRING_ID_ITEM | TYPE_TYPE_ID_ITEM | TYPE_CALL_SITE_ID_ITEM => Some(4),
+        TYPE_PROTO_ID_ITEM => Some(12),
+        TYPE_FIELD_ID_ITEM | TYPE_METHOD_ID_ITEM | TYPE_METHOD_HANDLE_ITEM => Some(8),
+        TYPE_CLASS_DEF_ITEM => Some(32),
+        _ => None,
+    }
+}
+
+fn verify_map_list(data: &[u8], findings: &mut Vec<Finding>) {
+    let map_off = read_u32(data, MAP_OFFSET).unwrap_or(0);
+    let map_end = |size: u32| {
+        (size as usize)
+            .checked_mul(12)?
+            .checked_add(map_off as usize)?
+            .checked_add(4)
+    };
+    let map_size = match read_u32(data, map_off as usize) {
+        Ok(size) if map_end(size).is_some_and(|end| end <= data.len()) => size,
+        _ => {
+            findings.push(Finding::MapListOutOfBounds(map_off));
+            return;
+        }
+    };
+
+    // (type, offset, size, end) of every map item
+    let mut items: Vec<(u16, u32, u32, Option<u64>)> = vec![];
+    for index in 0..map_size as usize {
+        let item = map_off as usize + 4 + index * 12;
+        let item_type = read_u16(data, item).unwrap_or(0);
+        let size = read_u32(data, item + 4).unwrap_or(0);
+        let offset = read_u32(data, item + 8).unwrap_or(0);
+
+        let end = match item_type {
+            TYPE_MAP_LIST => Some(offset as u64 + 4 + size as u64 * 12),
+            _ => {
+                item_size(item_type).map(|item_size| offset as u64 + item_size as u64 * size as u64)
+            }
+        };
+
+        if offset as usize >= data.len() || end.is_some_and(|end| end > data.len() as u64) {
+            findings.push(Finding::MapItemOutOfBounds(item_type, offset, size));
+        }
+
+        if items.iter().any(|&(other, ..)| other == item_type) {
+            findings.push(Finding::DuplicateMapItem(item_type));
+        }
+
+        if let Some(&(previous_type, previous_offset, _, previous_end)) = items.last() {
+            if offset < previous_offset {
+                findings.push(Finding::MapItemsUnsorted(item_type));
+            } else if previous_end.is_some_and(|previous_end| previous_end > offset as u64) {
+                findings.push(Finding::MapItemsOverlap(previous_type, item_type));
+            }
+        }
+
+        items.push((item_type, offset, size, end));
+    }
+
+    // every non empty id table in the header must match its map entry
+    for table in [
+        IdTable::StringIds,
+        IdTable::TypeIds,
+        IdTable::ProtoIds,
+        IdTable::FieldIds,
+        IdTable::MethodIds,
+    ] {
+        let size = read_u32(data, table.header_offset()).unwrap_or(0);
+        let offset = read_u32(data, table.header_offset() + 4).unwrap_or(0);
+        let in_map = items.iter().any(|&(item_type, item_offset, item_size, _)| {
+            item_type == table.map_type() && item_offset == offset && item_size == size
+        });
+
+        if size != 0 && !in_map {
+            findings.push(Finding::MapHeaderMismatch(table.map_type()));
+        }
+    }
+}
+
+fn verify_id_ordering(data: &[u8], table: IdTable, findings: &mut Vec<Finding>) {
+    let size = read_u32(data, table.header_offset()).unwrap_or(0);
+    let offset = read_u32(data, table.header_offset() + 4).unwrap_or(0) as usize;
+    let item_size = item_size(table.map_type()).unwrap_or(0) as usize;
+
+    // out of bounds tables are reported by the map list check
+    let end = (size as usize)
+        .checked_mul(item_size)
+        .and_then(|len| offset.checked_add(len));
+    if end.is_none_or(|end| end > data.len()) {
+        return;
+    }
+
+    for index in 1..size {
+        let previous = offset + (index as usize - 1) * item_size;
+        let current = previous + item_size;
+
+        let ordering = match table {
+            IdTable::StringIds => compare_strings(data, previous, current),
+            IdTable::TypeIds => read_u32(data, previous)
+                .ok()
+                .zip(read_u32(data, current).ok())
+                .map(|(previous, current)| previous.cmp(&current)),
+            IdTable::ProtoIds => compare_protos(data, previous, current),
+            // (class, name, type) for fields and (class, name, proto) for methods
+            IdTable::FieldIds | IdTable::MethodIds => Some(
+                read_u16(data, previous)
+                    .ok()
+                    .cmp(&read_u16(data, current).ok())
+                    .then(
+                        read_u32(data, previous + 4)
+                            .ok()
+                            .cmp(&read_u32(data, current + 4).ok()),
+                    )
+                    .then(
+                        read_u16(data, previous + 2)
+                            .ok()
+                            .cmp(&read_u16(data, current + 2).ok()),
+                    ),
+            ),
+        };
+
+        if ordering != Some(Ordering::Less) {
+            findings.push(Finding::UnsortedIds(table, index));
+        }
+    }
+}
+
+/// string ids are sorted by the utf-16 code points of their contents
+fn compare_strings(data: &[u8], previous: usize, current: usize) -> Option<Ordering> {
+    let string = |id: usize| -> Option<Vec<u16>> {
+        let mut position = read_u32(data, id).ok()? as usize;
+        crate::leb128::read_uleb128(data, &mut position).ok()?;
+        let mutf8 = data.get(position..)?.split(|&byte| byte == 0).next()?;
+        Some(decode_mutf8(mutf8))
+    };
+
+    Some(string(previous)?.cmp(&string(current)?))
+}
+
+/// protos are sorted by return type and then by their parameter type lists
+fn compare_protos(data: &[u8], previous: usize, current: usize) -> Option<Ordering> {
+    let parameters = |proto: usize| -> Option<Vec<u16>> {
+        let type_list = read_u32(data, proto + 8).ok()? as usize;
+        if type_list == 0 {
+            return Some(vec![]);
+        }
+
+        let size = read_u32(data, type_list).ok()? as usize;
+        (0..size)
+            .map(|index| read_u16(data, type_list + 4 + index * 2).ok())
+            .collect()
+    };
+
+    Some(
+        read_u32(data, previous + 4)
+            .ok()?
+            .cmp(&read_u32(data, current + 4).ok()?)
+            .then(parameters(previous)?.cmp(&parameters(current)?)),
+    )
+}
+
+/// decode modified utf-8 into utf-16 code units
+fn decode_mutf8(bytes: &[u8]) -> Vec<u16> {
+    let mut units = vec![];
+    let mut position = 0;
+
+    while position < bytes.len() {
+        let byte = bytes[position] as u16;
+        let continuation =
+            |offset: usize| bytes.get(position + offset).map_or(0, |&b| b as u16 & 0x3f);
+
+        let (unit, length) = match byte {
+            0x00..=0x7f => (byte, 1),
+            0xc0..=0xdf => (((byte & 0x1f) << 6) | continuation(1), 2),
+            _ => (
+                ((byte & 0x0f) << 12) | (continuation(1) << 6) | continuation(2),
+                3,
+            ),
+        };
+
+        units.push(unit);
+        position += length;
+    }
+
+    units
+}
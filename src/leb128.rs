@@ -0,0 +1,51 @@
+use crate::{errors::Error, Result};
+
+/// read an unsigned LEB128 value starting at `*pos`, advancing `*pos` past it
+pub(crate) fn read_uleb128(data: &[u8], pos: &mut usize) -> Result<u32> {
+    let mut result = 0u32;
+
+    for shift in (0..35).step_by(7) {
+        let byte = *data.get(*pos).ok_or(Error::ReadByteFailed)?;
+        *pos += 1;
+
+        result |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(result);
+        }
+    }
+
+    Err(Error::ReadByteFailed)
+}
+
+/// append `value` as unsigned LEB128
+#[cfg(feature = "std")]
+pub(crate) fn write_uleb128(out: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        out.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// read a signed LEB128 value starting at `*pos`, advancing `*pos` past it
+#[cfg(feature = "std")]
+pub(crate) fn read_sleb128(data: &[u8], pos: &mut usize) -> Result<i32> {
+    let mut result = 0i32;
+
+    for shift in (0..35).step_by(7) {
+        let byte = *data.get(*pos).ok_or(Error::ReadByteFailed)?;
+        *pos += 1;
+
+        result |= ((byte & 0x7f) as i32) << shift;
+        if byte & 0x80 == 0 {
+            // sign extend from the last byte read
+            let bits = shift + 7;
+            return Ok(match bits < 32 {
+                true => (result << (32 - bits)) >> (32 - bits),
+                false => result,
+            });
+        }
+    }
+
+    Err(Error::ReadByteFailed)
+}
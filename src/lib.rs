@@ -1,26 +1,73 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod analysis;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "std")]
+pub mod container;
 pub mod dalvik;
+#[cfg(feature = "std")]
+pub mod decompiler;
+#[cfg(feature = "std")]
+pub mod disassembly;
 pub mod errors;
+#[cfg(feature = "serde")]
+pub mod export;
+#[cfg(feature = "std")]
+pub mod integrity;
+mod leb128;
+pub mod resolver;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 use dalvik::bytecode_format::DexInstructionFormatReader;
+use dalvik::version::DexVersion;
 use dalvik::DalvikInstruction;
+use errors::DecodeError;
 
-pub type Result<T> = std::result::Result<T, errors::Error>;
+use alloc::{vec, vec::Vec};
+
+pub type Result<T> = core::result::Result<T, errors::Error>;
 pub struct SmaliDecoder<'a> {
     stream: &'a [u8],
+    version: Option<DexVersion>,
+    quickened: bool,
 }
 
 impl<'a> SmaliDecoder<'a> {
-    pub fn new(stream: &'a [u8]) -> Self {
-        Self { stream }
+    /// `version` is the dex version of the file the code came from (see `DexVersion::from_magic`),
+    /// when it is `None` every known opcode is accepted.
+    pub fn new(stream: &'a [u8], version: Option<DexVersion>) -> Self {
+        Self {
+            stream,
+            version,
+            quickened: false,
+        }
     }
 
-    /// decode all instruction
+    /// opt in to decoding the odex/vdex quickened opcodes (`iget-quick`, `invoke-virtual-quick`, ...),
+    /// use `dalvik::quickening::dequicken` to turn them back into regular instructions.
+    pub fn with_quickened(mut self, quickened: bool) -> Self {
+        self.quickened = quickened;
+        self
+    }
+
+    pub fn version(&self) -> Option<DexVersion> {
+        self.version
+    }
+
+    /// decode all instruction, stops quietly at the first one that can not be decoded.
+    /// use `try_decode_all` to tell that apart from the end of the code.
     pub fn decode_all(&self) -> Vec<DalvikInstruction> {
         // vector to hold instructions
         let mut instructions = vec![];
 
         // new reader so we start at the beginning
-        let mut new_reader = DexInstructionFormatReader::new(&self.stream);
+        let mut new_reader = DexInstructionFormatReader::new(self.stream, self.version)
+            .with_quickened(self.quickened);
 
         // loop until finishing decoding all instructions
         while let Ok(inst) = DalvikInstruction::decode_instruction(&mut new_reader) {
@@ -30,4 +77,21 @@ impl<'a> SmaliDecoder<'a> {
         // decode all instructions
         instructions
     }
+
+    /// decode all instructions, failing with the offset of the first one that is invalid,
+    /// truncated or illegal for the dex version
+    pub fn try_decode_all(&self) -> core::result::Result<Vec<DalvikInstruction>, DecodeError> {
+        let mut instructions = vec![];
+        let mut reader = DexInstructionFormatReader::new(self.stream, self.version)
+            .with_quickened(self.quickened);
+
+        while !reader.is_at_end() {
+            let offset = reader.position();
+            match DalvikInstruction::decode_instruction(&mut reader) {
+                Ok(inst) => instructions.push(inst),
+                Err(error) => return Err(DecodeError { offset, error }),
+            }
+        }
+        Ok(instructions)
+    }
 }
@@ -1,30 +1,547 @@
-use smali_disassembler::SmaliDecoder;
-use std::{fs::File, io::Read};
-use zip::ZipArchive;
+use smali_disassembler::{
+    analysis::{
+        cache::AnalysisCache,
+        cfg::Cfg,
+        dot::{cfg_to_dot, Overlay},
+        hierarchy::ClassHierarchy,
+        xrefs::{XrefIndex, XrefTarget},
+    },
+    container::{
+        self,
+        dex_file::{self, DexClass, DexFile},
+        input::InputFile,
+        DexKind, EmbeddedDex,
+    },
+    dalvik::smali::{instruction_text, method_text},
+    decompiler,
+    errors::Error,
+    integrity, SmaliDecoder,
+};
+use std::{
+    borrow::Cow,
+    env, fs,
+    io::{BufReader, Cursor},
+    path::{Path, PathBuf},
+};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let zip_file = File::open("tmp/test.apk")?;
-    let mut file_data = vec![];
-    let mut archive = ZipArchive::new(zip_file)?;
-
-    let mut dex_file = archive.by_name("classes.dex")?;
-    dex_file.read_to_end(&mut file_data)?;
-    let dex_object = dex::DexReader::from_vec(file_data)?;
-
-    for c in dex_object.classes() {
-        let c = c.unwrap();
-
-        for method in c.methods() {
-            let class_name = dex_object.get_type(c.id())?.to_string();
-            if let Some(code) = method.code() {
-                println!("{}->{}", class_name, method.name().to_string());
-                let code: Vec<u8> = code
-                    .insns()
-                    .iter()
-                    .flat_map(|num| num.to_ne_bytes())
-                    .collect();
-                let decoder = SmaliDecoder::new(&code);
-                let _please = decoder.decode_all();
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("xrefs") => return xrefs(&args[1..]),
+        Some("decompile") => return decompile(&args[1..]),
+        Some("cfg") => return cfg(&args[1..]),
+        Some("dump") => return dump(&args[1..]),
+        Some("disassemble") => return disassemble(&args[1..]),
+        Some("dump-method") => return dump_method(&args[1..]),
+        Some("calls") => return calls(&args[1..]),
+        _ => {}
+    }
+
+    // apk, dex, vdex or oat file
+    let path = args
+        .first()
+        .cloned()
+        .unwrap_or_else(|| "tmp/test.apk".to_string());
+    let input = InputFile::open(path)?;
+
+    let apk_files = apk_files(&input)?;
+    for dex in dex_files(&input, &apk_files)? {
+        disassemble_dex(&dex)?;
+    }
+
+    Ok(())
+}
+
+const XREFS_USAGE: &str = "usage: xrefs (--string|--type|--field|--method|--proto) <pattern> \
+                           [--save <index>] [--no-cache] <apk|dex|vdex|oat|index>";
+
+/// `xrefs --string "http" app.apk`, lists every instruction referring to a matching target
+fn xrefs(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut query = None;
+    let mut save = None;
+    let mut cache = true;
+    let mut path = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let kind: fn(String) -> XrefTarget = match arg.as_str() {
+            "--string" => XrefTarget::String,
+            "--type" => XrefTarget::Type,
+            "--field" => XrefTarget::Field,
+            "--method" => XrefTarget::Method,
+            "--proto" => XrefTarget::Prototype,
+            "--save" => {
+                save = Some(args.next().ok_or(XREFS_USAGE)?);
+                continue;
+            }
+            "--no-cache" => {
+                cache = false;
+                continue;
+            }
+            flag if flag.starts_with("--") => {
+                return Err(format!("unknown flag {}, {}", flag, XREFS_USAGE).into())
+            }
+            _ => {
+                path = Some(arg);
+                continue;
+            }
+        };
+        query = Some((kind, args.next().ok_or(XREFS_USAGE)?.as_str()));
+    }
+    let path = path.ok_or(XREFS_USAGE)?;
+    let input = InputFile::open(path)?;
+
+    let index = if XrefIndex::is_index(&input) {
+        XrefIndex::read_from(BufReader::new(Cursor::new(input)))?
+    } else {
+        analysis(Path::new(path), &input, cache)?.xrefs
+    };
+
+    if let Some(save) = save {
+        index.write_to(std::io::BufWriter::new(fs::File::create(save)?))?;
+    }
+    if let Some((kind, pattern)) = query {
+        for (target, sites) in index.search(kind, pattern) {
+            println!("{} {:?}", target.kind(), target.name());
+            for site in sites {
+                println!(
+                    "    {}->{} @{:#x} {}",
+                    site.class, site.method, site.offset, site.access
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// the `classes*.dex` entries of an apk, which `dex_files` parses, nothing for the other
+/// containers whose dex files are read in place
+fn apk_files(input: &[u8]) -> Result<Vec<Cow<'_, [u8]>>, Box<dyn std::error::Error>> {
+    match input.starts_with(b"PK") {
+        true => Ok(container::dex_slices(input)?),
+        false => Ok(vec![]),
+    }
+}
+
+/// the standard and compact dex files of `input`, given what `apk_files` returned for it
+fn dex_files<'a>(
+    input: &'a [u8],
+    apk_files: &'a [Cow<'_, [u8]>],
+) -> Result<Vec<EmbeddedDex<'a>>, Box<dyn std::error::Error>> {
+    if !input.starts_with(b"PK") {
+        return Ok(container::extract(input)?);
+    }
+    Ok(apk_files
+        .iter()
+        .map(|data| EmbeddedDex::parse(data).ok_or(Error::InvalidContainer))
+        .collect::<Result<_, _>>()?)
+}
+
+/// `$SMALI_DISASSEMBLER_CACHE`, or `smali_disassembler` in the user's cache directory
+fn cache_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("SMALI_DISASSEMBLER_CACHE") {
+        return Some(dir.into());
+    }
+    let base = match env::var_os("XDG_CACHE_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".cache"),
+    };
+    Some(base.join("smali_disassembler"))
+}
+
+/// the xrefs and call graph of every dex file in `input`, read from `path`, from the cache when
+/// it has them
+fn analysis(
+    path: &Path,
+    input: &[u8],
+    cache: bool,
+) -> Result<AnalysisCache, Box<dyn std::error::Error>> {
+    let apk_files = apk_files(input)?;
+    let dex_files = dex_files(input, &apk_files)?;
+    match cache_dir().filter(|_| cache) {
+        Some(dir) => Ok(AnalysisCache::load_or_build(&dir, path, &dex_files)?),
+        None => Ok(AnalysisCache::build(&dex_files)?),
+    }
+}
+
+const CALLS_USAGE: &str = "usage: calls [--callers] [--no-cache] [--framework <android.jar>] \
+                           <Lclass;->name[(descriptor)]> <apk|dex|vdex|oat>";
+
+/// `calls 'Lcom/example/Main;->onCreate' app.apk`, lists what every matching method calls,
+/// or with `--callers` what calls it. `--framework` loads framework stubs so the methods app
+/// classes inherit from them resolve, the cache is keyed on the app alone so that graph is
+/// never cached.
+fn calls(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut callers = false;
+    let mut cache = true;
+    let mut framework = None;
+    let mut positional = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--callers" => callers = true,
+            "--no-cache" => cache = false,
+            "--framework" => framework = Some(args.next().ok_or(CALLS_USAGE)?),
+            _ => positional.push(arg),
+        }
+    }
+    let [method, path] = positional[..] else {
+        return Err(CALLS_USAGE.into());
+    };
+    let input = InputFile::open(path)?;
+    let graph = match framework {
+        Some(framework) => {
+            let mut hierarchy = ClassHierarchy::new();
+            hierarchy.add_framework(&InputFile::open(framework)?)?;
+            let apk_files = apk_files(&input)?;
+            let dex_files = dex_files(&input, &apk_files)?;
+            AnalysisCache::build_with_hierarchy(&dex_files, hierarchy)?.call_graph
+        }
+        None => analysis(Path::new(path), &input, cache)?.call_graph,
+    };
+
+    for (node, method_node) in graph.nodes().iter().enumerate() {
+        let key = method_node.key.to_string();
+        if key != *method && key.split_once('(').map(|(name, _)| name) != Some(method) {
+            continue;
+        }
+        println!("{}", key);
+        if callers {
+            for caller in graph.callers(node) {
+                println!("    <- {}", graph.nodes()[caller].key);
+            }
+            continue;
+        }
+        for edge in graph.callees(node) {
+            println!(
+                "    -> {} {:?} @{:#x}",
+                graph.nodes()[edge.target].key,
+                edge.kind,
+                edge.offset
+            );
+        }
+    }
+    Ok(())
+}
+
+const DECOMPILE_USAGE: &str = "usage: decompile [--class <descriptor>] <apk|dex|vdex|oat>";
+
+/// `decompile --class Lcom/example/Main; app.apk`, prints classes as java-like source
+fn decompile(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut class_filter = None;
+    let mut path = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--class" => class_filter = Some(args.next().ok_or(DECOMPILE_USAGE)?),
+            _ => path = Some(arg),
+        }
+    }
+    let input = InputFile::open(path.ok_or(DECOMPILE_USAGE)?)?;
+
+    let apk_files = apk_files(&input)?;
+    for embedded in dex_files(&input, &apk_files)? {
+        let dex = dex_file::open(&embedded)?;
+        let classes = match class_filter {
+            Some(descriptor) => dex.find_class(descriptor)?.into_iter().collect(),
+            None => dex.classes()?,
+        };
+        for class in &classes {
+            println!("{}", decompiler::class_source(dex.as_ref(), class)?);
+        }
+    }
+    Ok(())
+}
+
+const CFG_USAGE: &str = "usage: cfg --method <Lclass;->name[(descriptor)]> [--format dot|text] \
+                         [--dominators] [--post-dominators] <apk|dex|vdex|oat>";
+
+/// `cfg --method 'Lcom/example/Main;->onCreate' --format dot app.apk`, prints the control
+/// flow graph of every matching method, overloads included when no descriptor is given
+fn cfg(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut method = None;
+    let mut dot = true;
+    let mut overlays = vec![];
+    let mut path = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--method" => method = Some(args.next().ok_or(CFG_USAGE)?),
+            "--format" => {
+                dot = match args.next().map(String::as_str) {
+                    Some("dot") => true,
+                    Some("text") => false,
+                    _ => return Err(CFG_USAGE.into()),
+                }
+            }
+            "--dominators" => overlays.push(Overlay::Dominators),
+            "--post-dominators" => overlays.push(Overlay::PostDominators),
+            _ => path = Some(arg),
+        }
+    }
+    let method = method.ok_or(CFG_USAGE)?;
+    let input = InputFile::open(path.ok_or(CFG_USAGE)?)?;
+
+    let apk_files = apk_files(&input)?;
+    for embedded in dex_files(&input, &apk_files)? {
+        let dex = dex_file::open(&embedded)?;
+        for class in dex.classes()? {
+            for candidate in class.methods.iter().filter(|method| method.code_off != 0) {
+                let key = format!(
+                    "{}->{}{}",
+                    class.descriptor,
+                    candidate.name,
+                    candidate.prototype.descriptor()
+                );
+                if key != *method && key.split_once('(').map(|(name, _)| name) != Some(method) {
+                    continue;
+                }
+
+                let code = dex.code_item(candidate.code_off)?;
+                let instructions = SmaliDecoder::new(code.insns, dex.version()).decode_all();
+                let graph = Cfg::new(&instructions, &code.tries);
+
+                if dot {
+                    print!(
+                        "{}",
+                        cfg_to_dot(&key, &instructions, &graph, dex.as_ref(), &overlays)
+                    );
+                    continue;
+                }
+                println!("{}", key);
+                for (index, block) in graph.blocks.iter().enumerate() {
+                    println!("  B{}:", index);
+                    for instruction in &instructions[block.start..block.end] {
+                        println!(
+                            "    {:04x}: {}",
+                            instruction.offset / 2,
+                            instruction_text(instruction, dex.as_ref())
+                        );
+                    }
+                    for edge in &block.successors {
+                        println!("    -> B{} {:?}", edge.target, edge.kind);
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+const DUMP_USAGE: &str = "usage: dump [--format json|jsonl] <apk|dex|vdex|oat>";
+
+/// `dump --format jsonl app.apk`, prints every method with its decoded instructions as json,
+/// a single array or one object per line
+#[cfg(feature = "serde")]
+fn dump(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::{self, BufWriter, Write};
+
+    let mut lines = false;
+    let mut path = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                lines = match args.next().map(String::as_str) {
+                    Some("json") => false,
+                    Some("jsonl") => true,
+                    _ => return Err(DUMP_USAGE.into()),
+                }
+            }
+            _ => path = Some(arg),
+        }
+    }
+    let input = InputFile::open(path.ok_or(DUMP_USAGE)?)?;
+
+    let mut records = vec![];
+    let apk_files = apk_files(&input)?;
+    for embedded in dex_files(&input, &apk_files)? {
+        let dex = dex_file::open(&embedded)?;
+        records.extend(smali_disassembler::export::dex_methods(dex.as_ref())?);
+    }
+
+    let stdout = io::stdout().lock();
+    let mut out = BufWriter::new(stdout);
+    if lines {
+        for record in &records {
+            serde_json::to_writer(&mut out, record)?;
+            writeln!(out)?;
+        }
+    } else {
+        serde_json::to_writer(&mut out, &records)?;
+        writeln!(out)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(not(feature = "serde"))]
+fn dump(_: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    Err(format!("built without the serde feature, {}", DUMP_USAGE).into())
+}
+
+const DISASSEMBLE_USAGE: &str = "usage: disassemble [--jobs <n>] [--out <dir>] <apk|dex|vdex|oat>";
+
+/// `disassemble --out out app.apk`, disassembles every class of every dex file on a thread
+/// pool. with `--out` each class is written to `<dir>/smali*/<class>.smali`, otherwise the
+/// classes are printed in the order a serial run prints them.
+#[cfg(feature = "parallel")]
+fn disassemble(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    use smali_disassembler::disassembly;
+    use std::{
+        io::{self, BufWriter, Write},
+        path::Path,
+    };
+
+    let mut jobs = 0;
+    let mut out = None;
+    let mut path = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--jobs" => jobs = args.next().ok_or(DISASSEMBLE_USAGE)?.parse()?,
+            "--out" => out = Some(args.next().ok_or(DISASSEMBLE_USAGE)?),
+            _ => path = Some(arg),
+        }
+    }
+    let input = InputFile::open(path.ok_or(DISASSEMBLE_USAGE)?)?;
+
+    let apk_files = apk_files(&input)?;
+    let dex_files = dex_files(&input, &apk_files)?
+        .iter()
+        .map(dex_file::open)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // 0 jobs lets rayon use one thread per core
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(jobs).build()?;
+    match out {
+        Some(out) => pool.install(|| disassembly::write_all(&dex_files, Path::new(out)))?,
+        None => {
+            let classes = pool.install(|| disassembly::disassemble_all(&dex_files))?;
+            let mut stdout = BufWriter::new(io::stdout().lock());
+            for class in classes {
+                writeln!(stdout, "{}", class.text)?;
+            }
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "parallel"))]
+fn disassemble(_: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    Err(format!("built without the parallel feature, {}", DISASSEMBLE_USAGE).into())
+}
+
+const DUMP_METHOD_USAGE: &str =
+    "usage: dump-method [--no-cache] <Lclass;->name[(descriptor)]> <apk|dex|vdex|oat>";
+
+/// `dump-method 'Lcom/example/Main;->onCreate' app.apk`, prints the smali of every matching
+/// method, overloads included when no descriptor is given. only the class is parsed: when
+/// `xrefs` or `calls` have cached the file its dex file and index come from the cache's class
+/// index, otherwise it is looked up by its descriptor in each dex file.
+fn dump_method(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut cache = true;
+    let mut positional = vec![];
+    for arg in args {
+        match arg.as_str() {
+            "--no-cache" => cache = false,
+            _ => positional.push(arg),
+        }
+    }
+    let [method, path] = positional[..] else {
+        return Err(DUMP_METHOD_USAGE.into());
+    };
+    let (class_descriptor, _) = method.split_once("->").ok_or(DUMP_METHOD_USAGE)?;
+    let input = InputFile::open(path)?;
+
+    let apk_files = apk_files(&input)?;
+    let dex_files = dex_files(&input, &apk_files)?;
+    let found = match cache_dir()
+        .filter(|_| cache)
+        .and_then(|dir| AnalysisCache::load(&dir, Path::new(path), &dex_files))
+    {
+        Some(cache) => match cache.class(class_descriptor) {
+            Some(location) => {
+                let embedded = dex_files
+                    .get(location.dex_index as usize)
+                    .ok_or(Error::InvalidCache)?;
+                let dex = dex_file::open(embedded)?;
+                let class = dex.class(location.class_def)?;
+                Some((dex, class))
+            }
+            None => None,
+        },
+        None => find_class(&dex_files, class_descriptor)?,
+    };
+    let Some((dex, class)) = found else {
+        return Ok(());
+    };
+
+    for candidate in &class.methods {
+        let signature = format!("{}{}", candidate.name, candidate.prototype.descriptor());
+        let key = format!("{}->{}", class.descriptor, signature);
+        if key != *method && key.split_once('(').map(|(name, _)| name) != Some(method) {
+            continue;
+        }
+        if candidate.code_off == 0 {
+            println!(".method {}\n.end method", signature);
+            continue;
+        }
+
+        let code = dex.code_item(candidate.code_off)?;
+        let instructions = SmaliDecoder::new(code.insns, dex.version()).decode_all();
+        print!(
+            "{}",
+            method_text(
+                &signature,
+                code.registers_size,
+                &instructions,
+                &code.tries,
+                dex.as_ref()
+            )
+        );
+    }
+    Ok(())
+}
+
+/// the first definition of the class `descriptor`, the one the runtime loads, and its dex file
+fn find_class<'a>(
+    dex_files: &[EmbeddedDex<'a>],
+    descriptor: &str,
+) -> smali_disassembler::Result<Option<(Box<dyn DexFile + 'a>, DexClass)>> {
+    for embedded in dex_files {
+        let dex = dex_file::open(embedded)?;
+        if let Some(class) = dex.find_class(descriptor)? {
+            return Ok(Some((dex, class)));
+        }
+    }
+    Ok(None)
+}
+
+fn disassemble_dex(embedded: &EmbeddedDex) -> Result<(), Box<dyn std::error::Error>> {
+    // report tampering before trusting anything in the file
+    if embedded.kind == DexKind::Standard {
+        for finding in integrity::verify(embedded.data) {
+            eprintln!("integrity: {:?}", finding);
+        }
+    }
+
+    let dex = dex_file::open(embedded)?;
+
+    // one class at a time, only the pages of the class being decoded are touched
+    for index in 0..dex.class_count()? {
+        let class = dex.class(index)?;
+        for method in class.methods.iter().filter(|method| method.code_off != 0) {
+            println!("{}->{}", class.descriptor, method.name);
+            let code = dex.code_item(method.code_off)?;
+            let decoder = SmaliDecoder::new(code.insns, dex.version());
+            if let Err(error) = decoder.try_decode_all() {
+                eprintln!("{}->{}: {}", class.descriptor, method.name, error);
             }
         }
     }
@@ -0,0 +1,84 @@
+use alloc::{format, string::String, vec::Vec};
+
+/// a method prototype as type descriptors
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Prototype {
+    pub return_type: String,
+    pub parameters: Vec<String>,
+}
+
+impl Prototype {
+    /// the `(params)ret` form used by smali
+    pub fn descriptor(&self) -> String {
+        format!("({}){}", self.parameters.concat(), self.return_type)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldRef {
+    pub class: String,
+    pub name: String,
+    pub field_type: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MethodRef {
+    pub class: String,
+    pub name: String,
+    pub prototype: Prototype,
+}
+
+/// the field a method handle gets or puts, or the method it invokes
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MethodHandle {
+    Field(FieldRef),
+    Method(MethodRef),
+}
+
+/// turns the dex indices found in instructions into names and descriptors
+pub trait Resolver {
+    fn string(&self, string_idx: u32) -> Option<String>;
+    fn type_descriptor(&self, type_idx: u32) -> Option<String>;
+    fn field(&self, field_idx: u32) -> Option<FieldRef>;
+    fn method(&self, method_idx: u32) -> Option<MethodRef>;
+    fn prototype(&self, proto_idx: u32) -> Option<Prototype>;
+
+    /// method handles only exist in 038+ dex files, resolvers without them resolve none
+    fn method_handle(&self, _method_handle_idx: u32) -> Option<MethodHandle> {
+        None
+    }
+
+    /// the bootstrap method handle an `invoke-custom` call site is linked by
+    fn call_site_bootstrap(&self, _call_site_idx: u32) -> Option<MethodHandle> {
+        None
+    }
+}
+
+/// resolves nothing, for code decoded without its dex file
+pub struct Unresolved;
+
+impl Resolver for Unresolved {
+    fn string(&self, _: u32) -> Option<String> {
+        None
+    }
+
+    fn type_descriptor(&self, _: u32) -> Option<String> {
+        None
+    }
+
+    fn field(&self, _: u32) -> Option<FieldRef> {
+        None
+    }
+
+    fn method(&self, _: u32) -> Option<MethodRef> {
+        None
+    }
+
+    fn prototype(&self, _: u32) -> Option<Prototype> {
+        None
+    }
+}
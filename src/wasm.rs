@@ -0,0 +1,75 @@
+//! wasm-bindgen exports for running in a browser or node. nothing here touches the file
+//! system or zip archives: pass the bytes of a dex, vdex or oat file and unzip apks on the
+//! javascript side.
+//!
+//! build with `cargo rustc --lib --crate-type cdylib --target wasm32-unknown-unknown
+//! --no-default-features --features wasm` and test headless with
+//! `cargo test --target wasm32-unknown-unknown --no-default-features --features wasm`
+
+use crate::{
+    container::{
+        self,
+        dex_file::{self, DexFile},
+    },
+    dalvik::smali::instruction_text,
+    disassembly::class_text,
+    errors::Error,
+    export,
+    resolver::Unresolved,
+    Result, SmaliDecoder,
+};
+use wasm_bindgen::prelude::*;
+
+fn js_error(error: impl std::fmt::Display) -> JsError {
+    JsError::new(&error.to_string())
+}
+
+/// the standard and compact dex files in `data`
+fn dex_files(data: &[u8]) -> Result<Vec<Box<dyn DexFile + '_>>> {
+    container::extract(data)?
+        .iter()
+        .map(dex_file::open)
+        .collect()
+}
+
+fn smali(data: &[u8]) -> Result<String> {
+    let mut out = String::new();
+    for dex in dex_files(data)? {
+        for class in dex.classes()? {
+            out.push_str(&class_text(dex.as_ref(), &class)?);
+            out.push('\n');
+        }
+    }
+    Ok(out)
+}
+
+fn json(data: &[u8]) -> Result<String> {
+    let mut records = vec![];
+    for dex in dex_files(data)? {
+        records.extend(export::dex_methods(dex.as_ref())?);
+    }
+    serde_json::to_string(&records).map_err(|_| Error::InvalidContainer)
+}
+
+/// every class of a dex, vdex or oat file as smali text
+#[wasm_bindgen]
+pub fn disassemble(data: &[u8]) -> std::result::Result<String, JsError> {
+    smali(data).map_err(js_error)
+}
+
+/// every method with code of a dex, vdex or oat file as a json array, the objects are the
+/// ones the cli's `dump` command prints
+#[wasm_bindgen(js_name = disassembleJson)]
+pub fn disassemble_json(data: &[u8]) -> std::result::Result<String, JsError> {
+    json(data).map_err(js_error)
+}
+
+/// smali of raw code, one instruction per line with indices left unresolved
+#[wasm_bindgen(js_name = decodeCode)]
+pub fn decode_code(code: &[u8]) -> String {
+    SmaliDecoder::new(code, None)
+        .decode_all()
+        .iter()
+        .map(|instruction| instruction_text(instruction, &Unresolved) + "\n")
+        .collect()
+}
@@ -0,0 +1,43 @@
+use smali_disassembler::{
+    dalvik::opcodes::{ArgumentRegisters, DalvikBytecode, InvokeKind},
+    SmaliDecoder,
+};
+
+#[test]
+fn test_argument_order() {
+    // invoke-virtual {v0, v1, v2, v3, v4}, method@1, G is the fifth register
+    let code = [0x6e, 0x54, 0x01, 0x00, 0x10, 0x32];
+    let instructions = SmaliDecoder::new(&code, None).decode_all();
+
+    let DalvikBytecode::Invoke(InvokeKind::Virtual, registers, 1) = &instructions[0].inst else {
+        panic!("{:?}", instructions[0].inst);
+    };
+    assert_eq!(registers.as_slice(), &[0, 1, 2, 3, 4]);
+    assert_eq!(format!("{:?}", registers), "[0, 1, 2, 3, 4]");
+}
+
+#[test]
+fn test_argument_count() {
+    // filled-new-array {}, type@0 then filled-new-array {v0, v1}, type@0
+    let code = [
+        0x24, 0x00, 0x00, 0x00, 0x00, 0x00, 0x24, 0x20, 0x00, 0x00, 0x10, 0x00,
+    ];
+    let instructions = SmaliDecoder::new(&code, None).decode_all();
+
+    assert_eq!(
+        instructions[0].inst,
+        DalvikBytecode::FilledNewArray(0, ArgumentRegisters::default())
+    );
+    assert_eq!(
+        instructions[1].inst,
+        DalvikBytecode::FilledNewArray(0, ArgumentRegisters::new(&[0, 1]).unwrap())
+    );
+}
+
+#[test]
+fn test_too_many_arguments() {
+    // a 35c instruction naming six registers is invalid
+    let code = [0x6e, 0x60, 0x01, 0x00, 0x10, 0x32];
+    assert!(SmaliDecoder::new(&code, None).decode_all().is_empty());
+    assert!(ArgumentRegisters::new(&[0; 6]).is_none());
+}
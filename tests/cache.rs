@@ -0,0 +1,184 @@
+#![cfg(feature = "std")]
+
+use smali_disassembler::{
+    analysis::{
+        cache::{self, AnalysisCache},
+        callgraph::MethodKey,
+        xrefs::XrefTarget,
+    },
+    container::EmbeddedDex,
+    errors::Error,
+    integrity,
+};
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, UNIX_EPOCH},
+};
+
+mod common;
+
+const HELLO_DEX: &[u8] = include_bytes!("data/hello.dex");
+
+fn hello() -> [EmbeddedDex<'static>; 1] {
+    [EmbeddedDex::parse(HELLO_DEX).unwrap()]
+}
+
+#[test]
+fn test_round_trip() {
+    let built = AnalysisCache::build(&hello()).unwrap();
+    assert_eq!(
+        built.signatures,
+        vec![<[u8; 20]>::try_from(&HELLO_DEX[0xc..0x20]).unwrap()]
+    );
+    assert!(!built
+        .xrefs
+        .get(&XrefTarget::String("hello, world".to_string()))
+        .is_empty());
+    let run = built
+        .call_graph
+        .find(&MethodKey::new("LHello;", "run", "(I)I"))
+        .unwrap();
+    assert!(built.call_graph.nodes()[run].has_body);
+    assert_eq!(built.class("LHello;").unwrap().class_def, 0);
+
+    let mut data = vec![];
+    built.write_to(&mut data).unwrap();
+    let loaded = AnalysisCache::read_from(&data).unwrap();
+
+    assert_eq!(loaded.signatures, built.signatures);
+    assert_eq!(loaded.classes, built.classes);
+    assert_eq!(loaded.xrefs, built.xrefs);
+    assert_eq!(loaded.call_graph.nodes(), built.call_graph.nodes());
+    for node in 0..built.call_graph.nodes().len() {
+        assert_eq!(
+            loaded.call_graph.callees(node),
+            built.call_graph.callees(node)
+        );
+        assert!(loaded
+            .call_graph
+            .callers(node)
+            .eq(built.call_graph.callers(node)));
+    }
+}
+
+#[test]
+fn test_invalid() {
+    let mut data = vec![];
+    AnalysisCache::build(&hello())
+        .unwrap()
+        .write_to(&mut data)
+        .unwrap();
+
+    for truncated in [&data[..4], &data[..data.len() / 2], &data[..data.len() - 1]] {
+        assert!(matches!(
+            AnalysisCache::read_from(truncated),
+            Err(Error::InvalidCache)
+        ));
+    }
+}
+
+/// a fresh directory for a test, with `hello.dex` written to `input/hello.dex`
+fn test_dir(name: &str) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!(
+        "smali_disassembler_{}_{}",
+        name,
+        std::process::id()
+    ));
+    let source = dir.join("input/hello.dex");
+    fs::create_dir_all(source.parent().unwrap()).unwrap();
+    fs::write(&source, HELLO_DEX).unwrap();
+    (dir, source)
+}
+
+#[test]
+fn test_load_or_build() {
+    let (dir, source) = test_dir("cache");
+    let cache_dir = dir.join("cache");
+    let signatures = cache::signatures(&hello()).unwrap();
+    let path = cache_dir.join(cache::file_name(&signatures, &source).unwrap());
+
+    assert!(AnalysisCache::load(&cache_dir, &source, &hello()).is_none());
+    let built = AnalysisCache::load_or_build(&cache_dir, &source, &hello()).unwrap();
+    assert!(path.exists());
+    let loaded = AnalysisCache::load(&cache_dir, &source, &hello()).unwrap();
+    assert_eq!(loaded.classes, built.classes);
+
+    // a stale or broken cache file is rebuilt
+    fs::write(&path, b"smali-cache\0junk").unwrap();
+    let rebuilt = AnalysisCache::load_or_build(&cache_dir, &source, &hello()).unwrap();
+    assert_eq!(rebuilt.xrefs, built.xrefs);
+    assert!(AnalysisCache::read_from(&fs::read(&path).unwrap()).is_ok());
+
+    let loaded = AnalysisCache::load_or_build(&cache_dir, &source, &hello()).unwrap();
+    assert_eq!(loaded.xrefs, built.xrefs);
+
+    // a source written since has a new key
+    let file = fs::File::options().write(true).open(&source).unwrap();
+    file.set_modified(UNIX_EPOCH + Duration::from_secs(1))
+        .unwrap();
+    assert_ne!(
+        cache::file_name(&signatures, &source).unwrap(),
+        path.file_name().unwrap().to_str().unwrap()
+    );
+    assert!(AnalysisCache::load(&cache_dir, &source, &hello()).is_none());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_tampered_dex() {
+    let (dir, _) = test_dir("tampered");
+    let cache_dir = dir.join("cache");
+
+    // edited and given a fresh checksum, but the old signature
+    let mut tampered = HELLO_DEX.to_vec();
+    let position = tampered
+        .windows(12)
+        .position(|window| window == b"hello, world")
+        .unwrap();
+    tampered[position] = b'j';
+    let checksum = integrity::adler32(&tampered[0xc..]);
+    tampered[0x8..0xc].copy_from_slice(&checksum.to_le_bytes());
+    let source = dir.join("input/tampered.dex");
+    fs::write(&source, &tampered).unwrap();
+
+    // analysed as it is, but never saved under a signature it doesn't have
+    let dex_files = [EmbeddedDex::parse(&tampered).unwrap()];
+    let cache = AnalysisCache::load_or_build(&cache_dir, &source, &dex_files).unwrap();
+    let string = |name: &str| XrefTarget::String(name.to_string());
+    assert!(cache.xrefs.get(&string("hello, world")).is_empty());
+    assert!(!cache.xrefs.get(&string("jello, world")).is_empty());
+    assert!(AnalysisCache::load(&cache_dir, &source, &dex_files).is_none());
+    assert!(!cache_dir.exists());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_failed_write() {
+    let (dir, source) = test_dir("failed");
+    let cache_dir = dir.join("cache");
+    let signatures = cache::signatures(&hello()).unwrap();
+    let path = cache_dir.join(cache::file_name(&signatures, &source).unwrap());
+
+    // the cache file can not be renamed over a directory
+    fs::create_dir_all(&path).unwrap();
+    assert!(AnalysisCache::load_or_build(&cache_dir, &source, &hello()).is_err());
+    assert_eq!(fs::read_dir(&cache_dir).unwrap().count(), 1);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_compact_dex() {
+    let cdex = common::compact_dex(HELLO_DEX);
+    let standard = AnalysisCache::build(&hello()).unwrap();
+    let compact = AnalysisCache::build(&[EmbeddedDex::parse(&cdex).unwrap()]).unwrap();
+
+    assert_eq!(compact.xrefs, standard.xrefs);
+    assert_eq!(compact.call_graph.nodes(), standard.call_graph.nodes());
+    for node in 0..standard.call_graph.nodes().len() {
+        assert_eq!(
+            compact.call_graph.callees(node),
+            standard.call_graph.callees(node)
+        );
+    }
+}
@@ -0,0 +1,142 @@
+#![cfg(feature = "std")]
+
+use smali_disassembler::{
+    analysis::{
+        callgraph::{CallGraph, CallKind, MethodKey},
+        hierarchy::{ClassHierarchy, ClassInfo, MethodInfo, Visibility},
+    },
+    SmaliDecoder,
+};
+
+mod common;
+
+use common::TestResolver;
+
+fn methods() -> TestResolver {
+    TestResolver::new()
+        .with_method(0, "LBase;", "run", &[], "V")
+        .with_method(
+            1,
+            "Landroid/telephony/TelephonyManager;",
+            "getDeviceId",
+            &[],
+            "Ljava/lang/String;",
+        )
+}
+
+fn class(descriptor: &str, super_class: &str, methods: &[(&str, &str, bool)]) -> ClassInfo {
+    ClassInfo {
+        descriptor: descriptor.to_string(),
+        super_class: Some(super_class.to_string()),
+        interfaces: vec![],
+        is_interface: false,
+        is_abstract: methods.iter().any(|&(_, _, has_code)| !has_code),
+        methods: methods
+            .iter()
+            .map(|&(name, descriptor, has_code)| MethodInfo {
+                name: name.to_string(),
+                descriptor: descriptor.to_string(),
+                visibility: Visibility::Public,
+                is_static: false,
+                is_abstract: !has_code,
+                is_native: false,
+                has_code,
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn test_class_hierarchy_analysis() {
+    let mut hierarchy = ClassHierarchy::new();
+    hierarchy.add_class(class(
+        "LBase;",
+        "Ljava/lang/Object;",
+        &[("run", "()V", false)],
+    ));
+    hierarchy.add_class(class("LA;", "LBase;", &[("run", "()V", true)]));
+    hierarchy.add_class(class("LB;", "LBase;", &[("run", "()V", true)]));
+    hierarchy.add_class(class(
+        "LMain;",
+        "Landroid/app/Activity;",
+        &[("onCreate", "(Landroid/os/Bundle;)V", true)],
+    ));
+
+    let code = [
+        0x6e, 0x10, 0x00, 0x00, 0x01, 0x00, // invoke-virtual {v1}, LBase;->run()V
+        0x6e, 0x10, 0x01, 0x00, 0x01, 0x00, // invoke-virtual {v1}, ->getDeviceId()
+        0x0e, 0x00, // return-void
+    ];
+    let instructions = SmaliDecoder::new(&code, None).decode_all();
+    let on_create = MethodKey::new("LMain;", "onCreate", "(Landroid/os/Bundle;)V");
+
+    let mut graph = CallGraph::with_hierarchy(&hierarchy);
+    graph.add_method(on_create.clone(), &instructions, &methods(), &hierarchy);
+
+    let caller = graph.find(&on_create).unwrap();
+    let mut callees: Vec<String> = graph
+        .callees(caller)
+        .iter()
+        .map(|edge| {
+            assert_eq!(edge.kind, CallKind::Virtual);
+            graph.nodes()[edge.target].key.to_string()
+        })
+        .collect();
+    callees.sort();
+    assert_eq!(
+        callees,
+        vec![
+            "LA;->run()V",
+            "LB;->run()V",
+            "Landroid/telephony/TelephonyManager;->getDeviceId()Ljava/lang/String;",
+        ]
+    );
+
+    let device_id = graph
+        .find(&MethodKey::new(
+            "Landroid/telephony/TelephonyManager;",
+            "getDeviceId",
+            "()Ljava/lang/String;",
+        ))
+        .unwrap();
+    assert!(!graph.nodes()[device_id].has_body);
+    assert_eq!(graph.callers(device_id).collect::<Vec<_>>(), vec![caller]);
+
+    let entry_points = graph.entry_points(&hierarchy);
+    assert_eq!(entry_points, vec![caller]);
+    assert_eq!(
+        graph.path(&entry_points, device_id),
+        Some(vec![caller, device_id])
+    );
+    let a_run = graph.find(&MethodKey::new("LA;", "run", "()V")).unwrap();
+    assert!(graph.reachable(&entry_points).contains(&a_run));
+}
+
+#[test]
+fn test_inherited_framework_method() {
+    // LView; overrides the framework's draw, LText; inherits it from Landroid/view/View;
+    let mut hierarchy = ClassHierarchy::new();
+    hierarchy.add_class(class("LWidget;", "Landroid/view/View;", &[]));
+    hierarchy.add_class(class("LView;", "LWidget;", &[("draw", "()V", true)]));
+    hierarchy.add_class(class("LText;", "LWidget;", &[]));
+
+    let resolver = TestResolver::new().with_method(0, "LWidget;", "draw", &[], "V");
+    let code = [
+        0x6e, 0x10, 0x00, 0x00, 0x01, 0x00, // invoke-virtual {v1}, LWidget;->draw()V
+        0x0e, 0x00, // return-void
+    ];
+    let instructions = SmaliDecoder::new(&code, None).decode_all();
+    let caller = MethodKey::new("LMain;", "run", "()V");
+
+    let mut graph = CallGraph::with_hierarchy(&hierarchy);
+    graph.add_method(caller.clone(), &instructions, &resolver, &hierarchy);
+
+    let caller = graph.find(&caller).unwrap();
+    let mut callees: Vec<String> = graph
+        .callees(caller)
+        .iter()
+        .map(|edge| graph.nodes()[edge.target].key.to_string())
+        .collect();
+    callees.sort();
+    assert_eq!(callees, vec!["LView;->draw()V", "LWidget;->draw()V"]);
+}
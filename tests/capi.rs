@@ -0,0 +1,94 @@
+#![cfg(feature = "capi")]
+
+use smali_disassembler::capi::*;
+use std::{ffi::CStr, ptr};
+
+#[test]
+fn test_decode_and_iterate() {
+    let code = [
+        0x12, 0xf0, // const/4 v0, -1
+        0x1a, 0x01, 0x03, 0x00, // const-string v1, string@3
+        0x38, 0x00, 0xfd, 0xff, // if-eqz v0, -3
+        0x0e, 0x00, // return-void
+    ];
+    unsafe {
+        let decoder = smali_decoder_new(code.as_ptr(), code.len());
+        assert_eq!(smali_decoder_count(decoder), 4);
+
+        let mut mnemonics = vec![];
+        loop {
+            let instruction = smali_decoder_next(decoder);
+            if instruction.is_null() {
+                break;
+            }
+            let mnemonic = CStr::from_ptr(smali_instruction_mnemonic(instruction));
+            mnemonics.push(mnemonic.to_str().unwrap().to_string());
+        }
+        assert_eq!(
+            mnemonics,
+            ["const/4", "const-string", "if-eqz", "return-void"]
+        );
+
+        let branch = smali_decoder_get(decoder, 2);
+        assert_eq!(smali_instruction_offset(branch), 6);
+        assert_eq!(smali_instruction_code_units(branch), 2);
+        assert_eq!(
+            CStr::from_ptr(smali_instruction_text(branch)).to_str(),
+            Ok("if-eqz v0, :addr_0")
+        );
+        assert_eq!(smali_instruction_operand_count(branch), 2);
+
+        let mut operand = SmaliOperand {
+            kind: SmaliOperandKind::Register,
+            wide: false,
+            value: 0,
+        };
+        assert!(smali_instruction_operand(branch, 1, &mut operand));
+        assert_eq!(operand.kind, SmaliOperandKind::BranchTarget);
+        assert_eq!(operand.value, 0);
+        assert!(!smali_instruction_operand(branch, 2, &mut operand));
+
+        let literal = smali_decoder_get(decoder, 0);
+        assert!(smali_instruction_operand(literal, 1, &mut operand));
+        assert_eq!(
+            (operand.kind, operand.value),
+            (SmaliOperandKind::Literal, -1)
+        );
+
+        let string = smali_decoder_get(decoder, 1);
+        assert!(smali_instruction_operand(string, 1, &mut operand));
+        assert_eq!(
+            (operand.kind, operand.value),
+            (SmaliOperandKind::StringIndex, 3)
+        );
+
+        assert!(smali_decoder_get(decoder, 4).is_null());
+        smali_decoder_reset(decoder);
+        assert_eq!(smali_decoder_next(decoder), smali_decoder_get(decoder, 0));
+        smali_decoder_free(decoder);
+    }
+    unsafe {
+        assert!(smali_decoder_new(ptr::null(), 0).is_null());
+        smali_decoder_free(ptr::null_mut());
+    }
+}
+
+#[test]
+fn test_decode_error_offset() {
+    let code = [
+        0x0e, 0x00, // return-void
+        0x14, 0x00, 0x01, // const v0, truncated
+    ];
+    unsafe {
+        let decoder = smali_decoder_new(code.as_ptr(), code.len());
+        assert_eq!(smali_decoder_count(decoder), 1);
+        let mut offset = 0;
+        assert!(smali_decoder_error_offset(decoder, &mut offset));
+        assert_eq!(offset, 2);
+        smali_decoder_free(decoder);
+
+        let decoder = smali_decoder_new(code.as_ptr(), 2);
+        assert!(!smali_decoder_error_offset(decoder, &mut offset));
+        smali_decoder_free(decoder);
+    }
+}
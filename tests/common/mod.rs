@@ -0,0 +1,200 @@
+// every test file uses a different part of this
+#![allow(dead_code)]
+
+use smali_disassembler::resolver::{FieldRef, MethodHandle, MethodRef, Prototype, Resolver};
+use std::collections::HashMap;
+
+/// a resolver over the ids a test registers, any other index resolves to `None`
+#[derive(Default)]
+pub struct TestResolver {
+    strings: HashMap<u32, String>,
+    types: HashMap<u32, String>,
+    fields: HashMap<u32, FieldRef>,
+    methods: HashMap<u32, MethodRef>,
+    prototypes: HashMap<u32, Prototype>,
+    method_handles: HashMap<u32, MethodHandle>,
+    call_sites: HashMap<u32, MethodHandle>,
+}
+
+impl TestResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_string(mut self, string_idx: u32, string: &str) -> Self {
+        self.strings.insert(string_idx, string.to_string());
+        self
+    }
+
+    pub fn with_type(mut self, type_idx: u32, descriptor: &str) -> Self {
+        self.types.insert(type_idx, descriptor.to_string());
+        self
+    }
+
+    /// `class->name:field_type`
+    pub fn with_field(mut self, field_idx: u32, class: &str, name: &str, field_type: &str) -> Self {
+        let field = FieldRef {
+            class: class.to_string(),
+            name: name.to_string(),
+            field_type: field_type.to_string(),
+        };
+        self.fields.insert(field_idx, field);
+        self
+    }
+
+    /// `class->name(parameters)return_type`
+    pub fn with_method(
+        mut self,
+        method_idx: u32,
+        class: &str,
+        name: &str,
+        parameters: &[&str],
+        return_type: &str,
+    ) -> Self {
+        let method = MethodRef {
+            class: class.to_string(),
+            name: name.to_string(),
+            prototype: prototype(parameters, return_type),
+        };
+        self.methods.insert(method_idx, method);
+        self
+    }
+
+    pub fn with_prototype(
+        mut self,
+        proto_idx: u32,
+        parameters: &[&str],
+        return_type: &str,
+    ) -> Self {
+        self.prototypes
+            .insert(proto_idx, prototype(parameters, return_type));
+        self
+    }
+
+    /// a method handle invoking the method registered as `method_idx`
+    pub fn with_method_handle(mut self, method_handle_idx: u32, method_idx: u32) -> Self {
+        let method = MethodHandle::Method(self.methods[&method_idx].clone());
+        self.method_handles.insert(method_handle_idx, method);
+        self
+    }
+
+    /// a call site bootstrapped by the method handle registered as `method_handle_idx`
+    pub fn with_call_site(mut self, call_site_idx: u32, method_handle_idx: u32) -> Self {
+        let bootstrap = self.method_handles[&method_handle_idx].clone();
+        self.call_sites.insert(call_site_idx, bootstrap);
+        self
+    }
+}
+
+fn prototype(parameters: &[&str], return_type: &str) -> Prototype {
+    Prototype {
+        return_type: return_type.to_string(),
+        parameters: parameters
+            .iter()
+            .map(|parameter| parameter.to_string())
+            .collect(),
+    }
+}
+
+impl Resolver for TestResolver {
+    fn string(&self, string_idx: u32) -> Option<String> {
+        self.strings.get(&string_idx).cloned()
+    }
+
+    fn type_descriptor(&self, type_idx: u32) -> Option<String> {
+        self.types.get(&type_idx).cloned()
+    }
+
+    fn field(&self, field_idx: u32) -> Option<FieldRef> {
+        self.fields.get(&field_idx).cloned()
+    }
+
+    fn method(&self, method_idx: u32) -> Option<MethodRef> {
+        self.methods.get(&method_idx).cloned()
+    }
+
+    fn prototype(&self, proto_idx: u32) -> Option<Prototype> {
+        self.prototypes.get(&proto_idx).cloned()
+    }
+
+    fn method_handle(&self, method_handle_idx: u32) -> Option<MethodHandle> {
+        self.method_handles.get(&method_handle_idx).cloned()
+    }
+
+    fn call_site_bootstrap(&self, call_site_idx: u32) -> Option<MethodHandle> {
+        self.call_sites.get(&call_site_idx).cloned()
+    }
+}
+
+fn read_uleb128(data: &[u8], position: &mut usize) -> u32 {
+    let mut value = 0;
+    for shift in (0..35).step_by(7) {
+        let byte = data[*position];
+        *position += 1;
+        value |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    value
+}
+
+/// `dex` rewritten as a standalone compact dex with the same classes and code: every code
+/// item's fields are packed into the 4 bytes right before its instructions and its method's
+/// `code_off` moved there. the fields have to fit the inline nibbles, as they do in `hello.dex`
+pub fn compact_dex(dex: &[u8]) -> Vec<u8> {
+    let u16_at = |data: &[u8], offset: usize| u16::from_le_bytes([data[offset], data[offset + 1]]);
+    let u32_at = |data: &[u8], offset: usize| {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap()) as usize
+    };
+
+    let mut cdex = dex.to_vec();
+    cdex[..8].copy_from_slice(b"cdex001\0");
+    // every offset is relative to the data section, make it the whole file
+    cdex[0x68..0x6c].copy_from_slice(&(dex.len() as u32).to_le_bytes());
+    cdex[0x6c..0x70].copy_from_slice(&0u32.to_le_bytes());
+
+    for class in 0..u32_at(dex, 0x60) {
+        let class_data_off = u32_at(dex, u32_at(dex, 0x64) + class * 32 + 24);
+        if class_data_off == 0 {
+            continue;
+        }
+        let mut position = class_data_off;
+        let sizes = [(); 4].map(|()| read_uleb128(dex, &mut position));
+        for _ in 0..(sizes[0] + sizes[1]) * 2 {
+            read_uleb128(dex, &mut position);
+        }
+        for _ in 0..sizes[2] + sizes[3] {
+            read_uleb128(dex, &mut position);
+            read_uleb128(dex, &mut position);
+            let code_position = position;
+            let code_off = read_uleb128(dex, &mut position) as usize;
+            if code_off == 0 {
+                continue;
+            }
+
+            let [registers, ins, outs, tries] =
+                [0, 2, 4, 6].map(|field| u16_at(dex, code_off + field));
+            let insns_size = u32_at(dex, code_off + 12) as u16;
+            assert!(
+                registers - ins < 16 && ins < 16 && outs < 16 && tries < 16 && insns_size < 2048
+            );
+            let fields = (registers - ins) << 12 | ins << 8 | outs << 4 | tries;
+            cdex[code_off + 12..code_off + 14].copy_from_slice(&fields.to_le_bytes());
+            cdex[code_off + 14..code_off + 16].copy_from_slice(&(insns_size << 5).to_le_bytes());
+
+            // the new offset has to take as many uleb128 bytes as the old one
+            let new_off = code_off as u32 + 12;
+            let mut encoded = vec![];
+            let mut value = new_off;
+            while value >= 0x80 {
+                encoded.push(value as u8 | 0x80);
+                value >>= 7;
+            }
+            encoded.push(value as u8);
+            assert_eq!(encoded.len(), position - code_position);
+            cdex[code_position..position].copy_from_slice(&encoded);
+        }
+    }
+    cdex
+}
@@ -0,0 +1,90 @@
+#![cfg(feature = "std")]
+
+use smali_disassembler::{
+    analysis::{
+        cfg::Cfg,
+        constants::{ConstValue, ConstantPropagation, OpaquePredicate},
+        ssa::Ssa,
+    },
+    SmaliDecoder,
+};
+
+mod common;
+
+use common::TestResolver;
+
+/// `new String(char[])` as method@0
+fn string_constructor() -> TestResolver {
+    TestResolver::new().with_method(0, "Ljava/lang/String;", "<init>", &["[C"], "V")
+}
+
+#[test]
+fn test_opaque_predicate() {
+    let code = [
+        0x12, 0x30, // const/4 v0, 3
+        0xda, 0x01, 0x00, 0x03, // mul-int/lit8 v1, v0, 3
+        0xdc, 0x01, 0x01, 0x02, // rem-int/lit8 v1, v1, 2
+        0x38, 0x01, 0x03, 0x00, // if-eqz v1, +3
+        0x0f, 0x00, // return v0
+        0x0f, 0x01, // return v1
+    ];
+    let instructions = SmaliDecoder::new(&code, None).decode_all();
+    let cfg = Cfg::new(&instructions, &[]);
+    let ssa = Ssa::new(&instructions, &cfg, 2);
+    let constants = ConstantPropagation::new(&instructions, &cfg, &ssa, &string_constructor());
+
+    let remainder = ssa.instruction(2).unwrap().defs[0].1;
+    assert_eq!(constants.constant(remainder), Some(&ConstValue::Narrow(1)));
+    assert_eq!(
+        constants.opaque_predicates(),
+        &[OpaquePredicate {
+            index: 3,
+            taken: false
+        }]
+    );
+    assert_eq!(
+        constants.unreachable_blocks(),
+        vec![cfg.block_of(5).unwrap()]
+    );
+}
+
+#[test]
+fn test_negative_const4() {
+    let code = [
+        0x12, 0xf0, // const/4 v0, -1
+        0xd8, 0x01, 0x00, 0xfd, // add-int/lit8 v1, v0, -3
+        0x0f, 0x01, // return v1
+    ];
+    let instructions = SmaliDecoder::new(&code, None).decode_all();
+    let cfg = Cfg::new(&instructions, &[]);
+    let ssa = Ssa::new(&instructions, &cfg, 2);
+    let constants = ConstantPropagation::new(&instructions, &cfg, &ssa, &string_constructor());
+
+    let literal = ssa.instruction(0).unwrap().defs[0].1;
+    let sum = ssa.instruction(1).unwrap().defs[0].1;
+    assert_eq!(constants.constant(literal), Some(&ConstValue::Narrow(-1)));
+    assert_eq!(constants.constant(sum), Some(&ConstValue::Narrow(-4)));
+}
+
+#[test]
+fn test_string_from_array_literal() {
+    let code = [
+        0x12, 0x30, // const/4 v0, 3
+        0x23, 0x01, 0x00, 0x00, // new-array v1, v0, type@0
+        0x26, 0x01, 0x09, 0x00, 0x00, 0x00, // fill-array-data v1, +9
+        0x22, 0x02, 0x01, 0x00, // new-instance v2, type@1
+        0x70, 0x20, 0x00, 0x00, 0x12, 0x00, // invoke-direct {v2, v1}, method@0
+        0x11, 0x02, // return-object v2
+        0x00, 0x03, 0x02, 0x00, 0x03, 0x00, 0x00, 0x00, // array payload of 3 chars
+        b'H', 0x00, b'i', 0x00, b'!', 0x00,
+    ];
+    let instructions = SmaliDecoder::new(&code, None).decode_all();
+    let cfg = Cfg::new(&instructions, &[]);
+    let ssa = Ssa::new(&instructions, &cfg, 3);
+    let constants = ConstantPropagation::new(&instructions, &cfg, &ssa, &string_constructor());
+
+    assert_eq!(
+        constants.folded_strings().get(&4).map(String::as_str),
+        Some("Hi!")
+    );
+}
@@ -0,0 +1,252 @@
+#![cfg(feature = "std")]
+
+use smali_disassembler::{
+    container::{self, cdex::CompactDexFile, dex_file::DexFile, input::InputFile, DexKind},
+    errors::Error,
+    resolver::Resolver,
+    SmaliDecoder,
+};
+use std::borrow::Cow;
+#[cfg(feature = "zip")]
+use {
+    std::io::{Cursor, Write},
+    zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter},
+};
+
+fn fake_dex(magic: &[u8; 8]) -> Vec<u8> {
+    let mut dex = vec![0u8; 0x70];
+    dex[..8].copy_from_slice(magic);
+    dex[0x20..0x24].copy_from_slice(&0x70u32.to_le_bytes());
+    dex[0x24..0x28].copy_from_slice(&0x70u32.to_le_bytes());
+    dex[0x28..0x2c].copy_from_slice(&0x12345678u32.to_le_bytes());
+    dex
+}
+
+#[test]
+fn test_vdex_019() {
+    let dex = fake_dex(b"dex\n039\0");
+    let mut vdex = vec![];
+    vdex.extend_from_slice(b"vdex019\0");
+    vdex.extend_from_slice(b"002\0"); // dex section version
+    vdex.extend_from_slice(&1u32.to_le_bytes()); // number of dex files
+    vdex.extend_from_slice(&0u32.to_le_bytes()); // verifier deps size
+    vdex.extend_from_slice(&0u32.to_le_bytes()); // checksum
+    vdex.extend_from_slice(&(4 + dex.len() as u32).to_le_bytes()); // dex size
+    vdex.extend_from_slice(&0u32.to_le_bytes()); // shared data size
+    vdex.extend_from_slice(&0u32.to_le_bytes()); // quickening info size
+    vdex.extend_from_slice(&0u32.to_le_bytes()); // quickening table offset
+    vdex.extend_from_slice(&dex);
+
+    let dex_files = container::extract(&vdex).unwrap();
+    assert_eq!(dex_files.len(), 1);
+    assert_eq!(dex_files[0].kind, DexKind::Standard);
+    assert_eq!(dex_files[0].data, &dex[..]);
+}
+
+#[test]
+fn test_vdex_027() {
+    let dex = fake_dex(b"cdex001\0");
+    let mut vdex = vec![];
+    vdex.extend_from_slice(b"vdex027\0");
+    vdex.extend_from_slice(&2u32.to_le_bytes()); // number of sections
+    vdex.extend_from_slice(&[0, 0, 0, 0, 36, 0, 0, 0, 4, 0, 0, 0]); // checksum section
+    vdex.extend_from_slice(&[1, 0, 0, 0, 40, 0, 0, 0, 0x70, 0, 0, 0]); // dex file section
+    vdex.extend_from_slice(&0u32.to_le_bytes());
+    vdex.extend_from_slice(&dex);
+
+    let dex_files = container::extract(&vdex).unwrap();
+    assert_eq!(dex_files.len(), 1);
+    assert_eq!(dex_files[0].kind, DexKind::Compact);
+}
+
+/// a 64-bit elf with a string table and `rodata`, the section headers are at the end
+fn fake_oat(rodata: &[u8]) -> Vec<u8> {
+    let string_table = b"\0.shstrtab\0.rodata\0";
+    let string_table_offset = 64;
+    let rodata_offset = string_table_offset + string_table.len();
+    let section_headers_offset = rodata_offset + rodata.len();
+
+    let mut elf = vec![0u8; 64];
+    elf[..6].copy_from_slice(b"\x7fELF\x02\x01");
+    elf[0x28..0x30].copy_from_slice(&(section_headers_offset as u64).to_le_bytes());
+    elf[0x3a..0x3c].copy_from_slice(&64u16.to_le_bytes());
+    elf[0x3c..0x3e].copy_from_slice(&3u16.to_le_bytes());
+    elf[0x3e..0x40].copy_from_slice(&1u16.to_le_bytes());
+    elf.extend_from_slice(string_table);
+    elf.extend_from_slice(rodata);
+
+    for (name, offset, size) in [
+        (0u32, 0usize, 0usize),
+        (1, string_table_offset, string_table.len()),
+        (11, rodata_offset, rodata.len()),
+    ] {
+        let mut header = [0u8; 64];
+        header[..4].copy_from_slice(&name.to_le_bytes());
+        header[24..32].copy_from_slice(&(offset as u64).to_le_bytes());
+        header[32..40].copy_from_slice(&(size as u64).to_le_bytes());
+        elf.extend_from_slice(&header);
+    }
+    elf
+}
+
+#[test]
+fn test_oat_rodata() {
+    let mut rodata = b"oat\n183\0".to_vec();
+    rodata.extend_from_slice(&[0u8; 8]);
+    rodata.extend_from_slice(&fake_dex(b"dex\n035\0"));
+    rodata.extend_from_slice(&fake_dex(b"dex\n035\0"));
+
+    let elf = fake_oat(&rodata);
+    let dex_files = container::extract(&elf).unwrap();
+    assert_eq!(dex_files.len(), 2);
+}
+
+#[test]
+fn test_oat_overflow() {
+    let elf = fake_oat(b"oat\n183\0");
+    let section_headers = elf.len() - 3 * 64;
+
+    // section header table past the end of the address space
+    let mut crafted = elf.clone();
+    crafted[0x28..0x30].copy_from_slice(&(u64::MAX - 1).to_le_bytes());
+    assert!(matches!(
+        container::extract(&crafted),
+        Err(Error::InvalidContainer)
+    ));
+
+    // .rodata offset + size wraps around
+    let mut crafted = elf.clone();
+    let rodata_header = section_headers + 2 * 64;
+    crafted[rodata_header + 24..rodata_header + 32].copy_from_slice(&(u64::MAX - 4).to_le_bytes());
+    crafted[rodata_header + 32..rodata_header + 40].copy_from_slice(&16u64.to_le_bytes());
+    assert!(matches!(
+        container::extract(&crafted),
+        Err(Error::InvalidContainer)
+    ));
+}
+
+#[test]
+fn test_cdex_code_item() {
+    let mut cdex = fake_dex(b"cdex001\0");
+    cdex.resize(0x88, 0);
+    cdex.extend_from_slice(&20u16.to_le_bytes()); // preheader registers size extension
+    cdex.extend_from_slice(&((3u16 << 12) | (1 << 8) | (1 << 4)).to_le_bytes()); // 3 registers, 1 in, 1 out
+    cdex.extend_from_slice(&((2u16 << 5) | 1).to_le_bytes()); // 2 code units, preheader registers
+    cdex.extend_from_slice(&[0x0e, 0x00, 0x00, 0x00]); // return-void; nop
+    let file_size = cdex.len() as u32;
+    cdex[0x20..0x24].copy_from_slice(&file_size.to_le_bytes());
+    cdex[0x68..0x6c].copy_from_slice(&file_size.to_le_bytes()); // the whole file as data
+
+    let dex = container::extract(&cdex).unwrap()[0];
+    let cdex = CompactDexFile::new(&dex).unwrap();
+    assert!(cdex.classes().unwrap().is_empty());
+
+    let code = cdex.code_item(0x8a).unwrap();
+    assert_eq!(code.registers_size, 24);
+    assert_eq!(code.ins_size, 1);
+    assert_eq!(code.outs_size, 1);
+    assert_eq!(SmaliDecoder::new(code.insns, None).decode_all().len(), 2);
+}
+
+#[test]
+fn test_standalone_cdex_data_off() {
+    // string data and code item offsets of a standalone cdex are relative to its data_off
+    let mut cdex = fake_dex(b"cdex001\0");
+    cdex.resize(0x88, 0);
+    cdex[0x38..0x40].copy_from_slice(&[1, 0, 0, 0, 0x88, 0, 0, 0]); // string ids
+    cdex[0x40..0x48].copy_from_slice(&[1, 0, 0, 0, 0x8c, 0, 0, 0]); // type ids
+    cdex.extend_from_slice(&0u32.to_le_bytes()); // string data at the start of the data
+    cdex.extend_from_slice(&0u32.to_le_bytes()); // descriptor string
+    let data_off = cdex.len();
+    cdex.extend_from_slice(b"\x03LA;\0\0");
+    cdex.extend_from_slice(&((3u16 << 12) | (1 << 8)).to_le_bytes()); // 3 registers, 1 in
+    cdex.extend_from_slice(&(2u16 << 5).to_le_bytes()); // 2 code units
+    cdex.extend_from_slice(&[0x0e, 0x00, 0x00, 0x00]); // return-void; nop
+    let data_size = (cdex.len() - data_off) as u32;
+    let file_size = cdex.len() as u32;
+    cdex[0x20..0x24].copy_from_slice(&file_size.to_le_bytes());
+    cdex[0x68..0x6c].copy_from_slice(&data_size.to_le_bytes());
+    cdex[0x6c..0x70].copy_from_slice(&(data_off as u32).to_le_bytes());
+
+    let dex = container::extract(&cdex).unwrap()[0];
+    let cdex = CompactDexFile::new(&dex).unwrap();
+    assert_eq!(cdex.type_descriptor(0).unwrap(), "LA;");
+    let code = cdex.code_item(6).unwrap();
+    assert_eq!(code.registers_size, 4);
+    assert_eq!(SmaliDecoder::new(code.insns, None).decode_all().len(), 2);
+}
+
+#[test]
+fn test_cdex_overflow() {
+    // one class `LA;` whose class data holds uleb128 values that overflow a u32 when summed
+    let mut cdex = fake_dex(b"cdex001\0");
+    cdex.resize(0x88, 0);
+    let ids = cdex.len() as u32;
+    cdex[0x38..0x40].copy_from_slice(&[1, 0, 0, 0, ids as u8, 0, 0, 0]); // string ids
+    cdex[0x40..0x48].copy_from_slice(&[1, 0, 0, 0, ids as u8 + 4, 0, 0, 0]); // type ids
+    cdex[0x60..0x68].copy_from_slice(&[1, 0, 0, 0, ids as u8 + 8, 0, 0, 0]); // class defs
+    cdex.extend_from_slice(&(ids + 40).to_le_bytes()); // string data
+    cdex.extend_from_slice(&0u32.to_le_bytes()); // descriptor string
+    let mut class_def = [0u8; 32];
+    class_def[24..28].copy_from_slice(&(ids + 45).to_le_bytes());
+    cdex.extend_from_slice(&class_def);
+    cdex.extend_from_slice(b"\x03LA;\0");
+    cdex.extend_from_slice(&[0xff, 0xff, 0xff, 0xff, 0x0f, 0x01, 0x00, 0x00]);
+
+    // a code item whose registers size extension overflows
+    let code_off = cdex.len() as u32 + 2;
+    cdex.extend_from_slice(&0xffffu16.to_le_bytes());
+    cdex.extend_from_slice(&(3u16 << 12).to_le_bytes());
+    cdex.extend_from_slice(&((1u16 << 5) | 1).to_le_bytes());
+    cdex.extend_from_slice(&[0x0e, 0x00]);
+    let file_size = cdex.len() as u32;
+    cdex[0x20..0x24].copy_from_slice(&file_size.to_le_bytes());
+    cdex[0x68..0x6c].copy_from_slice(&file_size.to_le_bytes()); // the whole file as data
+
+    let dex = container::extract(&cdex).unwrap()[0];
+    let cdex = CompactDexFile::new(&dex).unwrap();
+    assert_eq!(cdex.type_descriptor(0).unwrap(), "LA;");
+    assert!(matches!(cdex.classes(), Err(Error::InvalidContainer)));
+    assert!(matches!(
+        cdex.code_item(code_off),
+        Err(Error::InvalidContainer)
+    ));
+}
+
+#[test]
+#[cfg(feature = "zip")]
+fn test_apk_dex_slices() {
+    let dex = fake_dex(b"dex\n035\0");
+    let mut apk = ZipWriter::new(Cursor::new(vec![]));
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    apk.start_file("classes.dex", stored).unwrap();
+    apk.write_all(&dex).unwrap();
+    apk.start_file("classes2.dex", SimpleFileOptions::default())
+        .unwrap();
+    apk.write_all(&dex).unwrap();
+    let apk = apk.finish().unwrap().into_inner();
+
+    let files = container::dex_slices(&apk).unwrap();
+    assert_eq!(files.len(), 2);
+    // the stored entry is borrowed from the apk, the deflated one had to be inflated
+    assert!(matches!(files[0], Cow::Borrowed(_)));
+    assert!(matches!(files[1], Cow::Owned(_)));
+    assert!(files.iter().all(|file| **file == dex[..]));
+    assert_eq!(container::dex_files(&apk).unwrap(), vec![dex.clone(), dex]);
+}
+
+#[test]
+fn test_input_file() {
+    let dex = fake_dex(b"dex\n035\0");
+    let path = std::env::temp_dir().join(format!("smali_disassembler_{}.dex", std::process::id()));
+    std::fs::write(&path, &dex).unwrap();
+
+    let input = InputFile::open(&path).unwrap();
+    let files = container::dex_slices(&input).unwrap();
+    assert!(matches!(files[0], Cow::Borrowed(_)));
+    assert_eq!(*input, dex[..]);
+
+    drop(files);
+    drop(input);
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,66 @@
+#![cfg(feature = "std")]
+
+use smali_disassembler::{
+    analysis::{cfg::Cfg, liveness::liveness, reaching::DefUseChains},
+    dalvik::registers::Location,
+    SmaliDecoder,
+};
+use std::collections::BTreeSet;
+
+const CODE: [u8; 18] = [
+    0x12, 0x10, // const/4 v0, 1
+    0x38, 0x01, 0x03, 0x00, // if-eqz v1, +3
+    0x12, 0x20, // const/4 v0, 2
+    0x71, 0x10, 0x00, 0x00, 0x00, 0x00, // invoke-static {v0}, method@0
+    0x0b, 0x02, // move-result-wide v2
+    0x10, 0x02, // return-wide v2
+];
+
+#[test]
+fn test_def_use() {
+    let instructions = SmaliDecoder::new(&CODE, None).decode_all();
+
+    let invoke = instructions[3].inst.def_use();
+    assert_eq!(invoke.defs, vec![Location::Result]);
+    assert_eq!(invoke.uses, vec![Location::Register(0)]);
+
+    let move_result = instructions[4].inst.def_use();
+    assert_eq!(
+        move_result.defs,
+        vec![Location::Register(2), Location::Register(3)]
+    );
+    assert_eq!(move_result.uses, vec![Location::Result]);
+}
+
+#[test]
+fn test_liveness() {
+    let instructions = SmaliDecoder::new(&CODE, None).decode_all();
+    let cfg = Cfg::new(&instructions, &[]);
+    let live = liveness(&instructions, &cfg);
+
+    let registers = |regs: &[u16]| -> BTreeSet<Location> {
+        regs.iter().map(|&reg| Location::Register(reg)).collect()
+    };
+    assert_eq!(live.before(0), Some(&registers(&[1])));
+    assert_eq!(live.before(1), Some(&registers(&[0, 1])));
+    assert_eq!(live.before(5), Some(&registers(&[2, 3])));
+    assert_eq!(live.after(5), Some(&registers(&[])));
+}
+
+#[test]
+fn test_def_use_chains() {
+    let instructions = SmaliDecoder::new(&CODE, None).decode_all();
+    let cfg = Cfg::new(&instructions, &[]);
+    let chains = DefUseChains::new(&instructions, &cfg, 4);
+
+    assert_eq!(
+        chains.definitions(3, Location::Register(0)),
+        &[Some(0), Some(2)]
+    );
+    assert_eq!(chains.definitions(1, Location::Register(1)), &[None]);
+    assert_eq!(chains.uses(Some(3)), &[(4, Location::Result)]);
+    assert_eq!(
+        chains.uses(Some(4)),
+        &[(5, Location::Register(2)), (5, Location::Register(3))]
+    );
+}
@@ -0,0 +1,206 @@
+#![cfg(feature = "std")]
+
+use smali_disassembler::{
+    analysis::cfg::{CatchHandler, TryBlock},
+    container::{dex_file::DexFile, standard::StandardDexFile, EmbeddedDex},
+    decompiler::{class_source, java_type, method_source, MethodBody},
+    resolver::Prototype,
+    SmaliDecoder,
+};
+
+mod common;
+
+use common::TestResolver;
+
+fn names() -> TestResolver {
+    TestResolver::new()
+        .with_type(0, "Ljava/io/IOException;")
+        .with_type(1, "Lcom/example/Bar;")
+        .with_field(0, "Lcom/example/Foo;", "bar", "Lcom/example/Bar;")
+        .with_method(0, "Lcom/example/Foo;", "work", &[], "V")
+        .with_method(
+            1,
+            "Lcom/example/Foo;",
+            "log",
+            &["Ljava/lang/Throwable;"],
+            "V",
+        )
+        .with_method(
+            2,
+            "Lcom/example/Bar;",
+            "<init>",
+            &["Lcom/example/Foo;"],
+            "V",
+        )
+}
+
+fn decompile(
+    name: &str,
+    is_static: bool,
+    signature: (&str, &[&str]),
+    registers_size: u16,
+    code: &[u8],
+    tries: &[TryBlock],
+) -> String {
+    let prototype = Prototype {
+        return_type: signature.0.to_string(),
+        parameters: signature.1.iter().map(|param| param.to_string()).collect(),
+    };
+    let instructions = SmaliDecoder::new(code, None).decode_all();
+    let method = MethodBody {
+        class: "Lcom/example/Foo;",
+        name,
+        is_static,
+        prototype: &prototype,
+        registers_size,
+        instructions: &instructions,
+        tries,
+    };
+    method_source(&method, &names(), 0)
+}
+
+#[test]
+fn test_java_type() {
+    assert_eq!(java_type("I"), "int");
+    assert_eq!(java_type("[[J"), "long[][]");
+    assert_eq!(java_type("Ljava/lang/String;"), "String");
+    assert_eq!(
+        java_type("Ljava/lang/reflect/Method;"),
+        "java.lang.reflect.Method"
+    );
+    assert_eq!(
+        java_type("Lcom/example/Foo$Inner;"),
+        "com.example.Foo$Inner"
+    );
+}
+
+#[test]
+fn test_if_else() {
+    let code = [
+        0x37, 0x10, 0x03, 0x00, // if-le v0, v1, +3
+        0x0f, 0x00, // return v0
+        0x0f, 0x01, // return v1
+    ];
+    let source = decompile("max", true, ("I", &["I", "I"]), 2, &code, &[]);
+    assert_eq!(
+        source,
+        "static int max(int p0, int p1) {\n    \
+            if (p0 > p1) {\n        \
+                return p0;\n    \
+            }\n    \
+            return p1;\n\
+        }\n"
+    );
+}
+
+#[test]
+fn test_while_loop() {
+    let code = [
+        0x12, 0x00, // const/4 v0, 0
+        0x12, 0x01, // const/4 v1, 0
+        0x35, 0x21, 0x06, 0x00, // if-ge v1, v2, +6
+        0xb0, 0x10, // add-int/2addr v0, v1
+        0xd8, 0x01, 0x01, 0x01, // add-int/lit8 v1, v1, 1
+        0x28, 0xfb, // goto -5
+        0x0f, 0x00, // return v0
+    ];
+    let source = decompile("sum", true, ("I", &["I"]), 3, &code, &[]);
+    assert_eq!(
+        source,
+        "static int sum(int p0) {\n    \
+            v0 = 0;\n    \
+            v1 = 0;\n    \
+            while (v1 < p0) {\n        \
+                v0 = v0 + v1;\n        \
+                v1 = v1 + 1;\n    \
+            }\n    \
+            return v0;\n\
+        }\n"
+    );
+}
+
+#[test]
+fn test_switch() {
+    let code = [
+        0x2b, 0x01, 0x08, 0x00, 0x00, 0x00, // packed-switch v1, +8
+        0x12, 0xf0, // const/4 v0, -1
+        0x0f, 0x00, // return v0
+        0x12, 0x10, // const/4 v0, 1
+        0x0f, 0x00, // return v0
+        0x00, 0x00, // nop
+        0x00, 0x01, 0x02, 0x00, // packed-switch-payload, 2 targets
+        0x0a, 0x00, 0x00, 0x00, // first key 10
+        0x05, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, // both to +5
+    ];
+    let source = decompile("code", true, ("I", &["I"]), 2, &code, &[]);
+    assert_eq!(
+        source,
+        "static int code(int p0) {\n    \
+            switch (p0) {\n        \
+                case 10:\n        \
+                case 11:\n            \
+                    return 1;\n        \
+                default:\n            \
+                    return -1;\n    \
+            }\n\
+        }\n"
+    );
+}
+
+#[test]
+fn test_try_catch() {
+    let code = [
+        0x71, 0x00, 0x00, 0x00, 0x00, 0x00, // invoke-static {}, Foo.work()
+        0x0e, 0x00, // return-void
+        0x0d, 0x00, // move-exception v0
+        0x71, 0x10, 0x01, 0x00, 0x00, 0x00, // invoke-static {v0}, Foo.log(Throwable)
+        0x0e, 0x00, // return-void
+    ];
+    let tries = [TryBlock {
+        start_addr: 0,
+        insn_count: 3,
+        handlers: vec![CatchHandler {
+            type_idx: Some(0),
+            addr: 4,
+        }],
+    }];
+    let source = decompile("run", true, ("V", &[]), 1, &code, &tries);
+    assert_eq!(
+        source,
+        "static void run() {\n    \
+            try {\n        \
+                com.example.Foo.work();\n    \
+            } catch (java.io.IOException v0) {\n        \
+                com.example.Foo.log(v0);\n        \
+                return;\n    \
+            }\n\
+        }\n"
+    );
+}
+
+#[test]
+fn test_constructor_and_field_store() {
+    let code = [
+        0x22, 0x00, 0x01, 0x00, // new-instance v0, Bar
+        0x70, 0x20, 0x02, 0x00, 0x10, 0x00, // invoke-direct {v0, v1}, Bar.<init>(Foo)
+        0x5b, 0x10, 0x00, 0x00, // iput-object v0, v1, Foo.bar
+        0x0e, 0x00, // return-void
+    ];
+    let source = decompile("init", false, ("V", &[]), 2, &code, &[]);
+    assert_eq!(
+        source,
+        "void init() {\n    this.bar = new com.example.Bar(this);\n}\n"
+    );
+}
+
+#[test]
+fn test_class_source() {
+    let data = include_bytes!("data/hello.dex");
+    let embedded = EmbeddedDex::parse(data).unwrap();
+    let dex = StandardDexFile::new(&embedded).unwrap();
+    let class = dex.find_class("LHello;").unwrap().unwrap();
+
+    let source = class_source(&dex, &class).unwrap();
+    assert!(source.starts_with("class Hello {\n    static int count;\n\n    Hello() {\n"));
+    assert!(source.contains("    static int run(int p0) {\n"));
+}
@@ -0,0 +1,84 @@
+use smali_disassembler::{
+    dalvik::version::DexVersion,
+    errors::{DecodeError, Error},
+    SmaliDecoder,
+};
+
+// nop; invoke-polymorphic {v0}, meth@0, proto@0
+const POLYMORPHIC_CODE: [u8; 10] = [0x00, 0x00, 0xfa, 0x10, 0, 0, 0, 0, 0, 0];
+
+#[test]
+fn test_magic() {
+    assert_eq!(
+        DexVersion::from_magic(b"dex\n035\0"),
+        Some(DexVersion::V035)
+    );
+    assert_eq!(
+        DexVersion::from_magic(b"dex\n039\0"),
+        Some(DexVersion::V039)
+    );
+    assert_eq!(DexVersion::from_magic(b"dey\n035\0"), None);
+    assert_eq!(DexVersion::from_magic(b"dex\n035"), None);
+}
+
+#[test]
+fn test_version_gated_opcodes() {
+    let decoder = SmaliDecoder::new(&POLYMORPHIC_CODE, Some(DexVersion::V038));
+    assert_eq!(decoder.decode_all().len(), 2);
+
+    let decoder = SmaliDecoder::new(&POLYMORPHIC_CODE, None);
+    assert_eq!(decoder.decode_all().len(), 2);
+
+    assert!(!DexVersion::V038.supports_opcode(0xfe));
+    assert!(DexVersion::from_api_level(28).supports_opcode(0xfe));
+}
+
+#[test]
+fn test_rejected_opcode_offset() {
+    let decoder = SmaliDecoder::new(&POLYMORPHIC_CODE, Some(DexVersion::V035));
+    assert!(matches!(
+        decoder.try_decode_all(),
+        Err(DecodeError {
+            offset: 2,
+            error: Error::UnsupportedOpcode
+        })
+    ));
+
+    let decoder = SmaliDecoder::new(&POLYMORPHIC_CODE, Some(DexVersion::V038));
+    assert_eq!(decoder.try_decode_all().unwrap().len(), 2);
+
+    // a truncated instruction is not the end of the code either
+    let decoder = SmaliDecoder::new(&POLYMORPHIC_CODE[..6], None);
+    assert!(matches!(
+        decoder.try_decode_all(),
+        Err(DecodeError {
+            offset: 2,
+            error: Error::ReadByteFailed
+        })
+    ));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_version_from_header() {
+    use smali_disassembler::{
+        container::{dex_file, EmbeddedDex},
+        disassembly::class_text,
+    };
+
+    // `run` of hello.dex starting with a const-method-type, which needs a 039 dex
+    let hello = include_bytes!("data/hello.dex");
+    let dex = dex_file::open(&EmbeddedDex::parse(hello).unwrap()).unwrap();
+    let code_off = dex.find_class("LHello;").unwrap().unwrap().methods[1].code_off as usize;
+    let mut data = hello.to_vec();
+    data[code_off + 16] = 0xff;
+
+    let mut text = |magic: &[u8; 8]| {
+        data[..8].copy_from_slice(magic);
+        let dex = dex_file::open(&EmbeddedDex::parse(&data).unwrap()).unwrap();
+        let class = dex.find_class("LHello;").unwrap().unwrap();
+        class_text(dex.as_ref(), &class).unwrap()
+    };
+    assert!(!text(b"dex\n035\0").contains("const-method-type"));
+    assert!(text(b"dex\n039\0").contains("const-method-type v0"));
+}
@@ -0,0 +1,105 @@
+#![cfg(feature = "parallel")]
+
+use smali_disassembler::{
+    container::{dex_file, EmbeddedDex},
+    disassembly::{class_text, disassemble_all, unique_paths, write_all, SmaliClass},
+};
+use std::{fs, path::PathBuf};
+
+mod common;
+
+const HELLO_DEX: &[u8] = include_bytes!("data/hello.dex");
+
+#[test]
+fn test_disassemble_all() {
+    let embedded = EmbeddedDex::parse(HELLO_DEX).unwrap();
+    let dex_files = vec![
+        dex_file::open(&embedded).unwrap(),
+        dex_file::open(&embedded).unwrap(),
+    ];
+
+    let classes = disassemble_all(&dex_files).unwrap();
+    assert_eq!(classes.len(), 2);
+    assert_eq!(classes[0].dex_index, 0);
+    assert_eq!(classes[1].dex_index, 1);
+    assert_eq!(classes[0].descriptor, "LHello;");
+
+    let serial = class_text(dex_files[0].as_ref(), &dex_files[0].classes().unwrap()[0]).unwrap();
+    assert_eq!(classes[0].text, serial);
+    assert!(serial.starts_with(".class LHello;\n.super Ljava/lang/Object;\n"));
+    assert!(serial.contains(".catchall"));
+}
+
+fn class(dex_index: usize, descriptor: &str) -> SmaliClass {
+    SmaliClass {
+        dex_index,
+        descriptor: descriptor.to_string(),
+        text: String::new(),
+    }
+}
+
+#[test]
+fn test_path() {
+    assert_eq!(
+        class(0, "Lcom/example/Main$1;").path(),
+        PathBuf::from("smali/com/example/Main$1.smali")
+    );
+    assert_eq!(
+        class(2, "La.b;").path(),
+        PathBuf::from("smali_classes3/a.b.smali")
+    );
+    assert_eq!(
+        class(0, "L../../etc/passwd;").path(),
+        PathBuf::from("smali/etc/passwd.smali")
+    );
+}
+
+#[test]
+fn test_unique_paths() {
+    let classes = [
+        class(0, "La/b;"),
+        class(0, "La/../b;"),
+        class(0, "LA/B;"),
+        class(0, "La/b.2;"),
+        class(1, "La/b;"),
+    ];
+    assert_eq!(
+        unique_paths(&classes),
+        [
+            "smali/a/b.smali",
+            "smali/a/b.2.smali",
+            "smali/A/B.3.smali",
+            "smali/a/b.2.2.smali",
+            "smali_classes2/a/b.smali",
+        ]
+        .map(PathBuf::from)
+    );
+}
+
+#[test]
+fn test_write_all() {
+    let embedded = EmbeddedDex::parse(HELLO_DEX).unwrap();
+    let dex_files = vec![dex_file::open(&embedded).unwrap()];
+    let out = std::env::temp_dir().join(format!("smali_disassembler_{}", std::process::id()));
+
+    write_all(&dex_files, &out).unwrap();
+    let text = fs::read_to_string(out.join("smali/Hello.smali")).unwrap();
+    fs::remove_dir_all(&out).unwrap();
+
+    assert_eq!(text, disassemble_all(&dex_files).unwrap()[0].text);
+}
+
+#[test]
+fn test_compact_dex() {
+    let cdex = common::compact_dex(HELLO_DEX);
+    let dex_files = [
+        dex_file::open(&EmbeddedDex::parse(HELLO_DEX).unwrap()).unwrap(),
+        dex_file::open(&EmbeddedDex::parse(&cdex).unwrap()).unwrap(),
+    ];
+
+    let [standard, compact] = dex_files.each_ref().map(|dex| {
+        let class = dex.find_class("LHello;").unwrap().unwrap();
+        class_text(dex.as_ref(), &class).unwrap()
+    });
+    assert_eq!(compact, standard);
+}
@@ -0,0 +1,50 @@
+#![cfg(feature = "std")]
+
+use smali_disassembler::{
+    analysis::{cfg::Cfg, dominators::DominanceInfo},
+    SmaliDecoder,
+};
+use std::collections::BTreeSet;
+
+#[test]
+fn test_guard_and_loop() {
+    let code = [
+        0x38, 0x00, 0x03, 0x00, // if-eqz v0, +3
+        0x12, 0x11, // const/4 v1, 1
+        0xd8, 0x00, 0x00, 0xff, // add-int/lit8 v0, v0, -1
+        0x39, 0x00, 0xfe, 0xff, // if-nez v0, -2
+        0x0e, 0x00, // return-void
+    ];
+    let instructions = SmaliDecoder::new(&code, None).decode_all();
+    let cfg = Cfg::new(&instructions, &[]);
+    let blocks: Vec<_> = cfg
+        .blocks
+        .iter()
+        .map(|block| (block.start, block.end))
+        .collect();
+    assert_eq!(blocks, vec![(0, 1), (1, 2), (2, 4), (4, 5)]);
+
+    let info = DominanceInfo::new(&cfg);
+    let idoms: Vec<_> = (0..4)
+        .map(|block| info.dominators.immediate_dominator(block))
+        .collect();
+    assert_eq!(idoms, vec![None, Some(0), Some(0), Some(2)]);
+
+    let ipdoms: Vec<_> = (0..4)
+        .map(|block| info.post_dominators.immediate_post_dominator(block))
+        .collect();
+    assert_eq!(ipdoms, vec![Some(2), Some(2), Some(3), None]);
+    assert!(info.post_dominators.post_dominates(3, 0));
+    assert!(!info.post_dominators.post_dominates(1, 0));
+
+    assert_eq!(info.frontiers[1], BTreeSet::from([2]));
+    assert_eq!(info.frontiers[2], BTreeSet::from([2]));
+    assert_eq!(info.control_dependents[0], BTreeSet::from([1]));
+    assert_eq!(info.control_dependents[2], BTreeSet::from([2]));
+
+    assert_eq!(info.loops.len(), 1);
+    assert_eq!(info.loops[0].header, 2);
+    assert_eq!(info.loops[0].back_edges, vec![2]);
+    assert_eq!(info.loops[0].blocks, BTreeSet::from([2]));
+    assert_eq!(info.loops[0].exits(&cfg), vec![(2, 3)]);
+}
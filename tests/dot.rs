@@ -0,0 +1,84 @@
+#![cfg(feature = "std")]
+
+use smali_disassembler::{
+    analysis::{
+        cfg::{CatchHandler, Cfg, TryBlock},
+        dot::{cfg_to_dot, Overlay},
+    },
+    dalvik::smali::instruction_text,
+    SmaliDecoder,
+};
+
+mod common;
+
+use common::TestResolver;
+
+fn names() -> TestResolver {
+    TestResolver::new()
+        .with_string(0, "say \"hi\"")
+        .with_type(0, "Ljava/io/IOException;")
+        .with_method(0, "LFoo;", "run", &["I"], "V")
+}
+
+#[test]
+fn test_smali_text() {
+    let code = [
+        0x12, 0xf0, // const/4 v0, -1
+        0x1a, 0x01, 0x00, 0x00, // const-string v1, string@0
+        0x71, 0x10, 0x00, 0x00, 0x00, 0x00, // invoke-static {v0}, method@0
+        0x38, 0x00, 0xfb, 0xff, // if-eqz v0, -5
+        0xd8, 0x00, 0x00, 0x02, // add-int/lit8 v0, v0, 2
+        0x0e, 0x00, // return-void
+    ];
+    let text: Vec<String> = SmaliDecoder::new(&code, None)
+        .decode_all()
+        .iter()
+        .map(|instruction| instruction_text(instruction, &names()))
+        .collect();
+    assert_eq!(
+        text,
+        vec![
+            "const/4 v0, -0x1",
+            "const-string v1, \"say \\\"hi\\\"\"",
+            "invoke-static {v0}, LFoo;->run(I)V",
+            "if-eqz v0, :addr_1",
+            "add-int/lit8 v0, v0, 0x2",
+            "return-void",
+        ]
+    );
+}
+
+#[test]
+fn test_cfg_to_dot() {
+    let code = [
+        0x71, 0x10, 0x00, 0x00, 0x00, 0x00, // invoke-static {v0}, method@0
+        0x38, 0x00, 0x03, 0x00, // if-eqz v0, +3
+        0x0e, 0x00, // return-void
+        0x0d, 0x00, // move-exception v0
+        0x0e, 0x00, // return-void
+    ];
+    let instructions = SmaliDecoder::new(&code, None).decode_all();
+    let tries = [TryBlock {
+        start_addr: 0,
+        insn_count: 3,
+        handlers: vec![CatchHandler {
+            type_idx: Some(0),
+            addr: 6,
+        }],
+    }];
+    let cfg = Cfg::new(&instructions, &tries);
+    let dot = cfg_to_dot("LFoo;->f()V", &instructions, &cfg, &names(), &[]);
+
+    assert!(dot.starts_with("digraph \"LFoo;->f()V\" {\n"));
+    assert!(dot.contains("0000: invoke-static {v0}, LFoo;->run(I)V\\l"));
+    assert!(dot.contains("[color=green, label=\"taken\"]"));
+    assert!(dot.contains("[color=black]"));
+    assert!(dot.contains("[color=red, style=dashed, label=\"Ljava/io/IOException;\"]"));
+    assert!(!dot.contains("idom"));
+    assert!(dot.ends_with("}\n"));
+
+    let overlaid = cfg_to_dot("f", &instructions, &cfg, &names(), &[Overlay::Dominators]);
+    let idoms = overlaid.matches("label=\"idom\"").count();
+    // every reachable block but the entry has an immediate dominator
+    assert_eq!(idoms, cfg.blocks.len() - 1);
+}
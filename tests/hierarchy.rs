@@ -0,0 +1,288 @@
+#![cfg(feature = "std")]
+
+use smali_disassembler::{
+    analysis::{
+        classfile::read_class_file,
+        hierarchy::{ClassHierarchy, ClassInfo, MethodInfo, Visibility},
+    },
+    dalvik::opcodes::InvokeKind,
+};
+#[cfg(feature = "zip")]
+use {
+    std::io::{Cursor, Write},
+    zip::{write::SimpleFileOptions, ZipWriter},
+};
+
+fn method(name: &str, visibility: Visibility, is_abstract: bool) -> MethodInfo {
+    MethodInfo {
+        name: name.to_string(),
+        descriptor: "()V".to_string(),
+        visibility,
+        is_static: false,
+        is_abstract,
+        is_native: false,
+        has_code: !is_abstract,
+    }
+}
+
+fn class(
+    descriptor: &str,
+    super_class: Option<&str>,
+    interfaces: &[&str],
+    methods: Vec<MethodInfo>,
+) -> ClassInfo {
+    ClassInfo {
+        descriptor: descriptor.to_string(),
+        super_class: super_class.map(str::to_string),
+        interfaces: interfaces
+            .iter()
+            .map(|interface| interface.to_string())
+            .collect(),
+        is_interface: false,
+        is_abstract: false,
+        methods,
+    }
+}
+
+fn interface(descriptor: &str, interfaces: &[&str], methods: Vec<MethodInfo>) -> ClassInfo {
+    ClassInfo {
+        is_interface: true,
+        is_abstract: true,
+        ..class(descriptor, Some("Ljava/lang/Object;"), interfaces, methods)
+    }
+}
+
+/// `LRunnable;` <- `LTask;` (default run) <- `La/Base;` <- `La/Child;` <- `Lb/Other;`
+fn hierarchy() -> ClassHierarchy {
+    let mut hierarchy = ClassHierarchy::new();
+    hierarchy.add_class(interface(
+        "LRunnable;",
+        &[],
+        vec![method("run", Visibility::Public, true)],
+    ));
+    hierarchy.add_class(interface(
+        "LTask;",
+        &["LRunnable;"],
+        vec![
+            method("run", Visibility::Public, false),
+            method("cancel", Visibility::Public, false),
+        ],
+    ));
+    hierarchy.add_class(class(
+        "La/Base;",
+        Some("Ljava/lang/Object;"),
+        &["LTask;"],
+        vec![
+            method("<init>", Visibility::Public, false),
+            method("run", Visibility::Public, false),
+            method("step", Visibility::Package, false),
+            method("secret", Visibility::Private, false),
+        ],
+    ));
+    hierarchy.add_class(class(
+        "La/Child;",
+        Some("La/Base;"),
+        &[],
+        vec![
+            method("run", Visibility::Public, false),
+            method("step", Visibility::Package, false),
+            method("secret", Visibility::Public, false),
+        ],
+    ));
+    hierarchy.add_class(class(
+        "Lb/Other;",
+        Some("La/Child;"),
+        &[],
+        vec![
+            method("run", Visibility::Public, false),
+            method("step", Visibility::Package, false),
+        ],
+    ));
+    hierarchy
+}
+
+fn names<'a>(methods: &[(&'a str, &MethodInfo)]) -> Vec<&'a str> {
+    methods.iter().map(|&(class, _)| class).collect()
+}
+
+#[test]
+fn test_override_chain() {
+    let hierarchy = hierarchy();
+
+    assert_eq!(
+        names(&hierarchy.override_chain("Lb/Other;", "run", "()V")),
+        ["Lb/Other;", "La/Child;", "La/Base;", "LTask;", "LRunnable;"]
+    );
+    // a package private method is not overridden from another package
+    assert_eq!(
+        names(&hierarchy.override_chain("Lb/Other;", "step", "()V")),
+        ["Lb/Other;"]
+    );
+    assert_eq!(
+        names(&hierarchy.override_chain("La/Child;", "step", "()V")),
+        ["La/Child;", "La/Base;"]
+    );
+    // nor is a private one
+    assert_eq!(
+        names(&hierarchy.override_chain("La/Child;", "secret", "()V")),
+        ["La/Child;"]
+    );
+
+    assert_eq!(
+        names(&hierarchy.overriding_methods("La/Base;", "run", "()V")),
+        ["La/Child;", "Lb/Other;"]
+    );
+    assert_eq!(
+        names(&hierarchy.overriding_methods("La/Base;", "step", "()V")),
+        ["La/Child;"]
+    );
+}
+
+#[test]
+fn test_dispatch() {
+    let hierarchy = hierarchy();
+
+    assert_eq!(
+        hierarchy.dispatch("Lb/Other;", "run", "()V").unwrap().0,
+        "Lb/Other;"
+    );
+    // default method of an interface of a superclass
+    assert_eq!(
+        hierarchy.dispatch("Lb/Other;", "cancel", "()V").unwrap().0,
+        "LTask;"
+    );
+    // private methods are not dispatched
+    assert_eq!(hierarchy.dispatch("La/Base;", "secret", "()V"), None);
+    assert_eq!(hierarchy.dispatch("La/Base;", "missing", "()V"), None);
+}
+
+#[test]
+fn test_resolve_invoke() {
+    let hierarchy = hierarchy();
+
+    // super.run() from Other runs Child's implementation
+    let (class, _) = hierarchy
+        .resolve_invoke(InvokeKind::Super, "Lb/Other;", "La/Child;", "run", "()V")
+        .unwrap();
+    assert_eq!(class, "La/Child;");
+    // inherited from further up
+    let (class, _) = hierarchy
+        .resolve_invoke(InvokeKind::Super, "Lb/Other;", "La/Child;", "cancel", "()V")
+        .unwrap();
+    assert_eq!(class, "LTask;");
+    // Task.super.run() from Base
+    let (class, _) = hierarchy
+        .resolve_invoke(InvokeKind::Super, "La/Base;", "LTask;", "run", "()V")
+        .unwrap();
+    assert_eq!(class, "LTask;");
+    assert_eq!(
+        hierarchy.resolve_invoke(InvokeKind::Super, "La/Base;", "LRunnable;", "run", "()V"),
+        None
+    );
+
+    let (class, _) = hierarchy
+        .resolve_invoke(InvokeKind::Direct, "La/Base;", "La/Base;", "secret", "()V")
+        .unwrap();
+    assert_eq!(class, "La/Base;");
+    assert_eq!(
+        hierarchy.resolve_invoke(
+            InvokeKind::Direct,
+            "Lb/Other;",
+            "Lb/Other;",
+            "<init>",
+            "()V"
+        ),
+        None
+    );
+    // virtual calls resolve to the declaration of the referenced class
+    let (class, _) = hierarchy
+        .resolve_invoke(InvokeKind::Virtual, "LMain;", "Lb/Other;", "cancel", "()V")
+        .unwrap();
+    assert_eq!(class, "LTask;");
+}
+
+/// `public class android.app.Activity { public void onCreate(android.os.Bundle) }`
+fn activity_class_file() -> Vec<u8> {
+    let utf8 = |text: &str| {
+        let mut entry = vec![1];
+        entry.extend((text.len() as u16).to_be_bytes());
+        entry.extend(text.as_bytes());
+        entry
+    };
+
+    let mut data = vec![0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x34];
+    data.extend(10u16.to_be_bytes());
+    data.extend(utf8("android/app/Activity")); // 1
+    data.extend([7, 0, 1]); // 2
+    data.extend(utf8("java/lang/Object")); // 3
+    data.extend([7, 0, 3]); // 4
+    data.extend(utf8("onCreate")); // 5
+    data.extend(utf8("(Landroid/os/Bundle;)V")); // 6
+    data.extend([5, 0, 0, 0, 0, 0, 0, 0, 1]); // 7 and 8
+    data.extend(utf8("Code")); // 9
+
+    // access flags, this class, super class, interfaces, fields
+    data.extend([0x00, 0x21, 0, 2, 0, 4, 0, 0, 0, 0]);
+    // one method with a two byte attribute
+    data.extend([0, 1, 0x00, 0x01, 0, 5, 0, 6, 0, 1, 0, 9, 0, 0, 0, 2, 0, 0]);
+    // class attributes
+    data.extend([0, 0]);
+    data
+}
+
+#[test]
+fn test_class_file() {
+    let class = read_class_file(&activity_class_file()).unwrap();
+    assert_eq!(class.descriptor, "Landroid/app/Activity;");
+    assert_eq!(class.super_class.as_deref(), Some("Ljava/lang/Object;"));
+    assert!(!class.is_interface);
+
+    let on_create = class.method("onCreate", "(Landroid/os/Bundle;)V").unwrap();
+    assert_eq!(on_create.visibility, Visibility::Public);
+    assert!(!on_create.has_code);
+
+    let class_file = activity_class_file();
+    assert!(read_class_file(&class_file[..class_file.len() - 3]).is_err());
+    assert!(read_class_file(b"not a class file").is_err());
+}
+
+#[test]
+fn test_class_file_long_in_last_slot() {
+    // a long takes slots 1 and 2 but the pool only has slot 1
+    let mut data = vec![0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x34];
+    data.extend(2u16.to_be_bytes());
+    data.extend([5, 0, 0, 0, 0, 0, 0, 0, 1]);
+    assert!(read_class_file(&data).is_err());
+}
+
+#[test]
+#[cfg(feature = "zip")]
+fn test_framework_stubs() {
+    let mut jar = ZipWriter::new(Cursor::new(vec![]));
+    jar.start_file("android/app/Activity.class", SimpleFileOptions::default())
+        .unwrap();
+    jar.write_all(&activity_class_file()).unwrap();
+    jar.start_file("META-INF/MANIFEST.MF", SimpleFileOptions::default())
+        .unwrap();
+    jar.write_all(b"Manifest-Version: 1.0\n").unwrap();
+    let jar = jar.finish().unwrap().into_inner();
+
+    let mut hierarchy = ClassHierarchy::new();
+    hierarchy.add_framework(&jar).unwrap();
+    hierarchy.add_class(class(
+        "Lcom/example/Main;",
+        Some("Landroid/app/Activity;"),
+        &[],
+        vec![],
+    ));
+
+    assert!(hierarchy.is_framework("Landroid/app/Activity;"));
+    assert!(!hierarchy.is_framework("Lcom/example/Main;"));
+    assert!(hierarchy.is_subtype_of("Lcom/example/Main;", "Landroid/app/Activity;"));
+    let (class, _) = hierarchy
+        .dispatch("Lcom/example/Main;", "onCreate", "(Landroid/os/Bundle;)V")
+        .unwrap();
+    assert_eq!(class, "Landroid/app/Activity;");
+
+    assert!(hierarchy.add_framework(b"neither a jar nor a dex").is_err());
+}
@@ -0,0 +1,67 @@
+#![cfg(feature = "std")]
+
+use sha1::{Digest, Sha1};
+use smali_disassembler::integrity::{self, Finding};
+
+/// a dex file holding nothing but its header and map list
+fn minimal_dex() -> Vec<u8> {
+    let mut dex = vec![0u8; 0x70];
+    dex[..8].copy_from_slice(b"dex\n035\0");
+    dex[0x20..0x24].copy_from_slice(&0x8cu32.to_le_bytes());
+    dex[0x24..0x28].copy_from_slice(&0x70u32.to_le_bytes());
+    dex[0x28..0x2c].copy_from_slice(&0x12345678u32.to_le_bytes());
+    dex[0x34..0x38].copy_from_slice(&0x70u32.to_le_bytes());
+
+    dex.extend_from_slice(&2u32.to_le_bytes());
+    dex.extend_from_slice(&[0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]); // header item
+    dex.extend_from_slice(&[0, 0x10, 0, 0, 1, 0, 0, 0, 0x70, 0, 0, 0]); // map list
+    sign(&mut dex);
+    dex
+}
+
+fn sign(dex: &mut [u8]) {
+    let signature = Sha1::digest(&dex[0x20..]);
+    dex[0xc..0x20].copy_from_slice(&signature);
+    let checksum = integrity::adler32(&dex[0xc..]);
+    dex[0x8..0xc].copy_from_slice(&checksum.to_le_bytes());
+}
+
+#[test]
+fn test_valid_dex() {
+    assert_eq!(integrity::verify(&minimal_dex()), vec![]);
+}
+
+#[test]
+fn test_tampered_dex() {
+    let mut dex = minimal_dex();
+    dex[0x28..0x2c].copy_from_slice(&0x78563412u32.to_le_bytes());
+    dex[0x88] = 0x90; // the map list entry now points past the end of the file
+
+    let findings = integrity::verify(&dex);
+    assert!(findings.contains(&Finding::ReverseEndianTag));
+    assert!(findings
+        .iter()
+        .any(|finding| matches!(finding, Finding::ChecksumMismatch(..))));
+    assert!(findings
+        .iter()
+        .any(|finding| matches!(finding, Finding::SignatureMismatch(..))));
+    assert!(findings
+        .iter()
+        .any(|finding| matches!(finding, Finding::MapItemOutOfBounds(0x1000, ..))));
+}
+
+#[test]
+fn test_oversized_tables() {
+    let mut dex = minimal_dex();
+    // a map list and string id table whose byte sizes overflow a 32-bit usize
+    dex[0x70..0x74].copy_from_slice(&0x8000_0000u32.to_le_bytes());
+    dex[0x38..0x3c].copy_from_slice(&0xffff_ffffu32.to_le_bytes());
+    dex[0x3c..0x40].copy_from_slice(&0x70u32.to_le_bytes());
+    sign(&mut dex);
+
+    let findings = integrity::verify(&dex);
+    assert!(findings.contains(&Finding::MapListOutOfBounds(0x70)));
+    assert!(!findings
+        .iter()
+        .any(|finding| matches!(finding, Finding::UnsortedIds(..))));
+}
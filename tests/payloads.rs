@@ -0,0 +1,77 @@
+use smali_disassembler::{dalvik::opcodes::DalvikBytecode, SmaliDecoder};
+
+fn decode(code: &[u8]) -> Vec<(usize, DalvikBytecode)> {
+    SmaliDecoder::new(code, None)
+        .decode_all()
+        .into_iter()
+        .map(|instruction| (instruction.offset, instruction.inst))
+        .collect()
+}
+
+#[test]
+fn test_little_endian_operands() {
+    let code = [
+        0x14, 0x00, 0x78, 0x56, 0x34, 0x12, // const v0, 0x12345678
+        0x18, 0x02, 0xef, 0xcd, 0xab, 0x89, 0x67, 0x45, 0x23, 0x01, // const-wide v2, ...
+        0x1f, 0x01, 0x03, 0x00, // check-cast v1, type@3
+    ];
+
+    assert_eq!(
+        decode(&code),
+        vec![
+            (0x0, DalvikBytecode::Const(0, 0x12345678)),
+            (0x6, DalvikBytecode::ConstWide(2, 0x0123456789abcdef)),
+            (0x10, DalvikBytecode::CheckCast(1, 3)),
+        ]
+    );
+}
+
+#[test]
+fn test_switch_payloads() {
+    let code = [
+        0x2b, 0x00, 0x04, 0x00, 0x00, 0x00, // packed-switch v0, +4
+        0x2c, 0x00, 0x0a, 0x00, 0x00, 0x00, // sparse-switch v0, +10
+        0x00, 0x00, // nop
+        0x00, 0x01, 0x02, 0x00, 0x0a, 0x00, 0x00, 0x00, // packed-switch-payload, first key 10
+        0x05, 0x00, 0x00, 0x00, 0x07, 0x00, 0x00, 0x00, // targets
+        0x00, 0x02, 0x02, 0x00, // sparse-switch-payload
+        0x01, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x00, // keys
+        0x04, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, // targets
+    ];
+
+    assert_eq!(
+        decode(&code),
+        vec![
+            (0x0, DalvikBytecode::PackedSwitch(0, 4)),
+            (0x6, DalvikBytecode::SparseSwitch(0, 10)),
+            (0xc, DalvikBytecode::Nop),
+            (0xe, DalvikBytecode::PackedSwitchPayload(10, vec![5, 7])),
+            (
+                0x1e,
+                DalvikBytecode::SparseSwitchPayload(vec![1, 10], vec![4, 8])
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_fill_array_data_payload() {
+    let code = [
+        0x26, 0x00, 0x04, 0x00, 0x00, 0x00, // fill-array-data v0, +4
+        0x0e, 0x00, // return-void
+        0x00, 0x03, 0x01, 0x00, 0x03, 0x00, 0x00, 0x00, // fill-array-data-payload, 3 bytes
+        0x01, 0x02, 0x03, 0x00, // data, padded to a whole code unit
+        0x0e, 0x00, // return-void
+    ];
+
+    let instructions = decode(&code);
+    assert_eq!(
+        instructions[0],
+        (0x0, DalvikBytecode::FilledArrayData(0, 4))
+    );
+    assert_eq!(
+        instructions[2],
+        (0x8, DalvikBytecode::FillArrayDataPayload(1, vec![1, 2, 3]))
+    );
+    assert_eq!(instructions[3].0, 0x14);
+}
@@ -0,0 +1,105 @@
+use smali_disassembler::{
+    dalvik::{
+        opcodes::{ArgumentRegisters, DalvikBytecode, InvokeKind, OpKind, ReturnKind},
+        quickening::{dequicken, QuickeningInfo},
+    },
+    SmaliDecoder,
+};
+
+// iget-quick v0, v1, [obj+0x8]; invoke-virtual-quick {v1}, vtable@0x2; return-void-no-barrier
+const QUICKENED_CODE: [u8; 12] = [
+    0xe3, 0x10, 0x08, 0x00, 0xe9, 0x10, 0x02, 0x00, 0x01, 0x00, 0x73, 0x00,
+];
+
+#[test]
+fn test_quickened_opcodes_are_opt_in() {
+    let decoder = SmaliDecoder::new(&QUICKENED_CODE, None);
+    assert!(decoder.decode_all().is_empty());
+
+    let decoder = SmaliDecoder::new(&QUICKENED_CODE, None).with_quickened(true);
+    let instructions = decoder.decode_all();
+    assert_eq!(instructions.len(), 3);
+    assert!(matches!(
+        instructions[0].inst,
+        DalvikBytecode::InstanceOpQuick(OpKind::Get, 0, 1, _)
+    ));
+    assert_eq!(instructions[2].inst, DalvikBytecode::ReturnVoidNoBarrier);
+}
+
+#[test]
+fn test_dequicken() {
+    let decoder = SmaliDecoder::new(&QUICKENED_CODE, None).with_quickened(true);
+    let mut instructions = decoder.decode_all();
+
+    let info = QuickeningInfo::from_u16_table(&[0x05, 0x00, 0x07, 0x00]);
+    dequicken(&mut instructions, &info).unwrap();
+
+    assert_eq!(
+        instructions[0].inst,
+        DalvikBytecode::InstanceOp(OpKind::Get, 0, 1, 5)
+    );
+    assert_eq!(
+        instructions[1].inst,
+        DalvikBytecode::Invoke(
+            InvokeKind::Virtual,
+            ArgumentRegisters::new(&[1]).unwrap(),
+            7
+        )
+    );
+    assert_eq!(
+        instructions[2].inst,
+        DalvikBytecode::Return(ReturnKind::ReturnVoid, 0)
+    );
+
+    let mut instructions = decoder.decode_all();
+    let info = QuickeningInfo::from_u16_table(&[0x05, 0x00]);
+    assert!(dequicken(&mut instructions, &info).is_err());
+}
+
+#[test]
+fn test_dequicken_nops() {
+    #[rustfmt::skip]
+    let code = [
+        0x00, 0x00, // nop
+        0x00, 0x00, 0x00, 0x00, // check-cast v2, type@9 elided to two nops
+        0xe3, 0x10, 0x08, 0x00, // iget-quick v0, v1, [obj+0x8]
+        0x73, 0x00, // return-void-no-barrier
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // empty packed-switch payload
+    ];
+    let decoder = SmaliDecoder::new(&code, None).with_quickened(true);
+    let mut instructions = decoder.decode_all();
+    assert_eq!(instructions.len(), 6);
+
+    #[rustfmt::skip]
+    let info = QuickeningInfo::from_u16_table(&[
+        0xff, 0xff, 0x02, 0x00, 0x09, 0x00, 0x05, 0x00, 0xff, 0xff,
+    ]);
+    dequicken(&mut instructions, &info).unwrap();
+
+    let dequickened: Vec<_> = instructions
+        .iter()
+        .map(|instruction| (instruction.offset, &instruction.inst))
+        .collect();
+    assert_eq!(
+        dequickened,
+        [
+            (0, &DalvikBytecode::Nop),
+            (2, &DalvikBytecode::CheckCast(2, 9)),
+            (6, &DalvikBytecode::InstanceOp(OpKind::Get, 0, 1, 5)),
+            (10, &DalvikBytecode::Return(ReturnKind::ReturnVoid, 0)),
+            (12, &DalvikBytecode::PackedSwitchPayload(0, vec![])),
+        ]
+    );
+
+    // without an entry for the payload the table is one short
+    let mut instructions = decoder.decode_all();
+    let info = QuickeningInfo::from_u16_table(&[0xff, 0xff, 0x02, 0x00, 0x09, 0x00, 0x05, 0x00]);
+    assert!(dequicken(&mut instructions, &info).is_err());
+}
+
+#[test]
+fn test_leb128_index_out_of_range() {
+    // dex pc 0, index 0x10000
+    assert!(QuickeningInfo::from_leb128_pairs(&[0x00, 0x80, 0x80, 0x04]).is_err());
+    assert!(QuickeningInfo::from_leb128_pairs(&[0x00, 0xff, 0xff, 0x03]).is_ok());
+}
@@ -0,0 +1,76 @@
+#![cfg(feature = "serde")]
+
+use smali_disassembler::{
+    analysis::xrefs::XrefTarget,
+    dalvik::{opcodes::DalvikBytecode, DalvikInstruction},
+    export::MethodRecord,
+    SmaliDecoder,
+};
+
+mod common;
+
+use common::TestResolver;
+
+fn names() -> TestResolver {
+    TestResolver::new().with_string(0, "hello").with_method(
+        0,
+        "LFoo;",
+        "say",
+        &["Ljava/lang/String;"],
+        "V",
+    )
+}
+
+const CODE: [u8; 12] = [
+    0x1a, 0x00, 0x00, 0x00, // const-string v0, string@0
+    0x71, 0x10, 0x00, 0x00, 0x00, 0x00, // invoke-static {v0}, method@0
+    0x0e, 0x00, // return-void
+];
+
+#[test]
+fn test_instruction_round_trip() {
+    let instructions = SmaliDecoder::new(&CODE, None).decode_all();
+    let json = serde_json::to_string(&instructions).unwrap();
+    let decoded: Vec<DalvikInstruction> = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, instructions);
+}
+
+#[test]
+fn test_argument_registers() {
+    let instructions = SmaliDecoder::new(&CODE, None).decode_all();
+    let json = serde_json::to_value(&instructions[1].inst).unwrap();
+    assert_eq!(json["Invoke"][1], serde_json::json!([0]));
+
+    let too_many = serde_json::json!({"Invoke": ["Static", [0, 1, 2, 3, 4, 5], 0]});
+    assert!(serde_json::from_value::<DalvikBytecode>(too_many).is_err());
+}
+
+#[test]
+fn test_method_record() {
+    let instructions = SmaliDecoder::new(&CODE, None).decode_all();
+    let record = MethodRecord::new("LFoo;", "run", "()V", 1, &instructions, &names());
+
+    assert_eq!(record.instructions.len(), 3);
+    assert_eq!(record.instructions[1].offset, 4);
+    assert_eq!(
+        record.instructions[0].reference,
+        Some(XrefTarget::String("hello".to_string()))
+    );
+    assert_eq!(
+        record.instructions[1].text,
+        "invoke-static {v0}, LFoo;->say(Ljava/lang/String;)V"
+    );
+    assert_eq!(record.instructions[2].reference, None);
+
+    let json = serde_json::to_value(&record).unwrap();
+    assert_eq!(json["class"], "LFoo;");
+    assert_eq!(json["signature"], "()V");
+    assert_eq!(json["registers"], 1);
+    assert_eq!(
+        json["instructions"][1]["reference"]["Method"],
+        "LFoo;->say(Ljava/lang/String;)V"
+    );
+
+    let decoded: MethodRecord = serde_json::from_value(json).unwrap();
+    assert_eq!(decoded, record);
+}
@@ -1,4 +1,4 @@
-use smali_disassembler::SmaliDecoder;
+use smali_disassembler::{dalvik::version::DexVersion, SmaliDecoder};
 use std::{fs::File, io::Read};
 use zip::ZipArchive;
 
@@ -10,6 +10,7 @@ fn test_wow() -> Result<(), Box<dyn std::error::Error>> {
 
     let mut dex_file = archive.by_name("classes.dex")?;
     dex_file.read_to_end(&mut file_data)?;
+    let version = DexVersion::from_magic(&file_data);
     let dex_object = dex::DexReader::from_vec(file_data)?;
 
     for c in dex_object.classes() {
@@ -17,14 +18,14 @@ fn test_wow() -> Result<(), Box<dyn std::error::Error>> {
         for method in c.methods() {
             let class_name = dex_object.get_type(c.id())?.to_string();
             if let Some(code) = method.code() {
-                println!("{}->{}", class_name, method.name().to_string());
+                println!("{}->{}", class_name, method.name());
                 let code: Vec<u8> = code
                     .insns()
                     .iter()
                     .flat_map(|num| num.to_ne_bytes())
                     .collect();
-                let decoder = SmaliDecoder::new(&code);
-                let please = decoder.decode_all();
+                let decoder = SmaliDecoder::new(&code, version);
+                let _please = decoder.decode_all();
                 return Ok(());
             }
         }
@@ -0,0 +1,147 @@
+#![cfg(feature = "std")]
+
+use smali_disassembler::{
+    analysis::{
+        cfg::{CatchHandler, Cfg, TryBlock},
+        dominators::Dominators,
+        reaching::DefUseChains,
+        ssa::{PhiOperand, Ssa, Value, ValueSource},
+    },
+    dalvik::registers::Location,
+    SmaliDecoder,
+};
+
+#[test]
+fn test_phi_at_join() {
+    let code = [
+        0x12, 0x10, // const/4 v0, 1
+        0x38, 0x01, 0x03, 0x00, // if-eqz v1, +3
+        0x12, 0x20, // const/4 v0, 2
+        0x71, 0x10, 0x00, 0x00, 0x00, 0x00, // invoke-static {v0}, method@0
+        0x0b, 0x02, // move-result-wide v2
+        0x10, 0x02, // return-wide v2
+    ];
+    let instructions = SmaliDecoder::new(&code, None).decode_all();
+    let cfg = Cfg::new(&instructions, &[]);
+    let ssa = Ssa::new(&instructions, &cfg, 4);
+
+    let join = cfg.block_of(3).unwrap();
+    let dominators = Dominators::new(&cfg);
+    assert_eq!(dominators.immediate_dominator(join), Some(0));
+
+    let phis = &ssa.blocks[join].phis;
+    assert_eq!(phis.len(), 1);
+    assert_eq!(phis[0].location, Location::Register(0));
+    let sources: Vec<_> = phis[0]
+        .operands
+        .iter()
+        .map(|operand| ssa.value(operand.value).source)
+        .collect();
+    assert!(sources.contains(&ValueSource::Instruction(0)));
+    assert!(sources.contains(&ValueSource::Instruction(2)));
+
+    let invoke = ssa.instruction(3).unwrap();
+    assert_eq!(invoke.offset, 8);
+    assert_eq!(invoke.uses, vec![(Location::Register(0), phis[0].value)]);
+
+    let move_result = ssa.instruction(4).unwrap();
+    assert_eq!(move_result.uses[0].1, invoke.defs[0].1);
+    assert_eq!(ssa.instruction(5).unwrap().uses, move_result.defs);
+}
+
+#[test]
+fn test_loop_to_entry() {
+    let code = [
+        0xd8, 0x00, 0x00, 0x01, // add-int/lit8 v0, v0, 1
+        0x39, 0x00, 0xfe, 0xff, // if-nez v0, -2
+        0x0e, 0x00, // return-void
+    ];
+    let instructions = SmaliDecoder::new(&code, None).decode_all();
+    let cfg = Cfg::new(&instructions, &[]);
+    let ssa = Ssa::new(&instructions, &cfg, 1);
+
+    let phis = &ssa.blocks[0].phis;
+    assert_eq!(phis.len(), 1);
+    let add = ssa.instruction(0).unwrap();
+    assert_eq!(add.uses, vec![(Location::Register(0), phis[0].value)]);
+    assert_eq!(
+        phis[0].operands,
+        vec![
+            PhiOperand {
+                predecessor: None,
+                value: ssa
+                    .values()
+                    .iter()
+                    .position(|info| info.source == ValueSource::Entry)
+                    .map(|value| Value(value as u32))
+                    .unwrap(),
+            },
+            PhiOperand {
+                predecessor: Some(0),
+                value: add.defs[0].1,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_phi_at_dominated_handler() {
+    let code = [
+        0x12, 0x10, // const/4 v0, 1
+        0x71, 0x00, 0x00, 0x00, 0x00, 0x00, // invoke-static {}, method@0
+        0x12, 0x20, // const/4 v0, 2
+        0x0f, 0x00, // return v0
+        0x0f, 0x00, // return v0, the catch-all handler
+    ];
+    let tries = [TryBlock {
+        start_addr: 0,
+        insn_count: 5,
+        handlers: vec![CatchHandler {
+            type_idx: None,
+            addr: 6,
+        }],
+    }];
+    let instructions = SmaliDecoder::new(&code, None).decode_all();
+    let cfg = Cfg::new(&instructions, &tries);
+    let ssa = Ssa::new(&instructions, &cfg, 1);
+
+    // the try block is the handler's only predecessor
+    let handler = cfg.block_of(4).unwrap();
+    assert_eq!(cfg.blocks[handler].predecessors, vec![0]);
+
+    let phis = &ssa.blocks[handler].phis;
+    assert_eq!(phis.len(), 1);
+    assert_eq!(
+        ssa.instruction(4).unwrap().uses,
+        vec![(Location::Register(0), phis[0].value)]
+    );
+
+    // the phi merges the same definitions the reaching definitions find
+    let sources: Vec<_> = phis[0]
+        .operands
+        .iter()
+        .map(|operand| match ssa.value(operand.value).source {
+            ValueSource::Instruction(index) => Some(index),
+            _ => None,
+        })
+        .collect();
+    let chains = DefUseChains::new(&instructions, &cfg, 1);
+    assert_eq!(sources, chains.definitions(4, Location::Register(0)));
+    assert_eq!(sources, vec![None, Some(0)]);
+}
+
+#[test]
+fn test_copy_source() {
+    let code = [
+        0x12, 0x10, // const/4 v0, 1
+        0x01, 0x01, // move v1, v0
+        0x0f, 0x01, // return v1
+    ];
+    let instructions = SmaliDecoder::new(&code, None).decode_all();
+    let cfg = Cfg::new(&instructions, &[]);
+    let ssa = Ssa::new(&instructions, &cfg, 2);
+
+    let returned = ssa.instruction(2).unwrap().uses[0].1;
+    let source = ssa.copy_source(&instructions, returned);
+    assert_eq!(source, ssa.instruction(0).unwrap().defs[0].1);
+}
@@ -0,0 +1,220 @@
+#![cfg(feature = "std")]
+
+use smali_disassembler::{
+    analysis::cfg::CatchHandler,
+    container::{dex_file::DexFile, standard::StandardDexFile, EmbeddedDex},
+    dalvik::smali::method_text,
+    errors::Error,
+    resolver::{MethodHandle, Prototype, Resolver},
+    SmaliDecoder,
+};
+
+// class Hello extends Object with a static int field `count`, a constructor and
+// `static int run(int)` whose `sget` is covered by an IOException handler and a catch-all
+const HELLO_DEX: &[u8] = include_bytes!("data/hello.dex");
+
+#[test]
+fn test_classes() {
+    let embedded = EmbeddedDex::parse(HELLO_DEX).unwrap();
+    let dex = StandardDexFile::new(&embedded).unwrap();
+
+    let classes = dex.classes().unwrap();
+    assert_eq!(classes.len(), 1);
+    assert_eq!(classes[0].descriptor, "LHello;");
+    assert_eq!(
+        classes[0].super_class.as_deref(),
+        Some("Ljava/lang/Object;")
+    );
+    assert!(classes[0].interfaces.is_empty());
+
+    let fields: Vec<(&str, &str, u32)> = classes[0]
+        .fields
+        .iter()
+        .map(|field| {
+            (
+                field.name.as_str(),
+                field.field_type.as_str(),
+                field.access_flags,
+            )
+        })
+        .collect();
+    assert_eq!(fields, [("count", "I", 0x0008)]);
+
+    let methods: Vec<(&str, String)> = classes[0]
+        .methods
+        .iter()
+        .map(|method| (method.name.as_str(), method.prototype.descriptor()))
+        .collect();
+    assert_eq!(
+        methods,
+        [("<init>", "()V".to_string()), ("run", "(I)I".to_string())]
+    );
+
+    let field = dex.field(0).unwrap();
+    assert_eq!(
+        (
+            field.class.as_str(),
+            field.name.as_str(),
+            field.field_type.as_str()
+        ),
+        ("LHello;", "count", "I")
+    );
+    assert_eq!(
+        Resolver::prototype(&dex, 0),
+        Some(Prototype {
+            return_type: "I".to_string(),
+            parameters: vec!["I".to_string()],
+        })
+    );
+    assert!(dex.method_ref(3).is_err());
+}
+
+#[test]
+fn test_code_item() {
+    let embedded = EmbeddedDex::parse(HELLO_DEX).unwrap();
+    let dex = StandardDexFile::new(&embedded).unwrap();
+    let run = &dex.classes().unwrap()[0].methods[1];
+
+    let code = dex.code_item(run.code_off).unwrap();
+    assert_eq!(
+        (code.registers_size, code.ins_size, code.outs_size),
+        (3, 1, 0)
+    );
+    assert_eq!(code.insns.len(), 18);
+    assert_eq!(code.tries.len(), 1);
+    assert_eq!((code.tries[0].start_addr, code.tries[0].insn_count), (2, 2));
+    assert_eq!(
+        code.tries[0].handlers,
+        [
+            CatchHandler {
+                type_idx: Some(2),
+                addr: 6
+            },
+            CatchHandler {
+                type_idx: None,
+                addr: 6
+            },
+        ]
+    );
+
+    let instructions = SmaliDecoder::new(code.insns, None).decode_all();
+    assert_eq!(
+        method_text("run(I)I", 3, &instructions, &code.tries, &dex),
+        ".method run(I)I\n    \
+            .registers 3\n    \
+            const-string v0, \"hello, world\"\n\n    \
+            :addr_2\n    \
+            sget v1, LHello;->count:I\n\n    \
+            :addr_4\n    \
+            add-int/2addr v1, v2\n    \
+            return v1\n\n    \
+            :addr_6\n    \
+            move-exception v0\n    \
+            const/4 v1, -0x1\n    \
+            return v1\n\n    \
+            .catch Ljava/io/IOException; {:addr_2 .. :addr_4} :addr_6\n    \
+            .catchall {:addr_2 .. :addr_4} :addr_6\n\
+        .end method\n"
+    );
+}
+
+#[test]
+fn test_find_class() {
+    let embedded = EmbeddedDex::parse(HELLO_DEX).unwrap();
+    let dex = StandardDexFile::new(&embedded).unwrap();
+
+    assert_eq!(dex.class_count().unwrap(), 1);
+    let type_idx = dex.type_index("LHello;").unwrap().unwrap();
+    assert_eq!(dex.type_descriptor(type_idx).unwrap(), "LHello;");
+    assert_eq!(dex.type_index("LMissing;").unwrap(), None);
+
+    let class = dex.find_class("LHello;").unwrap().unwrap();
+    assert_eq!(class.class_idx, type_idx);
+    assert_eq!(class.methods.len(), 2);
+    // referenced but defined elsewhere
+    assert!(dex.type_index("Ljava/lang/Object;").unwrap().is_some());
+    assert!(dex.find_class("Ljava/lang/Object;").unwrap().is_none());
+}
+
+#[test]
+fn test_class_data_overflow() {
+    // class data of the only class, with delta encoded indices that overflow a u32
+    let class_data_off = 0x1b6;
+    for class_data in [
+        &[
+            0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0x0f, 0x00,
+        ][..], // field_idx
+        &[
+            0x00, 0x00, 0x02, 0x00, 0x01, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0x0f, 0x00, 0x00,
+        ], // method_idx
+    ] {
+        let mut data = HELLO_DEX.to_vec();
+        data[class_data_off..class_data_off + class_data.len()].copy_from_slice(class_data);
+
+        let embedded = EmbeddedDex::parse(&data).unwrap();
+        let dex = StandardDexFile::new(&embedded).unwrap();
+        assert!(matches!(dex.classes(), Err(Error::InvalidContainer)));
+    }
+}
+
+#[test]
+fn test_code_item_overflow() {
+    let embedded = EmbeddedDex::parse(HELLO_DEX).unwrap();
+    let dex = StandardDexFile::new(&embedded).unwrap();
+    let code_off = dex.classes().unwrap()[0].methods[1].code_off as usize;
+    assert!(matches!(
+        dex.code_item(u32::MAX),
+        Err(Error::InvalidContainer)
+    ));
+
+    // an insns_size whose byte length doesn't fit in the file, or in a 32 bit usize
+    let mut data = HELLO_DEX.to_vec();
+    data[code_off + 12..code_off + 16].copy_from_slice(&u32::MAX.to_le_bytes());
+    let embedded = EmbeddedDex::parse(&data).unwrap();
+    let dex = StandardDexFile::new(&embedded).unwrap();
+    assert!(matches!(
+        dex.code_item(code_off as u32),
+        Err(Error::InvalidContainer)
+    ));
+}
+
+#[test]
+fn test_method_handles() {
+    // a method handle and a call site section appended after the map list, which ends the file
+    let mut data = HELLO_DEX.to_vec();
+    let map_off = 0x1c8;
+    data[map_off..map_off + 4].copy_from_slice(&14u32.to_le_bytes());
+    let method_handles = data.len() as u32 + 24;
+    let call_sites = method_handles + 16;
+    for (item_type, size, offset) in [(0x8u16, 2u32, method_handles), (0x7, 1, call_sites)] {
+        data.extend_from_slice(&item_type.to_le_bytes());
+        data.extend_from_slice(&0u16.to_le_bytes());
+        data.extend_from_slice(&size.to_le_bytes());
+        data.extend_from_slice(&offset.to_le_bytes());
+    }
+    data.extend_from_slice(&[0x04, 0, 0, 0, 0x02, 0, 0, 0]); // invoke-static method@2
+    data.extend_from_slice(&[0x03, 0, 0, 0, 0x00, 0, 0, 0]); // instance-get field@0
+    data.extend_from_slice(&(call_sites + 4).to_le_bytes());
+    // the bootstrap method handle@0, a name string@0 and a method type proto@0
+    data.extend_from_slice(&[0x03, 0x16, 0x00, 0x17, 0x00, 0x15, 0x00]);
+    let file_size = data.len() as u32;
+    data[0x20..0x24].copy_from_slice(&file_size.to_le_bytes());
+
+    let embedded = EmbeddedDex::parse(&data).unwrap();
+    let dex = StandardDexFile::new(&embedded).unwrap();
+    let method = MethodHandle::Method(dex.method(2).unwrap());
+    assert_eq!(dex.call_site_bootstrap(0).unwrap(), method);
+    assert_eq!(
+        dex.method_handle(1).unwrap(),
+        MethodHandle::Field(dex.field(0).unwrap())
+    );
+    assert!(matches!(dex.method_handle(2), Err(Error::InvalidContainer)));
+
+    // a file without the sections resolves none
+    let embedded = EmbeddedDex::parse(HELLO_DEX).unwrap();
+    let dex = StandardDexFile::new(&embedded).unwrap();
+    assert!(matches!(
+        dex.call_site_bootstrap(0),
+        Err(Error::InvalidContainer)
+    ));
+}
@@ -0,0 +1,114 @@
+#![cfg(feature = "std")]
+
+use smali_disassembler::{
+    analysis::{
+        cfg::Cfg,
+        types::{infer_types, MethodSignature, RegType},
+    },
+    resolver::Prototype,
+    SmaliDecoder,
+};
+
+mod common;
+
+use common::TestResolver;
+
+fn strings() -> TestResolver {
+    TestResolver::new().with_string(0, "hello")
+}
+
+#[test]
+fn test_null_merges_into_reference() {
+    let code = [
+        0x12, 0x00, // const/4 v0, 0
+        0x38, 0x01, 0x04, 0x00, // if-eqz v1, +4
+        0x1a, 0x00, 0x00, 0x00, // const-string v0, string@0
+        0x11, 0x00, // return-object v0
+    ];
+    let instructions = SmaliDecoder::new(&code, None).decode_all();
+    let cfg = Cfg::new(&instructions, &[]);
+    let prototype = Prototype {
+        return_type: "Ljava/lang/Object;".to_string(),
+        parameters: vec!["I".to_string()],
+    };
+    let signature = MethodSignature {
+        class: "LFoo;",
+        is_static: true,
+        prototype: &prototype,
+    };
+
+    let types = infer_types(&instructions, &cfg, 2, &signature, &strings());
+    assert_eq!(
+        types.before(1),
+        Some(&[RegType::Zero, RegType::Integer][..])
+    );
+    assert_eq!(
+        types.register(3, 0),
+        Some(&RegType::Reference("Ljava/lang/String;".to_string()))
+    );
+    assert_eq!(
+        types.constant_use(0),
+        Some(&RegType::Reference("Ljava/lang/Object;".to_string()))
+    );
+}
+
+#[test]
+fn test_wide_pairs_and_constants() {
+    let code = [
+        0x16, 0x00, 0x01, 0x00, // const-wide/16 v0, 1
+        0x12, 0x21, // const/4 v1, 2
+        0xc6, 0x11, // add-float/2addr v1, v1
+        0x0e, 0x00, // return-void
+    ];
+    let instructions = SmaliDecoder::new(&code, None).decode_all();
+    let cfg = Cfg::new(&instructions, &[]);
+    let prototype = Prototype {
+        return_type: "V".to_string(),
+        parameters: vec![],
+    };
+    let signature = MethodSignature {
+        class: "LFoo;",
+        is_static: true,
+        prototype: &prototype,
+    };
+
+    let types = infer_types(&instructions, &cfg, 2, &signature, &strings());
+    assert_eq!(
+        types.before(1),
+        Some(&[RegType::ConstantWideLo, RegType::ConstantWideHi][..])
+    );
+    assert_eq!(
+        types.before(2),
+        Some(&[RegType::Conflict, RegType::Constant][..])
+    );
+    assert_eq!(
+        types.before(3),
+        Some(&[RegType::Conflict, RegType::Float][..])
+    );
+    assert_eq!(types.constant_use(1), Some(&RegType::Float));
+}
+
+#[test]
+fn test_range_past_last_register() {
+    let code = [
+        0x74, 0x05, 0x00, 0x00, 0xfe,
+        0xff, // invoke-virtual/range {vfffe .. v10002}, method@0
+        0x25, 0x05, 0x00, 0x00, 0xfe,
+        0xff, // filled-new-array/range {vfffe .. v10002}, type@0
+        0x0e, 0x00, // return-void
+    ];
+    let instructions = SmaliDecoder::new(&code, None).decode_all();
+    let cfg = Cfg::new(&instructions, &[]);
+    let prototype = Prototype {
+        return_type: "V".to_string(),
+        parameters: vec![],
+    };
+    let signature = MethodSignature {
+        class: "LFoo;",
+        is_static: true,
+        prototype: &prototype,
+    };
+
+    let types = infer_types(&instructions, &cfg, 2, &signature, &strings());
+    assert!(types.before(2).is_some());
+}
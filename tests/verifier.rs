@@ -0,0 +1,53 @@
+#![cfg(feature = "std")]
+
+use smali_disassembler::{
+    analysis::verifier::{verify_method, DiagnosticKind},
+    SmaliDecoder,
+};
+
+#[test]
+fn test_valid_method() {
+    let code = [
+        0x12, 0x00, // const/4 v0, 0
+        0x2b, 0x00, 0x07, 0x00, 0x00, 0x00, // packed-switch v0, +7
+        0x38, 0x00, 0x02, 0x00, // if-eqz v0, +2
+        0x0e, 0x00, // return-void
+        0x00, 0x00, // nop, aligns the payload
+        0x00, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, // payload
+    ];
+
+    let instructions = SmaliDecoder::new(&code, None).decode_all();
+    assert_eq!(instructions.len(), 6);
+    assert_eq!(verify_method(&instructions, 1), vec![]);
+}
+
+#[test]
+fn test_invalid_method() {
+    let code = [
+        0x12, 0x05, // const/4 v5, 0
+        0x0a, 0x00, // move-result v0
+        0x9b, 0x01, 0x00, 0x01, // add-long v1, v0, v1
+        0x28, 0x00, // goto +0
+        0x29, 0x00, 0x03, 0x00, // goto/16 +3
+        0x12, 0x00, // const/4 v0, 0
+    ];
+
+    let instructions = SmaliDecoder::new(&code, None).decode_all();
+    let diagnostics: Vec<_> = verify_method(&instructions, 2)
+        .into_iter()
+        .map(|diagnostic| (diagnostic.offset, diagnostic.kind))
+        .collect();
+
+    assert_eq!(
+        diagnostics,
+        vec![
+            (0x0, DiagnosticKind::RegisterOutOfBounds(5)),
+            (0x2, DiagnosticKind::MisplacedMoveResult),
+            (0x4, DiagnosticKind::RegisterPairOutOfBounds(1)),
+            (0x4, DiagnosticKind::RegisterPairOutOfBounds(1)),
+            (0x8, DiagnosticKind::ZeroBranchOffset),
+            (0xa, DiagnosticKind::TargetOutOfBounds(0x10)),
+            (0xe, DiagnosticKind::FallsOffEnd),
+        ]
+    );
+}
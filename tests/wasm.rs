@@ -0,0 +1,30 @@
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+use smali_disassembler::wasm::{decode_code, disassemble, disassemble_json};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+const HELLO_DEX: &[u8] = include_bytes!("data/hello.dex");
+
+#[wasm_bindgen_test]
+fn test_disassemble() {
+    let smali = disassemble(HELLO_DEX).unwrap();
+    assert!(smali.starts_with(".class LHello;\n.super Ljava/lang/Object;\n"));
+    assert!(smali.contains("    invoke-direct {v0}, Ljava/lang/Object;-><init>()V\n"));
+    assert!(smali.contains("    .catch Ljava/io/IOException; {:addr_2 .. :addr_4} :addr_6\n"));
+    assert!(disassemble(b"not a dex file").is_err());
+}
+
+#[wasm_bindgen_test]
+fn test_disassemble_json() {
+    let json = disassemble_json(HELLO_DEX).unwrap();
+    assert!(json.starts_with("[{\"class\":\"LHello;\",\"name\":\"<init>\""));
+    assert!(json.contains("\"signature\":\"(I)I\""));
+}
+
+#[wasm_bindgen_test]
+fn test_decode_code() {
+    assert_eq!(
+        decode_code(&[0x1a, 0x00, 0x08, 0x00, 0x0e, 0x00]),
+        "const-string v0, string@8\nreturn-void\n"
+    );
+}
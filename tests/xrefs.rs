@@ -0,0 +1,154 @@
+#![cfg(feature = "std")]
+
+use smali_disassembler::{
+    analysis::{
+        callgraph::CallKind,
+        xrefs::{Access, XrefIndex, XrefSite, XrefTarget},
+    },
+    SmaliDecoder,
+};
+
+mod common;
+
+use common::TestResolver;
+use std::io::Cursor;
+
+fn ids() -> TestResolver {
+    TestResolver::new()
+        .with_string(0, "https://example.com/\tapi\n")
+        .with_type(0, "Ljava/lang/StringBuilder;")
+        .with_field(0, "LCounter;", "count", "I")
+        .with_method(0, "LCounter;", "reset", &[], "V")
+}
+
+fn index() -> XrefIndex {
+    #[rustfmt::skip]
+    let code = [
+        0x1a, 0x00, 0x00, 0x00, // const-string v0, string@0
+        0x52, 0x21, 0x00, 0x00, // iget v1, v2, LCounter;->count:I
+        0x59, 0x21, 0x00, 0x00, // iput v1, v2, LCounter;->count:I
+        0x71, 0x00, 0x00, 0x00, 0x00, 0x00, // invoke-static {}, LCounter;->reset()V
+        0x22, 0x00, 0x00, 0x00, // new-instance v0, Ljava/lang/StringBuilder;
+        0x0e, 0x00, // return-void
+    ];
+    let instructions = SmaliDecoder::new(&code, None).decode_all();
+
+    let mut index = XrefIndex::new();
+    index.add_method("LMain;", "run()V", &instructions, &ids());
+    index
+}
+
+fn site(offset: usize, access: Access) -> XrefSite {
+    XrefSite {
+        class: "LMain;".to_string(),
+        method: "run()V".to_string(),
+        offset,
+        access,
+    }
+}
+
+#[test]
+fn test_xrefs_by_target() {
+    let index = index();
+
+    assert_eq!(
+        index.get(&XrefTarget::String(
+            "https://example.com/\tapi\n".to_string()
+        )),
+        [site(0, Access::Load)]
+    );
+    assert_eq!(
+        index.get(&XrefTarget::Field("LCounter;->count:I".to_string())),
+        [site(4, Access::Read), site(8, Access::Write)]
+    );
+    assert_eq!(
+        index.get(&XrefTarget::Method("LCounter;->reset()V".to_string())),
+        [site(12, Access::Invoke(CallKind::Static))]
+    );
+    assert_eq!(
+        index.get(&XrefTarget::Type("Ljava/lang/StringBuilder;".to_string())),
+        [site(18, Access::New)]
+    );
+    assert_eq!(index.targets().count(), 4);
+}
+
+#[test]
+fn test_xrefs_search() {
+    let index = index();
+
+    let found: Vec<_> = index.search(XrefTarget::String, "https").collect();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].1, [site(0, Access::Load)]);
+
+    // only targets of the requested kind match
+    assert_eq!(index.search(XrefTarget::Type, "https").count(), 0);
+    assert_eq!(index.search(XrefTarget::Field, "LCounter;").count(), 1);
+}
+
+#[test]
+fn test_xrefs_round_trip() {
+    let index = index();
+
+    let mut saved = vec![];
+    index.write_to(&mut saved).unwrap();
+    assert!(XrefIndex::is_index(&saved));
+
+    let loaded = XrefIndex::read_from(Cursor::new(saved)).unwrap();
+    assert_eq!(loaded, index);
+}
+
+#[test]
+fn test_xrefs_malformed() {
+    assert!(XrefIndex::read_from(Cursor::new("not an index\n")).is_err());
+    assert!(XrefIndex::read_from(Cursor::new("smali-xrefs 1\nstring\tx\n")).is_err());
+    assert!(XrefIndex::read_from(Cursor::new(
+        "smali-xrefs 1\nstring\tx\tLMain;\trun()V\t0\tdelete\n"
+    ))
+    .is_err());
+}
+
+#[test]
+fn test_method_handles() {
+    let ids = ids()
+        .with_method(1, "LMain;", "lambda$run$0", &[], "V")
+        .with_method(
+            2,
+            "Ljava/lang/invoke/LambdaMetafactory;",
+            "metafactory",
+            &[],
+            "V",
+        )
+        .with_method_handle(0, 2)
+        .with_method_handle(1, 1)
+        .with_call_site(0, 0)
+        .with_prototype(0, &["I"], "V");
+    #[rustfmt::skip]
+    let code = [
+        0xfc, 0x10, 0x00, 0x00, 0x00, 0x00, // invoke-custom {v0}, call_site@0
+        0xfd, 0x02, 0x00, 0x00, 0x00, 0x00, // invoke-custom/range {v0 .. v1}, call_site@0
+        0xfe, 0x00, 0x01, 0x00, // const-method-handle v0, method_handle@1
+        0xff, 0x00, 0x00, 0x00, // const-method-type v0, proto@0
+    ];
+    let instructions = SmaliDecoder::new(&code, None).decode_all();
+    let mut index = XrefIndex::new();
+    index.add_method("LMain;", "run()V", &instructions, &ids);
+
+    assert_eq!(
+        index.get(&XrefTarget::Method(
+            "Ljava/lang/invoke/LambdaMetafactory;->metafactory()V".to_string()
+        )),
+        [site(0, Access::Bootstrap), site(6, Access::Bootstrap)]
+    );
+    assert_eq!(
+        index.get(&XrefTarget::Method("LMain;->lambda$run$0()V".to_string())),
+        [site(12, Access::Load)]
+    );
+    assert_eq!(
+        index.get(&XrefTarget::Prototype("(I)V".to_string())),
+        [site(16, Access::Load)]
+    );
+
+    let mut saved = vec![];
+    index.write_to(&mut saved).unwrap();
+    assert_eq!(XrefIndex::read_from(Cursor::new(saved)).unwrap(), index);
+}